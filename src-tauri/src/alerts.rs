@@ -0,0 +1,402 @@
+//! Missed check-in alerts for supervisors.
+//!
+//! On supervisor-mode devices this module periodically asks the remote API for
+//! each supervised device's last sign-in once the check-in deadline has passed,
+//! and alerts the supervisor through the channels chosen in the notification
+//! preferences about every device that missed the day.
+//!
+//! Polling only helps supervisors whose app happens to be running, so the
+//! supervised device also reports its own missed check-in to the server, which
+//! fans the alert out to every supervisor as a `missed_checkin` push event.
+//! Supervisors can mute the alerts of a single relationship.
+//!
+//! A supervisor can also set a missed-days threshold per relationship. The
+//! supervision sync compares it against each device's last sign-in and sends
+//! one escalated alert per gap once that many consecutive days were missed.
+//! Devices that paused sharing are neither reported nor alerted about.
+
+use chrono::NaiveDate;
+use rust_i18n::t;
+use tauri::{AppHandle, Manager};
+
+use crate::api_client;
+use crate::clock::{Clock, SharedClock};
+use crate::deadline::{checkin_deadline, signin_day};
+use crate::dispatch::{self, EventNotification};
+use crate::error::AppError;
+use crate::models::{
+    is_paused, AppSettings, DeviceConfig, DeviceStatus, PreferenceEvent, SupervisionHistoryKind,
+    SupervisionRelationship, WebhookEvent, WebhookPayload,
+};
+use crate::remote_models::DeviceStatus as RemoteDeviceStatus;
+use crate::reports::record_digest_alert;
+use crate::services::{
+    missed_days_email, missed_days_message, missed_signin_email, missed_signin_message,
+};
+use crate::state::AppState;
+use crate::storage;
+use crate::supervision;
+use crate::webhooks;
+
+/// Check supervised devices on supervisors and report this device's own miss on signers
+pub async fn run_alert_check(app: &AppHandle) -> Result<(), AppError> {
+    let clock = app.state::<SharedClock>();
+    let state = app.state::<AppState>();
+    let config = state.device_config().map_err(|e| e.to_string())?;
+    let settings = storage::load_settings().map_err(|e| e.to_string())?;
+    let date = last_closed_date(clock.as_ref(), &settings);
+
+    if config.device.mode.can_supervise() {
+        check_supervised_devices(app, &config, date).await?;
+    }
+    if config.device.mode.can_signin() {
+        report_own_missed_checkin(&state, clock.as_ref(), &config, date).await?;
+    }
+    Ok(())
+}
+
+/// Alert the supervisor about every supervised device that missed the last closed day
+async fn check_supervised_devices(
+    app: &AppHandle,
+    config: &DeviceConfig,
+    date: NaiveDate,
+) -> Result<(), AppError> {
+    let clock = app.state::<SharedClock>();
+    let date_key = date.format("%Y-%m-%d").to_string();
+    let mut state = storage::load_missed_alert_state().map_err(|e| e.to_string())?;
+    let mut changed = false;
+
+    let relationships: Vec<_> = config
+        .supervision_relationships
+        .iter()
+        .filter(|r| r.supervisor_device_id == config.device.device_id && !r.muted)
+        .filter(|r| state.last_alerted.get(&r.supervised_device_id) != Some(&date_key))
+        .collect();
+    let device_ids: Vec<String> = relationships
+        .iter()
+        .map(|r| r.supervised_device_id.clone())
+        .collect();
+    let statuses = api_client::get_device_statuses(&device_ids)
+        .await
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to fetch status of supervised devices: {}", e);
+            vec![]
+        });
+
+    for relationship in relationships {
+        let device_id = &relationship.supervised_device_id;
+        let Some(status) = statuses.iter().find(|s| &s.device_id == device_id) else {
+            log::warn!("No status for supervised device {}", device_id);
+            continue;
+        };
+        if signed_in_on_or_after(status.last_signin.as_deref(), date)
+            || is_paused(status.paused_until.as_deref(), clock.now())
+        {
+            continue;
+        }
+
+        alert_missed(app, relationship, &date_key, status).await;
+        state
+            .last_alerted
+            .insert(device_id.clone(), date_key.clone());
+        changed = true;
+    }
+
+    if changed {
+        storage::save_missed_alert_state(&state).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Set the missed-days threshold of a relationship this device supervises
+pub async fn set_missed_days_threshold(
+    state: &AppState,
+    relationship_id: &str,
+    days: Option<u32>,
+) -> Result<(), AppError> {
+    if days == Some(0) {
+        return Err(t!("error.threshold_too_small").into());
+    }
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let supervisor_id = config.device.device_id;
+    let target_id = config
+        .supervision_relationships
+        .iter()
+        .find(|r| r.relationship_id == relationship_id && r.supervisor_device_id == supervisor_id)
+        .map(|r| r.supervised_device_id.clone())
+        .ok_or_else(|| {
+            log::warn!("Supervising relationship {} not found", relationship_id);
+            t!("error.relationship_not_found").into_owned()
+        })?;
+
+    api_client::set_missed_days_threshold_api(&supervisor_id, &target_id, days).await?;
+    state.update_device_config(|config| {
+        let relationship = config
+            .supervision_relationships
+            .iter_mut()
+            .find(|r| r.relationship_id == relationship_id)
+            .ok_or_else(|| t!("error.relationship_not_found").into_owned())?;
+        relationship.missed_days_threshold = days;
+        Ok(())
+    })
+}
+
+/// Alert about supervised devices whose missed days reached their relationship's threshold
+pub async fn check_missed_day_thresholds(app: &AppHandle, devices: &[DeviceStatus]) {
+    let clock = app.state::<SharedClock>();
+    let state = app.state::<AppState>();
+    let (config, settings, mut state) = match (
+        state.device_config(),
+        storage::load_settings(),
+        storage::load_missed_alert_state(),
+    ) {
+        (Ok(config), Ok(settings), Ok(state)) => (config, settings, state),
+        _ => {
+            log::warn!("Failed to load state for missed-days alerts");
+            return;
+        }
+    };
+    let date = last_closed_date(clock.as_ref(), &settings);
+    let mut changed = false;
+
+    for relationship in config
+        .supervision_relationships
+        .iter()
+        .filter(|r| r.supervisor_device_id == config.device.device_id && !r.muted)
+    {
+        let Some(threshold) = relationship.missed_days_threshold else {
+            continue;
+        };
+        let device_id = &relationship.supervised_device_id;
+        let Some(status) = devices.iter().find(|d| {
+            &d.device_id == device_id && !is_paused(d.paused_until.as_deref(), clock.now())
+        }) else {
+            continue;
+        };
+        let Some(last_signin) = status
+            .last_signin_date
+            .get(..10)
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        else {
+            continue;
+        };
+
+        let missed = (date - last_signin).num_days();
+        if missed < i64::from(threshold)
+            || state.threshold_alerted.get(device_id) == Some(&status.last_signin_date)
+        {
+            continue;
+        }
+
+        alert_missed_days(app, relationship, missed, &status.last_signin_date).await;
+        state
+            .threshold_alerted
+            .insert(device_id.clone(), status.last_signin_date.clone());
+        changed = true;
+    }
+
+    if changed {
+        if let Err(e) = storage::save_missed_alert_state(&state) {
+            log::warn!("Failed to save missed alert state: {}", e);
+        }
+    }
+}
+
+/// Send the escalated alert for a device that missed `days` consecutive days
+async fn alert_missed_days(
+    app: &AppHandle,
+    relationship: &SupervisionRelationship,
+    days: i64,
+    last_signin: &str,
+) {
+    let clock = app.state::<SharedClock>();
+    let name = relationship
+        .name_of(&relationship.supervised_device_id, &relationship.supervisor_device_id);
+    log::info!("Supervised device {} missed {} days in a row", name, days);
+
+    let vars = vec![
+        ("name", name.to_string()),
+        ("device_id", relationship.supervised_device_id.clone()),
+        ("days", days.to_string()),
+        ("last_signin", last_signin.to_string()),
+    ];
+    dispatch::notify(
+        app,
+        EventNotification {
+            event: PreferenceEvent::MissedCheckin,
+            message: missed_days_message(name, days, last_signin),
+            email: Some(missed_days_email(name, days, last_signin)),
+            webhook: Some(WebhookPayload {
+                event: WebhookEvent::MissedSignin,
+                vars: webhooks::template_vars(clock.as_ref(), WebhookEvent::MissedSignin, vars),
+            }),
+            critical: true,
+        },
+    )
+    .await;
+}
+
+/// Alert about a missed check-in the server fanned out to this supervisor
+///
+/// Shares the per-device bookkeeping with the polling loop, so a miss is only
+/// announced once however it was noticed.
+pub async fn handle_missed_checkin_event(app: &AppHandle, data: &serde_json::Value) {
+    let (Some(device_id), Some(date_key)) = (
+        data.get("device_id").and_then(|v| v.as_str()),
+        data.get("date").and_then(|v| v.as_str()),
+    ) else {
+        log::warn!("Ignoring malformed missed check-in event: {}", data);
+        return;
+    };
+
+    let state = app.state::<AppState>();
+    let config = match state.device_config() {
+        Ok(config) => config,
+        Err(e) => {
+            log::warn!("Failed to load device config: {}", e);
+            return;
+        }
+    };
+    let Some(relationship) = config.supervision_relationships.iter().find(|r| {
+        r.supervisor_device_id == config.device.device_id && r.supervised_device_id == device_id
+    }) else {
+        log::debug!("Missed check-in event for unknown device {}", device_id);
+        return;
+    };
+    if relationship.muted {
+        log::info!("Missed check-in alerts for {} are muted", device_id);
+        return;
+    }
+
+    let mut state = match storage::load_missed_alert_state() {
+        Ok(state) => state,
+        Err(e) => {
+            log::warn!("Failed to load missed alert state: {}", e);
+            return;
+        }
+    };
+    if state.last_alerted.get(device_id).map(String::as_str) == Some(date_key) {
+        return;
+    }
+
+    let status = match api_client::get_device_status(device_id).await {
+        Ok(status) => status,
+        Err(e) => {
+            log::warn!("Failed to fetch status for supervised device {}: {}", device_id, e);
+            return;
+        }
+    };
+    let clock = app.state::<SharedClock>();
+    if is_paused(status.paused_until.as_deref(), clock.now()) {
+        log::info!("Sharing of {} is paused, not alerting", device_id);
+        return;
+    }
+    alert_missed(app, relationship, date_key, &status).await;
+
+    state
+        .last_alerted
+        .insert(device_id.to_string(), date_key.to_string());
+    if let Err(e) = storage::save_missed_alert_state(&state) {
+        log::warn!("Failed to save missed alert state: {}", e);
+    }
+}
+
+/// Send the missed check-in alert for one supervised device
+async fn alert_missed(
+    app: &AppHandle,
+    relationship: &SupervisionRelationship,
+    date_key: &str,
+    status: &RemoteDeviceStatus,
+) {
+    let clock = app.state::<SharedClock>();
+    let device_id = &relationship.supervised_device_id;
+    log::info!("Supervised device {} missed check-in on {}", device_id, date_key);
+
+    let name = relationship.name_of(device_id, &relationship.supervisor_device_id);
+    let last_signin = status.last_signin.as_deref();
+    let message = missed_signin_message(name, date_key, last_signin);
+    record_digest_alert(&app.state::<AppState>(), date_key, message.title.clone());
+    supervision::record_history(
+        SupervisionHistoryKind::MissedCheckin,
+        device_id,
+        Some(name.to_string()),
+        Some(date_key.to_string()),
+        None,
+    );
+    let vars = vec![
+        ("name", name.to_string()),
+        ("device_id", device_id.clone()),
+        ("date", date_key.to_string()),
+        ("last_signin", last_signin.unwrap_or_default().to_string()),
+        ("streak", status.streak.to_string()),
+    ];
+
+    // Missed check-ins are critical, so they skip the quiet-hours queue
+    dispatch::notify(
+        app,
+        EventNotification {
+            event: PreferenceEvent::MissedCheckin,
+            message,
+            email: Some(missed_signin_email(name, date_key, last_signin)),
+            webhook: Some(WebhookPayload {
+                event: WebhookEvent::MissedSignin,
+                vars: webhooks::template_vars(clock.as_ref(), WebhookEvent::MissedSignin, vars),
+            }),
+            critical: true,
+        },
+    )
+    .await;
+}
+
+/// Tell the server this device missed the last closed day, once per day
+async fn report_own_missed_checkin(
+    state: &AppState,
+    clock: &dyn Clock,
+    config: &DeviceConfig,
+    date: NaiveDate,
+) -> Result<(), AppError> {
+    let Some(data) = state.signin_data().map_err(|e| e.to_string())? else {
+        return Ok(());
+    };
+    if signed_in_on_or_after(Some(&data.last_signin_date), date)
+        || config.sharing_paused(clock.now())
+    {
+        return Ok(());
+    }
+
+    let date_key = date.format("%Y-%m-%d").to_string();
+    let mut state = storage::load_missed_alert_state().map_err(|e| e.to_string())?;
+    if state.last_reported.as_deref() == Some(date_key.as_str()) {
+        return Ok(());
+    }
+
+    log::info!("Reporting missed check-in on {} to supervisors", date_key);
+    api_client::report_missed_checkin(&config.device.device_id, &date_key).await?;
+    state.last_reported = Some(date_key);
+    storage::save_missed_alert_state(&state).map_err(AppError::from)
+}
+
+/// Most recent day whose check-in window has closed
+fn last_closed_date(clock: &dyn Clock, settings: &AppSettings) -> NaiveDate {
+    evaluated_date(signin_day(clock, settings), checkin_deadline(clock, settings) <= clock.now())
+}
+
+/// Pick the most recent day whose check-in window has closed
+fn evaluated_date(today: NaiveDate, deadline_passed: bool) -> NaiveDate {
+    if deadline_passed {
+        today
+    } else {
+        today.pred_opt().unwrap_or(today)
+    }
+}
+
+/// Check whether a server-reported sign-in timestamp covers `date`
+fn signed_in_on_or_after(last_signin: Option<&str>, date: NaiveDate) -> bool {
+    last_signin
+        .and_then(|s| s.get(..10))
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .is_some_and(|d| d >= date)
+}
@@ -1,47 +1,927 @@
 //! HTTP client for remote API calls.
 //!
-//! This module provides functions to call the remote server API.
+//! This module provides functions to call the remote server API; [`events`]
+//! holds the persistent connection for server push events.
+//!
+//! Calls that fail for transient reasons, such as a dropped mobile connection
+//! or a gateway error, are retried with jittered exponential backoff as set in
+//! [`ApiRetryPolicy`]. Non-idempotent calls carry an idempotency key that stays
+//! the same across attempts, so the server can drop duplicates of a request
+//! that did arrive even though its response was lost.
+//!
+//! Every call is bounded: connecting and each read time out, each attempt has
+//! its own limit and the whole call, retries included, has a deadline.
+//!
+//! A command the frontend may abandon, e.g. loading a screen the user leaves,
+//! takes a call id from the frontend and runs through [`cancellable`]. When
+//! the frontend gives up on it, [`cancel_call`] aborts that command's calls
+//! and nothing else: calls of other commands and of the background jobs keep
+//! running. A cancelled caller stops waiting for a GET it shares with other
+//! callers, but the GET itself still completes for them.
+//!
+//! When the server is down, calls shouldn't each wait for a full network
+//! failure. After several calls in a row found it unreachable, a circuit
+//! breaker opens and calls fail fast. Once a pause has passed, a single call
+//! is let through to probe whether the server is back; it closes the breaker
+//! again on success, or keeps it open for another pause.
+//!
+//! Connections to the server must use https unless plain http is explicitly
+//! allowed for a development server on the local network. A self-hosted
+//! server can be trusted by pinning its certificate, see [`pinning`].
+//!
+//! Every request names the API version this build speaks. At startup the
+//! server is asked which versions it supports; when it can't serve this
+//! build, calls fail with an [`AppError::UpdateRequired`] instead of with
+//! parse errors once the response schema has drifted.
+//!
+//! Each call carries a correlation ID in the X-Request-Id header, the same
+//! across retries. It appears in the log lines and error messages of the
+//! call, so users can quote it when reporting sync problems, and the most
+//! recent calls are kept in memory with their status and duration.
+//!
+//! All HTTP traffic, to the server and to third-party services, goes through
+//! the system's proxy, or through a proxy configured in the settings. Responses
+//! are requested gzip or brotli compressed to spare mobile data, and are
+//! decompressed transparently by the client.
+//!
+//! Identical GETs made while one is still in flight, e.g. when the user
+//! refreshes repeatedly, don't go out again: they wait for the call in flight
+//! and parse the response it gets.
+//!
+//! GET responses carrying an ETag are kept in a small on-disk cache. Repeated
+//! polls of device status and supervision lists send the ETag back, and when
+//! the server answers 304 Not Modified the cached body is used instead.
+//!
+//! When the server answers 429 Too Many Requests, no further calls are sent
+//! until its Retry-After has passed: calls made meanwhile fail right away with
+//! an [`AppError::RateLimited`] telling how long to wait, and background jobs
+//! call [`wait_for_rate_limit`] to hold their next round until then.
+//!
+//! Calls fail with an [`AppError`] telling why, e.g. whether the server was
+//! unreachable, did not find the resource or failed itself.
 //! Server: http://20.41.108.70
 
-use reqwest::Client;
+pub mod events;
+mod pinning;
+
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::future::{BoxFuture, FutureExt, Shared};
+use reqwest::{Client, Method, StatusCode};
+use rust_i18n::t;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Notify;
+use uuid::Uuid;
 
+use crate::clock;
+use crate::error::AppError;
+use crate::models::{
+    ApiCallLogEntry, ApiRetryPolicy, AppSettings, CachedResponse, CrashReport, ProxySettings,
+    ServerSettings,
+};
 use crate::remote_models::*;
+use crate::storage;
+
+/// Version of the server API this build speaks, sent with every request
+const API_VERSION: u32 = 1;
+
+/// Header naming the API version of a request or response
+const API_VERSION_HEADER: &str = "X-API-Version";
+
+/// Endpoint reporting the API versions and capabilities of the server
+const VERSION_ENDPOINT: &str = "/version";
+
+/// Server used unless a self-hosted one is configured
+const DEFAULT_API_BASE_URL: &str = "https://localhost:3000";
+
+/// Wait assumed when a 429 response carries no usable Retry-After header
+const DEFAULT_RETRY_AFTER_SECS: u64 = 60;
+
+/// Longest Retry-After honoured, so a bogus header can't stall calls for days
+const MAX_RETRY_AFTER_SECS: u64 = 60 * 60;
+
+/// Longest wait for a connection to the server
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest wait for the next chunk of a response
+const READ_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Longest single attempt of a call
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest a call may take, retries included
+const CALL_DEADLINE: Duration = Duration::from_secs(90);
+
+tokio::task_local! {
+    /// Cancellation of the frontend call the current task runs for
+    static FRONTEND_CALL: Arc<CancelSignal>;
+}
+
+/// Cancellation of frontend calls in flight, by the id the frontend gave them
+static CANCELLABLE_CALLS: LazyLock<Mutex<HashMap<String, Arc<CancelSignal>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Calls in a row that must find the server down before the circuit breaker opens
+const BREAKER_FAILURE_THRESHOLD: u32 = 5;
+
+/// How long an open circuit breaker fails calls fast before letting a probe through
+const BREAKER_OPEN_DURATION: Duration = Duration::from_secs(30);
+
+/// Circuit breaker shared by all calls
+static BREAKER: Mutex<Breaker> = Mutex::new(Breaker::CLOSED);
 
-const API_BASE_URL: &str = "http://localhost:3000";
+/// Calls fail fast until then because the server is rate limiting this device
+static RATE_LIMITED_UNTIL: Mutex<Option<Instant>> = Mutex::new(None);
 
-/// Create HTTP client instance
-fn create_client() -> Result<Client, String> {
-    Client::builder().build().map_err(|e| {
+/// API versions of the server, once probed
+static SERVER_VERSION: Mutex<Option<ServerVersion>> = Mutex::new(None);
+
+/// Set once the server turned out not to have the batch status endpoint
+static BATCH_STATUS_UNSUPPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Most calls kept in the log of recent calls
+const MAX_LOGGED_CALLS: usize = 200;
+
+/// Recent calls to the server, oldest first
+static CALL_LOG: Mutex<VecDeque<ApiCallLogEntry>> = Mutex::new(VecDeque::new());
+
+/// Most GET responses kept in the cache; the oldest are dropped beyond this
+const MAX_CACHED_RESPONSES: usize = 50;
+
+/// Serializes updates of the response cache file
+static HTTP_CACHE_LOCK: Mutex<()> = Mutex::new(());
+
+/// GETs in flight by endpoint, awaited by every caller of the same GET
+static IN_FLIGHT: LazyLock<Mutex<HashMap<String, SharedCall>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// A call whose response can be awaited by several callers
+type SharedCall = Shared<BoxFuture<'static, Result<RawResponse, AppError>>>;
+
+/// HTTP client for third-party services and the proxy it was built for
+static HTTP_CLIENT: Mutex<Option<(Option<ProxySettings>, Client)>> = Mutex::new(None);
+
+/// Clients for calls to the server, rebuilt when its transport settings change
+static SERVER_CLIENTS: Mutex<Option<ServerClients>> = Mutex::new(None);
+
+/// Shared HTTP client for requests to third-party services
+///
+/// Clones share one connection pool, so callers can keep their own handle.
+/// Connections and TLS sessions are reused until the proxy settings change.
+pub fn http_client() -> Result<Client, AppError> {
+    let proxy = storage::load_settings().unwrap_or_default().proxy;
+    let mut cached = HTTP_CLIENT.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, client)) = cached.as_ref().filter(|(built_for, _)| *built_for == proxy) {
+        return Ok(client.clone());
+    }
+    let builder = Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT);
+    let client = with_proxy(builder, proxy.as_ref())?.build().map_err(|e| {
         log::error!("Failed to create HTTP client: {}", e);
         format!("Failed to create HTTP client: {}", e)
+    })?;
+    *cached = Some((proxy, client.clone()));
+    Ok(client)
+}
+
+/// Route a client through the configured proxy, keeping the system's when there is none
+fn with_proxy(
+    builder: reqwest::ClientBuilder,
+    proxy: Option<&ProxySettings>,
+) -> Result<reqwest::ClientBuilder, AppError> {
+    let Some(settings) = proxy else {
+        return Ok(builder);
+    };
+    log::info!("Using proxy {}:{}", settings.host, settings.port);
+    Ok(builder.proxy(manual_proxy(settings)?))
+}
+
+/// Proxy for all schemes as configured in the settings
+fn manual_proxy(settings: &ProxySettings) -> Result<reqwest::Proxy, AppError> {
+    let address = format!("http://{}:{}", settings.host.trim(), settings.port);
+    let proxy = reqwest::Proxy::all(address).map_err(|e| {
+        t!("error.invalid_proxy", host = settings.host, port = settings.port, error = e)
+    })?;
+    Ok(match &settings.username {
+        Some(username) => {
+            proxy.basic_auth(username, settings.password.as_deref().unwrap_or_default())
+        }
+        None => proxy,
     })
 }
 
-/// Generic API request function
+/// Check that a manually configured proxy is usable before it is saved
+pub fn validate_proxy_settings(proxy: Option<&ProxySettings>) -> Result<(), AppError> {
+    if let Some(settings) = proxy {
+        if settings.host.trim().is_empty() || settings.port == 0 {
+            return Err(t!("error.proxy_incomplete").into());
+        }
+        manual_proxy(settings)?;
+    }
+    Ok(())
+}
+
+/// Clients for calls to the server and the settings they were built for
+struct ServerClients {
+    server: ServerSettings,
+    proxy: Option<ProxySettings>,
+    api: Client,
+    /// Client for long-lived streams, kept apart as it has no read timeout
+    stream: Client,
+}
+
+/// Check that the server settings are usable before they are saved
+pub fn validate_server_settings(server: &ServerSettings) -> Result<(), AppError> {
+    server_base_url(server)?;
+    for pin in &server.pinned_sha256 {
+        pinning::decode_pin(pin)?;
+    }
+    Ok(())
+}
+
+/// Base URL of the server, refusing plain http unless explicitly allowed
+fn server_base_url(server: &ServerSettings) -> Result<String, AppError> {
+    let base_url = server
+        .base_url
+        .as_deref()
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .unwrap_or(DEFAULT_API_BASE_URL);
+    let url = reqwest::Url::parse(base_url)
+        .map_err(|e| AppError::from(t!("error.invalid_server_url", url = base_url, error = e)))?;
+    match url.scheme() {
+        "https" => {}
+        "http" if server.allow_insecure_http => {
+            log::debug!("Using plain http for {} as allowed in the settings", base_url);
+        }
+        "http" => {
+            return Err(AppError::InsecureConnection {
+                message: t!("error.plain_http", url = base_url).into_owned(),
+            })
+        }
+        scheme => return Err(t!("error.unsupported_url_scheme", scheme = scheme).into()),
+    }
+    Ok(base_url.trim_end_matches('/').to_string())
+}
+
+/// Base URL and client for calls to the server, `stream` selecting the client for streams
+fn server_connection(settings: &AppSettings, stream: bool) -> Result<(String, Client), AppError> {
+    let server = &settings.server;
+    let base_url = server_base_url(server)?;
+    let mut clients = SERVER_CLIENTS.lock().unwrap_or_else(|e| e.into_inner());
+    let current = clients
+        .as_ref()
+        .is_some_and(|c| c.server == *server && c.proxy == settings.proxy);
+    if !current {
+        if !server.pinned_sha256.is_empty() {
+            log::info!("Building server clients with {} pinned hashes", server.pinned_sha256.len());
+        }
+        let api = server_client(settings, |builder| builder.read_timeout(READ_TIMEOUT))?;
+        *clients = Some(ServerClients {
+            server: server.clone(),
+            proxy: settings.proxy.clone(),
+            api,
+            stream: server_client(settings, |builder| builder)?,
+        });
+    }
+    let clients = clients.as_ref().expect("server clients were just built");
+    let client = if stream {
+        &clients.stream
+    } else {
+        &clients.api
+    };
+    Ok((base_url, client.clone()))
+}
+
+/// Build a client for the server following its transport settings
+fn server_client(
+    settings: &AppSettings,
+    configure: impl FnOnce(reqwest::ClientBuilder) -> reqwest::ClientBuilder,
+) -> Result<Client, AppError> {
+    let server = &settings.server;
+    let mut builder = Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .https_only(!server.allow_insecure_http);
+    if !server.pinned_sha256.is_empty() {
+        builder = builder.use_preconfigured_tls(pinning::pinned_tls_config(&server.pinned_sha256)?);
+    }
+    builder = with_proxy(builder, settings.proxy.as_ref())?;
+    configure(builder).build().map_err(|e| {
+        log::error!("Failed to create server HTTP client: {}", e);
+        AppError::from(format!("Failed to create HTTP client: {}", e))
+    })
+}
+
+/// Start the background task that checks the server speaks this build's API version
+pub fn spawn_version_probe(app: AppHandle) {
+    log::info!("Probing server API version");
+    tauri::async_runtime::spawn(async move {
+        match probe_server_version().await {
+            Ok(_) => {}
+            Err(e @ AppError::UpdateRequired { .. }) => {
+                log::error!("Server is incompatible with this build: {}", e);
+                if let Err(e) = app.emit("server-update-required", &e) {
+                    log::warn!("Failed to emit update required event: {}", e);
+                }
+            }
+            Err(e) => log::warn!("Failed to probe server API version: {}", e),
+        }
+    });
+}
+
+/// Ask the server which API versions it supports and remember the answer
+pub async fn probe_server_version() -> Result<ServerVersion, AppError> {
+    let version: ServerVersion =
+        api_request(reqwest::Method::GET, VERSION_ENDPOINT, None::<()>).await?;
+    log::info!(
+        "Server speaks API versions {} to {}, this build {}",
+        version.min_api_version,
+        version.api_version,
+        API_VERSION
+    );
+    *SERVER_VERSION.lock().unwrap_or_else(|e| e.into_inner()) = Some(version.clone());
+    check_compatible(&version)?;
+    Ok(version)
+}
+
+/// Fail fast when the server was found not to speak this build's API version
+fn check_server_version() -> Result<(), AppError> {
+    match &*SERVER_VERSION.lock().unwrap_or_else(|e| e.into_inner()) {
+        Some(version) => check_compatible(version),
+        None => Ok(()),
+    }
+}
+
+/// Check that the server accepts this build's API version
+fn check_compatible(version: &ServerVersion) -> Result<(), AppError> {
+    if (version.min_api_version..=version.api_version).contains(&API_VERSION) {
+        Ok(())
+    } else {
+        Err(update_required(Some(version.api_version)))
+    }
+}
+
+/// Error for a server that speaks `server_version` of the API, when known
+fn update_required(server_version: Option<u32>) -> AppError {
+    let message = match server_version {
+        Some(version) if version < API_VERSION => {
+            t!("error.server_outdated", server = version, app = API_VERSION)
+        }
+        Some(version) => t!("error.app_outdated", server = version, app = API_VERSION),
+        None => t!("error.api_version_dropped", app = API_VERSION),
+    };
+    AppError::UpdateRequired {
+        message: message.into_owned(),
+    }
+}
+
+/// Up to `limit` of the most recent calls to the server, newest first
+pub fn recent_calls(limit: usize) -> Vec<ApiCallLogEntry> {
+    CALL_LOG
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .rev()
+        .take(limit)
+        .cloned()
+        .collect()
+}
+
+/// Cancellation of one frontend call, shared by all API calls it makes
+#[derive(Default)]
+struct CancelSignal {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelSignal {
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    /// Resolve once the frontend call is cancelled
+    async fn cancelled(&self) {
+        let notified = self.notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        if !self.cancelled.load(Ordering::SeqCst) {
+            notified.await;
+        }
+    }
+}
+
+/// Removes a frontend call from [`CANCELLABLE_CALLS`] once it is done or dropped
+struct CancellableGuard {
+    call_id: String,
+    signal: Arc<CancelSignal>,
+}
+
+impl Drop for CancellableGuard {
+    fn drop(&mut self) {
+        let mut calls = CANCELLABLE_CALLS.lock().unwrap_or_else(|e| e.into_inner());
+        if calls
+            .get(&self.call_id)
+            .is_some_and(|signal| Arc::ptr_eq(signal, &self.signal))
+        {
+            calls.remove(&self.call_id);
+        }
+    }
+}
+
+/// Run `future` as the frontend call `call_id`, so [`cancel_call`] can abort its API calls
+///
+/// Without an id the call can't be cancelled.
+pub async fn cancellable<F: Future>(call_id: Option<String>, future: F) -> F::Output {
+    let Some(call_id) = call_id else {
+        return future.await;
+    };
+    let signal = Arc::new(CancelSignal::default());
+    CANCELLABLE_CALLS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(call_id.clone(), signal.clone());
+    let _guard = CancellableGuard {
+        call_id,
+        signal: signal.clone(),
+    };
+    FRONTEND_CALL.scope(signal, future).await
+}
+
+/// Abort the API calls of the frontend call `call_id`, if it is still running
+pub fn cancel_call(call_id: &str) {
+    let signal = CANCELLABLE_CALLS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(call_id)
+        .cloned();
+    match signal {
+        Some(signal) => {
+            log::info!("Cancelling API calls of {}", call_id);
+            signal.cancel();
+        }
+        None => log::debug!("No call {} to cancel", call_id),
+    }
+}
+
+/// Resolve when the frontend call the current task runs for is cancelled, never without one
+async fn frontend_call_cancelled() {
+    match FRONTEND_CALL.try_with(Arc::clone) {
+        Ok(signal) => signal.cancelled().await,
+        Err(_) => std::future::pending().await,
+    }
+}
+
+/// Wait until the server accepts calls again after rate limiting this device
+///
+/// Background jobs call this before each round instead of running into the
+/// limit again; interactive calls rather fail with the time left to wait.
+pub async fn wait_for_rate_limit() {
+    while let Some(wait) = rate_limit_remaining() {
+        log::info!("Server is rate limiting, waiting {}s", wait.as_secs());
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Time left until the server accepts calls again, if it is rate limiting
+fn rate_limit_remaining() -> Option<Duration> {
+    let mut until = RATE_LIMITED_UNTIL.lock().unwrap_or_else(|e| e.into_inner());
+    let wait = until.map(|t| t.saturating_duration_since(Instant::now()));
+    match wait {
+        Some(wait) if !wait.is_zero() => Some(wait),
+        _ => {
+            *until = None;
+            None
+        }
+    }
+}
+
+/// Seconds to wait according to the Retry-After header of a 429 response
+///
+/// The header holds either a number of seconds or an HTTP date.
+fn retry_after_secs(headers: &reqwest::header::HeaderMap) -> u64 {
+    let value = headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim);
+    let secs = value.and_then(|v| {
+        v.parse::<u64>().ok().or_else(|| {
+            DateTime::parse_from_rfc2822(v).ok().map(|at| {
+                (at.with_timezone(&Utc) - clock::shared().now())
+                    .num_seconds()
+                    .max(0) as u64
+            })
+        })
+    });
+    secs.unwrap_or(DEFAULT_RETRY_AFTER_SECS)
+        .min(MAX_RETRY_AFTER_SECS)
+}
+
+/// State of the circuit breaker guarding calls to the server
+struct Breaker {
+    /// Calls in a row that found the server down
+    failures: u32,
+    /// Calls fail fast until then; afterwards a single probe may go through
+    open_until: Option<Instant>,
+    /// Whether the probe of a half-open breaker is in flight
+    probing: bool,
+}
+
+impl Breaker {
+    const CLOSED: Self = Self {
+        failures: 0,
+        open_until: None,
+        probing: false,
+    };
+}
+
+/// Let a call through unless the circuit breaker is open
+fn breaker_admit() -> Result<(), AppError> {
+    let mut breaker = BREAKER.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(open_until) = breaker.open_until else {
+        return Ok(());
+    };
+    let now = Instant::now();
+    if now < open_until {
+        return Err(AppError::NetworkError {
+            message: t!("error.server_down", secs = (open_until - now).as_secs().max(1))
+                .into_owned(),
+        });
+    }
+    if breaker.probing {
+        return Err(AppError::NetworkError {
+            message: t!("error.server_probing").into_owned(),
+        });
+    }
+    log::info!("Circuit breaker half-open, probing the server");
+    breaker.probing = true;
+    Ok(())
+}
+
+/// Update the circuit breaker with the outcome of a call it let through
+fn breaker_record<T>(result: &Result<T, AppError>) {
+    let mut breaker = BREAKER.lock().unwrap_or_else(|e| e.into_inner());
+    let server_down = match result {
+        Ok(_) => false,
+        Err(AppError::Cancelled) => {
+            // The probe never got an answer; let the next call probe instead
+            breaker.probing = false;
+            return;
+        }
+        Err(AppError::ServerError { status, .. }) => *status >= 500,
+        Err(e) => e.is_unreachable(),
+    };
+
+    if !server_down {
+        if breaker.open_until.is_some() {
+            log::info!("Server is back, closing circuit breaker");
+        }
+        *breaker = Breaker::CLOSED;
+        return;
+    }
+
+    breaker.failures += 1;
+    if breaker.probing || breaker.failures >= BREAKER_FAILURE_THRESHOLD {
+        log::warn!(
+            "Server down for {} calls in a row, failing calls fast for {}s",
+            breaker.failures,
+            BREAKER_OPEN_DURATION.as_secs()
+        );
+        breaker.open_until = Some(Instant::now() + BREAKER_OPEN_DURATION);
+    }
+    breaker.probing = false;
+}
+
+/// Generic API request function, bounded by the call deadline and cancellable
 async fn api_request<T: DeserializeOwned>(
     method: reqwest::Method,
     endpoint: &str,
     body: Option<impl Serialize>,
-) -> Result<T, String> {
-    let client = create_client()?;
-    let url = format!("{}{}", API_BASE_URL, endpoint);
+) -> Result<T, AppError> {
+    let idempotency_key = (!is_idempotent(&method)).then(|| Uuid::new_v4().to_string());
+    api_request_with_key(method, endpoint, body, idempotency_key).await
+}
 
-    log::debug!("{} {} - Starting API request", method, endpoint);
+/// [`api_request`] with an idempotency key chosen by the caller
+///
+/// A key derived from what the call does lets the server recognize a repeat
+/// even when the whole call is made again, e.g. after its outcome was lost.
+async fn api_request_with_key<T: DeserializeOwned>(
+    method: reqwest::Method,
+    endpoint: &str,
+    body: Option<impl Serialize>,
+    idempotency_key: Option<String>,
+) -> Result<T, AppError> {
+    let body = body
+        .map(serde_json::to_value)
+        .transpose()
+        .map_err(|e| AppError::Other {
+            message: format!("Failed to serialize request: {}", e),
+        })?;
+    let response = if method == Method::GET && body.is_none() {
+        join_get(endpoint).await?
+    } else {
+        call(method.clone(), endpoint.to_string(), body, idempotency_key).await?
+    };
+    response.parse(&method, endpoint)
+}
 
-    let mut request = client.request(method.clone(), &url);
+/// GET `endpoint`, joining the same GET if one is already in flight
+///
+/// The GET runs as its own task, so it is neither cancelled along with the
+/// frontend call that started it nor stalled when that caller stops waiting.
+async fn join_get(endpoint: &str) -> Result<RawResponse, AppError> {
+    let shared = {
+        let mut in_flight = IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner());
+        match in_flight.get(endpoint) {
+            Some(shared) => {
+                log::debug!("GET {} already in flight, awaiting its response", endpoint);
+                shared.clone()
+            }
+            None => {
+                let key = endpoint.to_string();
+                let task = tauri::async_runtime::spawn(async move {
+                    let result = call(Method::GET, key.clone(), None, None).await;
+                    IN_FLIGHT
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .remove(&key);
+                    result
+                });
+                let shared = async move {
+                    task.await.unwrap_or_else(|e| {
+                        Err(AppError::Other {
+                            message: format!("API call failed: {}", e),
+                        })
+                    })
+                }
+                .boxed()
+                .shared();
+                in_flight.insert(endpoint.to_string(), shared.clone());
+                shared
+            }
+        }
+    };
+    tokio::select! {
+        result = shared => result,
+        _ = frontend_call_cancelled() => {
+            log::info!("Stopped waiting for GET {}: cancelled", endpoint);
+            Err(AppError::Cancelled)
+        }
+    }
+}
 
-    if let Some(b) = body {
-        request = request.json(&b);
+/// Make a call through the circuit breaker, bounded by the call deadline
+async fn call(
+    method: reqwest::Method,
+    endpoint: String,
+    body: Option<serde_json::Value>,
+    idempotency_key: Option<String>,
+) -> Result<RawResponse, AppError> {
+    if let Some(wait) = rate_limit_remaining() {
+        log::debug!("{} {} not sent: rate limited for {:?}", method, endpoint, wait);
+        return Err(AppError::RateLimited {
+            retry_after_secs: wait.as_secs().max(1),
+        });
     }
+    breaker_admit().inspect_err(|e| log::debug!("{} {} not sent: {}", method, endpoint, e))?;
+
+    let mut trace = CallTrace {
+        request_id: Uuid::new_v4().to_string(),
+        started_at: clock::shared().now(),
+        status: None,
+        attempts: 0,
+    };
+    let started = Instant::now();
+    let request_id = trace.request_id.clone();
+    let result = tokio::select! {
+        result = tokio::time::timeout(CALL_DEADLINE, send_request(method.clone(), &endpoint, body, idempotency_key, &mut trace)) => {
+            result.unwrap_or_else(|_| {
+                log::error!("API request timed out for {} {} [{}]", method, endpoint, request_id);
+                Err(AppError::Timeout {
+                    message: t!("error.call_deadline", secs = CALL_DEADLINE.as_secs()).into_owned(),
+                })
+            })
+        }
+        _ = frontend_call_cancelled() => {
+            log::info!("API request cancelled: {} {} [{}]", method, endpoint, request_id);
+            Err(AppError::Cancelled)
+        }
+    };
+    breaker_record(&result);
+    log_call(&trace, &method, &endpoint, started.elapsed(), result.as_ref().err());
+    result.map_err(|e| e.with_request_id(&request_id))
+}
+
+/// Body of a successful response, not yet parsed
+#[derive(Clone)]
+struct RawResponse {
+    request_id: String,
+    body: Arc<str>,
+    /// API version the server answered with, if it said
+    server_version: Option<u32>,
+}
 
-    let response = request.send().await.map_err(|e| {
-        log::error!("API request failed for {} {}: {}", method, endpoint, e);
-        format!("Request failed: {}", e)
+impl RawResponse {
+    /// Parse the body as `T`
+    fn parse<T: DeserializeOwned>(&self, method: &Method, endpoint: &str) -> Result<T, AppError> {
+        parse_response(method, endpoint, &self.request_id, &self.body).map_err(|e| {
+            // A response that doesn't parse is most likely due to the schema of a newer API
+            let server_version = self.server_version.or_else(|| {
+                SERVER_VERSION
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .as_ref()
+                    .map(|v| v.api_version)
+            });
+            let e = match server_version {
+                Some(version) if version != API_VERSION => update_required(Some(version)),
+                _ => e,
+            };
+            e.with_request_id(&self.request_id)
+        })
+    }
+}
+
+/// Correlation ID and outcome of a call, filled in as it progresses
+struct CallTrace {
+    /// Sent as X-Request-Id with every attempt
+    request_id: String,
+    started_at: DateTime<Utc>,
+    /// Status of the last response received
+    status: Option<u16>,
+    attempts: u32,
+}
+
+/// Add a finished call to the log of recent calls
+fn log_call(
+    trace: &CallTrace,
+    method: &Method,
+    endpoint: &str,
+    duration: Duration,
+    error: Option<&AppError>,
+) {
+    let mut calls = CALL_LOG.lock().unwrap_or_else(|e| e.into_inner());
+    calls.push_back(ApiCallLogEntry {
+        request_id: trace.request_id.clone(),
+        method: method.to_string(),
+        endpoint: endpoint.to_string(),
+        started_at: trace.started_at.to_rfc3339(),
+        duration_ms: duration.as_millis().try_into().unwrap_or(u64::MAX),
+        attempts: trace.attempts,
+        status: trace.status,
+        error: error.cloned(),
+    });
+    while calls.len() > MAX_LOGGED_CALLS {
+        calls.pop_front();
+    }
+}
+
+/// Send a request, retrying transient failures, and read the response
+async fn send_request(
+    method: reqwest::Method,
+    endpoint: &str,
+    body: Option<serde_json::Value>,
+    idempotency_key: Option<String>,
+    trace: &mut CallTrace,
+) -> Result<RawResponse, AppError> {
+    let request_id = trace.request_id.clone();
+    if endpoint != VERSION_ENDPOINT {
+        check_server_version()?;
+    }
+    let settings = storage::load_settings().unwrap_or_default();
+    let (base_url, client) = server_connection(&settings, false)?;
+    let url = format!("{}{}", base_url, endpoint);
+    let policy = settings.api_retry;
+    let cached = if method == Method::GET {
+        cached_response(endpoint)
+    } else {
+        None
+    };
+
+    let mut attempt = 1;
+    let result = loop {
+        log::debug!(
+            "{} {} [{}] - Starting API request (attempt {})",
+            method,
+            endpoint,
+            request_id,
+            attempt
+        );
+        trace.attempts = attempt;
+
+        let mut request = client
+            .request(method.clone(), &url)
+            .timeout(ATTEMPT_TIMEOUT)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .header(API_VERSION_HEADER, API_VERSION)
+            .header("X-Request-Id", &request_id);
+        if let Some(key) = &idempotency_key {
+            request = request.header("Idempotency-Key", key);
+        }
+        if let Some(cached) = &cached {
+            request = request.header(reqwest::header::IF_NONE_MATCH, &cached.etag);
+        }
+        if let Some(b) = &body {
+            request = request.json(b);
+        }
+
+        let result = request.send().await;
+        let transient = match &result {
+            Ok(response) => is_transient_status(response.status()),
+            Err(e) => (e.is_connect() || e.is_timeout()) && !pinning::is_pin_mismatch(e),
+        };
+        if !transient || attempt >= policy.max_attempts {
+            break result;
+        }
+        let delay = retry_delay(&policy, attempt);
+        log::warn!(
+            "{} {} [{}] failed transiently (attempt {}/{}), retrying in {:?}",
+            method,
+            endpoint,
+            request_id,
+            attempt,
+            policy.max_attempts,
+            delay
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    };
+
+    let response = result.map_err(|e| {
+        log::error!("API request failed for {} {} [{}]: {}", method, endpoint, request_id, e);
+        if pinning::is_pin_mismatch(&e) {
+            AppError::InsecureConnection {
+                message: t!("error.pin_mismatch").into_owned(),
+            }
+        } else if e.is_timeout() {
+            AppError::Timeout {
+                message: t!("error.timeout", error = e).into_owned(),
+            }
+        } else {
+            AppError::NetworkError {
+                message: t!("error.unreachable", error = e).into_owned(),
+            }
+        }
     })?;
 
     let status = response.status();
+    trace.status = Some(status.as_u16());
+
+    let server_version = response
+        .headers()
+        .get(API_VERSION_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u32>().ok());
+
+    if let (StatusCode::NOT_MODIFIED, Some(cached)) = (status, cached) {
+        log::debug!(
+            "API response unchanged, using cache: {} {} [{}]",
+            method,
+            endpoint,
+            request_id
+        );
+        return Ok(RawResponse {
+            request_id,
+            body: cached.body.into(),
+            server_version,
+        });
+    }
+
+    if matches!(status, StatusCode::NOT_ACCEPTABLE | StatusCode::UPGRADE_REQUIRED) {
+        log::error!(
+            "Server rejected API version {} for {} {} [{}]",
+            API_VERSION,
+            method,
+            endpoint,
+            request_id
+        );
+        return Err(update_required(server_version));
+    }
+
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = retry_after_secs(response.headers());
+        *RATE_LIMITED_UNTIL.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some(Instant::now() + Duration::from_secs(retry_after));
+        log::warn!(
+            "API rate limited {} {} [{}], retry after {}s",
+            method,
+            endpoint,
+            request_id,
+            retry_after
+        );
+        return Err(AppError::RateLimited {
+            retry_after_secs: retry_after,
+        });
+    }
 
     if !status.is_success() {
         let error_text = response
@@ -49,36 +929,146 @@ async fn api_request<T: DeserializeOwned>(
             .await
             .unwrap_or_else(|_| "Unknown error".to_string());
         log::error!(
-            "API request returned error status {} for {} {}: {}",
+            "API request returned error status {} for {} {} [{}]: {}",
             status,
             method,
             endpoint,
+            request_id,
             error_text
         );
-        return Err(format!("API error {}: {}", status, error_text));
+        return Err(AppError::from_status(status.as_u16(), error_text));
     }
 
-    log::debug!("API request succeeded: {} {} (status: {})", method, endpoint, status);
+    log::debug!(
+        "API request succeeded: {} {} [{}] (status: {})",
+        method,
+        endpoint,
+        request_id,
+        status
+    );
 
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
     let response_text = response.text().await.map_err(|e| {
-        log::error!("Failed to read API response body for {} {}: {}", method, endpoint, e);
-        format!("Failed to read response: {}", e)
+        log::error!(
+            "Failed to read API response body for {} {} [{}]: {}",
+            method,
+            endpoint,
+            request_id,
+            e
+        );
+        AppError::NetworkError {
+            message: format!("Failed to read response: {}", e),
+        }
     })?;
 
     log::trace!("API response body: {}", response_text);
 
-    serde_json::from_str::<T>(&response_text).map_err(|e| {
+    let body: Arc<str> = response_text.into();
+    if let Some(etag) = etag.filter(|_| method == Method::GET) {
+        store_response(endpoint, etag, body.to_string());
+    }
+    Ok(RawResponse {
+        request_id,
+        body,
+        server_version,
+    })
+}
+
+/// Parse a response body, logging it when it doesn't match `T`
+fn parse_response<T: DeserializeOwned>(
+    method: &Method,
+    endpoint: &str,
+    request_id: &str,
+    response_text: &str,
+) -> Result<T, AppError> {
+    serde_json::from_str::<T>(response_text).map_err(|e| {
         log::error!(
-            "Failed to parse API response for {} {}: {}. Response body: {}",
+            "Failed to parse API response for {} {} [{}]: {}. Response body: {}",
             method,
             endpoint,
+            request_id,
             e,
             response_text
         );
-        format!("Failed to parse response: {}. Response: {}", e, response_text)
+        AppError::ParseError {
+            message: format!("Failed to parse response: {}. Response: {}", e, response_text),
+        }
     })
 }
 
+/// Cached response for a GET of `endpoint`, if there is one
+fn cached_response(endpoint: &str) -> Option<CachedResponse> {
+    storage::load_http_cache()
+        .inspect_err(|e| log::warn!("Failed to load HTTP cache: {}", e))
+        .ok()?
+        .remove(endpoint)
+}
+
+/// Keep the body of a GET response for the next request of `endpoint`
+fn store_response(endpoint: &str, etag: String, body: String) {
+    let _guard = HTTP_CACHE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut cache = storage::load_http_cache().unwrap_or_default();
+    cache.insert(
+        endpoint.to_string(),
+        CachedResponse {
+            etag,
+            body,
+            stored_at: clock::shared().now().to_rfc3339(),
+        },
+    );
+    while cache.len() > MAX_CACHED_RESPONSES {
+        let Some(oldest) = cache
+            .iter()
+            .min_by(|a, b| a.1.stored_at.cmp(&b.1.stored_at))
+            .map(|(key, _)| key.clone())
+        else {
+            break;
+        };
+        cache.remove(&oldest);
+    }
+    if let Err(e) = storage::save_http_cache(&cache) {
+        log::warn!("Failed to save HTTP cache: {}", e);
+    }
+}
+
+/// Check whether repeating a request has the same effect as sending it once
+fn is_idempotent(method: &Method) -> bool {
+    [
+        Method::GET,
+        Method::HEAD,
+        Method::PUT,
+        Method::DELETE,
+        Method::OPTIONS,
+    ]
+    .contains(method)
+}
+
+/// Check whether a response status is worth retrying
+///
+/// 429 is left out on purpose: the caller decides how to honour the server's wait.
+fn is_transient_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::REQUEST_TIMEOUT
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Delay before the retry following `attempt`, jittered between half and all of the backoff
+fn retry_delay(policy: &ApiRetryPolicy, attempt: u32) -> Duration {
+    let backoff = policy
+        .base_delay_ms
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(policy.max_delay_ms);
+    Duration::from_millis(rand::random_range(backoff / 2..=backoff))
+}
+
 // =============================================================================
 // Device APIs
 // =============================================================================
@@ -88,7 +1078,7 @@ pub async fn register_device(
     device_name: &str,
     imei: Option<&str>,
     mode: DeviceMode,
-) -> Result<Device, String> {
+) -> Result<Device, AppError> {
     log::info!("Registering device: {} (mode: {:?})", device_name, mode);
     #[derive(Serialize)]
     struct RequestBody {
@@ -107,14 +1097,14 @@ pub async fn register_device(
 }
 
 /// Get device info
-pub async fn get_device(device_id: &str) -> Result<Device, String> {
+pub async fn get_device(device_id: &str) -> Result<Device, AppError> {
     log::info!("Getting device info: {}", device_id);
     let endpoint = format!("/devices/{}", device_id);
     api_request(reqwest::Method::GET, &endpoint, None::<()>).await
 }
 
 /// Update device name
-pub async fn update_device_name(device_id: &str, new_name: &str) -> Result<Device, String> {
+pub async fn update_device_name(device_id: &str, new_name: &str) -> Result<Device, AppError> {
     log::info!("Updating device name: {} -> {}", device_id, new_name);
     #[derive(Serialize)]
     struct RequestBody {
@@ -129,22 +1119,221 @@ pub async fn update_device_name(device_id: &str, new_name: &str) -> Result<Devic
     api_request(reqwest::Method::PATCH, &endpoint, Some(body)).await
 }
 
-/// Sign in for a device
-pub async fn device_signin(device_id: &str) -> Result<SigninResponse, String> {
+/// Sign in for a device on the sign-in day `day`, at `signed_at` when
+/// replaying a sign-in made offline
+///
+/// The idempotency key is derived from the device and the sign-in day, so a
+/// sign-in sent again after its outcome was lost is not counted twice, and a
+/// server answering that the day is already signed in counts as success.
+pub async fn device_signin(
+    device_id: &str,
+    signed_at: Option<&str>,
+    day: NaiveDate,
+) -> Result<SigninResponse, AppError> {
     log::info!("Device sign-in: {}", device_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        signed_at: Option<String>,
+    }
+
+    let body = RequestBody {
+        signed_at: signed_at.map(str::to_string),
+    };
+
+    let idempotency_key = format!("signin:{}:{}", device_id, day);
+
     let endpoint = format!("/devices/{}/signin", device_id);
+    match api_request_with_key(reqwest::Method::POST, &endpoint, Some(body), Some(idempotency_key))
+        .await
+    {
+        Err(AppError::Conflict { message }) => {
+            log::info!("{} is already signed in for {}: {}", device_id, day, message);
+            let status = get_device_status(device_id).await?;
+            Ok(SigninResponse {
+                streak: status.streak,
+            })
+        }
+        result => result,
+    }
+}
+
+/// Tell the server the device is running
+pub async fn send_heartbeat(device_id: &str) -> Result<(), AppError> {
+    log::debug!("Sending heartbeat for {}", device_id);
+    let endpoint = format!("/devices/{}/heartbeat", device_id);
     api_request(reqwest::Method::POST, &endpoint, None::<()>).await
 }
 
 /// Get device status
-pub async fn get_device_status(device_id: &str) -> Result<DeviceStatus, String> {
+pub async fn get_device_status(device_id: &str) -> Result<DeviceStatus, AppError> {
     log::info!("Getting device status: {}", device_id);
     let endpoint = format!("/devices/{}/status", device_id);
     api_request(reqwest::Method::GET, &endpoint, None::<()>).await
 }
 
+/// Get the status of several devices in one request
+///
+/// Servers without the batch endpoint are asked one request per device, in
+/// parallel. Devices whose status couldn't be fetched are left out, unless
+/// none could be fetched at all.
+pub async fn get_device_statuses(device_ids: &[String]) -> Result<Vec<DeviceStatus>, AppError> {
+    log::info!("Getting status of {} devices", device_ids.len());
+    if device_ids.is_empty() {
+        return Ok(vec![]);
+    }
+    if !BATCH_STATUS_UNSUPPORTED.load(Ordering::Relaxed) {
+        #[derive(Serialize)]
+        struct RequestBody<'a> {
+            device_ids: &'a [String],
+        }
+        let body = RequestBody { device_ids };
+        match api_request(reqwest::Method::POST, "/devices/status", Some(body)).await {
+            Err(AppError::NotFound { .. } | AppError::ServerError { status: 405, .. }) => {
+                log::info!("Server has no batch status endpoint, fetching devices one by one");
+                BATCH_STATUS_UNSUPPORTED.store(true, Ordering::Relaxed);
+            }
+            result => return result,
+        }
+    }
+
+    let results =
+        futures::future::join_all(device_ids.iter().map(|id| get_device_status(id))).await;
+    let mut statuses = Vec::with_capacity(results.len());
+    let mut first_error = None;
+    for (device_id, result) in device_ids.iter().zip(results) {
+        match result {
+            Ok(status) => statuses.push(status),
+            Err(e) => {
+                log::warn!("Failed to get status of device {}: {}", device_id, e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    match first_error {
+        Some(e) if statuses.is_empty() => Err(e),
+        _ => Ok(statuses),
+    }
+}
+
+/// Get the sign-in records of a device for the last `days` days
+pub async fn get_signin_records_api(
+    device_id: &str,
+    days: u32,
+) -> Result<Vec<SigninRecord>, AppError> {
+    log::info!("Getting sign-in records via API for {}", device_id);
+    let endpoint = format!("/devices/{}/signins?days={}", device_id, days);
+    api_request(reqwest::Method::GET, &endpoint, None::<()>).await
+}
+
+/// Get the sign-in records of a device between `from` and `to`, both inclusive
+///
+/// Dates are `YYYY-MM-DD`. Unlike [`get_signin_records_api`] the range can
+/// reach back further than the local history, e.g. after a reinstall.
+pub async fn get_device_signin_history(
+    device_id: &str,
+    from: &str,
+    to: &str,
+) -> Result<Vec<SigninRecord>, AppError> {
+    log::info!("Getting sign-in history via API for {} from {} to {}", device_id, from, to);
+    let endpoint = format!(
+        "/devices/{}/signins?from={}&to={}",
+        device_id,
+        urlencoding::encode(from),
+        urlencoding::encode(to)
+    );
+    api_request(reqwest::Method::GET, &endpoint, None::<()>).await
+}
+
+/// Get a device's sign-in records for the last `days` days as shared with a supervisor
+///
+/// The server reports the device's sharing policy for this supervisor along
+/// with the records, and only returns records when that policy includes the history.
+pub async fn get_shared_history_api(
+    device_id: &str,
+    supervisor_id: &str,
+    days: u32,
+) -> Result<SharedHistory, AppError> {
+    log::info!("Getting shared sign-in history via API for {}", device_id);
+    let endpoint =
+        format!("/devices/{}/history?supervisor_id={}&days={}", device_id, supervisor_id, days);
+    api_request(reqwest::Method::GET, &endpoint, None::<()>).await
+}
+
+/// Report a missed check-in so the server can alert every supervisor of the device
+pub async fn report_missed_checkin(device_id: &str, date: &str) -> Result<(), AppError> {
+    log::info!("Reporting missed check-in via API: {} on {}", device_id, date);
+    #[derive(Serialize)]
+    struct RequestBody {
+        date: String,
+    }
+
+    let body = RequestBody {
+        date: date.to_string(),
+    };
+
+    let endpoint = format!("/devices/{}/missed-checkin", device_id);
+    api_request(reqwest::Method::POST, &endpoint, Some(body)).await
+}
+
+/// Upload a crash report the user agreed to share
+pub async fn submit_crash_report(report: &CrashReport) -> Result<(), AppError> {
+    log::info!("Submitting crash report {}", report.id);
+    api_request(reqwest::Method::POST, "/crash-reports", Some(report)).await
+}
+
+/// Raise an SOS so the server can alert every supervisor of the device
+pub async fn send_sos_api(
+    device_id: &str,
+    message: Option<&str>,
+    sent_at: &str,
+) -> Result<(), AppError> {
+    log::info!("Sending SOS via API for {}", device_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        message: Option<String>,
+        sent_at: String,
+    }
+
+    let body = RequestBody {
+        message: message.map(|m| m.to_string()),
+        sent_at: sent_at.to_string(),
+    };
+
+    let endpoint = format!("/devices/{}/sos", device_id);
+    api_request(reqwest::Method::POST, &endpoint, Some(body)).await
+}
+
+/// Publish the status a supervisor is allowed to see
+pub async fn publish_shared_status(device_id: &str, status: &SharedStatus) -> Result<(), AppError> {
+    log::info!(
+        "Publishing shared status via API: {} -> {} ({:?})",
+        device_id,
+        status.supervisor_id,
+        status.policy
+    );
+    let endpoint = format!("/devices/{}/shared-status", device_id);
+    api_request(reqwest::Method::PUT, &endpoint, Some(status)).await
+}
+
+/// Pause or resume a device's status updates to its supervisors
+pub async fn set_sharing_pause_api(device_id: &str, until: Option<&str>) -> Result<(), AppError> {
+    log::info!("Setting sharing pause via API for {}: {:?}", device_id, until);
+    #[derive(Serialize)]
+    struct RequestBody {
+        until: Option<String>,
+    }
+
+    let body = RequestBody {
+        until: until.map(str::to_string),
+    };
+
+    let endpoint = format!("/devices/{}/pause", device_id);
+    api_request(reqwest::Method::PUT, &endpoint, Some(body)).await
+}
+
 /// Search devices
-pub async fn search_devices(query: &str) -> Result<DeviceSearchResponse, String> {
+pub async fn search_devices(query: &str) -> Result<DeviceSearchResponse, AppError> {
     log::info!("Searching devices with query: {}", query);
     let endpoint = format!("/search/devices?q={}", urlencoding::encode(query));
     api_request(reqwest::Method::GET, &endpoint, None::<()>).await
@@ -158,44 +1347,235 @@ pub async fn search_devices(query: &str) -> Result<DeviceSearchResponse, String>
 pub async fn send_supervision_request_api(
     supervisor_id: &str,
     target_id: &str,
-) -> Result<SupervisionRequest, String> {
+    message: Option<&str>,
+    public_key: Option<&str>,
+    identified_by: RequestIdentifier,
+) -> Result<SupervisionRequest, AppError> {
     log::info!("Sending supervision request via API: {} -> {}", supervisor_id, target_id);
     #[derive(Serialize)]
     struct RequestBody {
         supervisor_id: String,
         target_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        public_key: Option<String>,
+        identified_by: RequestIdentifier,
     }
 
     let body = RequestBody {
         supervisor_id: supervisor_id.to_string(),
         target_id: target_id.to_string(),
+        message: message.map(str::to_string),
+        public_key: public_key.map(str::to_string),
+        identified_by,
     };
 
     api_request(reqwest::Method::POST, "/supervision/request", Some(body)).await
 }
 
+/// Issue a short-lived pairing token for a device
+pub async fn create_pairing_token(
+    device_id: &str,
+    public_key: Option<&str>,
+) -> Result<PairingToken, AppError> {
+    log::info!("Creating pairing token via API for {}", device_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        public_key: Option<String>,
+    }
+
+    let body = RequestBody {
+        public_key: public_key.map(str::to_string),
+    };
+
+    let endpoint = format!("/devices/{}/pairing-token", device_id);
+    api_request(reqwest::Method::POST, &endpoint, Some(body)).await
+}
+
+/// Establish supervision using a target device's pairing token
+pub async fn pair_with_token(
+    supervisor_id: &str,
+    target_id: &str,
+    token: &str,
+    public_key: Option<&str>,
+) -> Result<SupervisionRelation, AppError> {
+    log::info!("Pairing via API: {} -> {}", supervisor_id, target_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        supervisor_id: String,
+        target_id: String,
+        token: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        public_key: Option<String>,
+    }
+
+    let body = RequestBody {
+        supervisor_id: supervisor_id.to_string(),
+        target_id: target_id.to_string(),
+        token: token.to_string(),
+        public_key: public_key.map(str::to_string),
+    };
+
+    api_request(reqwest::Method::POST, "/supervision/pair", Some(body)).await
+}
+
+/// Issue an invitation code for a device, valid for `valid_minutes`
+pub async fn create_invite_code_api(
+    device_id: &str,
+    valid_minutes: u32,
+) -> Result<InviteCode, AppError> {
+    log::info!("Creating invite code via API for {}", device_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        valid_minutes: u32,
+    }
+
+    let body = RequestBody { valid_minutes };
+
+    let endpoint = format!("/devices/{}/invite-code", device_id);
+    api_request(reqwest::Method::POST, &endpoint, Some(body)).await
+}
+
+/// Send a supervision request to the device that issued an invitation code
+pub async fn redeem_invite_code_api(
+    supervisor_id: &str,
+    code: &str,
+    public_key: Option<&str>,
+) -> Result<SupervisionRequest, AppError> {
+    log::info!("Redeeming invite code via API for {}", supervisor_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        supervisor_id: String,
+        code: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        public_key: Option<String>,
+    }
+
+    let body = RequestBody {
+        supervisor_id: supervisor_id.to_string(),
+        code: code.to_string(),
+        public_key: public_key.map(str::to_string),
+    };
+
+    api_request(reqwest::Method::POST, "/supervision/invite/redeem", Some(body)).await
+}
+
+/// Stop a device from sending supervision requests to this one
+pub async fn block_device_api(device_id: &str, blocked_id: &str) -> Result<(), AppError> {
+    log::info!("Blocking device via API: {} blocks {}", device_id, blocked_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        device_id: String,
+    }
+
+    let body = RequestBody {
+        device_id: blocked_id.to_string(),
+    };
+
+    let endpoint = format!("/devices/{}/blocked", device_id);
+    api_request(reqwest::Method::POST, &endpoint, Some(body)).await
+}
+
+/// Allow a blocked device to send supervision requests again
+pub async fn unblock_device_api(device_id: &str, blocked_id: &str) -> Result<(), AppError> {
+    log::info!("Unblocking device via API: {} unblocks {}", device_id, blocked_id);
+    let endpoint = format!("/devices/{}/blocked/{}", device_id, blocked_id);
+    api_request(reqwest::Method::DELETE, &endpoint, None::<()>).await
+}
+
+/// Issue a code that moves a relationship to another supervisor device, valid for `valid_minutes`
+pub async fn initiate_transfer_api(
+    relation_id: &str,
+    supervisor_id: &str,
+    valid_minutes: u32,
+) -> Result<TransferCode, AppError> {
+    log::info!("Initiating supervision transfer via API for {}", relation_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        supervisor_id: String,
+        valid_minutes: u32,
+    }
+
+    let body = RequestBody {
+        supervisor_id: supervisor_id.to_string(),
+        valid_minutes,
+    };
+
+    let endpoint = format!("/supervision/{}/transfer", relation_id);
+    api_request(reqwest::Method::POST, &endpoint, Some(body)).await
+}
+
+/// Take over the relationship a transfer code was issued for
+pub async fn accept_transfer_api(
+    supervisor_id: &str,
+    code: &str,
+    public_key: Option<&str>,
+) -> Result<SupervisionRelation, AppError> {
+    log::info!("Accepting supervision transfer via API for {}", supervisor_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        supervisor_id: String,
+        code: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        public_key: Option<String>,
+    }
+
+    let body = RequestBody {
+        supervisor_id: supervisor_id.to_string(),
+        code: code.to_string(),
+        public_key: public_key.map(str::to_string),
+    };
+
+    api_request(reqwest::Method::POST, "/supervision/transfer/accept", Some(body)).await
+}
+
 /// Get pending supervision requests
-pub async fn get_pending_requests(device_id: &str) -> Result<PendingRequestsResponse, String> {
+pub async fn get_pending_requests(device_id: &str) -> Result<PendingRequestsResponse, AppError> {
     log::info!("Getting pending supervision requests via API for {}", device_id);
     let endpoint = format!("/supervision/pending/{}", device_id);
     api_request(reqwest::Method::GET, &endpoint, None::<()>).await
 }
 
+/// Get the pending supervision requests changed since `since`, or all without a cursor
+pub async fn get_pending_requests_since(
+    device_id: &str,
+    since: Option<&str>,
+) -> Result<ListResponse<SupervisionRequest>, AppError> {
+    log::info!("Getting pending supervision request changes for {}", device_id);
+    let endpoint = with_since(format!("/supervision/pending/{}", device_id), since);
+    api_request(reqwest::Method::GET, &endpoint, None::<()>).await
+}
+
 /// Accept supervision request
+///
+/// The request ID and the identifier the user was shown let the server check
+/// that the acceptance refers to the request the user actually reviewed.
 pub async fn accept_supervision_request_api(
     supervisor_id: &str,
     target_id: &str,
-) -> Result<(), String> {
+    request_id: &str,
+    identified_by: RequestIdentifier,
+    public_key: Option<&str>,
+) -> Result<(), AppError> {
     log::info!("Accepting supervision request via API: {} -> {}", supervisor_id, target_id);
     #[derive(Serialize)]
     struct RequestBody {
         supervisor_id: String,
         target_id: String,
+        request_id: String,
+        identified_by: RequestIdentifier,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        public_key: Option<String>,
     }
 
     let body = RequestBody {
         supervisor_id: supervisor_id.to_string(),
         target_id: target_id.to_string(),
+        request_id: request_id.to_string(),
+        identified_by,
+        public_key: public_key.map(str::to_string),
     };
 
     api_request(reqwest::Method::POST, "/supervision/accept", Some(body)).await
@@ -205,7 +1585,7 @@ pub async fn accept_supervision_request_api(
 pub async fn reject_supervision_request_api(
     supervisor_id: &str,
     target_id: &str,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     log::info!("Rejecting supervision request via API: {} -> {}", supervisor_id, target_id);
     #[derive(Serialize)]
     struct RequestBody {
@@ -221,16 +1601,251 @@ pub async fn reject_supervision_request_api(
     api_request(reqwest::Method::POST, "/supervision/reject", Some(body)).await
 }
 
+/// Mark a pending supervision request as expired
+pub async fn expire_supervision_request_api(request_id: &str) -> Result<(), AppError> {
+    log::info!("Expiring supervision request via API: {}", request_id);
+    let endpoint = format!("/supervision/requests/{}/expire", request_id);
+    api_request(reqwest::Method::POST, &endpoint, None::<()>).await
+}
+
 /// Get supervision relationship list
-pub async fn get_supervision_list(device_id: &str) -> Result<SupervisionListResponse, String> {
+pub async fn get_supervision_list(device_id: &str) -> Result<SupervisionListResponse, AppError> {
     log::info!("Getting supervision list via API for {}", device_id);
     let endpoint = format!("/supervision/list/{}", device_id);
     api_request(reqwest::Method::GET, &endpoint, None::<()>).await
 }
 
+/// Get the supervision relations changed since `since`, or all without a cursor
+pub async fn get_supervision_list_since(
+    device_id: &str,
+    since: Option<&str>,
+) -> Result<ListResponse<SupervisionRelation>, AppError> {
+    log::info!("Getting supervision list changes for {}", device_id);
+    let endpoint = with_since(format!("/supervision/list/{}", device_id), since);
+    api_request(reqwest::Method::GET, &endpoint, None::<()>).await
+}
+
+/// Add the `since` cursor of a delta fetch to `endpoint`
+fn with_since(endpoint: String, since: Option<&str>) -> String {
+    match since {
+        Some(since) => format!("{}?since={}", endpoint, urlencoding::encode(since)),
+        None => endpoint,
+    }
+}
+
+/// Ask a supervised device whether they are OK
+pub async fn send_checkin_ping_api(
+    supervisor_id: &str,
+    target_id: &str,
+) -> Result<CheckinPing, AppError> {
+    log::info!("Sending check-in ping via API: {} -> {}", supervisor_id, target_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        supervisor_id: String,
+    }
+
+    let body = RequestBody {
+        supervisor_id: supervisor_id.to_string(),
+    };
+
+    let endpoint = format!("/devices/{}/pings", target_id);
+    api_request(reqwest::Method::POST, &endpoint, Some(body)).await
+}
+
+/// Answer a check-in ping
+pub async fn respond_checkin_ping_api(ping_id: &str) -> Result<CheckinPing, AppError> {
+    log::info!("Responding to check-in ping via API: {}", ping_id);
+    let endpoint = format!("/pings/{}/respond", ping_id);
+    api_request(reqwest::Method::POST, &endpoint, None::<()>).await
+}
+
+/// Ask a supervised device to show an extra check-in reminder
+///
+/// The server enforces its own rate limit and answers with an error when it is exceeded.
+pub async fn send_nudge_api(supervisor_id: &str, target_id: &str) -> Result<Nudge, AppError> {
+    log::info!("Sending nudge via API: {} -> {}", supervisor_id, target_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        supervisor_id: String,
+    }
+
+    let body = RequestBody {
+        supervisor_id: supervisor_id.to_string(),
+    };
+
+    let endpoint = format!("/devices/{}/nudges", target_id);
+    api_request(reqwest::Method::POST, &endpoint, Some(body)).await
+}
+
+/// Leave an encouragement for a supervised device, in plain text or sealed for it
+pub async fn send_encouragement_api(
+    sender_id: &str,
+    target_id: &str,
+    text: Option<&str>,
+    sealed_text: Option<&str>,
+) -> Result<Encouragement, AppError> {
+    log::info!("Sending encouragement via API: {} -> {}", sender_id, target_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        sender_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sealed_text: Option<String>,
+    }
+
+    let body = RequestBody {
+        sender_id: sender_id.to_string(),
+        text: text.map(str::to_string),
+        sealed_text: sealed_text.map(str::to_string),
+    };
+
+    let endpoint = format!("/devices/{}/encouragements", target_id);
+    api_request(reqwest::Method::POST, &endpoint, Some(body)).await
+}
+
+/// Get encouragements not yet delivered to a device
+pub async fn get_encouragements_api(device_id: &str) -> Result<Vec<Encouragement>, AppError> {
+    log::info!("Getting undelivered encouragements via API for {}", device_id);
+    let endpoint = format!("/devices/{}/encouragements", device_id);
+    api_request(reqwest::Method::GET, &endpoint, None::<()>).await
+}
+
+/// Mark encouragements as shown so the server stops returning them
+pub async fn mark_encouragements_delivered_api(
+    device_id: &str,
+    message_ids: &[String],
+) -> Result<(), AppError> {
+    log::info!(
+        "Marking {} encouragements delivered via API for {}",
+        message_ids.len(),
+        device_id
+    );
+    #[derive(Serialize)]
+    struct RequestBody {
+        message_ids: Vec<String>,
+    }
+
+    let body = RequestBody {
+        message_ids: message_ids.to_vec(),
+    };
+
+    let endpoint = format!("/devices/{}/encouragements/delivered", device_id);
+    api_request(reqwest::Method::POST, &endpoint, Some(body)).await
+}
+
+/// Replace the wellness questions a supervisor asks a supervised device
+pub async fn set_wellness_questions_api(
+    supervisor_id: &str,
+    target_id: &str,
+    questions: &[String],
+) -> Result<Vec<WellnessQuestion>, AppError> {
+    log::info!("Setting wellness questions via API: {} -> {}", supervisor_id, target_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        supervisor_id: String,
+        target_id: String,
+        questions: Vec<String>,
+    }
+
+    let body = RequestBody {
+        supervisor_id: supervisor_id.to_string(),
+        target_id: target_id.to_string(),
+        questions: questions.to_vec(),
+    };
+
+    api_request(reqwest::Method::PUT, "/supervision/questions", Some(body)).await
+}
+
+/// Set or clear the end of a temporary supervision
+pub async fn set_relationship_valid_until_api(
+    supervisor_id: &str,
+    target_id: &str,
+    valid_until: Option<&str>,
+) -> Result<(), AppError> {
+    log::info!("Setting supervision end via API: {} -> {}", supervisor_id, target_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        supervisor_id: String,
+        target_id: String,
+        valid_until: Option<String>,
+    }
+
+    let body = RequestBody {
+        supervisor_id: supervisor_id.to_string(),
+        target_id: target_id.to_string(),
+        valid_until: valid_until.map(str::to_string),
+    };
+
+    api_request(reqwest::Method::PUT, "/supervision/valid-until", Some(body)).await
+}
+
+/// Set how many consecutive missed days trigger an alert for a relationship
+pub async fn set_missed_days_threshold_api(
+    supervisor_id: &str,
+    target_id: &str,
+    days: Option<u32>,
+) -> Result<(), AppError> {
+    log::info!("Setting missed-days threshold via API: {} -> {}", supervisor_id, target_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        supervisor_id: String,
+        target_id: String,
+        days: Option<u32>,
+    }
+
+    let body = RequestBody {
+        supervisor_id: supervisor_id.to_string(),
+        target_id: target_id.to_string(),
+        days,
+    };
+
+    api_request(reqwest::Method::PUT, "/supervision/alert-threshold", Some(body)).await
+}
+
+/// Get the wellness questions all supervisors ask a device
+pub async fn get_wellness_questions_api(
+    device_id: &str,
+) -> Result<Vec<WellnessQuestion>, AppError> {
+    log::info!("Getting wellness questions via API for {}", device_id);
+    let endpoint = format!("/devices/{}/questions", device_id);
+    api_request(reqwest::Method::GET, &endpoint, None::<()>).await
+}
+
+/// Submit the wellness answers given at a check-in
+pub async fn submit_wellness_answers_api(
+    device_id: &str,
+    date: &str,
+    answers: &[WellnessAnswer],
+) -> Result<(), AppError> {
+    log::info!("Submitting {} wellness answers via API for {}", answers.len(), device_id);
+    #[derive(Serialize)]
+    struct RequestBody {
+        date: String,
+        answers: Vec<WellnessAnswer>,
+    }
+
+    let body = RequestBody {
+        date: date.to_string(),
+        answers: answers.to_vec(),
+    };
+
+    let endpoint = format!("/devices/{}/answers", device_id);
+    api_request(reqwest::Method::POST, &endpoint, Some(body)).await
+}
+
 /// Remove supervision relationship
-pub async fn remove_supervision_relationship_api(relation_id: &str) -> Result<(), String> {
+pub async fn remove_supervision_relationship_api(relation_id: &str) -> Result<(), AppError> {
     log::info!("Removing supervision relationship via API: {}", relation_id);
     let endpoint = format!("/supervision/{}", relation_id);
     api_request(reqwest::Method::DELETE, &endpoint, None::<()>).await
 }
+
+/// Get the event timeline of a supervision relationship
+pub async fn get_supervision_events_api(
+    relation_id: &str,
+) -> Result<Vec<SupervisionEvent>, AppError> {
+    log::info!("Getting supervision events via API for {}", relation_id);
+    let endpoint = format!("/supervision/{}/events", relation_id);
+    api_request(reqwest::Method::GET, &endpoint, None::<()>).await
+}
@@ -0,0 +1,304 @@
+//! Real-time updates pushed by the server.
+//!
+//! The server streams events for a device as Server-Sent Events. This module
+//! keeps that connection open, forwards every event to the frontend as a
+//! `server-event` Tauri event and reconnects with exponential backoff whenever
+//! the connection drops. Events that change supervision state also trigger a
+//! sync, so the snapshot is current without waiting for the next poll, while
+//! missed check-ins, answers to sent requests, pings, nudges, SOS alerts and
+//! encouragements are handed to their modules.
+//!
+//! The ID of the last event received is sent as `Last-Event-ID` when
+//! reconnecting, so the server can replay what was pushed while the
+//! connection was down instead of it being lost until the next poll. A
+//! connection that stays silent for longer than [`IDLE_TIMEOUT`] is taken for
+//! dead and reopened, since a half-open connection would otherwise never end.
+
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::server_connection;
+use crate::alerts;
+use crate::encouragements;
+use crate::error::AppError;
+use crate::nudges;
+use crate::pings;
+use crate::remote_models::ServerEvent;
+use crate::request_limits;
+use crate::sos;
+use crate::state::AppState;
+use crate::storage;
+use crate::supervision;
+use crate::sync_queue;
+
+/// Delay before the first reconnect attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Upper bound for the reconnect delay
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Longest silence before the stream is reconnected
+///
+/// The server is expected to send keep-alive comments more often, so only a
+/// connection that is gone without being closed stays quiet for this long.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Server events after which the supervision snapshot is refreshed
+const SYNC_EVENTS: [&str; 4] = [
+    "signin",
+    "supervision_request",
+    "supervision_accepted",
+    "supervision_removed",
+];
+
+/// Start the background task that holds the event stream open
+pub fn spawn_event_stream(app: AppHandle) {
+    log::info!("Starting server event stream");
+    tauri::async_runtime::spawn(async move {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut last_event_id = None;
+        loop {
+            match stream_events(&app, &mut backoff, &mut last_event_id).await {
+                Ok(()) => log::info!("Server event stream closed"),
+                Err(e) => log::warn!("Server event stream failed: {}", e),
+            }
+            log::debug!("Reconnecting to server event stream in {:?}", backoff);
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    });
+}
+
+/// Connect and forward events until the server closes the stream
+///
+/// The backoff is reset once a connection is established, so only consecutive
+/// failures make the reconnect delay grow. `last_event_id` is resumed from and
+/// kept up to date for the next connection.
+async fn stream_events(
+    app: &AppHandle,
+    backoff: &mut Duration,
+    last_event_id: &mut Option<String>,
+) -> Result<(), AppError> {
+    let config = app
+        .state::<AppState>()
+        .device_config()
+        .map_err(|e| e.to_string())?;
+    let endpoint = format!("/events/{}", config.device.device_id);
+    let settings = storage::load_settings().unwrap_or_default();
+    let (base_url, client) = server_connection(&settings, true)?;
+    let url = format!("{}{}", base_url, endpoint);
+
+    let mut request = client
+        .get(&url)
+        .header(reqwest::header::ACCEPT, "text/event-stream");
+    if let Some(id) = last_event_id.as_deref() {
+        log::debug!("Resuming server event stream after event {}", id);
+        request = request.header("Last-Event-ID", id);
+    }
+    let mut response = request.send().await.map_err(|e| AppError::NetworkError {
+        message: format!("Request failed: {}", e),
+    })?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(AppError::from_status(status.as_u16(), format!("API error {}", status)));
+    }
+
+    log::info!("Connected to server event stream for {}", config.device.device_id);
+    *backoff = INITIAL_BACKOFF;
+
+    // Back online, so send what was queued meanwhile
+    let replay_app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = sync_queue::replay(&replay_app).await {
+            log::warn!("Sync queue replay failed: {}", e);
+        }
+    });
+
+    let mut parser = EventStreamParser {
+        last_event_id: last_event_id.clone(),
+        ..Default::default()
+    };
+    loop {
+        let chunk = tokio::time::timeout(IDLE_TIMEOUT, response.chunk())
+            .await
+            .map_err(|_| AppError::NetworkError {
+                message: format!("No event stream data for {:?}", IDLE_TIMEOUT),
+            })?
+            .map_err(|e| AppError::NetworkError {
+                message: format!("Failed to read event stream: {}", e),
+            })?;
+        let Some(chunk) = chunk else {
+            break;
+        };
+        for event in parser.feed(&chunk) {
+            forward(app, event).await;
+        }
+        last_event_id.clone_from(&parser.last_event_id);
+    }
+    Ok(())
+}
+
+/// Emit a server event to the frontend and act on the events that need it
+async fn forward(app: &AppHandle, event: ServerEvent) {
+    log::debug!("Server event: {}", event.event);
+    match event.event.as_str() {
+        "missed_checkin" => alerts::handle_missed_checkin_event(app, &event.data).await,
+        "supervision_accepted" | "supervision_rejected" => {
+            request_limits::handle_request_answered_event(app, &event.data)
+        }
+        "checkin_ping" => pings::handle_ping_event(app, &event.data),
+        "checkin_ping_response" => pings::handle_ping_response_event(app, &event.data),
+        "nudge" => nudges::handle_nudge_event(app, &event.data),
+        "sos" => sos::handle_sos_event(app, &event.data).await,
+        "encouragement" => encouragements::handle_encouragement_event(app, &event.data),
+        _ => {}
+    }
+    let refresh = SYNC_EVENTS.contains(&event.event.as_str());
+    if let Err(e) = app.emit("server-event", event) {
+        log::warn!("Failed to emit server event: {}", e);
+    }
+    if refresh {
+        if let Err(e) = supervision::sync(app).await {
+            log::warn!("Failed to sync supervision state after server event: {}", e);
+        }
+    }
+}
+
+/// Incremental parser for the `text/event-stream` format
+#[derive(Default)]
+struct EventStreamParser {
+    /// Bytes of the current, not yet terminated line
+    line: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+    /// ID set by the latest `id` field, kept across events
+    last_event_id: Option<String>,
+}
+
+impl EventStreamParser {
+    /// Consume a chunk of the stream and return the events it completed
+    fn feed(&mut self, chunk: &[u8]) -> Vec<ServerEvent> {
+        let mut events = vec![];
+        for &byte in chunk {
+            if byte != b'\n' {
+                self.line.push(byte);
+                continue;
+            }
+            let line = String::from_utf8_lossy(&self.line).into_owned();
+            self.line.clear();
+            if let Some(event) = self.process_line(line.trim_end_matches('\r')) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Apply one line; a blank line dispatches the event collected so far
+    fn process_line(&mut self, line: &str) -> Option<ServerEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            "id" if !value.contains('\0') => {
+                self.last_event_id = Some(value.to_string()).filter(|id| !id.is_empty())
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Build the collected event and reset for the next one
+    fn dispatch(&mut self) -> Option<ServerEvent> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+        let data = self.data.join("\n");
+        self.data.clear();
+
+        Some(ServerEvent {
+            id: self.last_event_id.clone(),
+            event: event.unwrap_or_else(|| "message".to_string()),
+            data: serde_json::from_str(&data).unwrap_or(serde_json::Value::String(data)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn line_split_across_chunks() {
+        let mut parser = EventStreamParser::default();
+        assert!(parser.feed(b"event: sig").is_empty());
+        assert!(parser.feed(b"nin\ndata: {\"device_id\"").is_empty());
+        let events = parser.feed(b": \"abc\"}\n\n");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "signin");
+        assert_eq!(events[0].data, json!({"device_id": "abc"}));
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let mut parser = EventStreamParser::default();
+        let events = parser.feed(b"event: nudge\r\ndata: {\"from\": \"a\"}\r\n\r\n");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "nudge");
+        assert_eq!(events[0].data, json!({"from": "a"}));
+    }
+
+    #[test]
+    fn data_lines_are_joined() {
+        let mut parser = EventStreamParser::default();
+        let events = parser.feed(b"data: first\ndata: second\n\n");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "message");
+        assert_eq!(events[0].data, json!("first\nsecond"));
+
+        let events = parser.feed(b"data: {\"a\":\ndata: 1}\n\n");
+        assert_eq!(events[0].data, json!({"a": 1}));
+    }
+
+    #[test]
+    fn comments_and_events_without_data_are_skipped() {
+        let mut parser = EventStreamParser::default();
+        assert!(parser.feed(b": keep-alive\n\n").is_empty());
+        assert!(parser.feed(b"event: ping\n\n").is_empty());
+        let events = parser.feed(b"data: x\n\n");
+        assert_eq!(events[0].event, "message");
+    }
+
+    #[test]
+    fn id_is_kept_until_changed() {
+        let mut parser = EventStreamParser::default();
+        let events = parser.feed(b"id: 7\ndata: a\n\ndata: b\n\n");
+        assert_eq!(events[0].id.as_deref(), Some("7"));
+        assert_eq!(events[1].id.as_deref(), Some("7"));
+        assert_eq!(parser.last_event_id.as_deref(), Some("7"));
+
+        let events = parser.feed(b"id\ndata: c\n\n");
+        assert_eq!(events[0].id, None);
+        assert_eq!(parser.last_event_id, None);
+    }
+
+    #[test]
+    fn id_with_nul_is_ignored() {
+        let mut parser = EventStreamParser {
+            last_event_id: Some("3".to_string()),
+            ..Default::default()
+        };
+        let events = parser.feed(b"id: 4\0\ndata: a\n\n");
+        assert_eq!(events[0].id.as_deref(), Some("3"));
+    }
+}
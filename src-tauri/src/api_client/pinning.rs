@@ -0,0 +1,124 @@
+//! Certificate pinning for self-hosted servers.
+//!
+//! A self-hosted server often uses a self-signed certificate that no root
+//! store trusts. Instead, its certificate or public key hash can be pinned in
+//! [`ServerSettings`](crate::models::ServerSettings): the server is then
+//! trusted if and only if the certificate it presents matches one of the pins.
+//! Pins are base64 SHA-256 hashes of either the whole DER certificate or of its
+//! SubjectPublicKeyInfo, the latter surviving certificate renewals that keep
+//! the key.
+
+use std::sync::Arc;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rust_i18n::t;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use sha2::{Digest, Sha256};
+use x509_cert::der::{Decode, Encode};
+use x509_cert::Certificate;
+
+use crate::error::AppError;
+
+/// Reason given to rustls when the server certificate matches no pin
+const PIN_MISMATCH: &str = "server certificate does not match any pinned hash";
+
+/// Decode a pin, checking it is a base64 SHA-256 hash
+pub fn decode_pin(pin: &str) -> Result<[u8; 32], AppError> {
+    BASE64
+        .decode(pin.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| t!("error.invalid_pin_hash", pin = pin).into())
+}
+
+/// TLS configuration trusting only servers that match one of `pins`
+pub fn pinned_tls_config(pins: &[String]) -> Result<ClientConfig, AppError> {
+    let pins = pins
+        .iter()
+        .map(|pin| decode_pin(pin))
+        .collect::<Result<Vec<_>, _>>()?;
+    let provider = Arc::new(ring::default_provider());
+    let mut config = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("Failed to set up TLS: {}", e))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(PinnedVerifier { pins, provider }))
+        .with_no_client_auth();
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+/// Check whether a request failed because the server matched no pin
+pub fn is_pin_mismatch(error: &reqwest::Error) -> bool {
+    let mut source: Option<&dyn std::error::Error> = Some(error);
+    while let Some(e) = source {
+        if e.to_string().contains(PIN_MISMATCH) {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+/// Accepts exactly the server certificates matching a pin
+#[derive(Debug)]
+struct PinnedVerifier {
+    pins: Vec<[u8; 32]>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let certificate_hash: [u8; 32] = Sha256::digest(end_entity.as_ref()).into();
+        let spki_hash: Option<[u8; 32]> = Certificate::from_der(end_entity.as_ref())
+            .ok()
+            .and_then(|cert| cert.tbs_certificate.subject_public_key_info.to_der().ok())
+            .map(|spki| Sha256::digest(spki).into());
+
+        if self
+            .pins
+            .iter()
+            .any(|pin| *pin == certificate_hash || Some(*pin) == spki_hash)
+        {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            log::error!("Server certificate matches none of the {} pins", self.pins.len());
+            Err(rustls::Error::General(PIN_MISMATCH.to_string()))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
@@ -0,0 +1,326 @@
+//! Optional app lock.
+//!
+//! Mood notes, wellness answers and check-in history of supervised users are
+//! sensitive, and a phone or family computer is often shared. Once the user
+//! sets a PIN the app starts locked, and every command except the few in
+//! [`UNLOCKED_COMMANDS`] fails with [`AppError::Locked`] until the PIN is
+//! entered, or on mobile until the user passes a biometric prompt.
+//!
+//! Only a salted PBKDF2 hash of the PIN is kept, in the
+//! [`keychain`](crate::keychain) like the device key. Wrong PINs are tolerated
+//! a few times, then every further attempt has to wait twice as long as the
+//! one before. The count of wrong PINs and the time of the next allowed try
+//! are stored next to the hash, so restarting the app doesn't reset them.
+//!
+//! On mobile the keychain is a private file in the app's data directory rather
+//! than the platform keystore, which Rust can't reach without a native plugin.
+//! Someone with access to that directory, e.g. through a rooted phone or an
+//! unencrypted backup, could reset the attempts or brute-force the PIN hash.
+//!
+//! Background jobs, the tray menu and the sign-in shortcut keep working while
+//! the app is locked; they never show stored data.
+
+use std::ops::RangeInclusive;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tauri::AppHandle;
+
+use crate::clock;
+use crate::error::AppError;
+use crate::keychain::{self, Secret};
+use crate::models::AppLockStatus;
+
+/// Commands that work while the app is locked
+const UNLOCKED_COMMANDS: &[&str] = &[
+    "get_app_lock_status",
+    "unlock_app",
+    "unlock_app_with_biometrics",
+    "lock_app",
+    "get_available_locales",
+];
+
+/// Allowed number of digits in a PIN
+const PIN_LENGTH: RangeInclusive<usize> = 4..=12;
+
+/// Identifies the hash format, bumped whenever it changes
+const HASH_SCHEME: &str = "pbkdf2-sha256";
+
+/// PBKDF2 rounds for new PINs; stored hashes keep the count they were made with
+const HASH_ROUNDS: u32 = 100_000;
+
+const SALT_LEN: usize = 16;
+
+const HASH_LEN: usize = 32;
+
+/// Wrong PINs accepted before attempts are throttled
+const FREE_ATTEMPTS: u32 = 5;
+
+/// Wait after the first throttled attempt, doubled for every further one
+const BASE_LOCKOUT: Duration = Duration::from_secs(30);
+
+/// Longest wait between attempts
+const MAX_LOCKOUT: Duration = Duration::from_secs(60 * 60);
+
+struct LockState {
+    /// Whether a PIN is set
+    enabled: bool,
+    locked: bool,
+}
+
+static STATE: Mutex<LockState> = Mutex::new(LockState {
+    enabled: false,
+    locked: false,
+});
+
+/// Wrong PINs entered since the last right one, stored next to the PIN hash
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Attempts {
+    failed: u32,
+    /// Earliest time the next PIN may be tried
+    retry_at: Option<DateTime<Utc>>,
+}
+
+/// Start locked when a PIN is set
+///
+/// When the PIN hash cannot be read the app starts locked as well, and every
+/// unlock attempt reads it again.
+pub fn init() {
+    let enabled = match keychain::load(Secret::AppLockPin) {
+        Ok(hash) => hash.is_some(),
+        Err(e) => {
+            log::error!("Failed to read app lock PIN, starting locked: {}", e);
+            true
+        }
+    };
+    let mut state = STATE.lock().unwrap();
+    state.enabled = enabled;
+    state.locked = enabled;
+    log::info!("App lock {}", if enabled { "enabled" } else { "disabled" });
+}
+
+/// Refuse `command` while the app is locked, unless it is needed to unlock it
+pub fn guard(command: &str) -> Result<(), AppError> {
+    if STATE.lock().unwrap().locked && !UNLOCKED_COMMANDS.contains(&command) {
+        log::debug!("Refusing {} while the app is locked", command);
+        return Err(AppError::Locked);
+    }
+    Ok(())
+}
+
+pub fn status(app: &AppHandle) -> AppLockStatus {
+    let state = STATE.lock().unwrap();
+    AppLockStatus {
+        enabled: state.enabled,
+        locked: state.locked,
+        biometrics_available: state.enabled && biometrics_available(app),
+    }
+}
+
+/// Unlock the app with its PIN
+pub fn unlock(pin: &str) -> Result<(), AppError> {
+    verify(pin)?;
+    STATE.lock().unwrap().locked = false;
+    log::info!("App unlocked");
+    Ok(())
+}
+
+/// Lock the app until the PIN is entered again
+pub fn lock() -> Result<(), AppError> {
+    let mut state = STATE.lock().unwrap();
+    if !state.enabled {
+        return Err(t!("error.no_pin").into());
+    }
+    state.locked = true;
+    log::info!("App locked");
+    Ok(())
+}
+
+/// Set a new PIN, which requires the current one when a PIN is already set
+pub fn set_pin(current_pin: Option<&str>, new_pin: &str) -> Result<(), AppError> {
+    if !PIN_LENGTH.contains(&new_pin.len()) || !new_pin.chars().all(|c| c.is_ascii_digit()) {
+        let (min, max) = (PIN_LENGTH.start(), PIN_LENGTH.end());
+        return Err(t!("error.pin_length", min = min, max = max).into());
+    }
+    if STATE.lock().unwrap().enabled {
+        verify(current_pin.ok_or_else(|| t!("error.current_pin_required").into_owned())?)?;
+    }
+    keychain::store(Secret::AppLockPin, &hash_pin(new_pin))?;
+    let mut state = STATE.lock().unwrap();
+    state.enabled = true;
+    state.locked = false;
+    log::info!("App lock PIN set");
+    Ok(())
+}
+
+/// Turn the lock off after checking the PIN
+pub fn remove_pin(pin: &str) -> Result<(), AppError> {
+    verify(pin)?;
+    keychain::delete(Secret::AppLockPin)?;
+    clear_attempts()?;
+    let mut state = STATE.lock().unwrap();
+    state.enabled = false;
+    state.locked = false;
+    log::info!("App lock PIN removed");
+    Ok(())
+}
+
+/// Unlock the app after the user passed the system's biometric prompt
+///
+/// Blocks until the user answered the prompt.
+#[cfg(mobile)]
+pub fn unlock_with_biometrics(app: &AppHandle) -> Result<(), AppError> {
+    use tauri_plugin_biometric::{AuthOptions, BiometricExt};
+
+    if !STATE.lock().unwrap().enabled {
+        return Err(t!("error.no_pin").into());
+    }
+    let options = AuthOptions {
+        allow_device_credential: false,
+        title: Some(t!("app_lock.biometric_title").into_owned()),
+        cancel_title: Some(t!("app_lock.use_pin").into_owned()),
+        fallback_title: Some(t!("app_lock.use_pin").into_owned()),
+        ..Default::default()
+    };
+    app.biometric()
+        .authenticate(t!("app_lock.biometric_reason").into_owned(), options)
+        .map_err(|e| {
+            log::warn!("Biometric unlock failed: {}", e);
+            e.to_string()
+        })?;
+    clear_attempts()?;
+    STATE.lock().unwrap().locked = false;
+    log::info!("App unlocked with biometrics");
+    Ok(())
+}
+
+#[cfg(mobile)]
+fn biometrics_available(app: &AppHandle) -> bool {
+    use tauri_plugin_biometric::BiometricExt;
+
+    app.biometric()
+        .status()
+        .map(|status| status.is_available)
+        .unwrap_or(false)
+}
+
+#[cfg(desktop)]
+fn biometrics_available(_app: &AppHandle) -> bool {
+    false
+}
+
+/// Check `pin` against the stored hash, throttling repeated failures
+fn verify(pin: &str) -> Result<(), AppError> {
+    let now = clock::shared().now();
+    let mut attempts = load_attempts()?;
+    if let Some(wait) = attempts
+        .retry_at
+        .and_then(|retry_at| (retry_at - now).to_std().ok())
+        .filter(|wait| !wait.is_zero())
+    {
+        // A clock set back must not stretch the wait beyond the longest lockout
+        return Err(AppError::RateLimited {
+            retry_after_secs: wait.min(MAX_LOCKOUT).as_secs().max(1),
+        });
+    }
+    let stored =
+        keychain::load(Secret::AppLockPin)?.ok_or_else(|| t!("error.no_pin").into_owned())?;
+
+    if matches_hash(pin, &stored)? {
+        return clear_attempts();
+    }
+    attempts.failed += 1;
+    log::warn!("Wrong app lock PIN ({} attempts)", attempts.failed);
+    if attempts.failed >= FREE_ATTEMPTS {
+        let doublings = (attempts.failed - FREE_ATTEMPTS).min(16);
+        let lockout = BASE_LOCKOUT.saturating_mul(1 << doublings).min(MAX_LOCKOUT);
+        attempts.retry_at = chrono::Duration::from_std(lockout)
+            .ok()
+            .map(|lockout| now + lockout);
+    }
+    save_attempts(&attempts)?;
+    Err(t!("error.wrong_pin").into())
+}
+
+/// Wrong PINs entered so far
+///
+/// A record that can't be read counts as all free attempts used up, so
+/// corrupting it doesn't grant new ones.
+fn load_attempts() -> Result<Attempts, AppError> {
+    let Some(encoded) = keychain::load(Secret::AppLockAttempts)? else {
+        return Ok(Attempts::default());
+    };
+    Ok(serde_json::from_str(&encoded).unwrap_or_else(|e| {
+        log::error!("Stored app lock attempts are corrupt: {}", e);
+        Attempts {
+            failed: FREE_ATTEMPTS,
+            retry_at: None,
+        }
+    }))
+}
+
+fn save_attempts(attempts: &Attempts) -> Result<(), AppError> {
+    let encoded = serde_json::to_string(attempts).map_err(|e| e.to_string())?;
+    keychain::store(Secret::AppLockAttempts, &encoded)
+}
+
+/// Forget wrong PINs after the right one was entered
+fn clear_attempts() -> Result<(), AppError> {
+    keychain::delete(Secret::AppLockAttempts)
+}
+
+/// Encoded hash of a new PIN, `<scheme>$<rounds>$<salt>$<hash>`
+fn hash_pin(pin: &str) -> String {
+    let salt: [u8; SALT_LEN] = rand::random();
+    let hash = derive(pin, &salt, HASH_ROUNDS);
+    format!(
+        "{}${}${}${}",
+        HASH_SCHEME,
+        HASH_ROUNDS,
+        BASE64.encode(salt),
+        BASE64.encode(hash)
+    )
+}
+
+fn matches_hash(pin: &str, stored: &str) -> Result<bool, AppError> {
+    let corrupt = || {
+        log::error!("Stored app lock PIN hash is corrupt");
+        AppError::from("Stored PIN hash is corrupt")
+    };
+    let mut parts = stored.split('$');
+    if parts.next() != Some(HASH_SCHEME) {
+        return Err(corrupt());
+    }
+    let rounds: u32 = parts
+        .next()
+        .and_then(|rounds| rounds.parse().ok())
+        .ok_or_else(corrupt)?;
+    let salt = parts
+        .next()
+        .and_then(|salt| BASE64.decode(salt).ok())
+        .ok_or_else(corrupt)?;
+    let expected = parts
+        .next()
+        .and_then(|hash| BASE64.decode(hash).ok())
+        .ok_or_else(corrupt)?;
+    let actual = derive(pin, &salt, rounds);
+    // Compare every byte so the time taken doesn't hint at the PIN
+    Ok(expected.len() == actual.len()
+        && expected
+            .iter()
+            .zip(actual.iter())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0)
+}
+
+fn derive(pin: &str, salt: &[u8], rounds: u32) -> [u8; HASH_LEN] {
+    let mut hash = [0u8; HASH_LEN];
+    pbkdf2::pbkdf2_hmac::<Sha256>(pin.as_bytes(), salt, rounds, &mut hash);
+    hash
+}
@@ -0,0 +1,55 @@
+//! Launch at login (desktop only).
+//!
+//! Reminders and the tray icon only work while the app runs. With autostart
+//! enabled the system starts the app when the user logs in; it then stays in
+//! the tray with its window hidden until opened.
+
+use tauri::{AppHandle, Manager};
+use tauri_plugin_autostart::{MacosLauncher, ManagerExt};
+
+use crate::error::AppError;
+
+/// Argument the system passes when starting the app at login
+const AUTOSTART_ARG: &str = "--autostart";
+
+/// Build the autostart plugin, registering the app with [`AUTOSTART_ARG`]
+pub fn plugin() -> tauri::plugin::TauriPlugin<tauri::Wry> {
+    tauri_plugin_autostart::init(MacosLauncher::LaunchAgent, Some(vec![AUTOSTART_ARG]))
+}
+
+/// Register or unregister the app to start at login
+pub fn apply_autostart(app: &AppHandle, enabled: bool) -> Result<(), AppError> {
+    let launcher = app.autolaunch();
+    let registered = launcher.is_enabled().map_err(|e| {
+        log::error!("Failed to query autostart: {}", e);
+        e.to_string()
+    })?;
+    if registered == enabled {
+        return Ok(());
+    }
+
+    let result = if enabled {
+        launcher.enable()
+    } else {
+        launcher.disable()
+    };
+    result.map_err(|e| {
+        log::error!("Failed to set autostart to {}: {}", enabled, e);
+        e.to_string()
+    })?;
+    log::info!("Autostart {}", if enabled { "enabled" } else { "disabled" });
+    Ok(())
+}
+
+/// Hide the window when the system started the app at login
+pub fn hide_if_autostarted(app: &AppHandle) {
+    if !std::env::args().any(|arg| arg == AUTOSTART_ARG) {
+        return;
+    }
+    log::info!("Started at login, keeping the window hidden");
+    if let Some(window) = app.get_webview_window("main") {
+        if let Err(e) = window.hide() {
+            log::warn!("Failed to hide main window: {}", e);
+        }
+    }
+}
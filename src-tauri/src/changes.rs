@@ -0,0 +1,45 @@
+//! Events telling the frontend that backend state changed.
+//!
+//! A command only returns its result to the window that invoked it, and
+//! background jobs return nothing at all. After every change the backend
+//! therefore broadcasts one of these events, so all windows and the tray icon
+//! show the same state without invoking commands again:
+//!
+//! - `signin-updated` with the sign-in data, `null` once it was deleted
+//! - `supervision-updated` with the local supervision requests and relationships
+//! - `config-updated` with the [`ConfigKind`] that was saved
+//!
+//! [`AppState`](crate::state::AppState) sends them for the files it caches as
+//! they are saved; everything else calls these functions after saving.
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::models::{ConfigKind, DeviceConfig, SigninData, SupervisionUpdated};
+
+/// Tell the frontend and the tray icon that the sign-in data changed
+pub fn signin_updated(app: &AppHandle, data: Option<&SigninData>) {
+    emit(app, "signin-updated", data);
+    #[cfg(desktop)]
+    crate::tray::refresh(app);
+}
+
+/// Tell the frontend that supervision requests or relationships changed
+pub fn supervision_updated(app: &AppHandle, config: &DeviceConfig) {
+    let payload = SupervisionUpdated {
+        requests: config.supervision_requests.clone(),
+        relationships: config.supervision_relationships.clone(),
+    };
+    emit(app, "supervision-updated", payload);
+}
+
+/// Tell the frontend that a configuration was saved
+pub fn config_updated(app: &AppHandle, kind: ConfigKind) {
+    emit(app, "config-updated", kind);
+}
+
+fn emit(app: &AppHandle, event: &str, payload: impl Serialize + Clone) {
+    if let Err(e) = app.emit(event, payload) {
+        log::warn!("Failed to emit {} event: {}", event, e);
+    }
+}
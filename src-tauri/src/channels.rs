@@ -0,0 +1,114 @@
+//! Notification delivery backends.
+//!
+//! Every way of reaching the user or their supervisor — email, system
+//! notifications, chat robots, Telegram and user-defined webhooks — implements
+//! [`NotificationChannel`]. [`registry`] builds the enabled channels from the
+//! stored configuration, so the dispatcher treats them all alike and adding a
+//! backend means adding one implementation.
+
+use async_trait::async_trait;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::error::AppError;
+use crate::history;
+use crate::models::{DeliveryChannel, NotificationMessage, OutgoingNotification};
+use crate::state::AppState;
+use crate::storage;
+
+/// A backend that can deliver notifications
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    /// Kind of backend, as recorded in the notification history
+    fn kind(&self) -> DeliveryChannel;
+
+    /// Stable identifier used to retry a failed delivery on the same channel
+    fn id(&self) -> String {
+        history::event_name(self.kind())
+    }
+
+    /// Check whether the notification carries a form this channel delivers
+    fn accepts(&self, notification: &OutgoingNotification) -> bool {
+        notification.message.is_some()
+    }
+
+    /// Deliver the notification
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), AppError>;
+}
+
+/// Build every configured and enabled channel
+pub fn registry(app: &AppHandle) -> Vec<Box<dyn NotificationChannel>> {
+    let mut channels: Vec<Box<dyn NotificationChannel>> =
+        vec![Box::new(SystemNotification(app.clone()))];
+
+    match app.state::<AppState>().email_config() {
+        Ok(config) if config.enabled && config.has_recipients() => channels.push(Box::new(config)),
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to load email config: {}", e),
+    }
+
+    match storage::load_notification_channels() {
+        Ok(config) => {
+            if let Some(slack) = config.slack.filter(|c| c.enabled) {
+                channels.push(Box::new(slack));
+            }
+            if let Some(wecom) = config.wecom.filter(|c| c.enabled) {
+                channels.push(Box::new(wecom));
+            }
+            if let Some(dingtalk) = config.dingtalk.filter(|c| c.enabled) {
+                channels.push(Box::new(dingtalk));
+            }
+            if let Some(ntfy) = config.ntfy.filter(|c| c.enabled) {
+                channels.push(Box::new(ntfy));
+            }
+            if let Some(gotify) = config.gotify.filter(|c| c.enabled) {
+                channels.push(Box::new(gotify));
+            }
+            if let Some(telegram) = config.telegram.filter(|c| c.enabled) {
+                channels.push(Box::new(telegram));
+            }
+        }
+        Err(e) => log::warn!("Failed to load notification channels: {}", e),
+    }
+
+    match storage::load_webhooks() {
+        Ok(webhooks) => channels.extend(
+            webhooks
+                .into_iter()
+                .filter(|w| w.enabled)
+                .map(|w| Box::new(w) as Box<dyn NotificationChannel>),
+        ),
+        Err(e) => log::warn!("Failed to load webhooks: {}", e),
+    }
+
+    channels
+}
+
+/// Message form of a notification, for channels that render title and body
+pub fn message_of(notification: &OutgoingNotification) -> Result<&NotificationMessage, AppError> {
+    notification
+        .message
+        .as_ref()
+        .ok_or_else(|| "Notification has no message".into())
+}
+
+/// Local notification on this device
+struct SystemNotification(AppHandle);
+
+#[async_trait]
+impl NotificationChannel for SystemNotification {
+    fn kind(&self) -> DeliveryChannel {
+        DeliveryChannel::SystemNotification
+    }
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), AppError> {
+        let message = message_of(notification)?;
+        self.0
+            .notification()
+            .builder()
+            .title(&message.title)
+            .body(&message.body)
+            .show()
+            .map_err(|e| e.to_string().into())
+    }
+}
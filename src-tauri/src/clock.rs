@@ -0,0 +1,106 @@
+//! Time source abstraction.
+//!
+//! All date logic asks a [`Clock`] for the current time instead of calling
+//! `Utc::now()` directly, so streak and reminder rules can be driven by a fixed
+//! or shifted clock. Commands and jobs take the clock from Tauri managed state;
+//! code that can't reach it, such as the API client or the panic hook, uses
+//! [`shared`], which is the same clock.
+
+use std::sync::{Arc, OnceLock};
+
+use chrono::{DateTime, Utc};
+
+/// Clock shared through Tauri managed state and background tasks
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The app's clock, handed out by [`shared`]
+static SHARED: OnceLock<SharedClock> = OnceLock::new();
+
+/// Source of the current time
+pub trait Clock: Send + Sync {
+    /// Current instant in UTC
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Current calendar date in UTC, for tests that pin the clock
+    ///
+    /// The app itself asks [`signin_day`](crate::deadline::signin_day), which
+    /// honours the configured time zone.
+    #[cfg(test)]
+    fn today(&self) -> chrono::NaiveDate {
+        self.now().date_naive()
+    }
+}
+
+/// The clock of the running app, also registered as managed state
+pub fn shared() -> SharedClock {
+    SHARED.get_or_init(|| Arc::new(SystemClock)).clone()
+}
+
+/// Clock backed by the system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Clock standing still at a chosen instant, moved by hand
+#[cfg(test)]
+pub struct FixedClock(std::sync::Mutex<DateTime<Utc>>);
+
+#[cfg(test)]
+impl FixedClock {
+    /// Clock showing the RFC 3339 time `at`
+    pub fn at(at: &str) -> Self {
+        Self(std::sync::Mutex::new(parse(at)))
+    }
+
+    /// Jump to the RFC 3339 time `at`, which may lie before the current one
+    pub fn set(&self, at: &str) {
+        *self.0.lock().unwrap() = parse(at);
+    }
+}
+
+#[cfg(test)]
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+fn parse(at: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(at)
+        .expect("valid RFC 3339 time")
+        .to_utc()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn today_changes_at_midnight() {
+        let clock = FixedClock::at("2024-03-10T23:59:59Z");
+        assert_eq!(clock.today(), NaiveDate::from_ymd_opt(2024, 3, 10).unwrap());
+        clock.set("2024-03-11T00:00:00Z");
+        assert_eq!(clock.today(), NaiveDate::from_ymd_opt(2024, 3, 11).unwrap());
+    }
+
+    #[test]
+    fn today_follows_a_backwards_jump() {
+        let clock = FixedClock::at("2024-03-11T00:00:05Z");
+        clock.set("2024-03-10T23:59:50Z");
+        assert_eq!(clock.today(), NaiveDate::from_ymd_opt(2024, 3, 10).unwrap());
+    }
+
+    #[test]
+    fn today_reaches_leap_day() {
+        let clock = FixedClock::at("2024-02-29T12:00:00Z");
+        assert_eq!(clock.today(), NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+    }
+}
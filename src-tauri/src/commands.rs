@@ -3,54 +3,125 @@
 //! This module contains all Tauri commands that can be invoked from the frontend.
 //! Commands are organized into logical groups: sign-in, device, supervision, and utilities.
 
-use chrono::{NaiveDate, Utc};
-use uuid::Uuid;
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_i18n::t;
+use tauri::{AppHandle, Emitter, Manager, State};
 use tauri_plugin_notification::NotificationExt;
+use uuid::Uuid;
 
+use crate::alerts;
 use crate::api_client::{
-    accept_supervision_request_api, device_signin, get_device, get_device_status,
-    get_pending_requests, get_supervision_list, register_device, reject_supervision_request_api,
+    block_device_api, cancel_call, cancellable, device_signin, get_device,
+    get_device_signin_history as get_device_signin_history_api, get_device_status,
+    get_device_statuses, get_pending_requests, get_supervision_list, probe_server_version,
+    recent_calls, register_device, reject_supervision_request_api,
     remove_supervision_relationship_api, search_devices, send_supervision_request_api,
+    set_relationship_valid_until_api, unblock_device_api,
     update_device_name as update_device_name_api,
 };
+use crate::app_lock;
+use crate::changes;
+use crate::clock::{Clock, SharedClock};
+use crate::crash;
+use crate::crypto;
+use crate::dashboard;
+use crate::deadline::{checkin_deadline, signin_day};
+use crate::dispatch::{self, EventNotification};
+use crate::encouragements;
+use crate::error::AppError;
+use crate::history;
+use crate::i18n;
+use crate::logs;
 use crate::models::{
-    DeviceConfig, DeviceMode, DeviceStatus, EmailConfig, Quote, SigninData,
-    SupervisionRelationship, SupervisionRequest, SupervisionRequestStatus, SupervisorStatus,
+    ApiCallLogEntry, AppLockStatus, AppSettings, AutoSigninEvent, BrokenStreak, CheckinDay,
+    ConfigKind, CrashReport, DeadlineStatus, DeepLink, DeviceConfig, DeviceMode, DeviceSettings,
+    DeviceStatus, EmailAuthMethod, EmailConfig, EmailTestResult, EscalationLogEntry,
+    EscalationPolicy, LocaleInfo, LogEntry, LogLevel, Milestone, NotificationChannels,
+    NotificationEvent, NotificationHistoryEntry, NotificationHistoryFilter, NotificationMessage,
+    NotificationPreferences, OAuth2Provider, OnboardingState, OnboardingStep, OutboxItem,
+    PairingQr, PartnerKeyStatus, PendingOperation, PendingSyncItem, PeriodReport, PreferenceEvent,
+    Quote, ReminderConfig, ReportPeriod, ScheduledJobStatus, Settings, SigninData, SigninEntry,
+    SigninPreview, SigninUndo, StreakPolicy, StreakRecoveryStatus, SupervisedDeviceStats,
+    SupervisionHistoryEntry, SupervisionHistoryKind, SupervisionRelationship, SupervisionRequest,
+    SupervisionRequestStatus, SupervisionSnapshot, SupervisorStatus, UpdateInfo, Webhook,
+    WebhookEvent, WebhookPayload, RELATIONSHIP_NICKNAME_MAX_CHARS,
+    SUPERVISION_REQUEST_MESSAGE_MAX_CHARS, SUPERVISION_REQUEST_TTL_DAYS,
 };
+use crate::nudges;
+use crate::oauth;
+use crate::onboarding;
+use crate::outbox;
+use crate::pings;
+use crate::reminders::ReminderState;
 use crate::remote_models::{
-    Device as RemoteDevice, DeviceMode as RemoteDeviceMode, DeviceStatus as RemoteDeviceStatus,
-    SigninResponse, SupervisionRelation, SupervisionRequest as RemoteSupervisionRequest,
+    CheckinPing, Device as RemoteDevice, DeviceMode as RemoteDeviceMode,
+    DeviceStatus as RemoteDeviceStatus, Encouragement, InviteCode, Nudge, RequestIdentifier,
+    ServerVersion, SharingPolicy, SigninRecord, SigninResponse, SupervisionRelation,
+    SupervisionRequest as RemoteSupervisionRequest, TransferCode, WellnessAnswer, WellnessQuestion,
 };
-use crate::services::{fetch_hitokoto, send_signin_email};
+use crate::reports::{build_period_report, record_digest_quote};
+use crate::request_limits;
+use crate::scheduler;
+use crate::services::{
+    detect_milestone, fetch_hitokoto, milestone_title, report_email, run_email_test, send_email,
+    signin_email, signin_message,
+};
+use crate::settings;
+use crate::sharing;
+use crate::sos;
+use crate::state::AppState;
 use crate::storage;
+use crate::streak::{calculate_streak, goal_progress, recent_days};
+use crate::streak_image::STREAK_IMAGE_DAYS;
+use crate::supervision;
+use crate::sync_queue;
+use crate::timeline;
+use crate::webhooks;
+use crate::wellness;
+
+/// Number of streak restores allowed within the rolling restore window
+const STREAK_RESTORE_ALLOWANCE: u32 = 2;
+
+/// Length of the rolling window (in days) the restore allowance applies to
+const STREAK_RESTORE_WINDOW_DAYS: i64 = 30;
+
+/// Minutes after signing in during which the sign-in can still be undone
+const SIGNIN_UNDO_WINDOW_MINUTES: i64 = 10;
+
+/// Days of history passed to the sign-in email, enough for the streak image grid
+const RECENT_DAYS_IN_EMAIL: u32 = STREAK_IMAGE_DAYS as u32;
+
+/// Calls returned by the recent API log when the frontend gives no limit
+const RECENT_API_CALLS: usize = 50;
+
+/// Log records returned when the frontend gives no limit
+const RECENT_LOG_RECORDS: usize = 200;
 
 // =============================================================================
 // Utility Functions
 // =============================================================================
 
-/// Get today's date in YYYY-MM-DD format
-fn get_today_date() -> String {
-    Utc::now().format("%Y-%m-%d").to_string()
+/// Get the current sign-in day in YYYY-MM-DD format
+fn get_today_date(clock: &dyn Clock, settings: &AppSettings) -> String {
+    signin_day(clock, settings).format("%Y-%m-%d").to_string()
 }
 
-/// Check if the streak should continue based on previous sign-in data
-fn should_continue_streak(data: &Option<SigninData>) -> bool {
-    let Some(saved) = data else { return false };
-
-    let Ok(last_date) = NaiveDate::parse_from_str(&saved.last_signin_date, "%Y-%m-%d") else {
+/// Check whether exactly one day was missed between the last sign-in and today
+fn missed_exactly_one_day(last_signin_date: &str, today: &str) -> bool {
+    let (Ok(last), Ok(today)) = (
+        NaiveDate::parse_from_str(last_signin_date, "%Y-%m-%d"),
+        NaiveDate::parse_from_str(today, "%Y-%m-%d"),
+    ) else {
         return false;
     };
-
-    let today = Utc::now().date_naive();
-    let yesterday = today - chrono::Duration::days(1);
-    last_date == yesterday
+    (today - last).num_days() == 2
 }
 
 /// Get a fallback quote when API fails
 fn get_fallback_quote() -> Quote {
     Quote {
-        text: "今天的签到已完成，继续加油！".to_string(),
-        author: "系统".to_string(),
+        text: t!("signin.fallback_quote").into_owned(),
+        author: t!("signin.fallback_quote_author").into_owned(),
     }
 }
 
@@ -64,99 +135,635 @@ pub fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-pub fn load_signin_data() -> Result<Option<SigninData>, String> {
+pub fn load_signin_data(state: State<'_, AppState>) -> Result<Option<SigninData>, AppError> {
     log::info!("Loading sign-in data");
-    storage::load_data().map_err(|e| {
+    state.signin_data().map_err(|e| {
         log::error!("Failed to load sign-in data: {}", e);
-        e.to_string()
+        e.to_string().into()
     })
 }
 
 #[tauri::command]
-pub async fn signin(name: String) -> Result<SigninData, String> {
+pub async fn signin(
+    app: AppHandle,
+    clock: State<'_, SharedClock>,
+    name: String,
+    mood: Option<u8>,
+    answers: Option<Vec<WellnessAnswer>>,
+) -> Result<SigninData, AppError> {
+    perform_signin(&app, clock.as_ref(), name, mood, Some(answers.unwrap_or_default())).await
+}
+
+/// Run the sign-in flow against the given clock
+///
+/// Interactive sign-ins pass `answers`, which must cover every wellness
+/// question; automatic sign-ins pass `None` and skip the questions.
+pub(crate) async fn perform_signin(
+    app: &AppHandle,
+    clock: &dyn Clock,
+    name: String,
+    mood: Option<u8>,
+    answers: Option<Vec<WellnessAnswer>>,
+) -> Result<SigninData, AppError> {
     log::info!("Sign-in requested for user: {}", name);
-    let saved_data = storage::load_data().map_err(|e| {
+    if let Some(m) = mood {
+        if !(1..=5).contains(&m) {
+            log::warn!("Invalid mood score: {}", m);
+            return Err(t!("error.invalid_mood").into());
+        }
+    }
+
+    let state = app.state::<AppState>();
+    let saved_data = state.signin_data().map_err(|e| {
         log::error!("Failed to load sign-in data: {}", e);
         e.to_string()
     })?;
-    let today = get_today_date();
+    let mut settings = storage::load_settings().map_err(|e| {
+        log::error!("Failed to load app settings: {}", e);
+        e.to_string()
+    })?;
+    let day = signin_day(clock, &settings);
+    let today = day.format("%Y-%m-%d").to_string();
+
+    let mut new_data =
+        calculate_signin_data(&saved_data, &name, day, clock.now(), mood, settings.streak_policy)?;
+    new_data.goal_progress = settings
+        .streak_goal
+        .map(|target| goal_progress(new_data.streak, target, day));
+
+    // Unchanged data means no entry was added, also when the clock moved backwards
+    let is_new_signin = saved_data
+        .as_ref()
+        .map(|d| d.last_signin_date != new_data.last_signin_date)
+        .unwrap_or(true);
+    let answers = match answers {
+        Some(answers) if is_new_signin => wellness::validate_answers(answers)?,
+        _ => vec![],
+    };
+    if let Some(entry) = new_data
+        .signin_entries
+        .last_mut()
+        .filter(|entry| entry.date == today && !answers.is_empty())
+    {
+        entry.answers = answers.clone();
+    }
 
-    let new_data = calculate_signin_data(&saved_data, &name, &today)?;
-    storage::save_data(&new_data).map_err(|e| {
+    let device_config = state.device_config();
+    match &device_config {
+        Ok(config) => {
+            let delivered = encouragements::take_for_signin(&config.device.device_id);
+            if !delivered.is_empty() {
+                new_data.encouragements = delivered;
+            }
+        }
+        Err(e) => log::warn!("Failed to load device config: {}", e),
+    }
+
+    if is_new_signin {
+        let undo = SigninUndo {
+            signed_at: clock.now().to_rfc3339(),
+            previous: saved_data.clone(),
+        };
+        if let Err(e) = storage::save_signin_undo(&undo) {
+            log::warn!("Failed to save sign-in undo snapshot: {}", e);
+        }
+    }
+
+    state.save_signin_data(&new_data).map_err(|e| {
         log::error!("Failed to save sign-in data: {}", e);
         e.to_string()
     })?;
+    if let Ok(config) = device_config {
+        wellness::submit_answers(&state, config.device.device_id, today.clone(), answers);
+    }
+    sharing::share_status(&state, clock, &new_data);
 
-    send_signin_notification(&name, new_data.streak).await;
+    if let (true, Some(target)) = (is_new_signin, settings.streak_goal) {
+        if new_data.streak == target as i32 {
+            notify_goal_reached(app, target);
+        }
+    }
+
+    let milestone = if is_new_signin {
+        record_profile_dates(app, &mut settings, &new_data, &today);
+        detect_milestone(&settings.profile, day)
+    } else {
+        None
+    };
+
+    // Notifications go out in the background so slow mail servers never delay the sign-in
+    let recent = recent_days(&new_data.signin_history, day, RECENT_DAYS_IN_EMAIL);
+    let (notify_name, streak) = (name.clone(), new_data.streak);
+    let notify_date = today.clone();
+    let notify_app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        send_signin_notification(
+            &notify_app,
+            &notify_name,
+            streak,
+            milestone,
+            &recent,
+            &notify_date,
+        )
+        .await;
+    });
 
     log::info!("User {} signed in successfully. New streak: {} days", name, new_data.streak);
     Ok(new_data)
 }
 
+#[tauri::command]
+pub fn preview_signin(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+) -> Result<SigninPreview, AppError> {
+    log::info!("Previewing sign-in");
+    let saved_data = state.signin_data().map_err(|e| {
+        log::error!("Failed to load sign-in data: {}", e);
+        e.to_string()
+    })?;
+    let settings = storage::load_settings().map_err(|e| {
+        log::error!("Failed to load app settings: {}", e);
+        e.to_string()
+    })?;
+
+    let name = saved_data
+        .as_ref()
+        .map(|d| d.name.clone())
+        .unwrap_or_default();
+    let day = signin_day(clock.as_ref(), &settings);
+    let preview =
+        calculate_signin_data(&saved_data, &name, day, clock.now(), None, settings.streak_policy)?;
+
+    let today = day.format("%Y-%m-%d").to_string();
+    let current_streak = saved_data.as_ref().map(|d| d.streak).unwrap_or(0);
+    let last_signin_date = saved_data.as_ref().map(|d| d.last_signin_date.clone());
+    let days_since_last_signin = last_signin_date
+        .as_deref()
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .map(|d| (day - d).num_days());
+    let already_signed_in = last_signin_date.as_deref() == Some(today.as_str());
+
+    Ok(SigninPreview {
+        already_signed_in,
+        current_streak,
+        new_streak: preview.streak,
+        will_reset: !already_signed_in && current_streak > 0 && preview.streak <= 1,
+        last_signin_date,
+        days_since_last_signin,
+    })
+}
+
+#[tauri::command]
+pub fn undo_signin(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+) -> Result<Option<SigninData>, AppError> {
+    log::info!("Undo of today's sign-in requested");
+    let undo = storage::load_signin_undo()
+        .map_err(|e| {
+            log::error!("Failed to load sign-in undo snapshot: {}", e);
+            e.to_string()
+        })?
+        .ok_or_else(|| t!("error.nothing_to_undo").into_owned())?;
+
+    let signed_at = DateTime::parse_from_rfc3339(&undo.signed_at)
+        .map_err(|e| format!("Invalid undo snapshot: {}", e))?
+        .with_timezone(&Utc);
+    let elapsed = clock.now() - signed_at;
+    if elapsed < chrono::Duration::zero()
+        || elapsed > chrono::Duration::minutes(SIGNIN_UNDO_WINDOW_MINUTES)
+    {
+        log::warn!("Undo window of {} minutes has passed", SIGNIN_UNDO_WINDOW_MINUTES);
+        return Err(t!("error.undo_expired").into());
+    }
+
+    let current = state.signin_data().map_err(|e| {
+        log::error!("Failed to load sign-in data: {}", e);
+        e.to_string()
+    })?;
+    let settings = storage::load_settings().map_err(|e| {
+        log::error!("Failed to load app settings: {}", e);
+        e.to_string()
+    })?;
+    if current.map(|d| d.last_signin_date) != Some(get_today_date(clock.as_ref(), &settings)) {
+        return Err(t!("error.nothing_to_undo").into());
+    }
+
+    match &undo.previous {
+        Some(previous) => state.save_signin_data(previous),
+        None => state.delete_signin_data(),
+    }
+    .map_err(|e| {
+        log::error!("Failed to restore sign-in data: {}", e);
+        e.to_string()
+    })?;
+    storage::delete_signin_undo().map_err(|e| e.to_string())?;
+
+    log::info!("Today's sign-in undone");
+    Ok(undo.previous)
+}
+
 /// Calculate new sign-in data based on existing data
 fn calculate_signin_data(
     saved_data: &Option<SigninData>,
     name: &str,
-    today: &str,
-) -> Result<SigninData, String> {
-    let (new_streak, mut signin_history) = match saved_data {
-        Some(data) if data.last_signin_date == today => {
+    today: NaiveDate,
+    now: DateTime<Utc>,
+    mood: Option<u8>,
+    policy: StreakPolicy,
+) -> Result<SigninData, AppError> {
+    let today = today.format("%Y-%m-%d").to_string();
+    let today = today.as_str();
+
+    if let Some(data) = saved_data {
+        if data.last_signin_date == today {
             log::info!("User {} already signed in today", name);
             return Ok(data.clone());
         }
-        Some(data) if should_continue_streak(saved_data) => {
-            log::debug!("Continuing streak for user {}", name);
-            (data.streak + 1, data.signin_history.clone())
-        }
-        _ => {
-            log::debug!("Starting new streak for user {}", name);
-            (1, vec![])
+        if data.last_signin_date.as_str() > today {
+            log::warn!(
+                "Last sign-in date {} is after today ({}), the clock may have moved backwards",
+                data.last_signin_date,
+                today
+            );
+            return Ok(data.clone());
         }
-    };
+    }
 
+    let mut signin_history = saved_data
+        .as_ref()
+        .map(|d| d.signin_history.clone())
+        .unwrap_or_default();
     if !signin_history.contains(&today.to_string()) {
         signin_history.push(today.to_string());
     }
 
+    let streak_restores = saved_data
+        .as_ref()
+        .map(|d| d.streak_restores.clone())
+        .unwrap_or_default();
+    let new_streak = calculate_streak(&signin_history, &streak_restores, policy);
+
+    let broken_streak = match saved_data {
+        Some(data)
+            if new_streak == 1
+                && data.streak > 0
+                && missed_exactly_one_day(&data.last_signin_date, today) =>
+        {
+            log::info!(
+                "User {} missed one day, keeping previous streak of {} for recovery",
+                name,
+                data.streak
+            );
+            Some(BrokenStreak {
+                streak: data.streak,
+                last_signin_date: data.last_signin_date.clone(),
+            })
+        }
+        _ => None,
+    };
+    log::debug!("Derived streak for user {} ({:?}): {}", name, policy, new_streak);
+
+    let mut signin_entries = saved_data
+        .as_ref()
+        .map(|d| d.signin_entries.clone())
+        .unwrap_or_default();
+    signin_entries.push(SigninEntry {
+        date: today.to_string(),
+        signed_at: now.to_rfc3339(),
+        mood,
+        answers: vec![],
+    });
+
     Ok(SigninData {
         name: name.to_string(),
         last_signin_date: today.to_string(),
         streak: new_streak,
         signin_history,
+        signin_entries,
+        broken_streak,
+        streak_restores,
+        goal_progress: None,
+        encouragements: vec![],
     })
 }
 
-/// Send email notification for sign-in (non-blocking)
-async fn send_signin_notification(name: &str, streak: i32) {
+/// Celebrate reaching the configured streak goal with a system notification
+fn notify_goal_reached(app: &AppHandle, target: u32) {
+    log::info!("Streak goal of {} days reached", target);
+    let message = NotificationMessage {
+        event: NotificationEvent::Signin,
+        title: t!("signin.goal_title").into_owned(),
+        body: t!("signin.goal_body", target = target).into_owned(),
+        quote: None,
+    };
+    dispatch::show_local(app, message, false);
+}
+
+/// Fill in the account creation date and track when the current streak started
+fn record_profile_dates(
+    app: &AppHandle,
+    settings: &mut AppSettings,
+    data: &SigninData,
+    today: &str,
+) {
+    let profile = &mut settings.profile;
+    let mut changed = false;
+
+    if profile.account_created_on.is_none() {
+        profile.account_created_on = data
+            .signin_history
+            .iter()
+            .min()
+            .cloned()
+            .or_else(|| Some(today.to_string()));
+        changed = true;
+    }
+    if data.streak == 1 && profile.streak_started_on.as_deref() != Some(today) {
+        profile.streak_started_on = Some(today.to_string());
+        changed = true;
+    }
+
+    if changed {
+        match storage::save_settings(settings) {
+            Ok(()) => changes::config_updated(app, ConfigKind::Settings),
+            Err(e) => log::warn!("Failed to save profile dates: {}", e),
+        }
+    }
+}
+
+/// Announce the sign-in and any milestone on the channels chosen in the preferences
+async fn send_signin_notification(
+    app: &AppHandle,
+    name: &str,
+    streak: i32,
+    milestone: Option<Milestone>,
+    recent_days: &[(NaiveDate, bool)],
+    today: &str,
+) {
     log::debug!("Preparing sign-in notification for {}", name);
-    let email_config = match storage::load_email_config() {
-        Ok(config) if config.enabled => config,
-        Ok(_config) => {
-            log::debug!("Email notification disabled for {}", name);
-            return;
+    let clock = app.state::<SharedClock>();
+
+    if let Some(milestone) = milestone {
+        log::info!("Milestone reached: {:?}", milestone);
+        let message = NotificationMessage {
+            event: NotificationEvent::Signin,
+            title: milestone_title(milestone),
+            body: t!("signin.milestone_body", name = name, streak = streak).into_owned(),
+            quote: None,
+        };
+        dispatch::notify(
+            app,
+            EventNotification {
+                event: PreferenceEvent::StreakMilestone,
+                message,
+                email: None,
+                webhook: None,
+                critical: false,
+            },
+        )
+        .await;
+    }
+
+    let quote = fetch_hitokoto().await.unwrap_or_else(|e| {
+        log::warn!("Failed to fetch quote, using fallback: {}", e);
+        get_fallback_quote()
+    });
+
+    let state = app.state::<AppState>();
+    // In digest mode the quote is saved for the weekly email instead of mailed now
+    let email = match state.email_config() {
+        Ok(config) if config.enabled && config.weekly_digest => {
+            record_digest_quote(&state, today, &quote);
+            None
+        }
+        Ok(config) if config.enabled => {
+            Some(signin_email(name, streak, &quote, milestone, recent_days))
         }
+        Ok(_) => None,
         Err(e) => {
             log::warn!("Failed to load email config: {}", e);
+            None
+        }
+    };
+    let vars = vec![
+        ("name", name.to_string()),
+        ("streak", streak.to_string()),
+        ("date", today.to_string()),
+    ];
+
+    dispatch::notify(
+        app,
+        EventNotification {
+            event: PreferenceEvent::SigninSuccess,
+            message: signin_message(name, streak, milestone, &quote),
+            email,
+            webhook: Some(WebhookPayload {
+                event: WebhookEvent::Signin,
+                vars: webhooks::template_vars(clock.as_ref(), WebhookEvent::Signin, vars),
+            }),
+            critical: false,
+        },
+    )
+    .await;
+}
+
+/// Sign in automatically at launch when enabled in settings and today is still missing
+pub(crate) async fn auto_signin_on_launch(app: AppHandle) {
+    let settings = match storage::load_settings() {
+        Ok(settings) => settings,
+        Err(e) => {
+            log::warn!("Failed to load app settings: {}", e);
             return;
         }
     };
+    if !settings.auto_signin_on_launch {
+        return;
+    }
 
-    let quote = fetch_hitokoto().await.unwrap_or_else(|e| {
-        log::warn!("Failed to fetch quote, using fallback: {}", e);
-        get_fallback_quote()
-    });
+    let state = app.state::<AppState>();
+    let saved = match state.signin_data() {
+        Ok(Some(data)) => data,
+        Ok(None) => {
+            log::info!("Auto sign-in skipped: no user has signed in on this device yet");
+            return;
+        }
+        Err(e) => {
+            log::warn!("Failed to load sign-in data for auto sign-in: {}", e);
+            return;
+        }
+    };
+    let clock = app.state::<SharedClock>().inner().clone();
+    if saved.last_signin_date == get_today_date(clock.as_ref(), &settings) {
+        log::debug!("Auto sign-in skipped: already signed in today");
+        return;
+    }
+
+    log::info!("Performing automatic sign-in on launch for {}", saved.name);
+    let event = match perform_signin(&app, clock.as_ref(), saved.name, None, None).await {
+        Ok(data) => AutoSigninEvent {
+            success: true,
+            data: Some(data),
+            error: None,
+        },
+        Err(e) => {
+            log::error!("Automatic sign-in failed: {}", e);
+            AutoSigninEvent {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+            }
+        }
+    };
+
+    if let Err(e) = app.emit("auto-signin", event) {
+        log::error!("Failed to emit auto-signin event: {}", e);
+    }
+}
+
+/// Count the streak restores still available in the rolling window ending today
+fn remaining_streak_restores(data: &SigninData, today: NaiveDate) -> u32 {
+    let window_start = today - chrono::Duration::days(STREAK_RESTORE_WINDOW_DAYS);
+    let used = data
+        .streak_restores
+        .iter()
+        .filter_map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .filter(|d| *d > window_start)
+        .count() as u32;
+    STREAK_RESTORE_ALLOWANCE.saturating_sub(used)
+}
+
+#[tauri::command]
+pub fn get_streak_recovery_status(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+) -> Result<StreakRecoveryStatus, AppError> {
+    log::info!("Getting streak recovery status");
+    let data = state.signin_data().map_err(|e| {
+        log::error!("Failed to load sign-in data: {}", e);
+        e.to_string()
+    })?;
+    let settings = storage::load_settings().map_err(|e| {
+        log::error!("Failed to load app settings: {}", e);
+        e.to_string()
+    })?;
+    let today = signin_day(clock.as_ref(), &settings);
+
+    let Some(data) = data else {
+        return Ok(StreakRecoveryStatus {
+            available: false,
+            previous_streak: None,
+            remaining_restores: STREAK_RESTORE_ALLOWANCE,
+        });
+    };
+
+    let remaining_restores = remaining_streak_restores(&data, today);
+    let previous_streak = data.broken_streak.as_ref().map(|b| b.streak);
+    Ok(StreakRecoveryStatus {
+        available: previous_streak.is_some()
+            && data.last_signin_date == today.format("%Y-%m-%d").to_string()
+            && remaining_restores > 0,
+        previous_streak,
+        remaining_restores,
+    })
+}
+
+#[tauri::command]
+pub fn restore_streak(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+) -> Result<SigninData, AppError> {
+    log::info!("Streak restore requested");
+    let mut data = state
+        .signin_data()
+        .map_err(|e| {
+            log::error!("Failed to load sign-in data: {}", e);
+            e.to_string()
+        })?
+        .ok_or_else(|| t!("error.no_signin_data").into_owned())?;
+    let settings = storage::load_settings().map_err(|e| {
+        log::error!("Failed to load app settings: {}", e);
+        e.to_string()
+    })?;
+    let today = signin_day(clock.as_ref(), &settings);
 
-    if let Err(e) = send_signin_email(name, streak, &quote, &email_config) {
-        log::error!("Failed to send email notification: {}", e);
+    if data.last_signin_date != today.format("%Y-%m-%d").to_string() {
+        return Err(t!("error.restore_needs_signin").into());
     }
+
+    let broken = data.broken_streak.clone().ok_or_else(|| {
+        log::warn!("No broken streak available to restore");
+        t!("error.no_streak_to_restore").into_owned()
+    })?;
+
+    if remaining_streak_restores(&data, today) == 0 {
+        log::warn!("Streak restore allowance exhausted");
+        return Err(t!("error.no_restores_left").into());
+    }
+
+    let missed_date = NaiveDate::parse_from_str(&broken.last_signin_date, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid last sign-in date: {}", e))?
+        + chrono::Duration::days(1);
+
+    data.streak_restores
+        .push(missed_date.format("%Y-%m-%d").to_string());
+    data.streak =
+        calculate_streak(&data.signin_history, &data.streak_restores, settings.streak_policy);
+    data.goal_progress = settings
+        .streak_goal
+        .map(|target| goal_progress(data.streak, target, today));
+    data.broken_streak = None;
+
+    state.save_signin_data(&data).map_err(|e| {
+        log::error!("Failed to save sign-in data: {}", e);
+        e.to_string()
+    })?;
+
+    log::info!("Streak restored to {} days", data.streak);
+    Ok(data)
+}
+
+#[tauri::command]
+pub fn get_time_until_deadline(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+) -> Result<DeadlineStatus, AppError> {
+    log::info!("Getting time until check-in deadline");
+    let settings = storage::load_settings().map_err(|e| {
+        log::error!("Failed to load app settings: {}", e);
+        e.to_string()
+    })?;
+    let data = state.signin_data().map_err(|e| {
+        log::error!("Failed to load sign-in data: {}", e);
+        e.to_string()
+    })?;
+
+    let deadline = checkin_deadline(clock.as_ref(), &settings);
+    let seconds_remaining = (deadline - clock.now()).num_seconds().max(0);
+    let is_signed_in_today = data
+        .as_ref()
+        .map(|d| d.last_signin_date == get_today_date(clock.as_ref(), &settings))
+        .unwrap_or(false);
+
+    Ok(DeadlineStatus {
+        deadline_at: deadline.to_rfc3339(),
+        seconds_remaining,
+        is_signed_in_today,
+        streak_at_risk: if is_signed_in_today {
+            0
+        } else {
+            data.map(|d| d.streak).unwrap_or(0)
+        },
+    })
 }
 
 #[tauri::command]
-pub fn signout() -> Result<(), String> {
+pub fn signout(state: State<'_, AppState>) -> Result<(), AppError> {
     log::info!("User signed out, clearing all sign-in data");
-    storage::delete_data().map_err(|e| {
+    state.delete_signin_data().map_err(|e| {
         log::error!("Failed to delete sign-in data: {}", e);
-        e.to_string()
+        e.to_string().into()
     })
 }
 
@@ -165,11 +772,208 @@ pub fn signout() -> Result<(), String> {
 // =============================================================================
 
 #[tauri::command]
-pub async fn get_daily_quote() -> Result<Quote, String> {
+pub async fn get_daily_quote() -> Result<Quote, AppError> {
     log::info!("Fetching daily quote");
-    fetch_hitokoto().await.map_err(|e| {
-        log::error!("Failed to fetch daily quote: {}", e);
-        e
+    fetch_hitokoto()
+        .await
+        .inspect_err(|e| log::error!("Failed to fetch daily quote: {}", e))
+}
+
+// =============================================================================
+// Report Commands
+// =============================================================================
+
+/// Parse an optional YYYY-MM-DD reference date, defaulting to today
+fn parse_reference_date(today: NaiveDate, date: Option<String>) -> Result<NaiveDate, AppError> {
+    match date {
+        Some(d) => NaiveDate::parse_from_str(&d, "%Y-%m-%d").map_err(|e| {
+            log::warn!("Invalid reference date '{}': {}", d, e);
+            t!("error.invalid_date", error = e).into()
+        }),
+        None => Ok(today),
+    }
+}
+
+#[tauri::command]
+pub fn get_period_report(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+    period: ReportPeriod,
+    date: Option<String>,
+) -> Result<PeriodReport, AppError> {
+    log::info!("Building {:?} report", period);
+    load_period_report(&state, clock.as_ref(), period, date)
+}
+
+/// Build a report from stored sign-in data
+fn load_period_report(
+    state: &AppState,
+    clock: &dyn Clock,
+    period: ReportPeriod,
+    date: Option<String>,
+) -> Result<PeriodReport, AppError> {
+    let settings = storage::load_settings().map_err(|e| {
+        log::error!("Failed to load app settings: {}", e);
+        e.to_string()
+    })?;
+    let today = signin_day(clock, &settings);
+    let reference = parse_reference_date(today, date)?;
+    let data = state.signin_data().map_err(|e| {
+        log::error!("Failed to load sign-in data: {}", e);
+        e.to_string()
+    })?;
+    Ok(build_period_report(&data, period, reference, today))
+}
+
+#[tauri::command]
+pub async fn send_period_report(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+    period: ReportPeriod,
+    date: Option<String>,
+) -> Result<(), AppError> {
+    log::info!("Emailing {:?} report", period);
+    let report = load_period_report(&state, clock.as_ref(), period, date)?;
+    let email_config = state.email_config().map_err(|e| {
+        log::error!("Failed to load email config: {}", e);
+        e.to_string()
+    })?;
+    if !email_config.enabled {
+        return Err(t!("error.email_disabled").into());
+    }
+    send_email(&report_email(&report), &email_config).await
+}
+
+// =============================================================================
+// Settings Commands
+// =============================================================================
+
+#[tauri::command]
+pub fn get_settings(state: State<'_, AppState>) -> Result<Settings, AppError> {
+    log::info!("Getting settings");
+    settings::load(&state)
+}
+
+#[tauri::command]
+pub fn update_settings(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    patch: serde_json::Value,
+) -> Result<Settings, AppError> {
+    log::info!("Updating settings");
+    settings::update(&app, &state, &patch)
+}
+
+// =============================================================================
+// App Settings Commands
+// =============================================================================
+
+#[tauri::command]
+pub fn get_app_settings() -> Result<AppSettings, AppError> {
+    log::info!("Getting app settings");
+    storage::load_settings().map_err(|e| {
+        log::error!("Failed to load app settings: {}", e);
+        e.to_string().into()
+    })
+}
+
+#[tauri::command]
+pub fn save_app_settings(app: AppHandle, settings: AppSettings) -> Result<(), AppError> {
+    log::info!("Saving app settings");
+    settings::save_app(&app, &settings)
+}
+
+#[tauri::command]
+pub fn get_available_locales() -> Vec<LocaleInfo> {
+    log::info!("Getting available locales");
+    i18n::available_locales()
+}
+
+#[tauri::command]
+pub fn set_autostart(app: AppHandle, enabled: bool) -> Result<(), AppError> {
+    log::info!("Setting autostart to {}", enabled);
+    #[cfg(desktop)]
+    crate::autostart::apply_autostart(&app, enabled)?;
+
+    let mut settings = storage::load_settings().map_err(|e| {
+        log::error!("Failed to load app settings: {}", e);
+        e.to_string()
+    })?;
+    settings.enable_autostart = enabled;
+    storage::save_settings(&settings).map_err(|e| {
+        log::error!("Failed to save app settings: {}", e);
+        e.to_string()
+    })?;
+    changes::config_updated(&app, ConfigKind::Settings);
+    Ok(())
+}
+
+// =============================================================================
+// Reminder Commands
+// =============================================================================
+
+#[tauri::command]
+pub fn get_reminder_config() -> Result<ReminderConfig, AppError> {
+    log::info!("Getting reminder configuration");
+    storage::load_reminder_config().map_err(|e| {
+        log::error!("Failed to load reminder config: {}", e);
+        e.to_string().into()
+    })
+}
+
+#[tauri::command]
+pub fn set_reminder_config(app: AppHandle, config: ReminderConfig) -> Result<(), AppError> {
+    log::info!("Saving reminder configuration: enabled={}", config.enabled);
+    settings::save_reminder(&app, &config)
+}
+
+#[tauri::command]
+pub fn snooze_reminder(
+    reminder_state: State<'_, ReminderState>,
+    clock: State<'_, SharedClock>,
+    minutes: Option<u32>,
+) -> Result<String, AppError> {
+    let minutes = match minutes {
+        Some(m) => m,
+        None => {
+            storage::load_reminder_config()
+                .map_err(|e| {
+                    log::error!("Failed to load reminder config: {}", e);
+                    e.to_string()
+                })?
+                .snooze_minutes
+        }
+    };
+    log::info!("Snoozing reminders for {} minutes", minutes);
+    let until = reminder_state.snooze(clock.as_ref(), minutes)?;
+    Ok(until.to_rfc3339())
+}
+
+// =============================================================================
+// Escalation Commands
+// =============================================================================
+
+#[tauri::command]
+pub fn get_escalation_policy() -> Result<EscalationPolicy, AppError> {
+    log::info!("Getting escalation policy");
+    storage::load_escalation_policy().map_err(|e| {
+        log::error!("Failed to load escalation policy: {}", e);
+        e.to_string().into()
+    })
+}
+
+#[tauri::command]
+pub fn set_escalation_policy(app: AppHandle, policy: EscalationPolicy) -> Result<(), AppError> {
+    log::info!("Saving escalation policy: enabled={}", policy.enabled);
+    settings::save_escalation(&app, &policy)
+}
+
+#[tauri::command]
+pub fn get_escalation_log() -> Result<Vec<EscalationLogEntry>, AppError> {
+    log::info!("Getting escalation log");
+    storage::load_escalation_log().map_err(|e| {
+        log::error!("Failed to load escalation log: {}", e);
+        e.to_string().into()
     })
 }
 
@@ -178,87 +982,255 @@ pub async fn get_daily_quote() -> Result<Quote, String> {
 // =============================================================================
 
 #[tauri::command]
-pub fn get_email_config() -> Result<EmailConfig, String> {
+pub fn get_email_config(state: State<'_, AppState>) -> Result<EmailConfig, AppError> {
     log::info!("Getting email configuration");
-    storage::load_email_config().map_err(|e| {
+    state.email_config().map_err(|e| {
         log::error!("Failed to load email config: {}", e);
-        e.to_string()
+        e.to_string().into()
     })
 }
 
 #[tauri::command]
-pub fn save_email_config_command(config: EmailConfig) -> Result<(), String> {
+pub fn save_email_config_command(
+    state: State<'_, AppState>,
+    config: EmailConfig,
+) -> Result<(), AppError> {
     log::info!("Saving email configuration: enabled={}", config.enabled);
-    storage::save_email_config(&config).map_err(|e| {
+    settings::save_email(&state, config)
+}
+
+#[tauri::command]
+pub async fn send_test_email(
+    state: State<'_, AppState>,
+    config: Option<EmailConfig>,
+) -> Result<EmailTestResult, AppError> {
+    log::info!("Sending test email");
+    let config = match config {
+        Some(mut config) => {
+            config.migrate_legacy_recipient();
+            config
+        }
+        None => state.email_config().map_err(|e| {
+            log::error!("Failed to load email config: {}", e);
+            e.to_string()
+        })?,
+    };
+    if !config.has_recipients() {
+        return Err(t!("error.no_recipient").into());
+    }
+    Ok(run_email_test(&config).await)
+}
+
+#[tauri::command]
+pub async fn authorize_email_oauth(
+    app: AppHandle,
+    provider: OAuth2Provider,
+    client_id: String,
+    client_secret: Option<String>,
+) -> Result<(), AppError> {
+    log::info!("Starting {:?} OAuth2 authorization for email", provider);
+    let credentials = oauth::authorize(&app, provider, client_id, client_secret)
+        .await
+        .map_err(|e| {
+            log::error!("OAuth2 authorization failed: {}", e);
+            e
+        })?;
+
+    let state = app.state::<AppState>();
+    let mut config = state.email_config().map_err(|e| {
+        log::error!("Failed to load email config: {}", e);
+        e.to_string()
+    })?;
+    config.auth_method = EmailAuthMethod::Oauth2;
+    config.oauth2 = Some(credentials);
+    state.save_email_config(&config).map_err(|e| {
         log::error!("Failed to save email config: {}", e);
+        e.to_string().into()
+    })
+}
+
+// =============================================================================
+// Notification Channel Commands
+// =============================================================================
+
+#[tauri::command]
+pub fn get_notification_channels() -> Result<NotificationChannels, AppError> {
+    log::info!("Getting notification channels");
+    storage::load_notification_channels().map_err(|e| {
+        log::error!("Failed to load notification channels: {}", e);
+        e.to_string().into()
+    })
+}
+
+#[tauri::command]
+pub fn save_notification_channels(
+    app: AppHandle,
+    channels: NotificationChannels,
+) -> Result<(), AppError> {
+    log::info!("Saving notification channels");
+    settings::save_notification_channels(&app, &channels)
+}
+
+#[tauri::command]
+pub fn get_webhooks() -> Result<Vec<Webhook>, AppError> {
+    log::info!("Getting webhooks");
+    storage::load_webhooks().map_err(|e| {
+        log::error!("Failed to load webhooks: {}", e);
+        e.to_string().into()
+    })
+}
+
+#[tauri::command]
+pub fn save_webhooks(app: AppHandle, mut webhooks: Vec<Webhook>) -> Result<Vec<Webhook>, AppError> {
+    log::info!("Saving {} webhooks", webhooks.len());
+    for webhook in &mut webhooks {
+        webhooks::parse_method(&webhook.method)?;
+        if webhook.url.is_empty() {
+            return Err(t!("error.webhook_no_url", name = webhook.name).into());
+        }
+        if webhook.id.is_empty() {
+            webhook.id = Uuid::new_v4().to_string();
+        }
+    }
+    storage::save_webhooks(&webhooks).map_err(|e| {
+        log::error!("Failed to save webhooks: {}", e);
         e.to_string()
+    })?;
+    changes::config_updated(&app, ConfigKind::Webhooks);
+    Ok(webhooks)
+}
+
+#[tauri::command]
+pub fn get_notification_history(
+    filter: Option<NotificationHistoryFilter>,
+) -> Result<Vec<NotificationHistoryEntry>, AppError> {
+    log::info!("Getting notification history");
+    history::query(&filter.unwrap_or_default()).map_err(|e| {
+        log::error!("Failed to load notification history: {}", e);
+        e.into()
     })
 }
 
+#[tauri::command]
+pub fn get_outbox() -> Result<Vec<OutboxItem>, AppError> {
+    log::info!("Getting outbox");
+    storage::load_outbox().map_err(|e| {
+        log::error!("Failed to load outbox: {}", e);
+        e.to_string().into()
+    })
+}
+
+#[tauri::command]
+pub async fn flush_outbox(app: AppHandle) -> Result<Vec<OutboxItem>, AppError> {
+    log::info!("Flushing outbox");
+    outbox::retry(&app, true).await.map_err(|e| {
+        log::error!("Failed to flush outbox: {}", e);
+        e.into()
+    })
+}
+
+#[tauri::command]
+pub fn remove_outbox_item(id: String) -> Result<Vec<OutboxItem>, AppError> {
+    log::info!("Removing outbox item {}", id);
+    let mut items = storage::load_outbox().map_err(|e| {
+        log::error!("Failed to load outbox: {}", e);
+        e.to_string()
+    })?;
+    items.retain(|item| item.id != id);
+    storage::save_outbox(&items).map_err(|e| {
+        log::error!("Failed to save outbox: {}", e);
+        e.to_string()
+    })?;
+    Ok(items)
+}
+
+#[tauri::command]
+pub fn get_notification_preferences() -> Result<NotificationPreferences, AppError> {
+    log::info!("Getting notification preferences");
+    storage::load_notification_preferences().map_err(|e| {
+        log::error!("Failed to load notification preferences: {}", e);
+        e.to_string().into()
+    })
+}
+
+#[tauri::command]
+pub fn save_notification_preferences(
+    app: AppHandle,
+    preferences: NotificationPreferences,
+) -> Result<(), AppError> {
+    log::info!("Saving notification preferences");
+    settings::save_notification_preferences(&app, &preferences)
+}
+
 // =============================================================================
 // Device Commands
 // =============================================================================
 
 #[tauri::command]
-pub fn get_device_config() -> Result<DeviceConfig, String> {
+pub fn get_device_config(state: State<'_, AppState>) -> Result<DeviceConfig, AppError> {
     log::info!("Getting device configuration");
-    storage::load_or_create_device_config().map_err(|e| {
+    state.device_config().map_err(|e| {
         log::error!("Failed to load device config: {}", e);
-        e.to_string()
+        e.to_string().into()
     })
 }
 
 #[tauri::command]
-pub fn set_device_mode(mode: DeviceMode) -> Result<DeviceConfig, String> {
+pub fn set_device_mode(
+    state: State<'_, AppState>,
+    mode: DeviceMode,
+) -> Result<DeviceConfig, AppError> {
     log::info!("Setting device mode to {:?}", mode);
-    let mut config = storage::load_or_create_device_config().map_err(|e| {
+    let config = state.device_config().map_err(|e| {
         log::error!("Failed to load device config: {}", e);
         e.to_string()
     })?;
-    config.device.mode = mode;
-    storage::save_device_config(&config).map_err(|e| {
-        log::error!("Failed to save device config: {}", e);
-        e.to_string()
-    })?;
-    Ok(config)
+    settings::save_device(
+        &state,
+        &DeviceSettings {
+            device_name: config.device.device_name,
+            mode,
+        },
+    )?;
+    state.device_config().map_err(|e| e.to_string().into())
 }
 
 #[tauri::command]
-pub fn update_device_name(name: String) -> Result<DeviceConfig, String> {
+pub fn update_device_name(
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<DeviceConfig, AppError> {
     log::info!("Updating device name to {}", name);
-    let mut config = storage::load_or_create_device_config().map_err(|e| {
+    let config = state.device_config().map_err(|e| {
         log::error!("Failed to load device config: {}", e);
         e.to_string()
     })?;
-    config.device.device_name = name.clone();
-    storage::save_device_config(&config).map_err(|e| {
-        log::error!("Failed to save device config: {}", e);
-        e.to_string()
-    })?;
+    settings::save_device(
+        &state,
+        &DeviceSettings {
+            device_name: name.clone(),
+            mode: config.device.mode,
+        },
+    )?;
     log::info!("Device name updated successfully to {}", name);
-    Ok(config)
+    state.device_config().map_err(|e| e.to_string().into())
 }
 
 #[tauri::command]
-pub fn set_device_imei(imei: String) -> Result<DeviceConfig, String> {
+pub fn set_device_imei(state: State<'_, AppState>, imei: String) -> Result<DeviceConfig, AppError> {
     log::info!("Setting device IMEI");
-    let mut config = storage::load_or_create_device_config().map_err(|e| {
-        log::error!("Failed to load device config: {}", e);
-        e.to_string()
-    })?;
-    config.device.imei = Some(imei.clone());
-    storage::save_device_config(&config).map_err(|e| {
-        log::error!("Failed to save device config: {}", e);
-        e.to_string()
+    let config = state.update_device_config(|config| {
+        config.device.imei = Some(imei.clone());
+        Ok(config.clone())
     })?;
     log::info!("Device IMEI set successfully");
     Ok(config)
 }
 
 #[tauri::command]
-pub async fn get_device_imei() -> Result<String, String> {
+pub async fn get_device_imei(state: State<'_, AppState>) -> Result<String, AppError> {
     log::info!("Getting device IMEI");
-    let config = storage::load_or_create_device_config().map_err(|e| {
+    let config = state.device_config().map_err(|e| {
         log::error!("Failed to load device config: {}", e);
         e.to_string()
     })?;
@@ -269,21 +1241,48 @@ pub async fn get_device_imei() -> Result<String, String> {
 // Supervision Request Commands
 // =============================================================================
 
+/// Trim a supervision request message, dropping it when blank
+fn normalize_request_message(message: Option<String>) -> Result<Option<String>, AppError> {
+    let message = message
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty());
+    if let Some(message) = &message {
+        if message.chars().count() > SUPERVISION_REQUEST_MESSAGE_MAX_CHARS {
+            return Err(t!(
+                "error.request_message_too_long",
+                max = SUPERVISION_REQUEST_MESSAGE_MAX_CHARS
+            )
+            .into());
+        }
+    }
+    Ok(message)
+}
+
 #[tauri::command]
-pub fn send_supervision_request(target_device_id: String) -> Result<SupervisionRequest, String> {
+pub async fn send_supervision_request(
+    app: AppHandle,
+    target_device_id: String,
+    message: Option<String>,
+) -> Result<SupervisionRequest, AppError> {
     log::info!("Sending supervision request to device {}", target_device_id);
-    let mut config = storage::load_or_create_device_config().map_err(|e| {
+    let message = normalize_request_message(message)?;
+    let state = app.state::<AppState>();
+    let config = state.device_config().map_err(|e| {
         log::error!("Failed to load device config: {}", e);
         e.to_string()
     })?;
 
-    if config.device.mode != DeviceMode::Supervisor {
+    if !config.device.mode.can_supervise() {
         log::warn!(
             "Non-supervisor device {} attempted to send supervision request",
             config.device.device_id
         );
-        return Err("Only supervisor devices can send supervision requests".to_string());
+        return Err(t!("error.supervisor_only_request").into());
     }
+    let (target_device_id, identified_by) = supervision::resolve_target(&target_device_id).await?;
+    let clock = app.state::<SharedClock>();
+    request_limits::check_request(clock.as_ref(), &target_device_id)?;
+    let now = clock.now();
 
     let request = SupervisionRequest {
         request_id: Uuid::new_v4().to_string(),
@@ -291,7 +1290,11 @@ pub fn send_supervision_request(target_device_id: String) -> Result<SupervisionR
         supervisor_device_name: config.device.device_name.clone(),
         target_device_id: target_device_id.clone(),
         status: SupervisionRequestStatus::Pending,
-        created_at: Utc::now().to_rfc3339(),
+        created_at: now.to_rfc3339(),
+        expires_at: Some((now + chrono::Duration::days(SUPERVISION_REQUEST_TTL_DAYS)).to_rfc3339()),
+        message,
+        supervisor_public_key: crypto::shareable_public_key(),
+        identified_by,
     };
 
     log::info!(
@@ -300,55 +1303,79 @@ pub fn send_supervision_request(target_device_id: String) -> Result<SupervisionR
         target_device_id
     );
 
-    config.supervision_requests.push(request.clone());
-    storage::save_device_config(&config).map_err(|e| {
-        log::error!("Failed to save device config: {}", e);
-        e.to_string()
+    state.update_device_config(|config| {
+        config.supervision_requests.push(request.clone());
+        Ok(())
     })?;
+    request_limits::record_request(
+        clock.as_ref(),
+        &request.target_device_id,
+        request.expires_at.as_deref(),
+    );
+    supervision::record_history(
+        SupervisionHistoryKind::RequestSent,
+        &request.target_device_id,
+        None,
+        Some(request.request_id.clone()),
+        request.message.clone(),
+    );
 
+    webhooks::dispatch(
+        &app,
+        WebhookEvent::SupervisionRequested,
+        vec![
+            ("name", config.device.device_name.clone()),
+            ("supervisor_device_id", request.supervisor_device_id.clone()),
+            ("target_device_id", request.target_device_id.clone()),
+        ],
+    );
     Ok(request)
 }
 
 #[tauri::command]
-pub fn cancel_supervision_request(request_id: String) -> Result<(), String> {
+pub fn cancel_supervision_request(
+    state: State<'_, AppState>,
+    request_id: String,
+) -> Result<(), AppError> {
     log::info!("Cancelling supervision request {}", request_id);
-    let mut config = storage::load_or_create_device_config().map_err(|e| {
-        log::error!("Failed to load device config: {}", e);
-        e.to_string()
-    })?;
-
-    let request = config
-        .supervision_requests
-        .iter_mut()
-        .find(|r| r.request_id == request_id)
-        .ok_or_else(|| {
-            log::warn!("Supervision request {} not found", request_id);
-            "Request not found".to_string()
-        })?;
-
-    request.status = SupervisionRequestStatus::Cancelled;
-    storage::save_device_config(&config).map_err(|e| {
-        log::error!("Failed to save device config: {}", e);
-        e.to_string()
+    let target_id = state.update_device_config(|config| {
+        let request = config
+            .supervision_requests
+            .iter_mut()
+            .find(|r| r.request_id == request_id)
+            .ok_or_else(|| {
+                log::warn!("Supervision request {} not found", request_id);
+                t!("error.request_not_found").into_owned()
+            })?;
+        request.status = SupervisionRequestStatus::Cancelled;
+        Ok(request.target_device_id.clone())
     })?;
+    request_limits::clear_pending(&target_id);
     log::info!("Supervision request {} cancelled successfully", request_id);
     Ok(())
 }
 
 #[tauri::command]
-pub fn get_pending_supervision_requests() -> Result<Vec<SupervisionRequest>, String> {
+pub fn get_pending_supervision_requests(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+) -> Result<Vec<SupervisionRequest>, AppError> {
     log::info!("Getting pending supervision requests");
-    let config = storage::load_or_create_device_config().map_err(|e| {
+    let config = state.device_config().map_err(|e| {
         log::error!("Failed to load device config: {}", e);
         e.to_string()
     })?;
     let my_device_id = &config.device.device_id;
+    let now = clock.now();
 
     let pending: Vec<SupervisionRequest> = config
         .supervision_requests
         .iter()
         .filter(|r| {
-            r.target_device_id == *my_device_id && r.status == SupervisionRequestStatus::Pending
+            r.target_device_id == *my_device_id
+                && r.status == SupervisionRequestStatus::Pending
+                && !r.is_expired(now)
+                && !config.blocked_device_ids.contains(&r.supervisor_device_id)
         })
         .cloned()
         .collect();
@@ -358,58 +1385,82 @@ pub fn get_pending_supervision_requests() -> Result<Vec<SupervisionRequest>, Str
 }
 
 #[tauri::command]
-pub fn accept_supervision_request(request_id: String) -> Result<SupervisionRelationship, String> {
+pub fn accept_supervision_request(
+    app: AppHandle,
+    request_id: String,
+    identified_by: Option<RequestIdentifier>,
+) -> Result<SupervisionRelationship, AppError> {
     log::info!("Accepting supervision request {}", request_id);
-    let mut config = storage::load_or_create_device_config().map_err(|e| {
-        log::error!("Failed to load device config: {}", e);
-        e.to_string()
-    })?;
-
-    let request = find_pending_request(&config, &request_id)?;
-    validate_request_target(&config, &request)?;
-
-    let relationship = create_relationship_from_request(&config, &request);
-    log::info!(
-        "Creating supervision relationship: {} supervised by {}",
-        config.device.device_id,
-        request.supervisor_device_id
-    );
-
-    config.supervision_relationships.push(relationship.clone());
-
-    update_request_status(&mut config, &request_id, SupervisionRequestStatus::Accepted);
-    storage::save_device_config(&config).map_err(|e| {
-        log::error!("Failed to save device config: {}", e);
-        e.to_string()
+    let state = app.state::<AppState>();
+    let now = app.state::<SharedClock>().now();
+
+    let (request, relationship) = state.update_device_config(|config| {
+        let request = find_pending_request(config, &request_id, now)?;
+        validate_request_target(config, &request)?;
+        supervision::confirm_identifier(request.identified_by, identified_by)?;
+
+        let relationship = create_relationship_from_request(config, &request, now);
+        log::info!(
+            "Creating supervision relationship: {} supervised by {}",
+            config.device.device_id,
+            request.supervisor_device_id
+        );
+        config.supervision_relationships.push(relationship.clone());
+        update_request_status(config, &request_id, SupervisionRequestStatus::Accepted);
+        Ok((request, relationship))
     })?;
 
+    request_limits::clear_pending(&request.target_device_id);
     log::info!("Supervision request {} accepted successfully", request_id);
+    supervision::record_history(
+        SupervisionHistoryKind::RequestAccepted,
+        &request.supervisor_device_id,
+        Some(request.supervisor_device_name.clone()),
+        Some(request_id.clone()),
+        None,
+    );
+    webhooks::dispatch(
+        &app,
+        WebhookEvent::SupervisionAccepted,
+        vec![
+            ("name", relationship.supervised_device_name.clone()),
+            ("supervisor_name", relationship.supervisor_device_name.clone()),
+            ("supervisor_device_id", relationship.supervisor_device_id.clone()),
+            ("supervised_device_id", relationship.supervised_device_id.clone()),
+        ],
+    );
     Ok(relationship)
 }
 
-/// Find a pending supervision request by ID
+/// Find a pending, unexpired supervision request by ID
 fn find_pending_request(
     config: &DeviceConfig,
     request_id: &str,
-) -> Result<SupervisionRequest, String> {
-    config
+    now: DateTime<Utc>,
+) -> Result<SupervisionRequest, AppError> {
+    let request = config
         .supervision_requests
         .iter()
         .find(|r| r.request_id == request_id && r.status == SupervisionRequestStatus::Pending)
         .cloned()
         .ok_or_else(|| {
             log::warn!("Pending supervision request {} not found", request_id);
-            "Request not found or already processed".to_string()
-        })
+            t!("error.request_processed").into_owned()
+        })?;
+    if request.is_expired(now) {
+        log::warn!("Supervision request {} has expired", request_id);
+        return Err(t!("error.request_expired").into());
+    }
+    Ok(request)
 }
 
 /// Validate that the request is for this device
 fn validate_request_target(
     config: &DeviceConfig,
     request: &SupervisionRequest,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     if request.target_device_id != config.device.device_id {
-        return Err("This request is not for this device".to_string());
+        return Err(t!("error.request_not_for_device").into());
     }
     Ok(())
 }
@@ -418,6 +1469,7 @@ fn validate_request_target(
 fn create_relationship_from_request(
     config: &DeviceConfig,
     request: &SupervisionRequest,
+    now: DateTime<Utc>,
 ) -> SupervisionRelationship {
     SupervisionRelationship {
         relationship_id: Uuid::new_v4().to_string(),
@@ -425,8 +1477,18 @@ fn create_relationship_from_request(
         supervisor_device_name: request.supervisor_device_name.clone(),
         supervised_device_id: config.device.device_id.clone(),
         supervised_device_name: config.device.device_name.clone(),
-        established_at: Utc::now().to_rfc3339(),
-        last_sync_at: Utc::now().to_rfc3339(),
+        established_at: now.to_rfc3339(),
+        last_sync_at: now.to_rfc3339(),
+        muted: false,
+        wellness_questions: vec![],
+        sharing_policy: SharingPolicy::default(),
+        missed_days_threshold: None,
+        valid_until: None,
+        remote_relation_id: None,
+        nickname: None,
+        partner_public_key: request.supervisor_public_key.clone(),
+        offered_partner_key: None,
+        partner_key_verified: false,
     }
 }
 
@@ -446,34 +1508,91 @@ fn update_request_status(
 }
 
 #[tauri::command]
-pub fn reject_supervision_request(request_id: String) -> Result<(), String> {
+pub fn reject_supervision_request(app: AppHandle, request_id: String) -> Result<(), AppError> {
     log::info!("Rejecting supervision request {}", request_id);
-    let mut config = storage::load_or_create_device_config().map_err(|e| {
+    let state = app.state::<AppState>();
+    let (request, device_name) = state.update_device_config(|config| {
+        let device_id = config.device.device_id.clone();
+        let request = config
+            .supervision_requests
+            .iter_mut()
+            .find(|r| r.request_id == request_id)
+            .ok_or_else(|| {
+                log::warn!("Supervision request {} not found", request_id);
+                t!("error.request_not_found").into_owned()
+            })?;
+        if request.target_device_id != device_id {
+            log::warn!("Supervision request {} not for this device", request_id);
+            return Err(t!("error.request_not_for_device").into());
+        }
+        request.status = SupervisionRequestStatus::Rejected;
+        Ok((request.clone(), config.device.device_name.clone()))
+    })?;
+    request_limits::clear_pending(&request.target_device_id);
+
+    supervision::record_history(
+        SupervisionHistoryKind::RequestRejected,
+        &request.supervisor_device_id,
+        Some(request.supervisor_device_name.clone()),
+        Some(request_id.clone()),
+        None,
+    );
+    let vars = vec![
+        ("name", device_name),
+        ("supervisor_name", request.supervisor_device_name),
+        ("supervisor_device_id", request.supervisor_device_id),
+    ];
+    log::info!("Supervision request {} rejected successfully", request_id);
+    webhooks::dispatch(&app, WebhookEvent::SupervisionRejected, vars);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn block_device(state: State<'_, AppState>, device_id: String) -> Result<(), AppError> {
+    log::info!("Blocking device {}", device_id);
+    let config = state.device_config().map_err(|e| {
         log::error!("Failed to load device config: {}", e);
         e.to_string()
     })?;
+    block_device_api(&config.device.device_id, &device_id).await?;
 
-    let request = config
-        .supervision_requests
-        .iter_mut()
-        .find(|r| r.request_id == request_id)
-        .ok_or_else(|| {
-            log::warn!("Supervision request {} not found", request_id);
-            "Request not found".to_string()
-        })?;
+    state.update_device_config(|config| {
+        if !config.blocked_device_ids.contains(&device_id) {
+            config.blocked_device_ids.push(device_id.clone());
+        }
+        for request in config.supervision_requests.iter_mut().filter(|r| {
+            r.supervisor_device_id == device_id && r.status == SupervisionRequestStatus::Pending
+        }) {
+            log::info!("Rejecting supervision request {} from blocked device", request.request_id);
+            request.status = SupervisionRequestStatus::Rejected;
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn unblock_device(state: State<'_, AppState>, device_id: String) -> Result<(), AppError> {
+    log::info!("Unblocking device {}", device_id);
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    unblock_device_api(&config.device.device_id, &device_id).await?;
 
-    if request.target_device_id != config.device.device_id {
-        log::warn!("Supervision request {} not for this device", request_id);
-        return Err("This request is not for this device".to_string());
-    }
+    state.update_device_config(|config| {
+        config.blocked_device_ids.retain(|id| id != &device_id);
+        Ok(())
+    })
+}
 
-    request.status = SupervisionRequestStatus::Rejected;
-    storage::save_device_config(&config).map_err(|e| {
-        log::error!("Failed to save device config: {}", e);
+#[tauri::command]
+pub fn get_blocked_devices(state: State<'_, AppState>) -> Result<Vec<String>, AppError> {
+    log::info!("Getting blocked devices");
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
         e.to_string()
     })?;
-    log::info!("Supervision request {} rejected successfully", request_id);
-    Ok(())
+    Ok(config.blocked_device_ids)
 }
 
 // =============================================================================
@@ -481,87 +1600,216 @@ pub fn reject_supervision_request(request_id: String) -> Result<(), String> {
 // =============================================================================
 
 #[tauri::command]
-pub fn remove_supervision_relationship(relationship_id: String) -> Result<(), String> {
+pub async fn remove_supervision_relationship(
+    app: AppHandle,
+    relationship_id: String,
+) -> Result<(), AppError> {
     log::info!("Removing supervision relationship {}", relationship_id);
-    let mut config = storage::load_or_create_device_config().map_err(|e| {
+    let state = app.state::<AppState>();
+    let config = state.device_config().map_err(|e| {
         log::error!("Failed to load device config: {}", e);
         e.to_string()
     })?;
 
-    let initial_len = config.supervision_relationships.len();
-    config
+    let removed = config
         .supervision_relationships
-        .retain(|r| r.relationship_id != relationship_id);
-
-    if config.supervision_relationships.len() < initial_len {
-        storage::save_device_config(&config).map_err(|e| {
-            log::error!("Failed to save device config: {}", e);
-            e.to_string()
+        .iter()
+        .find(|r| r.relationship_id == relationship_id)
+        .cloned()
+        .ok_or_else(|| {
+            log::warn!("Supervision relationship {} not found", relationship_id);
+            t!("error.relationship_not_found").into_owned()
         })?;
-        log::info!("Supervision relationship {} removed successfully", relationship_id);
+
+    // Offline removals are retried by the next supervision sync
+    let removed_remotely = supervision::remove_remote_relationship(&config, &removed)
+        .await
+        .map_err(|e| log::warn!("Failed to remove relationship on the server, will retry: {}", e))
+        .is_ok();
+    let config = state.update_device_config(|config| {
+        config
+            .supervision_relationships
+            .retain(|r| r.relationship_id != relationship_id);
+        if !removed_remotely {
+            config.pending_removals.push(removed.clone());
+        }
+        Ok(config.clone())
+    })?;
+    log::info!("Supervision relationship {} removed successfully", relationship_id);
+    supervision::record_removal(&config, &removed, None);
+    supervision::notify_relationship_removed(&app, &config, removed);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_supervision_muted(
+    state: State<'_, AppState>,
+    relationship_id: String,
+    muted: bool,
+) -> Result<(), AppError> {
+    log::info!("Setting supervision relationship {} muted: {}", relationship_id, muted);
+    state.update_device_config(|config| {
+        let relationship = config
+            .supervision_relationships
+            .iter_mut()
+            .find(|r| r.relationship_id == relationship_id)
+            .ok_or_else(|| {
+                log::warn!("Supervision relationship {} not found", relationship_id);
+                t!("error.relationship_not_found").into_owned()
+            })?;
+        relationship.muted = muted;
         Ok(())
-    } else {
-        log::warn!("Supervision relationship {} not found", relationship_id);
-        Err("Relationship not found".to_string())
+    })
+}
+
+#[tauri::command]
+pub fn set_relationship_nickname(
+    state: State<'_, AppState>,
+    relationship_id: String,
+    nickname: Option<String>,
+) -> Result<SupervisionRelationship, AppError> {
+    log::info!("Setting nickname of supervision relationship {}", relationship_id);
+    let nickname = nickname
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty());
+    if nickname
+        .as_ref()
+        .is_some_and(|n| n.chars().count() > RELATIONSHIP_NICKNAME_MAX_CHARS)
+    {
+        return Err(t!("error.nickname_too_long", max = RELATIONSHIP_NICKNAME_MAX_CHARS).into());
     }
+    state.update_device_config(|config| {
+        let relationship = config
+            .supervision_relationships
+            .iter_mut()
+            .find(|r| r.relationship_id == relationship_id)
+            .ok_or_else(|| {
+                log::warn!("Supervision relationship {} not found", relationship_id);
+                t!("error.relationship_not_found").into_owned()
+            })?;
+        relationship.nickname = nickname;
+        Ok(relationship.clone())
+    })
 }
 
 #[tauri::command]
-pub fn get_supervised_devices() -> Result<Vec<DeviceStatus>, String> {
-    log::info!("Getting supervised devices");
-    let config = storage::load_or_create_device_config().map_err(|e| {
+pub async fn set_supervision_valid_until(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+    relationship_id: String,
+    valid_until: Option<String>,
+) -> Result<SupervisionRelationship, AppError> {
+    log::info!(
+        "Setting end of supervision relationship {} to {:?}",
+        relationship_id,
+        valid_until
+    );
+    if let Some(valid_until) = &valid_until {
+        let end = DateTime::parse_from_rfc3339(valid_until).map_err(|e| {
+            log::warn!("Invalid supervision end {}: {}", valid_until, e);
+            t!("error.invalid_end_time", error = e)
+        })?;
+        if end.to_utc() <= clock.now() {
+            return Err(t!("error.end_time_in_past").into());
+        }
+    }
+    let config = state.device_config().map_err(|e| {
         log::error!("Failed to load device config: {}", e);
         e.to_string()
     })?;
-    let signin_data = storage::load_data().map_err(|e| {
-        log::error!("Failed to load sign-in data: {}", e);
-        e.to_string()
-    })?;
-    let today = get_today_date();
 
-    let statuses: Vec<DeviceStatus> = config
+    let relationship = config
         .supervision_relationships
         .iter()
-        .filter(|r| r.supervisor_device_id == config.device.device_id)
-        .map(|relationship| build_device_status(relationship, &signin_data, &today))
-        .collect();
+        .find(|r| r.relationship_id == relationship_id)
+        .ok_or_else(|| {
+            log::warn!("Supervision relationship {} not found", relationship_id);
+            t!("error.relationship_not_found").into_owned()
+        })?;
+    set_relationship_valid_until_api(
+        &relationship.supervisor_device_id,
+        &relationship.supervised_device_id,
+        valid_until.as_deref(),
+    )
+    .await?;
+
+    state.update_device_config(|config| {
+        let relationship = config
+            .supervision_relationships
+            .iter_mut()
+            .find(|r| r.relationship_id == relationship_id)
+            .ok_or_else(|| t!("error.relationship_not_found").into_owned())?;
+        relationship.valid_until = valid_until;
+        Ok(relationship.clone())
+    })
+}
 
-    log::info!("Found {} supervised devices", statuses.len());
-    Ok(statuses)
+#[tauri::command]
+pub async fn get_supervised_devices(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+    call_id: Option<String>,
+) -> Result<Vec<DeviceStatus>, AppError> {
+    log::info!("Getting supervised devices");
+    cancellable(call_id, supervision::supervised_devices(&state, clock.as_ref())).await
 }
 
-/// Build a device status from a supervision relationship
-fn build_device_status(
-    relationship: &SupervisionRelationship,
-    signin_data: &Option<SigninData>,
-    today: &str,
-) -> DeviceStatus {
-    let is_signed_in_today = signin_data
-        .as_ref()
-        .map(|d| d.last_signin_date == today)
-        .unwrap_or(false);
+#[tauri::command]
+pub fn get_supervision_snapshot() -> Result<SupervisionSnapshot, AppError> {
+    log::info!("Getting supervision snapshot");
+    storage::load_supervision_snapshot().map_err(|e| {
+        log::error!("Failed to load supervision snapshot: {}", e);
+        e.to_string().into()
+    })
+}
 
-    DeviceStatus {
-        device_id: relationship.supervised_device_id.clone(),
-        device_name: relationship.supervised_device_name.clone(),
-        last_signin_date: signin_data
-            .as_ref()
-            .map(|d| d.last_signin_date.clone())
-            .unwrap_or_default(),
-        streak: signin_data.as_ref().map(|d| d.streak).unwrap_or(0),
-        is_signed_in_today,
-        last_sync_at: relationship.last_sync_at.clone(),
-    }
+#[tauri::command]
+pub async fn sync_supervision(
+    app: AppHandle,
+    call_id: Option<String>,
+) -> Result<SupervisionSnapshot, AppError> {
+    log::info!("Syncing supervision state");
+    cancellable(call_id, supervision::sync(&app)).await
+}
+
+#[tauri::command]
+pub async fn get_supervisor_dashboard(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+    call_id: Option<String>,
+) -> Result<Vec<SupervisedDeviceStats>, AppError> {
+    log::info!("Getting supervisor dashboard");
+    let supervised_devices =
+        cancellable(call_id, supervision::supervised_devices(&state, clock.as_ref())).await?;
+    dashboard::supervisor_dashboard(clock.as_ref(), &supervised_devices)
+}
+
+#[tauri::command]
+pub async fn get_supervised_history(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+    device_id: String,
+    days: u32,
+    call_id: Option<String>,
+) -> Result<Vec<CheckinDay>, AppError> {
+    log::info!("Getting {} days of check-in history for {}", days, device_id);
+    let history = dashboard::supervised_history(&state, clock.as_ref(), &device_id, days);
+    cancellable(call_id, history).await
 }
 
 #[tauri::command]
-pub fn get_supervisor_status() -> Result<SupervisorStatus, String> {
+pub async fn get_supervisor_status(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+    call_id: Option<String>,
+) -> Result<SupervisorStatus, AppError> {
     log::info!("Getting supervisor status");
-    let config = storage::load_or_create_device_config().map_err(|e| {
+    let config = state.device_config().map_err(|e| {
         log::error!("Failed to load device config: {}", e);
         e.to_string()
     })?;
-    let supervised_devices = get_supervised_devices()?;
+    let supervised_devices =
+        cancellable(call_id, supervision::supervised_devices(&state, clock.as_ref())).await?;
 
     let pending_requests: Vec<SupervisionRequest> = config
         .supervision_requests
@@ -569,6 +1817,7 @@ pub fn get_supervisor_status() -> Result<SupervisorStatus, String> {
         .filter(|r| {
             r.supervisor_device_id == config.device.device_id
                 && r.status == SupervisionRequestStatus::Pending
+                && !r.is_expired(clock.now())
         })
         .cloned()
         .collect();
@@ -595,14 +1844,15 @@ pub async fn device_register(
     device_name: String,
     imei: Option<String>,
     mode: String,
-) -> Result<RemoteDevice, String> {
+) -> Result<RemoteDevice, AppError> {
     log::info!("Registering remote device: {} (mode: {})", device_name, mode);
     let remote_mode = match mode.as_str() {
         "signin" => RemoteDeviceMode::Signin,
         "supervisor" => RemoteDeviceMode::Supervisor,
+        "both" => RemoteDeviceMode::Both,
         _ => {
             log::warn!("Invalid device mode: {}", mode);
-            return Err("Invalid device mode".to_string());
+            return Err(t!("error.invalid_device_mode").into());
         }
     };
 
@@ -610,7 +1860,7 @@ pub async fn device_register(
 }
 
 #[tauri::command]
-pub async fn device_get_info(device_id: String) -> Result<RemoteDevice, String> {
+pub async fn device_get_info(device_id: String) -> Result<RemoteDevice, AppError> {
     log::info!("Getting remote device info for {}", device_id);
     get_device(&device_id).await
 }
@@ -619,76 +1869,414 @@ pub async fn device_get_info(device_id: String) -> Result<RemoteDevice, String>
 pub async fn device_update_name_api(
     device_id: String,
     new_name: String,
-) -> Result<RemoteDevice, String> {
+) -> Result<RemoteDevice, AppError> {
     log::info!("Updating remote device name: {} -> {}", device_id, new_name);
     update_device_name_api(&device_id, &new_name).await
 }
 
 #[tauri::command]
-pub async fn device_signin_api(device_id: String) -> Result<SigninResponse, String> {
+pub async fn device_signin_api(
+    clock: State<'_, SharedClock>,
+    device_id: String,
+) -> Result<Option<SigninResponse>, AppError> {
     log::info!("Remote device sign-in for {}", device_id);
-    device_signin(&device_id).await
+    let settings = storage::load_settings().map_err(|e| {
+        log::error!("Failed to load app settings: {}", e);
+        e.to_string()
+    })?;
+    let day = signin_day(clock.as_ref(), &settings);
+    let signed_at = clock.now().to_rfc3339();
+    match device_signin(&device_id, Some(&signed_at), day).await {
+        Ok(response) => Ok(Some(response)),
+        Err(e) if e.is_unreachable() => {
+            let operation = PendingOperation::Signin {
+                device_id,
+                signed_at,
+                day: Some(day.format("%Y-%m-%d").to_string()),
+            };
+            sync_queue::enqueue(clock.as_ref(), operation, &e)?;
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[tauri::command]
+pub fn cancel_api_requests(call_id: String) {
+    log::info!("Frontend abandoned call {}", call_id);
+    cancel_call(&call_id);
+}
+
+#[tauri::command]
+pub fn get_recent_api_log(limit: Option<usize>) -> Vec<ApiCallLogEntry> {
+    log::info!("Getting recent API calls");
+    recent_calls(limit.unwrap_or(RECENT_API_CALLS))
+}
+
+#[tauri::command]
+pub fn get_scheduler_status() -> Vec<ScheduledJobStatus> {
+    log::info!("Getting scheduler status");
+    scheduler::status()
+}
+
+#[tauri::command]
+pub fn get_recent_logs(
+    level: Option<LogLevel>,
+    limit: Option<usize>,
+) -> Result<Vec<LogEntry>, AppError> {
+    log::info!("Getting recent log records");
+    logs::recent(level.unwrap_or_default(), limit.unwrap_or(RECENT_LOG_RECORDS))
+}
+
+#[tauri::command]
+pub async fn get_server_version() -> Result<ServerVersion, AppError> {
+    log::info!("Checking server API version");
+    probe_server_version().await
 }
 
 #[tauri::command]
-pub async fn device_search(query: String) -> Result<Vec<RemoteDevice>, String> {
+pub async fn device_search(
+    query: String,
+    call_id: Option<String>,
+) -> Result<Vec<RemoteDevice>, AppError> {
     log::info!("Searching remote devices with query: {}", query);
-    search_devices(&query).await
+    cancellable(call_id, search_devices(&query)).await
 }
 
 #[tauri::command]
 pub async fn supervision_request_api(
+    clock: State<'_, SharedClock>,
     supervisor_id: String,
     target_id: String,
-) -> Result<RemoteSupervisionRequest, String> {
+    message: Option<String>,
+) -> Result<RemoteSupervisionRequest, AppError> {
     log::info!("Sending remote supervision request: {} -> {}", supervisor_id, target_id);
-    send_supervision_request_api(&supervisor_id, &target_id).await
+    let message = normalize_request_message(message)?;
+    let (target_id, identified_by) = supervision::resolve_target(&target_id).await?;
+    request_limits::check_request(clock.as_ref(), &target_id)?;
+    let public_key = crypto::shareable_public_key();
+    let request = send_supervision_request_api(
+        &supervisor_id,
+        &target_id,
+        message.as_deref(),
+        public_key.as_deref(),
+        identified_by,
+    )
+    .await
+    .inspect_err(|e| request_limits::record_failure(clock.as_ref(), e))?;
+    request_limits::record_request(clock.as_ref(), &target_id, request.expires_at.as_deref());
+    Ok(request)
 }
 
 #[tauri::command]
 pub async fn supervision_get_pending(
+    app: AppHandle,
     device_id: String,
-) -> Result<Vec<RemoteSupervisionRequest>, String> {
+) -> Result<Vec<RemoteSupervisionRequest>, AppError> {
     log::info!("Getting pending supervision requests for remote device {}", device_id);
-    get_pending_requests(&device_id).await
+    let requests = get_pending_requests(&device_id).await?;
+    supervision::announce_new_requests(&app, &requests).await;
+    Ok(requests)
+}
+
+#[tauri::command]
+pub async fn generate_pairing_qr(state: State<'_, AppState>) -> Result<PairingQr, AppError> {
+    log::info!("Generating pairing QR code");
+    supervision::generate_pairing_qr(&state).await
+}
+
+#[tauri::command]
+pub async fn pair_from_qr(
+    app: AppHandle,
+    payload: String,
+) -> Result<SupervisionRelation, AppError> {
+    log::info!("Pairing from scanned QR code");
+    supervision::pair_from_qr(&app, &payload).await
+}
+
+#[tauri::command]
+pub fn get_partner_key_status(
+    state: State<'_, AppState>,
+    relationship_id: String,
+) -> Result<PartnerKeyStatus, AppError> {
+    log::info!("Getting partner key status of relationship {}", relationship_id);
+    supervision::partner_key_status(&state, &relationship_id)
+}
+
+#[tauri::command]
+pub fn confirm_partner_key(
+    state: State<'_, AppState>,
+    relationship_id: String,
+    safety_number: String,
+) -> Result<(), AppError> {
+    log::info!("Confirming partner key of relationship {}", relationship_id);
+    supervision::confirm_partner_key(&state, &relationship_id, &safety_number)
+}
+
+#[tauri::command]
+pub async fn create_invite_code(state: State<'_, AppState>) -> Result<InviteCode, AppError> {
+    log::info!("Creating supervision invite code");
+    supervision::create_invite_code(&state).await
+}
+
+#[tauri::command]
+pub async fn redeem_invite_code(
+    state: State<'_, AppState>,
+    code: String,
+) -> Result<RemoteSupervisionRequest, AppError> {
+    log::info!("Redeeming supervision invite code");
+    supervision::redeem_invite_code(&state, &code).await
+}
+
+#[tauri::command]
+pub async fn initiate_transfer(
+    state: State<'_, AppState>,
+    relationship_id: String,
+) -> Result<TransferCode, AppError> {
+    log::info!("Initiating transfer of supervision relationship {}", relationship_id);
+    supervision::initiate_transfer(&state, &relationship_id).await
+}
+
+#[tauri::command]
+pub async fn accept_transfer(
+    app: AppHandle,
+    code: String,
+) -> Result<SupervisionRelationship, AppError> {
+    log::info!("Accepting supervision transfer");
+    supervision::accept_transfer(&app, &code).await
+}
+
+#[tauri::command]
+pub async fn send_checkin_ping(
+    state: State<'_, AppState>,
+    device_id: String,
+) -> Result<CheckinPing, AppError> {
+    log::info!("Sending check-in ping to {}", device_id);
+    pings::send_checkin_ping(&state, &device_id).await
+}
+
+#[tauri::command]
+pub async fn respond_checkin_ping(ping_id: String) -> Result<CheckinPing, AppError> {
+    log::info!("Responding to check-in ping {}", ping_id);
+    pings::respond_checkin_ping(&ping_id).await
+}
+
+#[tauri::command]
+pub async fn nudge_device(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+    device_id: String,
+) -> Result<Nudge, AppError> {
+    log::info!("Nudging {} to check in", device_id);
+    nudges::nudge_device(&state, clock.as_ref(), &device_id).await
+}
+
+#[tauri::command]
+pub async fn send_sos(app: AppHandle, message: Option<String>) -> Result<(), AppError> {
+    log::warn!("Sending SOS");
+    sos::send_sos(&app, message).await
+}
+
+#[tauri::command]
+pub async fn send_encouragement(
+    state: State<'_, AppState>,
+    device_id: String,
+    text: String,
+) -> Result<Encouragement, AppError> {
+    log::info!("Sending encouragement to {}", device_id);
+    encouragements::send_encouragement(&state, &device_id, &text).await
+}
+
+#[tauri::command]
+pub async fn set_wellness_questions(
+    state: State<'_, AppState>,
+    device_id: String,
+    questions: Vec<String>,
+) -> Result<Vec<WellnessQuestion>, AppError> {
+    log::info!("Setting wellness questions for {}", device_id);
+    wellness::set_questions(&state, &device_id, questions).await
+}
+
+#[tauri::command]
+pub fn get_wellness_questions() -> Result<Vec<WellnessQuestion>, AppError> {
+    log::info!("Getting wellness questions");
+    storage::load_wellness_questions().map_err(|e| {
+        log::error!("Failed to load wellness questions: {}", e);
+        e.to_string().into()
+    })
+}
+
+#[tauri::command]
+pub async fn set_missed_days_threshold(
+    state: State<'_, AppState>,
+    relationship_id: String,
+    days: Option<u32>,
+) -> Result<(), AppError> {
+    log::info!("Setting missed-days threshold of {} to {:?}", relationship_id, days);
+    alerts::set_missed_days_threshold(&state, &relationship_id, days).await
+}
+
+#[tauri::command]
+pub async fn set_sharing_policy(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+    relationship_id: String,
+    policy: SharingPolicy,
+) -> Result<(), AppError> {
+    log::info!("Setting sharing policy of {} to {:?}", relationship_id, policy);
+    sharing::set_sharing_policy(&state, clock.as_ref(), &relationship_id, policy).await
+}
+
+#[tauri::command]
+pub async fn pause_sharing(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+    until: Option<String>,
+) -> Result<(), AppError> {
+    log::info!("Pausing sharing until {:?}", until);
+    sharing::pause_sharing(&state, clock.as_ref(), until).await
+}
+
+#[tauri::command]
+pub async fn get_supervision_history(
+    state: State<'_, AppState>,
+    relationship_id: Option<String>,
+    call_id: Option<String>,
+) -> Result<Vec<SupervisionHistoryEntry>, AppError> {
+    log::info!("Getting supervision history");
+    if let Some(relationship_id) = relationship_id {
+        return cancellable(call_id, timeline::relationship_history(&state, &relationship_id))
+            .await;
+    }
+    let mut history = storage::load_supervision_history().map_err(|e| {
+        log::error!("Failed to load supervision history: {}", e);
+        e.to_string()
+    })?;
+    history.reverse();
+    Ok(history)
 }
 
 #[tauri::command]
 pub async fn supervision_accept_api(
+    app: AppHandle,
+    clock: State<'_, SharedClock>,
     supervisor_id: String,
     target_id: String,
-) -> Result<(), String> {
+    identified_by: Option<RequestIdentifier>,
+) -> Result<(), AppError> {
     log::info!("Accepting remote supervision request: {} -> {}", supervisor_id, target_id);
-    accept_supervision_request_api(&supervisor_id, &target_id).await
+    let state = app.state::<AppState>();
+    let result =
+        supervision::accept_remote_request(&state, &supervisor_id, &target_id, identified_by).await;
+    match result {
+        Err(e) if e.is_unreachable() => {
+            let operation = PendingOperation::AcceptRequest {
+                supervisor_id,
+                target_id,
+                confirmed: identified_by,
+            };
+            Ok(sync_queue::enqueue(clock.as_ref(), operation, &e)?)
+        }
+        result => {
+            result?;
+            remote_supervision_updated(&app);
+            Ok(())
+        }
+    }
 }
 
 #[tauri::command]
 pub async fn supervision_reject_api(
+    app: AppHandle,
+    clock: State<'_, SharedClock>,
     supervisor_id: String,
     target_id: String,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     log::info!("Rejecting remote supervision request: {} -> {}", supervisor_id, target_id);
-    reject_supervision_request_api(&supervisor_id, &target_id).await
+    match reject_supervision_request_api(&supervisor_id, &target_id).await {
+        Err(e) if e.is_unreachable() => {
+            let operation = PendingOperation::RejectRequest {
+                supervisor_id,
+                target_id,
+            };
+            Ok(sync_queue::enqueue(clock.as_ref(), operation, &e)?)
+        }
+        result => {
+            result?;
+            remote_supervision_updated(&app);
+            Ok(())
+        }
+    }
+}
+
+/// Tell the frontend about a supervision change made on the server only
+fn remote_supervision_updated(app: &AppHandle) {
+    match app.state::<AppState>().device_config() {
+        Ok(config) => changes::supervision_updated(app, &config),
+        Err(e) => log::warn!("Failed to load device config: {}", e),
+    }
+}
+
+#[tauri::command]
+pub fn get_pending_sync_items() -> Result<Vec<PendingSyncItem>, AppError> {
+    log::info!("Getting pending sync items");
+    sync_queue::pending_items()
 }
 
 #[tauri::command]
-pub async fn supervision_list_api(device_id: String) -> Result<Vec<SupervisionRelation>, String> {
+pub async fn supervision_list_api(device_id: String) -> Result<Vec<SupervisionRelation>, AppError> {
     log::info!("Getting supervision list for remote device {}", device_id);
     get_supervision_list(&device_id).await
 }
 
 #[tauri::command]
-pub async fn supervision_remove_api(relation_id: String) -> Result<(), String> {
+pub async fn supervision_remove_api(relation_id: String) -> Result<(), AppError> {
     log::info!("Removing remote supervision relationship {}", relation_id);
     remove_supervision_relationship_api(&relation_id).await
 }
 
 #[tauri::command]
-pub async fn device_get_status(device_id: String) -> Result<RemoteDeviceStatus, String> {
+pub async fn device_get_status(device_id: String) -> Result<RemoteDeviceStatus, AppError> {
     log::info!("Getting remote device status for {}", device_id);
     get_device_status(&device_id).await
 }
 
+#[tauri::command]
+pub async fn get_device_signin_history(
+    device_id: String,
+    from: String,
+    to: String,
+    call_id: Option<String>,
+) -> Result<Vec<SigninRecord>, AppError> {
+    log::info!("Getting remote sign-in history for {} from {} to {}", device_id, from, to);
+    let parse = |date: &str| {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|e| t!("error.invalid_date", error = e))
+    };
+    if parse(&from)? > parse(&to)? {
+        return Err(t!("error.date_range_reversed").into());
+    }
+    cancellable(call_id, get_device_signin_history_api(&device_id, &from, &to)).await
+}
+
+#[tauri::command]
+pub async fn get_all_supervised_statuses(
+    state: State<'_, AppState>,
+    call_id: Option<String>,
+) -> Result<Vec<RemoteDeviceStatus>, AppError> {
+    log::info!("Getting remote status of all supervised devices");
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let device_ids: Vec<String> = config
+        .supervision_relationships
+        .iter()
+        .filter(|r| r.supervisor_device_id == config.device.device_id)
+        .map(|r| r.supervised_device_id.clone())
+        .collect();
+    cancellable(call_id, get_device_statuses(&device_ids)).await
+}
+
 // =============================================================================
 // Notification Commands
 // =============================================================================
@@ -698,7 +2286,7 @@ pub async fn send_notification_command(
     app: tauri::AppHandle,
     title: String,
     body: String,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     log::info!("Sending notification: {} - {}", title, body);
 
     app.notification()
@@ -708,6 +2296,213 @@ pub async fn send_notification_command(
         .show()
         .map_err(|e| {
             log::error!("Failed to show notification: {}", e);
-            e.to_string()
+            e.to_string().into()
+        })
+}
+
+// =============================================================================
+// Deep Link Commands
+// =============================================================================
+
+#[tauri::command]
+pub fn take_launch_link() -> Option<DeepLink> {
+    log::info!("Taking the link the app was started with");
+    crate::deep_link::take_launch_link()
+}
+
+// =============================================================================
+// Crash Report Commands
+// =============================================================================
+
+#[tauri::command]
+pub fn get_last_crash_report() -> Result<Option<CrashReport>, AppError> {
+    log::info!("Getting last crash report");
+    storage::load_crash_report().map_err(|e| {
+        log::error!("Failed to load crash report: {}", e);
+        e.to_string().into()
+    })
+}
+
+#[tauri::command]
+pub async fn submit_crash_report(clock: State<'_, SharedClock>) -> Result<CrashReport, AppError> {
+    log::info!("Submitting last crash report");
+    crash::submit(clock.as_ref()).await
+}
+
+// =============================================================================
+// Update Commands
+// =============================================================================
+
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle) -> Result<UpdateInfo, AppError> {
+    log::info!("Checking for updates");
+    #[cfg(desktop)]
+    return crate::updater::check(&app).await;
+    #[cfg(mobile)]
+    {
+        let _ = app;
+        Err(t!("error.updates_via_store").into())
+    }
+}
+
+#[tauri::command]
+pub async fn install_update(app: AppHandle) -> Result<(), AppError> {
+    log::info!("Installing update");
+    #[cfg(desktop)]
+    return crate::updater::install(&app).await;
+    #[cfg(mobile)]
+    {
+        let _ = app;
+        Err(t!("error.updates_via_store").into())
+    }
+}
+
+// =============================================================================
+// App Lock Commands
+// =============================================================================
+
+#[tauri::command]
+pub fn get_app_lock_status(app: AppHandle) -> AppLockStatus {
+    log::info!("Getting app lock status");
+    app_lock::status(&app)
+}
+
+#[tauri::command]
+pub fn set_app_pin(current_pin: Option<String>, new_pin: String) -> Result<(), AppError> {
+    log::info!("Setting app lock PIN");
+    app_lock::set_pin(current_pin.as_deref(), &new_pin)
+}
+
+#[tauri::command]
+pub fn remove_app_pin(pin: String) -> Result<(), AppError> {
+    log::info!("Removing app lock PIN");
+    app_lock::remove_pin(&pin)
+}
+
+#[tauri::command]
+pub fn unlock_app(pin: String) -> Result<(), AppError> {
+    log::info!("Unlocking app with PIN");
+    app_lock::unlock(&pin)
+}
+
+#[tauri::command]
+pub async fn unlock_app_with_biometrics(app: AppHandle) -> Result<(), AppError> {
+    log::info!("Unlocking app with biometrics");
+    #[cfg(mobile)]
+    return tauri::async_runtime::spawn_blocking(move || app_lock::unlock_with_biometrics(&app))
+        .await
+        .map_err(|e| e.to_string())?;
+    #[cfg(desktop)]
+    {
+        let _ = app;
+        Err(t!("error.biometrics_mobile_only").into())
+    }
+}
+
+#[tauri::command]
+pub fn lock_app() -> Result<(), AppError> {
+    log::info!("Locking app");
+    app_lock::lock()
+}
+
+// =============================================================================
+// Onboarding Commands
+// =============================================================================
+
+#[tauri::command]
+pub fn get_onboarding_state(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+) -> Result<OnboardingState, AppError> {
+    log::info!("Getting onboarding state");
+    onboarding::state(&state, clock.as_ref())
+}
+
+#[tauri::command]
+pub fn complete_onboarding_step(
+    state: State<'_, AppState>,
+    clock: State<'_, SharedClock>,
+    step: OnboardingStep,
+    skipped: Option<bool>,
+) -> Result<OnboardingState, AppError> {
+    log::info!("Completing onboarding step {:?}", step);
+    onboarding::complete_step(&state, clock.as_ref(), step, skipped.unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+
+    fn signed_in_on(last_signin_date: &str, history: &[&str], streak: i32) -> Option<SigninData> {
+        Some(SigninData {
+            name: "Test".to_string(),
+            last_signin_date: last_signin_date.to_string(),
+            streak,
+            signin_history: history.iter().map(|d| d.to_string()).collect(),
+            signin_entries: vec![],
+            broken_streak: None,
+            streak_restores: vec![],
+            goal_progress: None,
+            encouragements: vec![],
         })
+    }
+
+    fn sign_in(saved: &Option<SigninData>, clock: &FixedClock) -> SigninData {
+        let now = clock.now();
+        calculate_signin_data(saved, "Test", now.date_naive(), now, None, StreakPolicy::StrictDaily)
+            .unwrap()
+    }
+
+    #[test]
+    fn second_sign_in_before_midnight_changes_nothing() {
+        let saved = signed_in_on("2024-03-10", &["2024-03-09", "2024-03-10"], 2);
+        let clock = FixedClock::at("2024-03-10T23:59:59Z");
+        let data = sign_in(&saved, &clock);
+        assert_eq!(data.streak, 2);
+        assert_eq!(data.signin_history.len(), 2);
+    }
+
+    #[test]
+    fn sign_in_after_midnight_extends_the_streak() {
+        let saved = signed_in_on("2024-03-10", &["2024-03-09", "2024-03-10"], 2);
+        let clock = FixedClock::at("2024-03-11T00:00:00Z");
+        let data = sign_in(&saved, &clock);
+        assert_eq!(data.streak, 3);
+        assert_eq!(data.last_signin_date, "2024-03-11");
+        assert_eq!(data.signin_entries.last().unwrap().date, "2024-03-11");
+    }
+
+    #[test]
+    fn sign_in_on_leap_day_extends_the_streak() {
+        let saved = signed_in_on("2024-02-28", &["2024-02-28"], 1);
+        let clock = FixedClock::at("2024-02-29T09:00:00Z");
+        assert_eq!(sign_in(&saved, &clock).streak, 2);
+    }
+
+    #[test]
+    fn one_missed_day_keeps_the_streak_for_recovery() {
+        let saved = signed_in_on("2024-02-28", &["2024-02-27", "2024-02-28"], 2);
+        let clock = FixedClock::at("2024-03-01T09:00:00Z");
+        let data = sign_in(&saved, &clock);
+        assert_eq!(data.streak, 1);
+        assert_eq!(data.broken_streak.map(|b| b.streak), Some(2));
+    }
+
+    #[test]
+    fn clock_moved_backwards_keeps_the_saved_data() {
+        let saved = signed_in_on("2024-03-11", &["2024-03-10", "2024-03-11"], 2);
+        let clock = FixedClock::at("2024-03-11T08:00:00Z");
+        clock.set("2024-03-10T22:00:00Z");
+        let data = sign_in(&saved, &clock);
+        assert_eq!(data.last_signin_date, "2024-03-11");
+        assert_eq!(data.signin_history.len(), 2);
+        assert!(data.signin_entries.is_empty());
+    }
+
+    #[test]
+    fn exactly_one_missed_day_spans_month_end() {
+        assert!(missed_exactly_one_day("2024-02-28", "2024-03-01"));
+        assert!(!missed_exactly_one_day("2023-02-28", "2023-03-01"));
+    }
 }
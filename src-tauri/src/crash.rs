@@ -0,0 +1,199 @@
+//! Crash reports for panics.
+//!
+//! A panic anywhere in the backend writes a report to the app directory: the
+//! panic message and location, a backtrace, the app version and platform, and
+//! the last [`LOG_LINES`] log lines. Names, device IDs and email addresses the
+//! app knows about are replaced before the report is saved, and anything else
+//! that looks like an email address is masked as well.
+//!
+//! Only the latest report is kept. Nothing leaves the device unless the user
+//! opted in with
+//! [`AppSettings::submit_crash_reports`](crate::models::AppSettings::submit_crash_reports):
+//! the next launch then sends a `crash-report` event so the frontend can offer
+//! to submit the report, which it does with `submit_crash_report`.
+
+use std::backtrace::Backtrace;
+use std::panic::PanicHookInfo;
+use std::sync::OnceLock;
+
+use rust_i18n::t;
+use tauri::{AppHandle, Emitter, Manager};
+use uuid::Uuid;
+
+use crate::api_client::submit_crash_report;
+use crate::clock::{self, Clock};
+use crate::error::AppError;
+use crate::logs;
+use crate::models::{CrashReport, LogLevel};
+use crate::state::AppState;
+use crate::storage;
+
+/// Number of log lines included in a report
+const LOG_LINES: usize = 100;
+
+/// Replacement for personal data in a report
+const REDACTED: &str = "<redacted>";
+
+/// App whose cached configuration the reports are redacted with, once it runs
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+/// Save a crash report whenever a thread panics, after the default panic output
+pub fn install_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if let Err(e) = storage::save_crash_report(&build_report(info)) {
+            eprintln!("Failed to save crash report: {}", e);
+        }
+    }));
+}
+
+/// Redact later reports with the configuration `app` holds in memory
+pub fn attach(app: &AppHandle) {
+    let _ = APP.set(app.clone());
+}
+
+/// Offer an unsubmitted report to the frontend when the user opted in
+pub fn offer_pending(app: &AppHandle) {
+    let opted_in = storage::load_settings().is_ok_and(|settings| settings.submit_crash_reports);
+    if !opted_in {
+        return;
+    }
+    match storage::load_crash_report() {
+        Ok(Some(report)) if report.submitted_at.is_none() => {
+            log::info!("Offering crash report {} for submission", report.id);
+            if let Err(e) = app.emit("crash-report", report) {
+                log::warn!("Failed to emit crash-report event: {}", e);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to load crash report: {}", e),
+    }
+}
+
+/// Send the latest report to the server, unless that already happened
+pub async fn submit(clock: &dyn Clock) -> Result<CrashReport, AppError> {
+    let settings = storage::load_settings().map_err(|e| e.to_string())?;
+    if !settings.submit_crash_reports {
+        return Err(t!("error.crash_reports_off").into());
+    }
+    let mut report = storage::load_crash_report()
+        .map_err(|e| {
+            log::error!("Failed to load crash report: {}", e);
+            e.to_string()
+        })?
+        .ok_or_else(|| t!("error.no_crash_report").into_owned())?;
+    if report.submitted_at.is_some() {
+        return Ok(report);
+    }
+
+    submit_crash_report(&report).await?;
+    report.submitted_at = Some(clock.now().to_rfc3339());
+    storage::save_crash_report(&report).map_err(|e| {
+        log::error!("Failed to save crash report: {}", e);
+        e.to_string()
+    })?;
+    log::info!("Submitted crash report {}", report.id);
+    Ok(report)
+}
+
+/// Describe a panic, with personal data removed
+fn build_report(info: &PanicHookInfo<'_>) -> CrashReport {
+    let personal = known_personal_data();
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string());
+    let log_lines = logs::recent(LogLevel::Trace, LOG_LINES)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|entry| {
+            let line = format!(
+                "{} {:?} {}: {}",
+                entry.timestamp, entry.level, entry.target, entry.message
+            );
+            redact(&line, &personal)
+        })
+        .collect();
+
+    CrashReport {
+        id: Uuid::new_v4().to_string(),
+        occurred_at: clock::shared().now().to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        thread: std::thread::current()
+            .name()
+            .unwrap_or("unnamed")
+            .to_string(),
+        message: redact(&message, &personal),
+        location: info
+            .location()
+            .map(|location| format!("{}:{}", location.file(), location.line())),
+        backtrace: Backtrace::force_capture().to_string(),
+        log_lines,
+        submitted_at: None,
+    }
+}
+
+/// Names, device IDs and addresses stored by the app, longest first
+///
+/// Copies the running app already holds are used first; the files are only
+/// read for what isn't loaded yet, e.g. when the panic happens during startup.
+fn known_personal_data() -> Vec<String> {
+    let (device_config, email_config, signin_data) = APP
+        .get()
+        .map(|app| app.state::<AppState>().peek())
+        .unwrap_or_default();
+    let signin_data = signin_data.unwrap_or_else(|| storage::load_data().ok().flatten());
+    let email_config = email_config.or_else(|| storage::load_email_config().ok());
+    let device_config = device_config.or_else(|| storage::load_device_config().ok().flatten());
+
+    let mut values = Vec::new();
+    if let Some(data) = signin_data {
+        values.push(data.name);
+    }
+    if let Some(config) = email_config {
+        values.push(config.from_email);
+        values.push(config.smtp_username);
+        values.extend(config.recipients.into_iter().map(|r| r.address));
+    }
+    if let Some(config) = device_config {
+        values.push(config.device.device_id);
+        values.push(config.device.device_name);
+        values.extend(config.device.imei);
+        for relationship in config.supervision_relationships {
+            values.push(relationship.supervisor_device_id);
+            values.push(relationship.supervisor_device_name);
+            values.push(relationship.supervised_device_id);
+            values.push(relationship.supervised_device_name);
+            values.extend(relationship.nickname);
+        }
+    }
+    values.retain(|value| value.chars().count() >= 2);
+    values.sort_by_key(|value| std::cmp::Reverse(value.len()));
+    values.dedup();
+    values
+}
+
+/// Replace personal data and email addresses in `text`
+fn redact(text: &str, personal: &[String]) -> String {
+    let text = personal
+        .iter()
+        .fold(text.to_string(), |text, value| text.replace(value.as_str(), REDACTED));
+    text.split(' ')
+        .map(|word| {
+            let is_address = word
+                .split_once('@')
+                .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.'));
+            if is_address {
+                REDACTED
+            } else {
+                word
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,184 @@
+//! End-to-end encryption of what supervision partners share.
+//!
+//! Every device has a long-lived X25519 key pair. The secret half stays in the
+//! [`keychain`](crate::keychain). Public keys travel with supervision
+//! requests, their acceptance and the other ways of pairing, and the server
+//! hands each side the other's key with the relationship.
+//!
+//! Two partners derive the same symmetric key from their key pairs and use it
+//! to seal moods, wellness answers and encouragements with ChaCha20-Poly1305.
+//! Partners whose key is not known yet, such as relationships created by an
+//! older app version, keep getting the plaintext fields until a sync brings in
+//! their key.
+//!
+//! The keys come through the relay, so the relay could hand out its own. The
+//! first key received for a partner is pinned and never replaced by a sync: a
+//! different key is only held as offered, and the pinned one stays in use until
+//! the user accepts the new one. Once a relationship has a key it never falls
+//! back to plaintext. To rule out a swapped key from the start, both users can
+//! compare the [`safety_number`] of their pair, and pairing QR codes carry the
+//! [`key_fingerprint`] of the device showing them, which the scanning device
+//! checks against the key the server reports.
+
+use std::sync::Mutex;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use rust_i18n::t;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::error::AppError;
+use crate::keychain::{self, Secret};
+
+/// Prefix of sealed payloads, bumped whenever the format changes
+const SEALED_PREFIX: &str = "v1.";
+
+/// Context mixed into the derived keys so they are never reused elsewhere
+const KEY_CONTEXT: &[u8] = b"areuok supervision payload v1";
+
+/// Length of a ChaCha20-Poly1305 nonce in bytes
+const NONCE_LEN: usize = 12;
+
+/// Number of five-digit groups in safety numbers and key fingerprints
+const FINGERPRINT_GROUPS: usize = 6;
+
+/// This device's secret key, loaded from the keychain on first use
+static DEVICE_KEY: Mutex<Option<StaticSecret>> = Mutex::new(None);
+
+/// This device's public key, base64 encoded for the server
+pub fn public_key() -> Result<String, AppError> {
+    let secret = device_key()?;
+    Ok(BASE64.encode(PublicKey::from(&secret).as_bytes()))
+}
+
+/// This device's public key to send along when pairing, if it can be loaded
+///
+/// Pairing still works without a key; the partners then share plaintext.
+pub fn shareable_public_key() -> Option<String> {
+    public_key()
+        .map_err(|e| log::warn!("Pairing without an encryption key: {}", e))
+        .ok()
+}
+
+/// Number both partners see for their pair of keys, to compare out of band
+///
+/// The keys are hashed in a fixed order, so both devices show the same number
+/// as long as each holds the other's real key.
+pub fn safety_number(partner_key: &str) -> Result<String, AppError> {
+    let own_key = public_key()?;
+    let mut keys = [own_key.as_str(), partner_key];
+    keys.sort_unstable();
+    Ok(fingerprint(&keys))
+}
+
+/// Fingerprint of a single public key, as printed in pairing QR codes
+pub fn key_fingerprint(key: &str) -> String {
+    fingerprint(&[key])
+}
+
+/// Render the hash of `keys` as groups of five digits
+fn fingerprint(keys: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for key in keys {
+        hasher.update(key.as_bytes());
+    }
+    hasher
+        .finalize()
+        .chunks_exact(4)
+        .take(FINGERPRINT_GROUPS)
+        .map(|chunk| {
+            let value = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            format!("{:05}", value % 100_000)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Encrypt `value` so that only the device with `partner_key` can read it
+pub fn seal<T: Serialize>(partner_key: &str, value: &T) -> Result<String, AppError> {
+    let cipher = partner_cipher(partner_key)?;
+    let plaintext = serde_json::to_vec(value).map_err(|e| e.to_string())?;
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|_| "Failed to encrypt payload".to_string())?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    Ok(format!("{}{}", SEALED_PREFIX, BASE64.encode(sealed)))
+}
+
+/// Decrypt a payload the device with `partner_key` sealed for this device
+pub fn open<T: DeserializeOwned>(partner_key: &str, sealed: &str) -> Result<T, AppError> {
+    let encoded = sealed
+        .strip_prefix(SEALED_PREFIX)
+        .ok_or_else(|| "Unsupported encrypted payload".to_string())?;
+    let bytes = BASE64
+        .decode(encoded)
+        .map_err(|e| format!("Invalid encrypted payload: {}", e))?;
+    if bytes.len() < NONCE_LEN {
+        return Err("Encrypted payload is truncated".into());
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+
+    let plaintext = partner_cipher(partner_key)?
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Failed to decrypt payload".to_string())?;
+    serde_json::from_slice(&plaintext).map_err(|e| e.to_string().into())
+}
+
+/// Cipher keyed with the secret shared with the device owning `partner_key`
+fn partner_cipher(partner_key: &str) -> Result<ChaCha20Poly1305, AppError> {
+    let bytes: [u8; 32] = BASE64
+        .decode(partner_key)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| t!("error.invalid_partner_key").into_owned())?;
+    let shared = device_key()?.diffie_hellman(&PublicKey::from(bytes));
+    if !shared.was_contributory() {
+        return Err(t!("error.invalid_partner_key").into());
+    }
+
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(KEY_CONTEXT).map_err(|e| e.to_string())?;
+    mac.update(shared.as_bytes());
+    let key = mac.finalize().into_bytes();
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+/// This device's secret key, generated and stored on first use
+fn device_key() -> Result<StaticSecret, AppError> {
+    let mut cached = DEVICE_KEY
+        .lock()
+        .map_err(|_| "Device key is unavailable".to_string())?;
+    if let Some(secret) = cached.as_ref() {
+        return Ok(secret.clone());
+    }
+
+    let secret = match keychain::load(Secret::DeviceKey)? {
+        Some(encoded) => {
+            let bytes: [u8; 32] = BASE64
+                .decode(encoded)
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| {
+                    log::error!("Stored device key is corrupt");
+                    "Stored device key is corrupt".to_string()
+                })?;
+            StaticSecret::from(bytes)
+        }
+        None => {
+            log::info!("Generating device encryption key");
+            let secret = StaticSecret::random_from_rng(OsRng);
+            keychain::store(Secret::DeviceKey, &BASE64.encode(secret.to_bytes()))?;
+            secret
+        }
+    };
+    *cached = Some(secret.clone());
+    Ok(secret)
+}
@@ -0,0 +1,264 @@
+//! Aggregated statistics for the supervisor dashboard.
+//!
+//! A single "signed in today" flag hides slow changes, so the background sync
+//! also pulls the recent sign-in records every supervised device shares with
+//! this supervisor and keeps them locally. The dashboard derives compliance,
+//! typical check-in time and the gap since the last missed day from those
+//! records, which makes a gradual decline visible before it turns into a
+//! missed check-in alert. Devices whose sharing policy leaves out their
+//! history are shown as not sharing it, with no statistics.
+//!
+//! Supervisors can also open a device's day-by-day check-in calendar, which
+//! the server only hands out when the device shares its history with them.
+//! Otherwise the calendar is reported as not shared instead of empty.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Duration, Local, NaiveDate, Timelike};
+use rust_i18n::t;
+
+use crate::api_client::get_shared_history_api;
+use crate::clock::Clock;
+use crate::deadline::signin_day;
+use crate::error::AppError;
+use crate::models::{AppSettings, CheckinDay, DeviceStatus, SupervisedDeviceStats};
+use crate::remote_models::SigninRecord;
+use crate::reports::average_time_of_day;
+use crate::state::AppState;
+use crate::storage;
+use crate::streak::parse_dates;
+
+/// Days covered by the compliance and check-in time statistics
+const DASHBOARD_DAYS: i64 = 30;
+
+/// Days of sign-in records kept per supervised device
+const RECORD_RETENTION_DAYS: i64 = 90;
+
+/// Longest check-in calendar a supervisor can request
+const MAX_HISTORY_DAYS: u32 = 365;
+
+/// Pull recent sign-in records the supervised devices share and store them
+///
+/// Devices that can't be reached keep their previously synced records; a
+/// device that stopped sharing its history has its records dropped.
+pub async fn refresh_signin_records(
+    state: &AppState,
+    clock: &dyn Clock,
+    devices: &[DeviceStatus],
+) -> Result<(), AppError> {
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    // The records are only a cache, so a file in an older format starts over
+    let mut records = storage::load_supervised_signins().unwrap_or_else(|e| {
+        log::warn!("Failed to load supervised sign-in records: {}", e);
+        BTreeMap::new()
+    });
+    records.retain(|device_id, _| devices.iter().any(|d| &d.device_id == device_id));
+
+    let cutoff = current_day(clock) - Duration::days(RECORD_RETENTION_DAYS);
+    for device in devices {
+        let fetched = match get_shared_history_api(
+            &device.device_id,
+            &config.device.device_id,
+            DASHBOARD_DAYS as u32,
+        )
+        .await
+        {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                log::warn!("Failed to sync sign-in records of {}: {}", device.device_id, e);
+                continue;
+            }
+        };
+        if !fetched.is_shared() {
+            records.insert(device.device_id.clone(), fetched);
+            continue;
+        }
+        let stored = records
+            .entry(device.device_id.clone())
+            .or_insert_with(|| fetched.clone());
+        stored.policy = fetched.policy;
+        for record in fetched.records {
+            stored.records.retain(|r| r.date != record.date);
+            stored.records.push(record);
+        }
+        stored
+            .records
+            .retain(|r| record_date(r).is_some_and(|date| date >= cutoff));
+        stored.records.sort_by(|a, b| a.date.cmp(&b.date));
+    }
+
+    storage::save_supervised_signins(&records).map_err(|e| {
+        log::error!("Failed to save supervised sign-in records: {}", e);
+        AppError::from(e)
+    })
+}
+
+/// Current sign-in day, which the dashboard windows end on
+fn current_day(clock: &dyn Clock) -> NaiveDate {
+    let settings = storage::load_settings().unwrap_or_else(|e| {
+        log::warn!("Failed to load app settings: {}", e);
+        AppSettings::default()
+    });
+    signin_day(clock, &settings)
+}
+
+/// Statistics for every supervised device from the synced records
+pub fn supervisor_dashboard(
+    clock: &dyn Clock,
+    devices: &[DeviceStatus],
+) -> Result<Vec<SupervisedDeviceStats>, AppError> {
+    let records = storage::load_supervised_signins().map_err(|e| {
+        log::error!("Failed to load supervised sign-in records: {}", e);
+        e.to_string()
+    })?;
+    let today = current_day(clock);
+
+    Ok(devices
+        .iter()
+        .map(|device| match records.get(&device.device_id) {
+            Some(history) if !history.is_shared() => unshared_stats(device),
+            Some(history) => device_stats(device, &history.records, today),
+            None => device_stats(device, &[], today),
+        })
+        .collect())
+}
+
+/// Check-in calendar of a supervised device for the `days` days ending today
+///
+/// Fails with [`AppError::NotShared`] when the device keeps its history from
+/// this supervisor, rather than showing every day as missed.
+pub async fn supervised_history(
+    state: &AppState,
+    clock: &dyn Clock,
+    device_id: &str,
+    days: u32,
+) -> Result<Vec<CheckinDay>, AppError> {
+    if days == 0 || days > MAX_HISTORY_DAYS {
+        return Err(t!("error.history_days_range", max = MAX_HISTORY_DAYS).into());
+    }
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        AppError::from(e.to_string())
+    })?;
+    let supervisor_id = &config.device.device_id;
+    if !config
+        .supervision_relationships
+        .iter()
+        .any(|r| &r.supervisor_device_id == supervisor_id && r.supervised_device_id == device_id)
+    {
+        log::warn!("Device {} is not supervised by this device", device_id);
+        return Err(t!("error.not_supervised").into());
+    }
+
+    let history = get_shared_history_api(device_id, supervisor_id, days).await?;
+    if !history.is_shared() {
+        log::info!("Device {} does not share its history", device_id);
+        return Err(AppError::NotShared);
+    }
+    let today = current_day(clock);
+    Ok((0..i64::from(days))
+        .rev()
+        .map(|offset| today - Duration::days(offset))
+        .map(|date| {
+            let record = history
+                .records
+                .iter()
+                .find(|r| record_date(r) == Some(date));
+            CheckinDay {
+                date: date.format("%Y-%m-%d").to_string(),
+                signed_in: record.is_some(),
+                signed_at: record.and_then(|r| r.signed_at.clone()),
+            }
+        })
+        .collect())
+}
+
+/// Compute the statistics of one device
+fn device_stats(
+    device: &DeviceStatus,
+    records: &[SigninRecord],
+    today: NaiveDate,
+) -> SupervisedDeviceStats {
+    let mut dates: BTreeSet<NaiveDate> = records.iter().filter_map(record_date).collect();
+    if let Some(recent) = &device.recent_signins {
+        dates.extend(parse_dates(recent));
+    }
+    let window_start = today - Duration::days(DASHBOARD_DAYS - 1);
+
+    let signin_seconds: Vec<u32> = records
+        .iter()
+        .filter(|r| record_date(r).is_some_and(|date| date >= window_start))
+        .filter_map(|r| r.signed_at.as_deref())
+        .filter_map(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Local).num_seconds_from_midnight())
+        .collect();
+
+    SupervisedDeviceStats {
+        device_id: device.device_id.clone(),
+        device_name: device.device_name.clone(),
+        streak: device.streak,
+        last_signin_date: device.last_signin_date.clone(),
+        history_shared: true,
+        compliance_percent: compliance_percent(&dates, window_start, today),
+        average_signin_time: average_time_of_day(&signin_seconds),
+        days_since_last_miss: days_since_last_miss(&dates, today),
+    }
+}
+
+/// Statistics of a device that doesn't share its history with this supervisor
+fn unshared_stats(device: &DeviceStatus) -> SupervisedDeviceStats {
+    SupervisedDeviceStats {
+        device_id: device.device_id.clone(),
+        device_name: device.device_name.clone(),
+        streak: device.streak,
+        last_signin_date: device.last_signin_date.clone(),
+        history_shared: false,
+        compliance_percent: None,
+        average_signin_time: None,
+        days_since_last_miss: None,
+    }
+}
+
+/// Percentage of the window's days with a sign-in
+///
+/// Today only counts once it has a sign-in, as the day is still open.
+fn compliance_percent(
+    dates: &BTreeSet<NaiveDate>,
+    window_start: NaiveDate,
+    today: NaiveDate,
+) -> Option<f32> {
+    if dates.is_empty() {
+        return None;
+    }
+    let signed = dates.range(window_start..=today).count() as f32;
+    let days = if dates.contains(&today) {
+        DASHBOARD_DAYS
+    } else {
+        DASHBOARD_DAYS - 1
+    };
+    Some(signed / days as f32 * 100.0)
+}
+
+/// Days between today and the latest closed day without a sign-in
+///
+/// Only days since the earliest known sign-in are considered, as older gaps
+/// can't be told apart from history that was never synced.
+fn days_since_last_miss(dates: &BTreeSet<NaiveDate>, today: NaiveDate) -> Option<u32> {
+    let first = *dates.first()?;
+    let mut date = today - Duration::days(1);
+    while date > first {
+        if !dates.contains(&date) {
+            return u32::try_from((today - date).num_days()).ok();
+        }
+        date -= Duration::days(1);
+    }
+    None
+}
+
+/// Parse the calendar date of a sign-in record
+fn record_date(record: &SigninRecord) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&record.date, "%Y-%m-%d").ok()
+}
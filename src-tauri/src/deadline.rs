@@ -0,0 +1,132 @@
+//! Daily check-in deadline.
+//!
+//! This module works out when today's check-in window closes, based on the
+//! configured cutoff time and time zone. The sign-in day is the calendar date
+//! in that time zone, so the window of a user in UTC+8 closes at their own
+//! midnight rather than at 08:00 the next morning.
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveTime, Offset, Utc};
+
+use crate::clock::Clock;
+use crate::models::AppSettings;
+
+/// Get the instant today's check-in window closes
+///
+/// Without a cutoff the window closes at the end of the sign-in day. A cutoff
+/// never extends past the end of the sign-in day.
+pub fn checkin_deadline(clock: &dyn Clock, settings: &AppSettings) -> DateTime<Utc> {
+    let offset = configured_offset(settings, clock.now());
+    let day = signin_day(clock, settings);
+    let end_of_day = local_instant(offset, day + chrono::Duration::days(1), NaiveTime::MIN);
+
+    settings
+        .checkin_cutoff
+        .as_deref()
+        .and_then(|c| NaiveTime::parse_from_str(c, "%H:%M").ok())
+        .map(|cutoff| local_instant(offset, day, cutoff).min(end_of_day))
+        .unwrap_or(end_of_day)
+}
+
+/// Get the date of the current sign-in day in the configured time zone
+pub fn signin_day(clock: &dyn Clock, settings: &AppSettings) -> NaiveDate {
+    let now = clock.now();
+    now.with_timezone(&configured_offset(settings, now))
+        .date_naive()
+}
+
+/// Resolve the configured UTC offset, falling back to the system time zone
+pub fn configured_offset(settings: &AppSettings, now: DateTime<Utc>) -> FixedOffset {
+    settings
+        .timezone_offset_minutes
+        .and_then(|minutes| FixedOffset::east_opt(minutes * 60))
+        .unwrap_or_else(|| now.with_timezone(&Local).offset().fix())
+}
+
+/// Instant at which the clocks in `offset` show `time` on `date`
+fn local_instant(offset: FixedOffset, date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+    date.and_time(time).and_utc() - chrono::Duration::seconds(offset.local_minus_utc().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+
+    fn settings(cutoff: Option<&str>) -> AppSettings {
+        settings_in(cutoff, 0)
+    }
+
+    fn settings_in(cutoff: Option<&str>, offset_minutes: i32) -> AppSettings {
+        AppSettings {
+            checkin_cutoff: cutoff.map(str::to_string),
+            timezone_offset_minutes: Some(offset_minutes),
+            ..AppSettings::default()
+        }
+    }
+
+    fn at(at: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(at).unwrap().to_utc()
+    }
+
+    #[test]
+    fn deadline_is_end_of_day_without_cutoff() {
+        let clock = FixedClock::at("2024-03-10T12:00:00Z");
+        assert_eq!(checkin_deadline(&clock, &settings(None)), at("2024-03-11T00:00:00Z"));
+    }
+
+    #[test]
+    fn deadline_is_the_cutoff_on_the_same_day() {
+        let clock = FixedClock::at("2024-03-10T12:00:00Z");
+        assert_eq!(checkin_deadline(&clock, &settings(Some("22:00"))), at("2024-03-10T22:00:00Z"));
+    }
+
+    #[test]
+    fn deadline_moves_to_the_next_day_at_midnight() {
+        let clock = FixedClock::at("2024-03-10T23:59:59Z");
+        let settings = settings(Some("22:00"));
+        assert_eq!(checkin_deadline(&clock, &settings), at("2024-03-10T22:00:00Z"));
+        clock.set("2024-03-11T00:00:00Z");
+        assert_eq!(checkin_deadline(&clock, &settings), at("2024-03-11T22:00:00Z"));
+    }
+
+    #[test]
+    fn deadline_on_leap_day_ends_on_march_first() {
+        let clock = FixedClock::at("2024-02-29T10:00:00Z");
+        assert_eq!(checkin_deadline(&clock, &settings(None)), at("2024-03-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn deadline_follows_a_backwards_jump() {
+        let clock = FixedClock::at("2024-03-11T00:00:05Z");
+        assert_eq!(checkin_deadline(&clock, &settings(None)), at("2024-03-12T00:00:00Z"));
+        clock.set("2024-03-10T23:59:50Z");
+        assert_eq!(checkin_deadline(&clock, &settings(None)), at("2024-03-11T00:00:00Z"));
+    }
+
+    #[test]
+    fn deadline_is_local_midnight_east_of_utc() {
+        // 01:00 on March 11 in UTC+8
+        let clock = FixedClock::at("2024-03-10T17:00:00Z");
+        let settings = settings_in(None, 8 * 60);
+        assert_eq!(signin_day(&clock, &settings), NaiveDate::from_ymd_opt(2024, 3, 11).unwrap());
+        assert_eq!(checkin_deadline(&clock, &settings), at("2024-03-11T16:00:00Z"));
+    }
+
+    #[test]
+    fn cutoff_applies_to_the_local_day_east_of_utc() {
+        let clock = FixedClock::at("2024-03-10T17:00:00Z");
+        assert_eq!(
+            checkin_deadline(&clock, &settings_in(Some("22:00"), 8 * 60)),
+            at("2024-03-11T14:00:00Z")
+        );
+    }
+
+    #[test]
+    fn deadline_is_local_midnight_west_of_utc() {
+        // 20:00 on March 10 in UTC-5
+        let clock = FixedClock::at("2024-03-11T01:00:00Z");
+        let settings = settings_in(None, -5 * 60);
+        assert_eq!(signin_day(&clock, &settings), NaiveDate::from_ymd_opt(2024, 3, 10).unwrap());
+        assert_eq!(checkin_deadline(&clock, &settings), at("2024-03-11T05:00:00Z"));
+    }
+}
@@ -0,0 +1,108 @@
+//! Links that open the app (`areuok://`).
+//!
+//! Pairing invitations sent by email or chat carry a link such as
+//! `areuok://pair?code=XYZ`, and `areuok://signin` opens the daily check-in.
+//! The pairing QR codes a device shows are links too
+//! (`areuok://pair?device_id=...&token=...`), so scanning one with the phone's
+//! camera opens the same pairing flow as scanning it in the app.
+//! Links are parsed here and handed to the frontend as a `deep-link` event
+//! naming the flow to open.
+//!
+//! A link that started the app arrives before the frontend listens for
+//! events, so it is kept until the frontend takes it on startup.
+
+use std::sync::Mutex;
+
+use reqwest::Url;
+use rust_i18n::t;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use crate::error::AppError;
+use crate::models::DeepLink;
+use crate::supervision::parse_pairing_payload;
+
+/// URL scheme of links that open the app
+pub const LINK_SCHEME: &str = "areuok";
+
+/// Link the app was started with, until the frontend takes it
+static LAUNCH_LINK: Mutex<Option<DeepLink>> = Mutex::new(None);
+
+/// Handle the link the app was started with and every link opened later
+pub fn listen(app: &AppHandle) {
+    match app.deep_link().get_current() {
+        Ok(urls) => {
+            let launch_link = urls
+                .unwrap_or_default()
+                .iter()
+                .rev()
+                .find_map(|url| parse_or_log(url.as_str()));
+            if let Some(link) = &launch_link {
+                log::info!("App started by link {:?}", link);
+            }
+            *LAUNCH_LINK.lock().unwrap_or_else(|e| e.into_inner()) = launch_link;
+        }
+        Err(e) => log::warn!("Failed to get the link the app was started with: {}", e),
+    }
+
+    let handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            open(&handle, url.as_str());
+        }
+    });
+}
+
+/// Bring the app to the front and tell the frontend which flow to open
+pub fn open(app: &AppHandle, link: &str) {
+    let Some(link) = parse_or_log(link) else {
+        return;
+    };
+    log::info!("Opening link {:?}", link);
+    #[cfg(desktop)]
+    crate::tray::show_window(app);
+    if let Err(e) = app.emit("deep-link", link) {
+        log::warn!("Failed to emit deep link: {}", e);
+    }
+}
+
+/// Take the link the app was started with, once
+pub fn take_launch_link() -> Option<DeepLink> {
+    LAUNCH_LINK.lock().unwrap_or_else(|e| e.into_inner()).take()
+}
+
+/// Parse an `areuok://` link
+pub fn parse(link: &str) -> Result<DeepLink, AppError> {
+    let url = Url::parse(link).map_err(|e| t!("error.invalid_link", error = e))?;
+    if url.scheme() != LINK_SCHEME {
+        return Err(t!("error.wrong_link_scheme", scheme = LINK_SCHEME).into());
+    }
+    match url.host_str() {
+        Some("pair") => {
+            let code = url
+                .query_pairs()
+                .find(|(key, _)| key == "code")
+                .map(|(_, code)| code.trim().to_string())
+                .filter(|code| !code.is_empty());
+            if let Some(code) = code {
+                return Ok(DeepLink::Pair { code });
+            }
+            let pairing = parse_pairing_payload(link)
+                .map_err(|_| t!("error.pairing_link_incomplete").into_owned())?;
+            Ok(DeepLink::PairToken {
+                device_id: pairing.device_id,
+                token: pairing.token,
+                key_fingerprint: pairing.key_fingerprint,
+            })
+        }
+        Some("signin") => Ok(DeepLink::Signin),
+        target => Err(t!("error.unknown_link_target", target = target.unwrap_or_default()).into()),
+    }
+}
+
+/// Parse a link, logging the ones that can't be opened
+fn parse_or_log(link: &str) -> Option<DeepLink> {
+    parse(link)
+        .inspect_err(|e| log::warn!("Ignoring link {}: {}", link, e))
+        .ok()
+}
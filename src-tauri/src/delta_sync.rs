@@ -0,0 +1,125 @@
+//! Delta sync of server lists polled by the periodic sync.
+//!
+//! Fetching the full pending-request and supervision lists on every poll
+//! transfers the same data over and over. Instead, a local copy of each list
+//! is kept together with the server's cursor from the last fetch, and only the
+//! changes since that cursor are requested and merged into the copy.
+//!
+//! Servers without delta support answer with the full list, which then
+//! replaces the copy; so does a delta the server marks as full because the
+//! cursor was too old.
+
+use std::future::Future;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::api_client::{get_pending_requests_since, get_supervision_list_since};
+use crate::error::AppError;
+use crate::models::SyncedList;
+use crate::remote_models::{ListResponse, SupervisionRelation, SupervisionRequest};
+use crate::storage;
+
+/// Item of a synced list, identified by the ID the server reports removals with
+trait ListItem: Serialize + DeserializeOwned {
+    fn id(&self) -> &str;
+}
+
+impl ListItem for SupervisionRequest {
+    fn id(&self) -> &str {
+        &self.request_id
+    }
+}
+
+impl ListItem for SupervisionRelation {
+    fn id(&self) -> &str {
+        &self.relation_id
+    }
+}
+
+/// Pending supervision requests of a device, fetching only what changed
+pub async fn pending_requests(device_id: &str) -> Result<Vec<SupervisionRequest>, AppError> {
+    let key = format!("pending_requests/{}", device_id);
+    synced_list(&key, |since| async move {
+        get_pending_requests_since(device_id, since.as_deref()).await
+    })
+    .await
+}
+
+/// Supervision relations of a device, fetching only what changed
+pub async fn supervision_list(device_id: &str) -> Result<Vec<SupervisionRelation>, AppError> {
+    let key = format!("supervision_list/{}", device_id);
+    synced_list(&key, |since| async move {
+        get_supervision_list_since(device_id, since.as_deref()).await
+    })
+    .await
+}
+
+/// Bring the local copy of the list under `key` up to date and return it
+async fn synced_list<T, F, Fut>(key: &str, fetch: F) -> Result<Vec<T>, AppError>
+where
+    T: ListItem,
+    F: FnOnce(Option<String>) -> Fut,
+    Fut: Future<Output = Result<ListResponse<T>, AppError>>,
+{
+    let stored = storage::load_synced_lists()
+        .map_err(|e| log::warn!("Failed to load synced lists: {}", e))
+        .ok()
+        .and_then(|mut lists| lists.remove(key))
+        .unwrap_or_default();
+    let copy: Option<Vec<T>> = stored
+        .items
+        .into_iter()
+        .map(serde_json::from_value)
+        .collect::<Result<_, _>>()
+        .map_err(|e| log::warn!("Dropping unreadable synced list {}: {}", key, e))
+        .ok();
+    // Without a readable copy, changes alone can't be applied
+    let since = stored.cursor.filter(|_| copy.is_some());
+    let mut items = copy.unwrap_or_default();
+
+    let cursor = match fetch(since.clone()).await? {
+        ListResponse::Full(all) => {
+            log::debug!("Server sent the full list for {}", key);
+            items = all;
+            None
+        }
+        ListResponse::Delta(delta) => {
+            log::debug!(
+                "Synced {}: {} changed, {} removed",
+                key,
+                delta.items.len(),
+                delta.removed.len()
+            );
+            // Without a cursor the server sends everything, replacing the copy
+            if delta.full || since.is_none() {
+                items.clear();
+            }
+            items.retain(|item| {
+                !delta.removed.iter().any(|id| id == item.id())
+                    && !delta.items.iter().any(|changed| changed.id() == item.id())
+            });
+            items.extend(delta.items);
+            Some(delta.cursor)
+        }
+    };
+
+    save_list(key, cursor, &items);
+    Ok(items)
+}
+
+/// Store the local copy of a list with the cursor to continue from
+fn save_list<T: Serialize>(key: &str, cursor: Option<String>, items: &[T]) {
+    let items = match items.iter().map(serde_json::to_value).collect() {
+        Ok(items) => items,
+        Err(e) => {
+            log::warn!("Failed to serialize synced list {}: {}", key, e);
+            return;
+        }
+    };
+    let mut lists = storage::load_synced_lists().unwrap_or_default();
+    lists.insert(key.to_string(), SyncedList { cursor, items });
+    if let Err(e) = storage::save_synced_lists(&lists) {
+        log::warn!("Failed to save synced lists: {}", e);
+    }
+}
@@ -0,0 +1,227 @@
+//! Notification dispatch.
+//!
+//! Event notifications start at [`notify`], which consults the per-event
+//! channel preferences. Everything then goes through [`deliver`], which holds
+//! non-critical notifications back during the configured quiet hours, and
+//! [`send_now`], which hands the notification to each selected channel from the
+//! [`registry`], records the outcome and parks failures in the outbox.
+
+use chrono::{DateTime, FixedOffset, NaiveTime, Utc};
+use tauri::{AppHandle, Manager};
+
+use crate::channels::{registry, NotificationChannel};
+use crate::clock::{Clock, SharedClock};
+use crate::deadline::configured_offset;
+use crate::error::AppError;
+use crate::history;
+use crate::models::{
+    AppSettings, ChannelSelection, DeliveryChannel, NotificationMessage, NotificationPreferences,
+    OutgoingEmail, OutgoingNotification, PreferenceEvent, QueuedNotification, QuietHours,
+    WebhookPayload,
+};
+use crate::outbox;
+use crate::storage;
+
+/// An event together with its preference key and urgency
+///
+/// [`notify`] picks the channels for it from the user's preferences.
+pub struct EventNotification {
+    pub event: PreferenceEvent,
+    pub message: NotificationMessage,
+    pub email: Option<OutgoingEmail>,
+    pub webhook: Option<WebhookPayload>,
+    /// Critical events bypass quiet hours
+    pub critical: bool,
+}
+
+/// Announce an event on every channel selected for it in the notification preferences
+pub async fn notify(app: &AppHandle, event: EventNotification) {
+    let preferences = storage::load_notification_preferences().unwrap_or_else(|e| {
+        log::warn!("Failed to load notification preferences, using defaults: {}", e);
+        NotificationPreferences::default()
+    });
+    let selection = preferences.channels_for(event.event);
+    log::debug!("Dispatching {:?} with {:?}", event.event, selection);
+
+    let notification = OutgoingNotification {
+        message: Some(event.message),
+        email: event.email,
+        webhook: event.webhook,
+    };
+    deliver(app, notification, selection, event.critical).await;
+}
+
+/// Deliver a notification now, or queue it when quiet hours are active
+///
+/// Critical notifications such as missed check-in alerts always go out immediately.
+pub async fn deliver(
+    app: &AppHandle,
+    notification: OutgoingNotification,
+    channels: ChannelSelection,
+    critical: bool,
+) {
+    let clock = app.state::<SharedClock>();
+    if !critical && in_quiet_hours(clock.as_ref()) {
+        log::info!("Quiet hours active, queueing notification");
+        let queued = QueuedNotification {
+            queued_at: clock.now().to_rfc3339(),
+            notification,
+            channels,
+        };
+        let saved = storage::load_quiet_queue().and_then(|mut queue| {
+            queue.push(queued);
+            storage::save_quiet_queue(&queue)
+        });
+        if let Err(e) = saved {
+            log::error!("Failed to queue notification for after quiet hours: {}", e);
+        }
+        return;
+    }
+
+    if let Err(e) = send_now(app, &notification, channels).await {
+        log::error!("Failed to deliver notification: {}", e);
+    }
+}
+
+/// Show a system notification on this device in the background
+///
+/// Like every other notification it waits for the end of quiet hours unless critical.
+pub fn show_local(app: &AppHandle, message: NotificationMessage, critical: bool) {
+    let notification = OutgoingNotification {
+        message: Some(message),
+        ..Default::default()
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        deliver(&app, notification, ChannelSelection::local_only(), critical).await;
+    });
+}
+
+/// Send a notification on every selected channel without consulting quiet hours
+///
+/// Each attempt is recorded in the history and failures are parked in the
+/// outbox for retries. Returns how many channels delivered the notification,
+/// which is zero when no channel was selected or accepts it, or the combined
+/// error when every attempted channel failed.
+pub async fn send_now(
+    app: &AppHandle,
+    notification: &OutgoingNotification,
+    channels: ChannelSelection,
+) -> Result<usize, AppError> {
+    let clock = app.state::<SharedClock>();
+    let mut sent = 0;
+    let mut errors = vec![];
+
+    for channel in registry(app)
+        .iter()
+        .filter(|c| channels.includes(c.kind()) && c.accepts(notification))
+    {
+        match send_on(channel.as_ref(), notification).await {
+            Ok(()) => sent += 1,
+            Err(e) => {
+                if channel.kind() != DeliveryChannel::SystemNotification {
+                    outbox::enqueue(
+                        clock.as_ref(),
+                        channel.id(),
+                        notification.clone(),
+                        &e.to_string(),
+                    );
+                }
+                errors.push(format!("{}: {}", channel.id(), e));
+            }
+        }
+    }
+
+    if sent == 0 && !errors.is_empty() {
+        Err(errors.join("; ").into())
+    } else {
+        Ok(sent)
+    }
+}
+
+/// Send on a single channel and record the attempt in the history
+pub async fn send_on(
+    channel: &dyn NotificationChannel,
+    notification: &OutgoingNotification,
+) -> Result<(), AppError> {
+    let result = channel.send(notification).await;
+    if let Err(e) = &result {
+        log::error!("Failed to send {} notification: {}", channel.id(), e);
+    }
+    let (event, summary) = describe(notification);
+    history::record(channel.kind(), &event, &summary, &result);
+    result
+}
+
+/// Event name and summary of a notification for the history
+fn describe(notification: &OutgoingNotification) -> (String, String) {
+    if let Some(email) = &notification.email {
+        return (history::event_name(email.event), email.subject.clone());
+    }
+    if let Some(message) = &notification.message {
+        return (history::event_name(message.event), message.title.clone());
+    }
+    match &notification.webhook {
+        Some(payload) => (history::event_name(payload.event), String::new()),
+        None => (String::new(), String::new()),
+    }
+}
+
+/// Send every queued notification once quiet hours are over
+pub async fn flush_quiet_queue(app: &AppHandle) -> Result<(), AppError> {
+    let clock = app.state::<SharedClock>();
+    if in_quiet_hours(clock.as_ref()) {
+        return Ok(());
+    }
+    let queue = storage::load_quiet_queue().map_err(|e| e.to_string())?;
+    if queue.is_empty() {
+        return Ok(());
+    }
+
+    log::info!("Quiet hours over, sending {} queued notifications", queue.len());
+    storage::save_quiet_queue(&[]).map_err(|e| e.to_string())?;
+    for item in queue {
+        if let Err(e) = send_now(app, &item.notification, item.channels).await {
+            log::error!("Failed to send queued notification: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Check whether the configured quiet hours cover the current time
+fn in_quiet_hours(clock: &dyn Clock) -> bool {
+    let settings = match storage::load_settings() {
+        Ok(settings) => settings,
+        Err(e) => {
+            log::warn!("Failed to load app settings for quiet hours: {}", e);
+            return false;
+        }
+    };
+    match &settings.quiet_hours {
+        Some(quiet) if quiet.enabled => is_quiet(quiet, &settings, clock.now()),
+        _ => false,
+    }
+}
+
+/// Check whether `now` falls inside the quiet window, which may span midnight
+pub fn is_quiet(quiet: &QuietHours, settings: &AppSettings, now: DateTime<Utc>) -> bool {
+    let (Ok(start), Ok(end)) = (
+        NaiveTime::parse_from_str(&quiet.start, "%H:%M"),
+        NaiveTime::parse_from_str(&quiet.end, "%H:%M"),
+    ) else {
+        log::warn!("Invalid quiet hours '{}'-'{}'", quiet.start, quiet.end);
+        return false;
+    };
+
+    let offset = quiet
+        .timezone_offset_minutes
+        .and_then(|minutes| FixedOffset::east_opt(minutes * 60))
+        .unwrap_or_else(|| configured_offset(settings, now));
+    let local = now.with_timezone(&offset).time();
+
+    if start <= end {
+        local >= start && local < end
+    } else {
+        local >= start || local < end
+    }
+}
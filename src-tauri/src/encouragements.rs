@@ -0,0 +1,180 @@
+//! Encouragements from supervisors.
+//!
+//! A supervisor leaves a short message for a supervised device through the
+//! server. The supervised device collects undelivered messages during the
+//! supervision sync or from an `encouragement` push event into a local inbox,
+//! and hands them out with the next sign-in, e.g. "妈妈: 干得好，继续加油".
+//! Delivered messages are acknowledged so the server stops returning them.
+//! Once the partners have exchanged encryption keys, the text travels sealed.
+
+use rust_i18n::t;
+use tauri::{AppHandle, Manager};
+
+use crate::api_client::{
+    get_encouragements_api, mark_encouragements_delivered_api, send_encouragement_api,
+};
+use crate::crypto;
+use crate::error::AppError;
+use crate::models::SupervisionHistoryKind;
+use crate::remote_models::Encouragement;
+use crate::state::AppState;
+use crate::storage;
+use crate::supervision::record_history;
+
+/// Maximum length of an encouragement, in characters
+const MAX_ENCOURAGEMENT_CHARS: usize = 200;
+
+/// Leave an encouragement for a supervised device
+pub async fn send_encouragement(
+    state: &AppState,
+    device_id: &str,
+    text: &str,
+) -> Result<Encouragement, AppError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(t!("error.encouragement_empty").into());
+    }
+    if text.chars().count() > MAX_ENCOURAGEMENT_CHARS {
+        return Err(t!("error.encouragement_too_long", max = MAX_ENCOURAGEMENT_CHARS).into());
+    }
+
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let relationship = config
+        .supervision_relationships
+        .iter()
+        .find(|r| r.supervised_device_id == device_id);
+    let sealed = match relationship.and_then(|r| r.partner_public_key.as_deref()) {
+        Some(key) => Some(crypto::seal(key, &text)?),
+        None => None,
+    };
+    let plain = if sealed.is_some() { None } else { Some(text) };
+    let mut message =
+        send_encouragement_api(&config.device.device_id, device_id, plain, sealed.as_deref())
+            .await?;
+    message.text = text.to_string();
+
+    let device_name = relationship.map(|r| r.supervised_device_name.clone());
+    record_history(
+        SupervisionHistoryKind::EncouragementSent,
+        device_id,
+        device_name,
+        Some(message.message_id.clone()),
+        Some(message.text.clone()),
+    );
+    Ok(message)
+}
+
+/// Fetch undelivered encouragements for this device into the inbox
+pub async fn refresh_inbox(state: &AppState, device_id: &str) -> Result<(), AppError> {
+    let messages = get_encouragements_api(device_id).await?;
+    add_to_inbox(state, messages)
+}
+
+/// Store an encouragement pushed by the server
+pub fn handle_encouragement_event(app: &AppHandle, data: &serde_json::Value) {
+    match serde_json::from_value::<Encouragement>(data.clone()) {
+        Ok(message) => {
+            if let Err(e) = add_to_inbox(&app.state::<AppState>(), vec![message]) {
+                log::warn!("Failed to store pushed encouragement: {}", e);
+            }
+        }
+        Err(e) => log::warn!("Ignoring malformed encouragement event: {}", e),
+    }
+}
+
+/// Empty the inbox for the sign-in being recorded
+///
+/// Each message is recorded in the supervision history and acknowledged to
+/// the server in the background. If the acknowledgement fails, the refetched
+/// message is recognised from the history and not shown twice.
+pub fn take_for_signin(device_id: &str) -> Vec<Encouragement> {
+    let inbox = match storage::load_encouragement_inbox() {
+        Ok(inbox) => inbox,
+        Err(e) => {
+            log::warn!("Failed to load encouragement inbox: {}", e);
+            return vec![];
+        }
+    };
+    if inbox.is_empty() {
+        return inbox;
+    }
+    if let Err(e) = storage::save_encouragement_inbox(&[]) {
+        log::warn!("Failed to clear encouragement inbox: {}", e);
+    }
+
+    log::info!("Delivering {} encouragements with sign-in", inbox.len());
+    for message in &inbox {
+        record_history(
+            SupervisionHistoryKind::EncouragementReceived,
+            &message.sender_id,
+            message.sender_name.clone(),
+            Some(message.message_id.clone()),
+            Some(message.text.clone()),
+        );
+    }
+
+    let device_id = device_id.to_string();
+    let message_ids: Vec<String> = inbox.iter().map(|m| m.message_id.clone()).collect();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = mark_encouragements_delivered_api(&device_id, &message_ids).await {
+            log::warn!("Failed to acknowledge delivered encouragements: {}", e);
+        }
+    });
+    inbox
+}
+
+/// Merge messages into the inbox, skipping ones already queued or delivered
+fn add_to_inbox(state: &AppState, messages: Vec<Encouragement>) -> Result<(), AppError> {
+    if messages.is_empty() {
+        return Ok(());
+    }
+    let mut inbox = storage::load_encouragement_inbox().map_err(|e| e.to_string())?;
+    let delivered: Vec<String> = storage::load_supervision_history()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|entry| entry.kind == SupervisionHistoryKind::EncouragementReceived)
+        .filter_map(|entry| entry.reference_id)
+        .collect();
+
+    let before = inbox.len();
+    for message in messages {
+        if !delivered.contains(&message.message_id)
+            && !inbox.iter().any(|m| m.message_id == message.message_id)
+        {
+            inbox.push(open_message(state, message));
+        }
+    }
+    if inbox.len() == before {
+        return Ok(());
+    }
+    storage::save_encouragement_inbox(&inbox).map_err(AppError::from)
+}
+
+/// Replace the sealed text of a message with the plaintext
+fn open_message(state: &AppState, mut message: Encouragement) -> Encouragement {
+    let Some(sealed) = message.sealed_text.take() else {
+        return message;
+    };
+    let key = state.device_config().ok().and_then(|config| {
+        config
+            .supervision_relationships
+            .into_iter()
+            .find(|r| r.supervisor_device_id == message.sender_id)
+            .and_then(|r| r.partner_public_key)
+    });
+    message.text = match key.map(|key| crypto::open::<String>(&key, &sealed)) {
+        Some(Ok(text)) => text,
+        Some(Err(e)) => {
+            log::warn!("Failed to open encouragement {}: {}", message.message_id, e);
+            t!("supervision.unreadable_encouragement").into_owned()
+        }
+        None => {
+            log::warn!("No key to open encouragement {}", message.message_id);
+            t!("supervision.unreadable_encouragement").into_owned()
+        }
+    };
+    message
+}
@@ -0,0 +1,204 @@
+//! Errors reported to the frontend.
+//!
+//! Commands fail with an [`AppError`], which serializes as an object with a
+//! stable `code` and a readable `message`, plus `status` or `retry_after_secs`
+//! where they apply, so the frontend can tell being offline from a missing
+//! resource or a server bug without parsing messages. The modules behind the
+//! commands return it as well, so the code reaches the frontend unchanged.
+//! Plain messages, e.g. from validating input, become [`AppError::Other`].
+//! Messages the user sees are looked up under `error` in the locales.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+
+use rust_i18n::t;
+use serde::{Serialize, Serializer};
+
+/// Error returned by API calls and commands
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppError {
+    /// The server could not be reached
+    NetworkError {
+        message: String,
+    },
+    /// The server did not answer in time
+    Timeout {
+        message: String,
+    },
+    NotFound {
+        message: String,
+    },
+    /// The request clashes with the current state on the server
+    Conflict {
+        message: String,
+    },
+    /// The server asked to wait before sending more requests
+    RateLimited {
+        retry_after_secs: u64,
+    },
+    ServerError {
+        status: u16,
+        message: String,
+    },
+    /// The response could not be understood
+    ParseError {
+        message: String,
+    },
+    /// The connection to the server would not be secure, e.g. plain http or
+    /// a certificate not matching the pinned hashes
+    InsecureConnection {
+        message: String,
+    },
+    /// The server speaks an API version this build doesn't, so the app needs updating
+    UpdateRequired {
+        message: String,
+    },
+    /// The frontend abandoned the call
+    Cancelled,
+    /// The supervised device doesn't share this with the supervisor
+    NotShared,
+    /// The app lock is on, so data stays hidden until the user unlocks the app
+    Locked,
+    /// Any other failure, e.g. invalid input or local storage errors
+    Other {
+        message: String,
+    },
+}
+
+impl AppError {
+    /// Stable code identifying the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NetworkError { .. } => "network_error",
+            Self::Timeout { .. } => "timeout",
+            Self::NotFound { .. } => "not_found",
+            Self::Conflict { .. } => "conflict",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::ServerError { .. } => "server_error",
+            Self::ParseError { .. } => "parse_error",
+            Self::InsecureConnection { .. } => "insecure_connection",
+            Self::UpdateRequired { .. } => "update_required",
+            Self::Cancelled => "cancelled",
+            Self::NotShared => "not_shared",
+            Self::Locked => "locked",
+            Self::Other { .. } => "other",
+        }
+    }
+
+    /// Readable description, without the code
+    pub fn message(&self) -> String {
+        match self {
+            Self::NetworkError { message }
+            | Self::Timeout { message }
+            | Self::NotFound { message }
+            | Self::Conflict { message }
+            | Self::ServerError { message, .. }
+            | Self::ParseError { message }
+            | Self::InsecureConnection { message }
+            | Self::UpdateRequired { message }
+            | Self::Other { message } => message.clone(),
+            Self::RateLimited { retry_after_secs } => {
+                t!("error.rate_limited", secs = retry_after_secs).into_owned()
+            }
+            Self::Cancelled => t!("error.cancelled").into_owned(),
+            Self::NotShared => t!("error.not_shared").into_owned(),
+            Self::Locked => t!("error.locked").into_owned(),
+        }
+    }
+
+    /// Mention the correlation ID of the failed call, for users to quote in bug reports
+    pub fn with_request_id(mut self, request_id: &str) -> Self {
+        match &mut self {
+            Self::NetworkError { message }
+            | Self::Timeout { message }
+            | Self::NotFound { message }
+            | Self::Conflict { message }
+            | Self::ServerError { message, .. }
+            | Self::ParseError { message }
+            | Self::InsecureConnection { message }
+            | Self::UpdateRequired { message }
+            | Self::Other { message } => {
+                message.push_str(&format!(" (request {})", request_id));
+            }
+            Self::RateLimited { .. } | Self::Cancelled | Self::NotShared | Self::Locked => {}
+        }
+        self
+    }
+
+    /// Check whether the server could not be reached at all, e.g. while offline
+    pub fn is_unreachable(&self) -> bool {
+        matches!(self, Self::NetworkError { .. } | Self::Timeout { .. })
+    }
+
+    /// Error for an HTTP status the server answered with
+    pub fn from_status(status: u16, message: String) -> Self {
+        match status {
+            404 => Self::NotFound { message },
+            409 => Self::Conflict { message },
+            _ => Self::ServerError { status, message },
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Payload {
+            code: &'static str,
+            message: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            status: Option<u16>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            retry_after_secs: Option<u64>,
+        }
+
+        Payload {
+            code: self.code(),
+            message: self.message(),
+            status: match self {
+                Self::ServerError { status, .. } => Some(*status),
+                _ => None,
+            },
+            retry_after_secs: match self {
+                Self::RateLimited { retry_after_secs } => Some(*retry_after_secs),
+                _ => None,
+            },
+        }
+        .serialize(serializer)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::Other { message }
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(error: io::Error) -> Self {
+        Self::Other {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(error: &str) -> Self {
+        Self::from(error.to_string())
+    }
+}
+
+impl From<Cow<'_, str>> for AppError {
+    fn from(message: Cow<'_, str>) -> Self {
+        Self::from(message.into_owned())
+    }
+}
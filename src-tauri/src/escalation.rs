@@ -0,0 +1,204 @@
+//! Missed check-in escalation.
+//!
+//! When the user misses a check-in deadline this module walks the configured
+//! escalation ladder: each stage fires once per missed day after its delay has
+//! elapsed, through its own channel, and every firing is recorded in the
+//! escalation log.
+
+use chrono::NaiveDate;
+use rust_i18n::t;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::clock::{Clock, SharedClock};
+use crate::deadline::{checkin_deadline, signin_day};
+use crate::dispatch;
+use crate::error::AppError;
+use crate::history;
+use crate::models::{
+    AppSettings, ChannelSelection, DeliveryChannel, EscalationChannel, EscalationLogEntry,
+    EscalationPolicy, OutgoingNotification,
+};
+use crate::reports::record_digest_alert;
+use crate::services::{missed_signin_email, send_twilio_sms};
+use crate::state::AppState;
+use crate::storage;
+
+/// Maximum number of entries kept in the escalation log
+const ESCALATION_LOG_LIMIT: usize = 200;
+
+/// Fire every escalation stage that has become due for the current missed day
+pub async fn run_escalation(app: &AppHandle) -> Result<(), AppError> {
+    let policy = storage::load_escalation_policy().map_err(|e| e.to_string())?;
+    if !policy.enabled || policy.stages.is_empty() {
+        return Ok(());
+    }
+
+    let Some(data) = app
+        .state::<AppState>()
+        .signin_data()
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(());
+    };
+    let Ok(last_signin) = NaiveDate::parse_from_str(&data.last_signin_date, "%Y-%m-%d") else {
+        return Ok(());
+    };
+
+    let clock = app.state::<SharedClock>();
+    let settings = storage::load_settings().map_err(|e| e.to_string())?;
+    let Some(overdue_minutes) = minutes_overdue(clock.as_ref(), last_signin, &settings) else {
+        return Ok(());
+    };
+    let Some(missed_date) = last_signin.succ_opt() else {
+        return Ok(());
+    };
+    let missed_key = missed_date.format("%Y-%m-%d").to_string();
+
+    let mut entries = storage::load_escalation_log().map_err(|e| e.to_string())?;
+    let mut fired = false;
+
+    for (index, stage) in policy.stages.iter().enumerate() {
+        if overdue_minutes < i64::from(stage.after_minutes) {
+            continue;
+        }
+        if entries
+            .iter()
+            .any(|entry| entry.missed_date == missed_key && entry.stage_index == index)
+        {
+            continue;
+        }
+
+        log::info!(
+            "Firing escalation stage {} ({:?}) for missed check-in on {}",
+            index,
+            stage.channel,
+            missed_key
+        );
+        let result = fire_stage(
+            app,
+            stage.channel,
+            &policy,
+            &data.name,
+            &missed_key,
+            &data.last_signin_date,
+        )
+        .await;
+        if let Err(e) = &result {
+            log::error!("Escalation stage {} failed: {}", index, e);
+        }
+        if let Some(channel) = delivery_channel(stage.channel) {
+            history::record(channel, "missed_signin", &data.name, &result);
+        }
+        record_digest_alert(
+            &app.state::<AppState>(),
+            &missed_key,
+            t!("alert.escalated", level = index + 1, channel = format!("{:?}", stage.channel))
+                .into_owned(),
+        );
+
+        entries.push(EscalationLogEntry {
+            missed_date: missed_key.clone(),
+            stage_index: index,
+            channel: stage.channel,
+            fired_at: clock.now().to_rfc3339(),
+            success: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
+        });
+        fired = true;
+    }
+
+    if fired {
+        let excess = entries.len().saturating_sub(ESCALATION_LOG_LIMIT);
+        entries.drain(..excess);
+        storage::save_escalation_log(&entries).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Minutes elapsed since the deadline of the first missed day, if it has passed
+fn minutes_overdue(
+    clock: &dyn Clock,
+    last_signin: NaiveDate,
+    settings: &AppSettings,
+) -> Option<i64> {
+    let missed_date = last_signin.succ_opt()?;
+    let today = signin_day(clock, settings);
+    if missed_date > today {
+        return None;
+    }
+
+    let missed_deadline = checkin_deadline(clock, settings)
+        - chrono::Duration::days((today - missed_date).num_days());
+    let overdue = clock.now() - missed_deadline;
+    (overdue >= chrono::Duration::zero()).then(|| overdue.num_minutes())
+}
+
+/// History channel for stages not already recorded by the email sender
+fn delivery_channel(channel: EscalationChannel) -> Option<DeliveryChannel> {
+    match channel {
+        EscalationChannel::Push => Some(DeliveryChannel::Push),
+        EscalationChannel::Sms => Some(DeliveryChannel::Sms),
+        EscalationChannel::SupervisorEmail => None,
+    }
+}
+
+/// Deliver one escalation stage through its channel
+async fn fire_stage(
+    app: &AppHandle,
+    channel: EscalationChannel,
+    policy: &EscalationPolicy,
+    name: &str,
+    missed_date: &str,
+    last_signin_date: &str,
+) -> Result<(), AppError> {
+    match channel {
+        EscalationChannel::Push => app
+            .notification()
+            .builder()
+            .title(t!("alert.missed_push_title"))
+            .body(t!("alert.missed_push_body", date = missed_date))
+            .show()
+            .map_err(|e| e.to_string().into()),
+        EscalationChannel::SupervisorEmail => {
+            let config = app
+                .state::<AppState>()
+                .email_config()
+                .map_err(|e| e.to_string())?;
+            if !config.enabled || !config.has_recipients() {
+                return Err(t!("error.email_not_configured").into());
+            }
+            let email = missed_signin_email(name, missed_date, Some(last_signin_date));
+            let notification = OutgoingNotification {
+                email: Some(email),
+                ..Default::default()
+            };
+            match dispatch::send_now(app, &notification, ChannelSelection::email_only()).await? {
+                0 => Err(t!("error.email_not_configured").into()),
+                _ => Ok(()),
+            }
+        }
+        EscalationChannel::Sms => {
+            let body =
+                t!("alert.missed_sms", name = name, date = missed_date, last = last_signin_date);
+            send_emergency_sms(policy, &body).await
+        }
+    }
+}
+
+/// Text the policy's emergency contact, or the SMS provider's default number
+pub async fn send_emergency_sms(policy: &EscalationPolicy, body: &str) -> Result<(), AppError> {
+    let channels = storage::load_notification_channels().map_err(|e| e.to_string())?;
+    let Some(twilio) = channels.twilio.filter(|c| c.enabled) else {
+        return Err(t!("error.no_sms_provider").into());
+    };
+    let to = policy
+        .emergency_contact
+        .as_deref()
+        .filter(|c| !c.is_empty())
+        .unwrap_or(&twilio.to_number);
+    if to.is_empty() {
+        return Err(t!("error.no_emergency_contact").into());
+    }
+    send_twilio_sms(&twilio, to, body).await
+}
@@ -0,0 +1,130 @@
+//! Reload files changed by another process (desktop only).
+//!
+//! The app directory may sit in a synced folder, and a second copy of the app
+//! may be running with another profile or an older build. Either can rewrite
+//! `data.json`, `device_config.json` or `email_config.json` while this
+//! instance keeps their contents in [`AppState`]. A watcher on the app
+//! directory therefore hands every change of those files to the matching
+//! `reload_*` method, which replaces the cached copy and tells the frontend
+//! when the contents really differ.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::storage;
+
+/// Quiet time after the last change before files are read, so writers that
+/// save in several steps, like sync clients, are done
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Files kept in memory by [`AppState`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum WatchedFile {
+    SigninData,
+    DeviceConfig,
+    EmailConfig,
+}
+
+/// Watch the app directory until the app exits
+pub fn start(app: &AppHandle) {
+    if let Err(e) = try_start(app) {
+        log::warn!("Not watching for external file changes: {}", e);
+    }
+}
+
+fn try_start(app: &AppHandle) -> Result<(), AppError> {
+    let dir = storage::get_app_dir().map_err(|e| e.to_string())?;
+    let files = vec![
+        (
+            storage::get_data_file_path().map_err(|e| e.to_string())?,
+            WatchedFile::SigninData,
+        ),
+        (
+            storage::get_device_config_path().map_err(|e| e.to_string())?,
+            WatchedFile::DeviceConfig,
+        ),
+        (
+            storage::get_email_config_path().map_err(|e| e.to_string())?,
+            WatchedFile::EmailConfig,
+        ),
+    ];
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = sender.send(event);
+    })
+    .map_err(|e| e.to_string())?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(|e| e.to_string())?;
+    log::info!("Watching {} for external changes", dir.display());
+
+    let app = app.clone();
+    std::thread::Builder::new()
+        .name("file-watcher".to_string())
+        .spawn(move || {
+            // Dropping the watcher would end the events
+            let _watcher = watcher;
+            run(&app, &files, &receiver);
+        })
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn run(
+    app: &AppHandle,
+    files: &[(PathBuf, WatchedFile)],
+    receiver: &Receiver<notify::Result<Event>>,
+) {
+    while let Ok(event) = receiver.recv() {
+        let mut changed = BTreeSet::new();
+        collect(files, event, &mut changed);
+        while let Ok(event) = receiver.recv_timeout(DEBOUNCE) {
+            collect(files, event, &mut changed);
+        }
+
+        let state = app.state::<AppState>();
+        for file in changed {
+            let result = match file {
+                WatchedFile::SigninData => state.reload_signin_data(),
+                WatchedFile::DeviceConfig => state.reload_device_config(),
+                WatchedFile::EmailConfig => state.reload_email_config(),
+            };
+            // A file caught halfway through a write fails to parse; the
+            // write that completes it is reported again
+            if let Err(e) = result {
+                log::warn!("Failed to reload {:?}: {}", file, e);
+            }
+        }
+    }
+}
+
+/// Add the watched files an event touched to `changed`
+fn collect(
+    files: &[(PathBuf, WatchedFile)],
+    event: notify::Result<Event>,
+    changed: &mut BTreeSet<WatchedFile>,
+) {
+    let event = match event {
+        Ok(event) => event,
+        Err(e) => {
+            log::warn!("File watcher error: {}", e);
+            return;
+        }
+    };
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+        return;
+    }
+    for path in &event.paths {
+        if let Some((_, file)) = files.iter().find(|(watched, _)| watched == path) {
+            changed.insert(*file);
+        }
+    }
+}
@@ -0,0 +1,21 @@
+//! Last-seen reporting for supervised devices.
+//!
+//! A missed check-in looks the same whether the user forgot or the phone has
+//! been off for days. The app therefore tells the server it is alive when it
+//! starts and every few minutes while it runs; the server reports the time of
+//! the last heartbeat as `last_seen_at` in the device status supervisors see.
+
+use tauri::{AppHandle, Manager};
+
+use crate::api_client::send_heartbeat;
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// Report this device as seen right now
+pub async fn beat(app: &AppHandle) -> Result<(), AppError> {
+    let config = app
+        .state::<AppState>()
+        .device_config()
+        .map_err(|e| e.to_string())?;
+    send_heartbeat(&config.device.device_id).await
+}
@@ -0,0 +1,77 @@
+//! Notification delivery history.
+//!
+//! Every delivery attempt — email, chat channel, webhook, SMS or escalation
+//! push — is appended to a capped log, so users can check afterwards whether
+//! an alert actually went out and why it failed if it didn't.
+
+use crate::clock;
+use crate::error::AppError;
+use crate::models::{DeliveryChannel, NotificationHistoryEntry, NotificationHistoryFilter};
+use crate::storage;
+
+/// Maximum number of entries kept in the notification history
+const HISTORY_LIMIT: usize = 500;
+
+/// Append the outcome of a delivery attempt to the history
+pub fn record(channel: DeliveryChannel, event: &str, summary: &str, result: &Result<(), AppError>) {
+    let mut history = match storage::load_notification_history() {
+        Ok(history) => history,
+        Err(e) => {
+            log::warn!("Failed to load notification history: {}", e);
+            return;
+        }
+    };
+
+    history.push(NotificationHistoryEntry {
+        timestamp: clock::shared().now().to_rfc3339(),
+        channel,
+        event: event.to_string(),
+        summary: summary.to_string(),
+        success: result.is_ok(),
+        error: result.as_ref().err().map(AppError::to_string),
+    });
+    let excess = history.len().saturating_sub(HISTORY_LIMIT);
+    history.drain(..excess);
+
+    if let Err(e) = storage::save_notification_history(&history) {
+        log::warn!("Failed to save notification history: {}", e);
+    }
+}
+
+/// Return matching history entries, newest first
+pub fn query(
+    filter: &NotificationHistoryFilter,
+) -> Result<Vec<NotificationHistoryEntry>, AppError> {
+    let history = storage::load_notification_history().map_err(|e| e.to_string())?;
+
+    // Timestamps are RFC 3339 in UTC, so bounds compare as string prefixes
+    let entries = history
+        .into_iter()
+        .rev()
+        .filter(|entry| filter.channel.is_none_or(|c| c == entry.channel))
+        .filter(|entry| filter.event.as_ref().is_none_or(|e| *e == entry.event))
+        .filter(|entry| filter.success.is_none_or(|s| s == entry.success))
+        .filter(|entry| {
+            filter
+                .since
+                .as_ref()
+                .is_none_or(|since| entry.timestamp.as_str() >= since.as_str())
+        })
+        .filter(|entry| {
+            filter.until.as_ref().is_none_or(|until| {
+                let end = until.len().min(entry.timestamp.len());
+                entry.timestamp[..end] <= *until.as_str()
+            })
+        })
+        .take(filter.limit.unwrap_or(usize::MAX))
+        .collect();
+    Ok(entries)
+}
+
+/// Snake-case name of a serializable event enum, as stored in the history
+pub fn event_name<T: serde::Serialize>(event: T) -> String {
+    serde_json::to_value(event)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
@@ -0,0 +1,65 @@
+//! Language of the text the backend writes.
+//!
+//! Notifications, emails, chat messages and the tray menu are looked up with
+//! `t!` in the catalogs under `locales/`, one YAML file per locale. The
+//! language follows [`AppSettings::language`](crate::models::AppSettings::language),
+//! or the system language when that is unset. Languages without a catalog, and
+//! texts missing from one, fall back to [`DEFAULT_LOCALE`].
+
+use rust_i18n::t;
+
+use crate::models::LocaleInfo;
+
+/// Locale of the original texts, used when no other one applies
+pub const DEFAULT_LOCALE: &str = "zh-CN";
+
+/// Switch to `language`, or to the system language when `None`
+pub fn apply(language: Option<&str>) {
+    let locale = language
+        .map(str::to_string)
+        .or_else(sys_locale::get_locale)
+        .and_then(|tag| resolve(&tag))
+        .unwrap_or(DEFAULT_LOCALE);
+    log::info!("Using locale {}", locale);
+    rust_i18n::set_locale(locale);
+}
+
+/// Locale currently in use, e.g. "en"
+pub fn current_locale() -> String {
+    rust_i18n::locale().to_string()
+}
+
+/// Locales with a catalog, each named in its own language
+pub fn available_locales() -> Vec<LocaleInfo> {
+    let mut locales: Vec<LocaleInfo> = rust_i18n::available_locales!()
+        .into_iter()
+        .map(|code| LocaleInfo {
+            code: code.to_string(),
+            name: t!("locale_name", locale = code).into_owned(),
+        })
+        .collect();
+    locales.sort_by(|a, b| a.code.cmp(&b.code));
+    locales
+}
+
+/// Catalog locale for a language tag such as "en-US" or "zh_CN"
+///
+/// Tags without an exact match fall back to a catalog of the same language in
+/// another region.
+pub fn resolve(tag: &str) -> Option<&'static str> {
+    let tag = tag.replace('_', "-");
+    let language = tag.split('-').next().unwrap_or_default();
+    let available = rust_i18n::available_locales!();
+    available
+        .iter()
+        .find(|locale| locale.eq_ignore_ascii_case(&tag))
+        .or_else(|| {
+            available.iter().find(|locale| {
+                locale
+                    .split('-')
+                    .next()
+                    .is_some_and(|l| l.eq_ignore_ascii_case(language))
+            })
+        })
+        .copied()
+}
@@ -0,0 +1,128 @@
+//! Secrets kept out of the app's regular files.
+//!
+//! On desktop each [`Secret`] is an entry in the OS keychain, under one service
+//! name with an account per secret. No keychain is reachable from Rust on
+//! mobile without a native plugin, so there every secret is a file in the
+//! app's data directory instead, which the OS keeps private to the app.
+
+use crate::error::AppError;
+#[cfg(mobile)]
+use crate::storage;
+
+#[cfg(desktop)]
+const SERVICE: &str = "areuok";
+
+/// A secret the app keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Secret {
+    /// This device's encryption key, see [`crypto`](crate::crypto)
+    DeviceKey,
+    /// Hash of the app lock PIN
+    AppLockPin,
+    /// Failed app lock attempts, kept with the PIN so a restart doesn't reset them
+    AppLockAttempts,
+    /// Refresh token of the OAuth2 login used to send email
+    OAuthRefreshToken,
+}
+
+impl Secret {
+    /// What the secret is, for log messages
+    fn description(self) -> &'static str {
+        match self {
+            Self::DeviceKey => "device key",
+            Self::AppLockPin => "PIN hash",
+            Self::AppLockAttempts => "PIN attempts",
+            Self::OAuthRefreshToken => "OAuth2 refresh token",
+        }
+    }
+
+    /// Keychain account the secret is stored under
+    #[cfg(desktop)]
+    fn account(self) -> &'static str {
+        match self {
+            Self::DeviceKey => "device-key",
+            Self::AppLockPin => "app-lock-pin",
+            Self::AppLockAttempts => "app-lock-attempts",
+            Self::OAuthRefreshToken => "smtp-oauth-refresh-token",
+        }
+    }
+
+    /// File in the app's data directory the secret is stored in
+    #[cfg(mobile)]
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::DeviceKey => "device_key",
+            Self::AppLockPin => "pin_hash",
+            Self::AppLockAttempts => "pin_attempts",
+            Self::OAuthRefreshToken => "oauth_refresh_token",
+        }
+    }
+}
+
+/// Read a secret from the OS keychain, `None` when it was never stored
+#[cfg(desktop)]
+pub fn load(secret: Secret) -> Result<Option<String>, AppError> {
+    let entry = keyring::Entry::new(SERVICE, secret.account()).map_err(|e| {
+        log::error!("Failed to open keychain entry: {}", e);
+        e.to_string()
+    })?;
+    match entry.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => {
+            log::error!("Failed to read {} from keychain: {}", secret.description(), e);
+            Err(e.to_string().into())
+        }
+    }
+}
+
+/// Write a secret to the OS keychain
+#[cfg(desktop)]
+pub fn store(secret: Secret, value: &str) -> Result<(), AppError> {
+    keyring::Entry::new(SERVICE, secret.account())
+        .and_then(|entry| entry.set_password(value))
+        .map_err(|e| {
+            log::error!("Failed to store {} in keychain: {}", secret.description(), e);
+            AppError::from(e.to_string())
+        })
+}
+
+/// Remove a secret from the OS keychain, if it is there
+#[cfg(desktop)]
+pub fn delete(secret: Secret) -> Result<(), AppError> {
+    match keyring::Entry::new(SERVICE, secret.account()).and_then(|entry| entry.delete_credential())
+    {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => {
+            log::error!("Failed to delete {} from keychain: {}", secret.description(), e);
+            Err(e.to_string().into())
+        }
+    }
+}
+
+/// Read a secret from the app's data directory, `None` when it was never stored
+#[cfg(mobile)]
+pub fn load(secret: Secret) -> Result<Option<String>, AppError> {
+    storage::load_secret(secret.file_name()).map_err(|e| {
+        log::error!("Failed to load {}: {}", secret.description(), e);
+        AppError::from(e)
+    })
+}
+
+/// Write a secret to the app's data directory
+#[cfg(mobile)]
+pub fn store(secret: Secret, value: &str) -> Result<(), AppError> {
+    storage::save_secret(secret.file_name(), value).map_err(|e| {
+        log::error!("Failed to save {}: {}", secret.description(), e);
+        AppError::from(e)
+    })
+}
+
+/// Remove a secret from the app's data directory, if it is there
+#[cfg(mobile)]
+pub fn delete(secret: Secret) -> Result<(), AppError> {
+    storage::delete_secret(secret.file_name()).map_err(|e| {
+        log::error!("Failed to delete {}: {}", secret.description(), e);
+        AppError::from(e)
+    })
+}
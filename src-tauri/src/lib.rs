@@ -3,12 +3,72 @@
 //! This is the main library module that exposes the Tauri application entry point
 //! and organizes the codebase into logical modules.
 
+mod alerts;
 mod api_client;
+mod app_lock;
+#[cfg(desktop)]
+mod autostart;
+mod changes;
+mod channels;
+mod clock;
 mod commands;
+mod crash;
+mod crypto;
+mod dashboard;
+mod deadline;
+mod deep_link;
+mod delta_sync;
+mod dispatch;
+mod encouragements;
+mod error;
+mod escalation;
+#[cfg(desktop)]
+mod file_watcher;
+mod heartbeat;
+mod history;
+mod i18n;
+mod keychain;
+mod logs;
 mod models;
+mod nudges;
+mod oauth;
+mod onboarding;
+mod outbox;
+mod pings;
+mod reminders;
 mod remote_models;
+mod reports;
+mod request_limits;
+mod scheduler;
 mod services;
+mod settings;
+mod sharing;
+#[cfg(desktop)]
+mod shortcuts;
+#[cfg(desktop)]
+mod single_instance;
+mod sos;
+mod state;
 mod storage;
+mod streak;
+mod streak_image;
+mod supervision;
+mod sync_queue;
+mod timeline;
+#[cfg(desktop)]
+mod tray;
+#[cfg(desktop)]
+mod updater;
+mod webhooks;
+mod wellness;
+#[cfg(desktop)]
+mod window_state;
+
+use tauri::Manager;
+
+use crate::models::AppSettings;
+
+rust_i18n::i18n!("locales", fallback = "zh-CN");
 
 pub use commands::*;
 pub use remote_models::*;
@@ -16,58 +76,209 @@ pub use remote_models::*;
 /// Run the Tauri application
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    env_logger::Builder::from_default_env()
-        .filter_level(log::LevelFilter::Info)
-        .init();
+    crash::install_hook();
 
-    log::info!("Starting areuok application...");
+    let builder = tauri::Builder::default();
+    #[cfg(desktop)]
+    let builder = builder.plugin(single_instance::plugin());
+    #[cfg(mobile)]
+    let builder = builder.plugin(tauri_plugin_biometric::init());
 
-    tauri::Builder::default()
+    builder
+        .plugin(logs::plugin())
         .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![
-            // Sign-in commands
-            greet,
-            load_signin_data,
-            signin,
-            signout,
-            // Quote commands
-            get_daily_quote,
-            // Email config commands
-            get_email_config,
-            save_email_config_command,
-            // Device commands (local)
-            get_device_config,
-            set_device_mode,
-            update_device_name,
-            set_device_imei,
-            get_device_imei,
-            // Supervision request commands (local)
-            send_supervision_request,
-            cancel_supervision_request,
-            get_pending_supervision_requests,
-            accept_supervision_request,
-            reject_supervision_request,
-            // Supervision relationship commands (local)
-            remove_supervision_relationship,
-            get_supervised_devices,
-            get_supervisor_status,
-            // Remote API commands
-            device_register,
-            device_get_info,
-            device_update_name_api,
-            device_signin_api,
-            device_search,
-            device_get_status,
-            supervision_request_api,
-            supervision_get_pending,
-            supervision_accept_api,
-            supervision_reject_api,
-            supervision_list_api,
-            supervision_remove_api,
-            // Notification commands
-            send_notification_command,
-        ])
+        .manage::<clock::SharedClock>(clock::shared())
+        .manage(reminders::ReminderState::default())
+        .manage(state::AppState::default())
+        .setup(|app| {
+            log::info!("Starting areuok application...");
+            let settings = storage::load_settings().unwrap_or_else(|e| {
+                log::warn!("Failed to load app settings: {}", e);
+                AppSettings::default()
+            });
+            logs::apply_level(settings.log_level);
+            i18n::apply(settings.language.as_deref());
+            app.state::<state::AppState>().attach(app.handle().clone());
+            app_lock::init();
+            scheduler::start(app.handle());
+            api_client::spawn_version_probe(app.handle().clone());
+            api_client::events::spawn_event_stream(app.handle().clone());
+            tauri::async_runtime::spawn(commands::auto_signin_on_launch(app.handle().clone()));
+            deep_link::listen(app.handle());
+            crash::attach(app.handle());
+            crash::offer_pending(app.handle());
+
+            #[cfg(desktop)]
+            {
+                app.handle().plugin(shortcuts::plugin())?;
+                app.handle().plugin(autostart::plugin())?;
+                app.handle().plugin(window_state::plugin())?;
+                if let Some(updater) = updater::plugin() {
+                    app.handle().plugin(updater)?;
+                }
+                file_watcher::start(app.handle());
+                window_state::restore(app.handle());
+                tray::create(app.handle())?;
+                autostart::hide_if_autostarted(app.handle());
+                if let Err(e) = shortcuts::apply_signin_shortcut(
+                    app.handle(),
+                    settings.signin_shortcut.as_deref(),
+                ) {
+                    log::warn!("Failed to register sign-in shortcut: {}", e);
+                }
+                if let Err(e) = autostart::apply_autostart(app.handle(), settings.enable_autostart)
+                {
+                    log::warn!("Failed to apply autostart setting: {}", e);
+                }
+            }
+
+            Ok(())
+        })
+        .invoke_handler({
+            let handler: fn(tauri::ipc::Invoke) -> bool = tauri::generate_handler![
+                // Sign-in commands
+                greet,
+                load_signin_data,
+                signin,
+                undo_signin,
+                preview_signin,
+                signout,
+                get_streak_recovery_status,
+                restore_streak,
+                get_time_until_deadline,
+                // Quote commands
+                get_daily_quote,
+                // Report commands
+                get_period_report,
+                send_period_report,
+                // Settings commands
+                get_settings,
+                update_settings,
+                // App settings commands
+                get_app_settings,
+                save_app_settings,
+                get_available_locales,
+                set_autostart,
+                // Reminder commands
+                get_reminder_config,
+                set_reminder_config,
+                snooze_reminder,
+                // Escalation commands
+                get_escalation_policy,
+                set_escalation_policy,
+                get_escalation_log,
+                // Email config commands
+                get_email_config,
+                save_email_config_command,
+                authorize_email_oauth,
+                send_test_email,
+                // Notification channel commands
+                get_notification_channels,
+                save_notification_channels,
+                get_webhooks,
+                save_webhooks,
+                get_notification_history,
+                get_outbox,
+                flush_outbox,
+                remove_outbox_item,
+                get_notification_preferences,
+                save_notification_preferences,
+                // Device commands (local)
+                get_device_config,
+                set_device_mode,
+                update_device_name,
+                set_device_imei,
+                get_device_imei,
+                // Supervision request commands (local)
+                send_supervision_request,
+                cancel_supervision_request,
+                get_pending_supervision_requests,
+                accept_supervision_request,
+                reject_supervision_request,
+                block_device,
+                unblock_device,
+                get_blocked_devices,
+                // Supervision relationship commands (local)
+                remove_supervision_relationship,
+                set_supervision_muted,
+                set_missed_days_threshold,
+                set_supervision_valid_until,
+                set_relationship_nickname,
+                get_supervised_devices,
+                get_supervisor_status,
+                get_supervisor_dashboard,
+                get_supervised_history,
+                get_supervision_snapshot,
+                sync_supervision,
+                // Remote API commands
+                device_register,
+                device_get_info,
+                device_update_name_api,
+                device_signin_api,
+                device_search,
+                cancel_api_requests,
+                get_recent_api_log,
+                get_scheduler_status,
+                get_recent_logs,
+                get_server_version,
+                device_get_status,
+                get_device_signin_history,
+                get_all_supervised_statuses,
+                supervision_request_api,
+                supervision_get_pending,
+                supervision_accept_api,
+                supervision_reject_api,
+                get_pending_sync_items,
+                supervision_list_api,
+                supervision_remove_api,
+                generate_pairing_qr,
+                pair_from_qr,
+                get_partner_key_status,
+                confirm_partner_key,
+                create_invite_code,
+                redeem_invite_code,
+                initiate_transfer,
+                accept_transfer,
+                send_checkin_ping,
+                nudge_device,
+                respond_checkin_ping,
+                get_supervision_history,
+                send_sos,
+                send_encouragement,
+                set_wellness_questions,
+                get_wellness_questions,
+                set_sharing_policy,
+                pause_sharing,
+                // Notification commands
+                send_notification_command,
+                take_launch_link,
+                // Crash report commands
+                get_last_crash_report,
+                submit_crash_report,
+                // Update commands
+                check_for_updates,
+                install_update,
+                // App lock commands
+                get_app_lock_status,
+                set_app_pin,
+                remove_app_pin,
+                unlock_app,
+                unlock_app_with_biometrics,
+                lock_app,
+                // Onboarding commands
+                get_onboarding_state,
+                complete_onboarding_step,
+            ];
+            move |invoke| {
+                if let Err(e) = app_lock::guard(invoke.message.command()) {
+                    invoke.resolver.reject(e);
+                    return true;
+                }
+                handler(invoke)
+            }
+        })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,187 @@
+//! Log files for support requests.
+//!
+//! Log output goes to stdout and to `logs/areuok.log` in the app directory.
+//! The file is rotated once it reaches [`MAX_FILE_SIZE`], keeping the
+//! [`KEPT_FILES`] newest old files next to it. The level follows
+//! [`AppSettings::log_level`](crate::models::AppSettings::log_level) and can be
+//! changed while the app runs.
+//!
+//! Each record is one line, `<rfc3339 time> <LEVEL> <target>: <message>`;
+//! messages spanning several lines continue on the following ones. [`recent`]
+//! reads the files back so users can paste them into a support request.
+
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use chrono::{DateTime, Local};
+use log::{Level, LevelFilter};
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind, TimezoneStrategy};
+
+use crate::error::AppError;
+use crate::models::{LogEntry, LogLevel};
+use crate::storage;
+
+/// Name of the current log file, without extension
+const LOG_FILE_NAME: &str = "areuok";
+
+/// Size in bytes at which the log file is rotated
+const MAX_FILE_SIZE: u128 = 1024 * 1024;
+
+/// Number of rotated log files kept besides the current one
+const KEPT_FILES: usize = 5;
+
+/// Dependencies whose debug output would drown out the app's own
+const NOISY_TARGETS: &[&str] = &[
+    "hyper",
+    "hyper_util",
+    "h2",
+    "reqwest",
+    "rustls",
+    "tao",
+    "wry",
+];
+
+/// Build the log plugin writing to stdout and the rotated log file
+pub fn plugin() -> tauri::plugin::TauriPlugin<tauri::Wry> {
+    let mut builder = tauri_plugin_log::Builder::new()
+        .clear_targets()
+        .target(Target::new(TargetKind::Stdout))
+        .level(LevelFilter::Trace)
+        .max_file_size(MAX_FILE_SIZE)
+        .rotation_strategy(RotationStrategy::KeepSome(KEPT_FILES))
+        .timezone_strategy(TimezoneStrategy::UseLocal)
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{} {} {}: {}",
+                Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false),
+                record.level(),
+                record.target(),
+                message
+            ))
+        });
+    for target in NOISY_TARGETS {
+        builder = builder.level_for(*target, LevelFilter::Info);
+    }
+    match storage::log_dir() {
+        Ok(path) => {
+            builder = builder.target(Target::new(TargetKind::Folder {
+                path,
+                file_name: Some(LOG_FILE_NAME.to_string()),
+            }))
+        }
+        Err(e) => eprintln!("Failed to locate log directory, logging to stdout only: {}", e),
+    }
+    builder.build()
+}
+
+/// Log records of `level` and above from now on
+pub fn apply_level(level: LogLevel) {
+    log::set_max_level(level_filter(level));
+    log::info!("Log level set to {:?}", level);
+}
+
+/// The last `limit` records of `level` and above, oldest first
+pub fn recent(level: LogLevel, limit: usize) -> Result<Vec<LogEntry>, AppError> {
+    let filter = level_filter(level);
+    let mut entries = Vec::new();
+    for path in log_files()?.iter().rev() {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            log::error!("Failed to read log file {}: {}", path.display(), e);
+            e.to_string()
+        })?;
+        let mut older: Vec<LogEntry> = parse(&contents)
+            .into_iter()
+            .filter(|(record_level, _)| *record_level <= filter)
+            .map(|(_, entry)| entry)
+            .collect();
+        older.append(&mut entries);
+        entries = older;
+        if entries.len() >= limit {
+            break;
+        }
+    }
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.split_off(skip))
+}
+
+/// Log files oldest first, the current one last
+fn log_files() -> Result<Vec<PathBuf>, AppError> {
+    let dir = storage::log_dir().map_err(|e| e.to_string())?;
+    let current = format!("{}.log", LOG_FILE_NAME);
+    let rotated_prefix = format!("{}_", LOG_FILE_NAME);
+    let mut rotated: Vec<PathBuf> = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(&rotated_prefix) && name.ends_with(".log"))
+            })
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    // Rotated files carry their rotation time in the name, so they sort by age
+    rotated.sort();
+    let current = dir.join(current);
+    if current.exists() {
+        rotated.push(current);
+    }
+    Ok(rotated)
+}
+
+/// Split a log file into records, joining continuation lines to their record
+fn parse(contents: &str) -> Vec<(Level, LogEntry)> {
+    let mut entries: Vec<(Level, LogEntry)> = Vec::new();
+    for line in contents.lines() {
+        match parse_line(line) {
+            Some(entry) => entries.push(entry),
+            None => {
+                if let Some((_, entry)) = entries.last_mut() {
+                    entry.message.push('\n');
+                    entry.message.push_str(line);
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// Parse the first line of a record, `None` for continuation lines
+fn parse_line(line: &str) -> Option<(Level, LogEntry)> {
+    let mut parts = line.splitn(3, ' ');
+    let timestamp = parts.next()?;
+    DateTime::parse_from_rfc3339(timestamp).ok()?;
+    let level = Level::from_str(parts.next()?).ok()?;
+    let (target, message) = parts.next()?.split_once(": ")?;
+    Some((
+        level,
+        LogEntry {
+            timestamp: timestamp.to_string(),
+            level: log_level(level),
+            target: target.to_string(),
+            message: message.to_string(),
+        },
+    ))
+}
+
+fn level_filter(level: LogLevel) -> LevelFilter {
+    match level {
+        LogLevel::Error => LevelFilter::Error,
+        LogLevel::Warn => LevelFilter::Warn,
+        LogLevel::Info => LevelFilter::Info,
+        LogLevel::Debug => LevelFilter::Debug,
+        LogLevel::Trace => LevelFilter::Trace,
+    }
+}
+
+fn log_level(level: Level) -> LogLevel {
+    match level {
+        Level::Error => LogLevel::Error,
+        Level::Warn => LogLevel::Warn,
+        Level::Info => LogLevel::Info,
+        Level::Debug => LogLevel::Debug,
+        Level::Trace => LogLevel::Trace,
+    }
+}
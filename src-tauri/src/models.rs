@@ -2,16 +2,212 @@
 //!
 //! This module contains all shared data structures used across the application.
 
-use chrono::Utc;
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use rust_i18n::t;
 use serde::{Deserialize, Serialize};
 
+use crate::clock;
+use crate::error::AppError;
+use crate::remote_models::{
+    Encouragement, RequestIdentifier, SharingPolicy,
+    SupervisionRequest as RemoteSupervisionRequest, WellnessAnswer, WellnessQuestion,
+};
+
 /// User sign-in data containing streak information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SigninData {
     pub name: String,
     pub last_signin_date: String,
+    /// Current streak, derived from `signin_history` at sign-in time
     pub streak: i32,
+    /// Every date the user has ever signed in on, kept across streak resets
     pub signin_history: Vec<String>,
+    #[serde(default)]
+    pub signin_entries: Vec<SigninEntry>,
+    /// Streak that ended because exactly one day was missed, eligible for restoring
+    #[serde(default)]
+    pub broken_streak: Option<BrokenStreak>,
+    /// Missed dates that were bridged by restoring a streak
+    #[serde(default)]
+    pub streak_restores: Vec<String>,
+    /// Progress towards the configured streak goal, if one is set
+    #[serde(default)]
+    pub goal_progress: Option<GoalProgress>,
+    /// Supervisor messages delivered with the latest sign-in
+    #[serde(default)]
+    pub encouragements: Vec<Encouragement>,
+}
+
+/// Progress towards a target streak length
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoalProgress {
+    pub target: u32,
+    pub progress_percent: f32,
+    pub achieved: bool,
+    /// Date the goal will be reached if the user keeps signing in every day
+    pub projected_completion_date: Option<String>,
+}
+
+/// Snapshot of the sign-in data taken right before today's sign-in, used for undo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SigninUndo {
+    pub signed_at: String,
+    pub previous: Option<SigninData>,
+}
+
+/// What a sign-in would do right now, without persisting anything
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SigninPreview {
+    pub already_signed_in: bool,
+    pub current_streak: i32,
+    pub new_streak: i32,
+    /// True when signing in now starts a new streak instead of extending the current one
+    pub will_reset: bool,
+    pub last_signin_date: Option<String>,
+    pub days_since_last_signin: Option<i64>,
+}
+
+/// A streak lost to a single missed day, kept so it can be reinstated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrokenStreak {
+    pub streak: i32,
+    pub last_signin_date: String,
+}
+
+/// Whether a broken streak can currently be restored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreakRecoveryStatus {
+    pub available: bool,
+    pub previous_streak: Option<i32>,
+    pub remaining_restores: u32,
+}
+
+/// A single check-in with the exact time it happened and an optional mood score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SigninEntry {
+    pub date: String,
+    pub signed_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mood: Option<u8>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub answers: Vec<WellnessAnswer>,
+}
+
+/// Reporting period for sign-in summaries
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportPeriod {
+    Week,
+    Month,
+}
+
+/// Direction of the mood scores over a reporting period
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MoodTrend {
+    Improving,
+    Stable,
+    Declining,
+    /// Not enough mood scores to tell
+    Unknown,
+}
+
+/// Structured summary of sign-in activity for a week or month
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodReport {
+    pub name: String,
+    pub period: ReportPeriod,
+    pub start_date: String,
+    pub end_date: String,
+    /// Days of the period that have already elapsed (up to and including today)
+    pub elapsed_days: u32,
+    pub days_signed_in: u32,
+    pub days_missed: u32,
+    /// Average local check-in time in HH:MM format
+    pub average_signin_time: Option<String>,
+    pub average_mood: Option<f32>,
+    pub mood_trend: MoodTrend,
+    pub current_streak: i32,
+}
+
+/// Bookkeeping for scheduled report emails
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportState {
+    /// Start date of the last weekly period that was emailed
+    pub last_weekly_report: Option<String>,
+    /// Start date of the last monthly period that was emailed
+    pub last_monthly_report: Option<String>,
+}
+
+/// Bookkeeping for missed check-in alerts sent to supervisors
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MissedAlertState {
+    /// Supervised device ID mapped to the last date an alert was sent for
+    #[serde(default)]
+    pub last_alerted: BTreeMap<String, String>,
+    /// Last missed date this device reported to the server for its supervisors
+    #[serde(default)]
+    pub last_reported: Option<String>,
+    /// Supervised device ID mapped to the last sign-in date its missed-days alert covered
+    #[serde(default)]
+    pub threshold_alerted: BTreeMap<String, String>,
+}
+
+/// Bookkeeping that keeps this device from flooding others with supervision requests
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RequestLimitState {
+    /// Target device ID mapped to the times requests were sent to it in the last day
+    #[serde(default)]
+    pub sent: BTreeMap<String, Vec<String>>,
+    /// Target device ID mapped to the expiry of the request still awaiting an answer
+    #[serde(default)]
+    pub pending: BTreeMap<String, String>,
+    /// Target device ID mapped to the number of rejections in a row
+    #[serde(default)]
+    pub rejections: BTreeMap<String, u32>,
+    /// Target device ID mapped to the end of its request cooldown
+    #[serde(default)]
+    pub cooldown_until: BTreeMap<String, String>,
+    /// The server asked this device not to send requests before this time
+    #[serde(default)]
+    pub server_retry_at: Option<String>,
+}
+
+/// Events accumulated for the weekly digest email
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DigestState {
+    #[serde(default)]
+    pub quotes: Vec<DigestQuote>,
+    #[serde(default)]
+    pub alerts: Vec<DigestAlert>,
+    /// Start date of the last week a digest was sent for
+    #[serde(default)]
+    pub last_digest_week: Option<String>,
+}
+
+/// Daily quote received with a sign-in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestQuote {
+    pub date: String,
+    pub quote: Quote,
+}
+
+/// Supervision or escalation alert raised during the week
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestAlert {
+    pub date: String,
+    pub message: String,
+}
+
+/// Weekly digest email content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeeklyDigest {
+    pub report: PeriodReport,
+    /// Quote from the day with the best mood, or the latest one
+    pub best_quote: Option<Quote>,
+    pub alerts: Vec<DigestAlert>,
 }
 
 /// Device operating mode
@@ -22,6 +218,20 @@ pub enum DeviceMode {
     Signin,
     /// Supervisor mode: device supervising others
     Supervisor,
+    /// Checks in itself while supervising others
+    Both,
+}
+
+impl DeviceMode {
+    /// Whether the device checks in and can be supervised
+    pub fn can_signin(&self) -> bool {
+        matches!(self, DeviceMode::Signin | DeviceMode::Both)
+    }
+
+    /// Whether the device can supervise other devices
+    pub fn can_supervise(&self) -> bool {
+        matches!(self, DeviceMode::Supervisor | DeviceMode::Both)
+    }
 }
 
 /// Device information
@@ -42,8 +252,18 @@ pub enum SupervisionRequestStatus {
     Accepted,
     Rejected,
     Cancelled,
+    Expired,
 }
 
+/// How long a supervision request stays pending before it expires
+pub const SUPERVISION_REQUEST_TTL_DAYS: i64 = 7;
+
+/// Longest message a supervisor can attach to a supervision request
+pub const SUPERVISION_REQUEST_MESSAGE_MAX_CHARS: usize = 200;
+
+/// Longest nickname that can be given to a supervision partner
+pub const RELATIONSHIP_NICKNAME_MAX_CHARS: usize = 30;
+
 /// Supervision request between devices
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SupervisionRequest {
@@ -53,6 +273,30 @@ pub struct SupervisionRequest {
     pub target_device_id: String,
     pub status: SupervisionRequestStatus,
     pub created_at: String,
+    /// When a pending request lapses; older requests count from `created_at`
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// Note from the supervisor shown to the target before they decide
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Encryption key of the supervisor device
+    #[serde(default)]
+    pub supervisor_public_key: Option<String>,
+    /// How the supervisor found this device, shown before accepting
+    #[serde(default)]
+    pub identified_by: RequestIdentifier,
+}
+
+impl SupervisionRequest {
+    /// Check whether the request is past its expiry time
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        let expires_at = match &self.expires_at {
+            Some(expires_at) => DateTime::parse_from_rfc3339(expires_at).map(|t| t.to_utc()),
+            None => DateTime::parse_from_rfc3339(&self.created_at)
+                .map(|t| t.to_utc() + Duration::days(SUPERVISION_REQUEST_TTL_DAYS)),
+        };
+        expires_at.is_ok_and(|expires_at| now >= expires_at)
+    }
 }
 
 /// Established supervision relationship
@@ -65,6 +309,64 @@ pub struct SupervisionRelationship {
     pub supervised_device_name: String,
     pub established_at: String,
     pub last_sync_at: String,
+    /// The supervisor has silenced missed check-in alerts for this device
+    #[serde(default)]
+    pub muted: bool,
+    /// Questions the supervisor asks at every check-in
+    #[serde(default)]
+    pub wellness_questions: Vec<WellnessQuestion>,
+    /// What the supervised device shares with this supervisor
+    #[serde(default)]
+    pub sharing_policy: SharingPolicy,
+    /// Alert the supervisor once this many consecutive days were missed
+    #[serde(default)]
+    pub missed_days_threshold: Option<u32>,
+    /// End of a temporary supervision, after which the relationship lapses
+    #[serde(default)]
+    pub valid_until: Option<String>,
+    /// ID of the matching relation on the server, once it has been seen there
+    #[serde(default)]
+    pub remote_relation_id: Option<String>,
+    /// Local name this device's user gave the other device of the relationship
+    #[serde(default)]
+    pub nickname: Option<String>,
+    /// Encryption key of the other device, pinned when first received
+    #[serde(default)]
+    pub partner_public_key: Option<String>,
+    /// Different key the server reported for the other device since, left
+    /// unused until the user compares safety numbers and accepts it
+    #[serde(default)]
+    pub offered_partner_key: Option<String>,
+    /// The user compared safety numbers for the pinned key
+    #[serde(default)]
+    pub partner_key_verified: bool,
+}
+
+impl SupervisionRelationship {
+    /// Name to show on `my_device_id` for one of the relationship's two devices
+    ///
+    /// The nickname applies to the other device; the remote device names are
+    /// used otherwise.
+    pub fn name_of(&self, device_id: &str, my_device_id: &str) -> &str {
+        if device_id != my_device_id {
+            if let Some(nickname) = &self.nickname {
+                return nickname;
+            }
+        }
+        if device_id == self.supervisor_device_id {
+            &self.supervisor_device_name
+        } else {
+            &self.supervised_device_name
+        }
+    }
+
+    /// Check whether a temporary supervision has reached its end
+    pub fn has_lapsed(&self, now: DateTime<Utc>) -> bool {
+        self.valid_until
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .is_some_and(|t| now >= t.to_utc())
+    }
 }
 
 /// Device status for supervisors to view supervised devices
@@ -76,6 +378,140 @@ pub struct DeviceStatus {
     pub streak: i32,
     pub is_signed_in_today: bool,
     pub last_sync_at: String,
+    pub streak_policy: StreakPolicy,
+    pub goal_progress: Option<GoalProgress>,
+    /// Answers to the wellness questions given at the last check-in
+    #[serde(default)]
+    pub wellness_answers: Vec<WellnessAnswer>,
+    /// Mood of the last check-in, when the device shares it
+    #[serde(default)]
+    pub mood: Option<u8>,
+    /// Recent sign-in dates, when the device shares its history
+    #[serde(default)]
+    pub recent_signins: Option<Vec<String>>,
+    /// Last time the device's app was running, from its heartbeats
+    #[serde(default)]
+    pub last_seen_at: Option<String>,
+    /// The device paused its status updates until this time
+    #[serde(default)]
+    pub paused_until: Option<String>,
+}
+
+/// Check whether a pause that lasts until `paused_until` is still running
+pub fn is_paused(paused_until: Option<&str>, now: DateTime<Utc>) -> bool {
+    paused_until
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .is_some_and(|t| now < t.to_utc())
+}
+
+/// Check-in statistics of one supervised device for the supervisor dashboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupervisedDeviceStats {
+    pub device_id: String,
+    pub device_name: String,
+    pub streak: i32,
+    pub last_signin_date: String,
+    /// The device shares its check-in history with this supervisor; the
+    /// statistics below are left out when it doesn't
+    pub history_shared: bool,
+    /// Share of the last 30 days with a check-in, unknown before any history is synced
+    pub compliance_percent: Option<f32>,
+    /// Average local check-in time in HH:MM format
+    pub average_signin_time: Option<String>,
+    /// Days since the most recent missed day, if one falls in the synced history
+    pub days_since_last_miss: Option<u32>,
+}
+
+/// One day of a supervised device's check-in calendar
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckinDay {
+    pub date: String,
+    pub signed_in: bool,
+    /// Exact time of the sign-in, when the server reports it
+    pub signed_at: Option<String>,
+}
+
+/// Pairing code shown by a supervised device for a supervisor to scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairingQr {
+    /// Text to render as the QR code
+    pub payload: String,
+    pub expires_at: String,
+}
+
+/// Encryption key state of a relationship, for comparing with the partner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartnerKeyStatus {
+    /// Safety number of the pinned key, once a key was received
+    pub safety_number: Option<String>,
+    /// The user compared the pinned key's safety number with the partner
+    pub verified: bool,
+    /// Safety number of a different key the server reported since
+    pub offered_safety_number: Option<String>,
+}
+
+/// Kind of interaction recorded in the supervision history
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SupervisionHistoryKind {
+    /// This supervisor asked a supervised device whether they are OK
+    PingSent,
+    /// A supervisor asked this device whether they are OK
+    PingReceived,
+    /// The supervised device answered a ping with "I'm OK"
+    OkResponse,
+    /// This device asked all of its supervisors for help
+    SosSent,
+    /// A supervised device asked for help
+    SosReceived,
+    /// This supervisor left an encouragement for a supervised device
+    EncouragementSent,
+    /// A supervisor's encouragement was shown after a sign-in
+    EncouragementReceived,
+    /// This supervisor asked to supervise a device
+    RequestSent,
+    /// A supervisor asked to supervise this device
+    RequestReceived,
+    /// The supervised device accepted a supervision request
+    RequestAccepted,
+    /// The supervised device rejected a supervision request
+    RequestRejected,
+    /// The supervised device missed a day's check-in
+    MissedCheckin,
+    /// One side ended the supervision relationship
+    RelationshipRemoved,
+    /// This supervisor asked a supervised device to show a check-in reminder
+    NudgeSent,
+    /// A supervisor's check-in reminder was scheduled on this device
+    NudgeReceived,
+    /// The server reported a different encryption key for a partner
+    PartnerKeyChanged,
+}
+
+/// One interaction between this device and a supervision partner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupervisionHistoryEntry {
+    pub timestamp: String,
+    pub kind: SupervisionHistoryKind,
+    /// The other device of the interaction, or this device for SOS broadcasts
+    pub device_id: String,
+    #[serde(default)]
+    pub device_name: Option<String>,
+    /// Server ID of the ping or message the entry belongs to
+    #[serde(default)]
+    pub reference_id: Option<String>,
+    /// Free text sent along, such as the SOS message or encouragement
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Supervision state from the last background sync, kept for offline viewing
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SupervisionSnapshot {
+    pub synced_at: String,
+    pub supervised_devices: Vec<DeviceStatus>,
+    /// Incoming requests waiting for this device's answer
+    pub pending_requests: Vec<RemoteSupervisionRequest>,
 }
 
 /// Supervisor status containing supervised devices and pending requests
@@ -97,34 +533,1147 @@ pub struct Quote {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmailConfig {
     pub enabled: bool,
-    pub to_email: String,
+    #[serde(default)]
+    pub recipients: Vec<EmailRecipient>,
+    /// Single recipient address from configs saved before `recipients` existed
+    #[serde(default, rename = "to_email", skip_serializing)]
+    pub legacy_to_email: String,
     pub smtp_server: String,
     pub smtp_port: u16,
     pub smtp_username: String,
     pub smtp_password: String,
     pub from_email: String,
+    #[serde(default)]
+    pub security_mode: SmtpSecurity,
+    /// Email a weekly or monthly report automatically when set
+    #[serde(default)]
+    pub report_schedule: Option<ReportPeriod>,
+    /// Send one weekly digest instead of an email for every sign-in
+    #[serde(default)]
+    pub weekly_digest: bool,
+    #[serde(default)]
+    pub auth_method: EmailAuthMethod,
+    /// OAuth2 credentials used when `auth_method` is `oauth2`
+    #[serde(default)]
+    pub oauth2: Option<OAuth2Credentials>,
+}
+
+/// Step of the SMTP test that failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailTestStep {
+    Connection,
+    Authentication,
+    Send,
+}
+
+/// Outcome of a test email, step by step
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmailTestResult {
+    pub connection_ok: bool,
+    pub auth_ok: bool,
+    pub send_ok: bool,
+    pub failed_step: Option<EmailTestStep>,
+    pub error: Option<String>,
+}
+
+impl EmailTestResult {
+    /// Record the failing step and its error
+    pub fn fail(mut self, step: EmailTestStep, error: &str) -> Self {
+        self.failed_step = Some(step);
+        self.error = Some(error.to_string());
+        self
+    }
+}
+
+impl EmailConfig {
+    /// Move a pre-`recipients` single address into the recipient list
+    pub fn migrate_legacy_recipient(&mut self) -> bool {
+        let legacy = std::mem::take(&mut self.legacy_to_email);
+        if legacy.is_empty() || !self.recipients.is_empty() {
+            return false;
+        }
+        self.recipients.push(EmailRecipient {
+            address: legacy,
+            enabled: true,
+            kind: RecipientKind::To,
+        });
+        true
+    }
+
+    /// Recipients that should receive mail
+    pub fn active_recipients(&self) -> impl Iterator<Item = &EmailRecipient> {
+        self.recipients
+            .iter()
+            .filter(|r| r.enabled && !r.address.is_empty())
+    }
+
+    /// Check whether at least one primary recipient is enabled
+    pub fn has_recipients(&self) -> bool {
+        self.active_recipients()
+            .any(|r| r.kind == RecipientKind::To)
+    }
+}
+
+/// Email recipient with its own switch and header field
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailRecipient {
+    pub address: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub kind: RecipientKind,
+}
+
+/// Header field a recipient is placed in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecipientKind {
+    #[default]
+    To,
+    Cc,
+    Bcc,
+}
+
+/// How the SMTP connection authenticates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailAuthMethod {
+    /// Username and password (or app password)
+    #[default]
+    Password,
+    /// XOAUTH2 with an access token obtained from a stored refresh token
+    Oauth2,
+    /// No authentication, for relays that trust the sender's network
+    None,
+}
+
+/// Transport security of the SMTP connection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS, usually port 587
+    #[default]
+    Starttls,
+    /// TLS from the first byte (SMTPS), usually port 465
+    Implicit,
+    /// Unencrypted connection, only for trusted local relays
+    None,
+}
+
+/// Mail provider supporting the OAuth2 authorization flow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OAuth2Provider {
+    Google,
+    Microsoft,
+}
+
+/// Stored OAuth2 client and refresh token for SMTP
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuth2Credentials {
+    pub provider: OAuth2Provider,
+    pub client_id: String,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    /// Kept in the [`keychain`](crate::keychain), empty in the saved email config
+    #[serde(default)]
+    pub refresh_token: String,
 }
 
 impl Default for EmailConfig {
     fn default() -> Self {
         Self {
             enabled: false,
-            to_email: String::new(),
+            recipients: vec![],
+            legacy_to_email: String::new(),
             smtp_server: "smtp.gmail.com".to_string(),
             smtp_port: 587,
             smtp_username: String::new(),
             smtp_password: String::new(),
             from_email: String::new(),
+            security_mode: SmtpSecurity::Starttls,
+            report_schedule: None,
+            weekly_digest: false,
+            auth_method: EmailAuthMethod::Password,
+            oauth2: None,
         }
     }
 }
 
+/// Chat and push channels that receive sign-in and alert messages besides email
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotificationChannels {
+    #[serde(default)]
+    pub slack: Option<SlackConfig>,
+    #[serde(default)]
+    pub wecom: Option<WeComConfig>,
+    #[serde(default)]
+    pub dingtalk: Option<DingTalkConfig>,
+    #[serde(default)]
+    pub ntfy: Option<NtfyConfig>,
+    #[serde(default)]
+    pub gotify: Option<GotifyConfig>,
+    /// SMS provider, reserved for high-severity escalations
+    #[serde(default)]
+    pub twilio: Option<TwilioConfig>,
+    /// Telegram bot, routed separately through the notification preferences
+    #[serde(default)]
+    pub telegram: Option<TelegramConfig>,
+}
+
+/// Slack incoming-webhook channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlackConfig {
+    pub enabled: bool,
+    pub webhook_url: String,
+}
+
+/// WeCom (企业微信) group robot channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeComConfig {
+    pub enabled: bool,
+    pub webhook_url: String,
+}
+
+/// DingTalk (钉钉) group robot channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DingTalkConfig {
+    pub enabled: bool,
+    pub webhook_url: String,
+    /// Signing secret (SEC...) when the robot uses signature verification
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+/// ntfy push notification channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NtfyConfig {
+    pub enabled: bool,
+    /// Full topic URL, e.g. https://ntfy.sh/my-topic
+    pub topic_url: String,
+    /// Access token for protected topics; takes precedence over username/password
+    #[serde(default)]
+    pub access_token: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// Gotify server channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GotifyConfig {
+    pub enabled: bool,
+    pub server_url: String,
+    pub app_token: String,
+    #[serde(default)]
+    pub events: ChannelEvents,
+}
+
+/// Twilio SMS provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwilioConfig {
+    pub enabled: bool,
+    pub account_sid: String,
+    pub auth_token: String,
+    pub from_number: String,
+    /// Default recipient when the escalation policy has no emergency contact
+    pub to_number: String,
+}
+
+/// Telegram bot channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelegramConfig {
+    pub enabled: bool,
+    pub bot_token: String,
+    /// Chat, group or channel the bot posts to
+    pub chat_id: String,
+}
+
+/// Per-event switches for a notification channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelEvents {
+    #[serde(default = "default_true")]
+    pub signin: bool,
+    #[serde(default = "default_true")]
+    pub missed_signin: bool,
+}
+
+impl Default for ChannelEvents {
+    fn default() -> Self {
+        Self {
+            signin: true,
+            missed_signin: true,
+        }
+    }
+}
+
+impl ChannelEvents {
+    /// Check whether the channel should deliver the given event
+    pub fn allows(&self, event: NotificationEvent) -> bool {
+        match event {
+            NotificationEvent::Signin => self.signin,
+            NotificationEvent::MissedSignin => self.missed_signin,
+            NotificationEvent::Reminder
+            | NotificationEvent::Report
+            | NotificationEvent::Digest
+            | NotificationEvent::Supervision
+            | NotificationEvent::Sos => true,
+        }
+    }
+}
+
+/// Event whose delivery channels are chosen in the notification preferences
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreferenceEvent {
+    SigninSuccess,
+    StreakMilestone,
+    MissedCheckin,
+    SupervisionRequestReceived,
+    RelationshipRemoved,
+}
+
+/// Channels a single event is delivered through
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ChannelSelection {
+    #[serde(default)]
+    pub email: bool,
+    #[serde(default)]
+    pub system_notification: bool,
+    #[serde(default)]
+    pub telegram: bool,
+    #[serde(default)]
+    pub webhook: bool,
+    /// Slack, WeCom, DingTalk, ntfy and Gotify
+    #[serde(default)]
+    pub chat: bool,
+}
+
+/// Event-to-channel matrix consulted before any event notification goes out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationPreferences {
+    pub signin_success: ChannelSelection,
+    pub streak_milestone: ChannelSelection,
+    pub missed_checkin: ChannelSelection,
+    pub supervision_request_received: ChannelSelection,
+    pub relationship_removed: ChannelSelection,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        let remote = ChannelSelection::remote();
+        let local = ChannelSelection::local_only();
+        Self {
+            signin_success: remote,
+            streak_milestone: local,
+            missed_checkin: remote,
+            supervision_request_received: local,
+            relationship_removed: ChannelSelection {
+                webhook: true,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl ChannelSelection {
+    /// Every channel that reaches someone other than this device
+    pub fn remote() -> Self {
+        Self {
+            email: true,
+            telegram: true,
+            webhook: true,
+            chat: true,
+            ..Default::default()
+        }
+    }
+
+    /// Only the system notification on this device
+    pub fn local_only() -> Self {
+        Self {
+            system_notification: true,
+            ..Default::default()
+        }
+    }
+
+    /// Only the email channel
+    pub fn email_only() -> Self {
+        Self {
+            email: true,
+            ..Default::default()
+        }
+    }
+
+    /// Only the webhook channel
+    pub fn webhook_only() -> Self {
+        Self {
+            webhook: true,
+            ..Default::default()
+        }
+    }
+
+    /// Check whether a delivery backend belongs to the selection
+    pub fn includes(&self, channel: DeliveryChannel) -> bool {
+        match channel {
+            DeliveryChannel::Email => self.email,
+            DeliveryChannel::SystemNotification => self.system_notification,
+            DeliveryChannel::Telegram => self.telegram,
+            DeliveryChannel::Webhook => self.webhook,
+            DeliveryChannel::Slack
+            | DeliveryChannel::Wecom
+            | DeliveryChannel::Dingtalk
+            | DeliveryChannel::Ntfy
+            | DeliveryChannel::Gotify => self.chat,
+            DeliveryChannel::Sms | DeliveryChannel::Push => false,
+        }
+    }
+}
+
+impl NotificationPreferences {
+    /// Channels selected for an event
+    pub fn channels_for(&self, event: PreferenceEvent) -> ChannelSelection {
+        match event {
+            PreferenceEvent::SigninSuccess => self.signin_success,
+            PreferenceEvent::StreakMilestone => self.streak_milestone,
+            PreferenceEvent::MissedCheckin => self.missed_checkin,
+            PreferenceEvent::SupervisionRequestReceived => self.supervision_request_received,
+            PreferenceEvent::RelationshipRemoved => self.relationship_removed,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Email content ready to be addressed and sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutgoingEmail {
+    pub event: NotificationEvent,
+    pub subject: String,
+    /// Plain-text body, also used as the fallback part of HTML emails
+    pub body: String,
+    #[serde(default)]
+    pub html: Option<String>,
+    /// PNG embedded in the HTML part as `cid:streak-image`
+    #[serde(default)]
+    pub inline_png: Option<Vec<u8>>,
+}
+
+/// Event variables rendered into webhook body templates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    pub event: WebhookEvent,
+    pub vars: Vec<(String, String)>,
+}
+
+/// A notification in every form the delivery channels can render
+///
+/// Each channel picks the form it understands and skips notifications that
+/// don't carry it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OutgoingNotification {
+    /// Title and body for system notifications, chat channels and Telegram
+    #[serde(default)]
+    pub message: Option<NotificationMessage>,
+    #[serde(default)]
+    pub email: Option<OutgoingEmail>,
+    #[serde(default)]
+    pub webhook: Option<WebhookPayload>,
+}
+
+/// Failed delivery waiting in the outbox to be retried
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxItem {
+    pub id: String,
+    /// Channel the delivery failed on, e.g. `email` or `webhook:<id>`
+    pub channel_id: String,
+    pub notification: OutgoingNotification,
+    pub created_at: String,
+    pub attempts: u32,
+    pub next_attempt_at: String,
+    pub last_error: String,
+    /// Retries were exhausted; the item stays until flushed or removed
+    #[serde(default)]
+    pub failed: bool,
+}
+
+/// Call to the server kept in the log of recent calls
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiCallLogEntry {
+    /// Correlation ID sent as X-Request-Id
+    pub request_id: String,
+    pub method: String,
+    pub endpoint: String,
+    pub started_at: String,
+    pub duration_ms: u64,
+    pub attempts: u32,
+    /// Status of the last response, if the server answered at all
+    pub status: Option<u16>,
+    pub error: Option<AppError>,
+}
+
+/// State of a periodic background job, for debugging
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScheduledJobStatus {
+    pub name: String,
+    pub enabled: bool,
+    pub interval_secs: u64,
+    /// Whether the job is running right now
+    pub running: bool,
+    /// Runs since the app started
+    pub runs: u64,
+    pub last_run_at: Option<String>,
+    pub last_duration_ms: Option<u64>,
+    /// Error of the last run, `None` if it succeeded
+    pub last_error: Option<String>,
+    pub next_run_at: Option<String>,
+}
+
+/// Local copy of a server list kept current with delta fetches
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncedList {
+    /// Server timestamp of the last fetch, sent as `since` on the next one
+    pub cursor: Option<String>,
+    pub items: Vec<serde_json::Value>,
+}
+
+/// Body of a GET response kept to answer the next request for it when unchanged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub etag: String,
+    pub body: String,
+    pub stored_at: String,
+}
+
+/// Server call made while the server was unreachable, replayed once it is back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingSyncItem {
+    pub id: String,
+    pub operation: PendingOperation,
+    pub queued_at: String,
+    /// Replays that failed because the server was still unreachable
+    pub attempts: u32,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+/// Server call kept in the sync queue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PendingOperation {
+    Signin {
+        device_id: String,
+        signed_at: String,
+        /// Sign-in day in YYYY-MM-DD, missing from sign-ins queued by older versions
+        #[serde(default)]
+        day: Option<String>,
+    },
+    AcceptRequest {
+        supervisor_id: String,
+        target_id: String,
+        /// Identifier the user confirmed when accepting
+        confirmed: Option<RequestIdentifier>,
+    },
+    RejectRequest {
+        supervisor_id: String,
+        target_id: String,
+    },
+    Sos {
+        device_id: String,
+        message: Option<String>,
+        sent_at: String,
+    },
+}
+
+/// Queued server call dropped on replay because it no longer applied
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncConflict {
+    pub item: PendingSyncItem,
+    pub reason: AppError,
+}
+
+/// Notification held back during quiet hours
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedNotification {
+    pub queued_at: String,
+    pub notification: OutgoingNotification,
+    /// Channels selected when the notification was queued
+    pub channels: ChannelSelection,
+}
+
+/// Kind of event a notification message reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    Signin,
+    MissedSignin,
+    /// Sign-in reminders and check-in pings
+    Reminder,
+    Report,
+    Digest,
+    Supervision,
+    Sos,
+}
+
+/// Channel-agnostic message composed once and rendered by each channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationMessage {
+    pub event: NotificationEvent,
+    pub title: String,
+    pub body: String,
+    pub quote: Option<Quote>,
+}
+
+/// Event that can trigger an outgoing webhook
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    Signin,
+    MissedSignin,
+    SupervisionRequested,
+    SupervisionAccepted,
+    SupervisionRejected,
+    SupervisionRemoved,
+    Sos,
+}
+
+/// User-defined outgoing webhook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    pub url: String,
+    #[serde(default = "default_webhook_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// JSON body with {{placeholder}} variables such as {{name}}, {{streak}} and {{event}}
+    pub body_template: String,
+    /// Events that trigger the webhook; every event when empty
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+}
+
+fn default_webhook_method() -> String {
+    "POST".to_string()
+}
+
+/// Backend a notification was delivered through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryChannel {
+    Email,
+    Slack,
+    Wecom,
+    Dingtalk,
+    Ntfy,
+    Gotify,
+    Webhook,
+    Sms,
+    Push,
+    Telegram,
+    SystemNotification,
+}
+
+/// Record of one delivery attempt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationHistoryEntry {
+    pub timestamp: String,
+    pub channel: DeliveryChannel,
+    /// Snake-case event name, e.g. `signin` or `missed_signin`
+    pub event: String,
+    /// Subject, title or target of the notification
+    pub summary: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// Filter for the notification history viewer
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotificationHistoryFilter {
+    #[serde(default)]
+    pub channel: Option<DeliveryChannel>,
+    #[serde(default)]
+    pub event: Option<String>,
+    #[serde(default)]
+    pub success: Option<bool>,
+    /// Earliest date or timestamp to include (YYYY-MM-DD or RFC 3339)
+    #[serde(default)]
+    pub since: Option<String>,
+    /// Latest date or timestamp to include (YYYY-MM-DD or RFC 3339)
+    #[serde(default)]
+    pub until: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Rule deciding which gaps between sign-ins keep a streak unbroken
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StreakPolicy {
+    /// Every calendar day must be signed in
+    #[default]
+    StrictDaily,
+    /// At most two missed days in any trailing week
+    FiveOfSeven,
+    /// Only Monday to Friday must be signed in
+    WeekdaysOnly,
+}
+
+/// General application settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppSettings {
+    /// Treat opening the app as the daily check-in
+    #[serde(default)]
+    pub auto_signin_on_launch: bool,
+    #[serde(default)]
+    pub streak_policy: StreakPolicy,
+    /// Local time of day (HH:MM) by which the daily check-in is due; end of day when unset
+    #[serde(default)]
+    pub checkin_cutoff: Option<String>,
+    /// Fixed UTC offset in minutes used to interpret the cutoff; system time zone when unset
+    #[serde(default)]
+    pub timezone_offset_minutes: Option<i32>,
+    /// Target streak length in days
+    #[serde(default)]
+    pub streak_goal: Option<u32>,
+    /// Global keyboard shortcut that signs in instantly (desktop only)
+    #[serde(default)]
+    pub signin_shortcut: Option<String>,
+    /// Start the app when the user logs in (desktop only)
+    #[serde(default)]
+    pub enable_autostart: bool,
+    /// Locale of notifications and emails, e.g. "en"; the system language when unset
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Least severe level written to the log
+    #[serde(default)]
+    pub log_level: LogLevel,
+    /// Offer to send crash reports to the server on the next launch
+    #[serde(default)]
+    pub submit_crash_reports: bool,
+    /// Release channel the app updates from (desktop only)
+    #[serde(default)]
+    pub update_channel: UpdateChannel,
+    /// Do-not-disturb window for emails, channel messages and webhooks
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    #[serde(default)]
+    pub profile: ProfileSettings,
+    /// Minutes between background syncs of supervision state; 5 when unset
+    #[serde(default)]
+    pub sync_interval_minutes: Option<u32>,
+    #[serde(default)]
+    pub api_retry: ApiRetryPolicy,
+    #[serde(default)]
+    pub server: ServerSettings,
+    /// Proxy for all HTTP traffic; the system's proxy settings when unset
+    #[serde(default)]
+    pub proxy: Option<ProxySettings>,
+    /// Background jobs switched on or off by name; jobs not listed run
+    #[serde(default)]
+    pub scheduled_jobs: BTreeMap<String, bool>,
+}
+
+/// Device settings shown on the settings screen
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceSettings {
+    pub device_name: String,
+    pub mode: DeviceMode,
+}
+
+/// Every user setting, stored across several files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    pub app: AppSettings,
+    pub device: DeviceSettings,
+    pub email: EmailConfig,
+    pub reminder: ReminderConfig,
+    pub escalation: EscalationPolicy,
+    pub notification_channels: NotificationChannels,
+    pub notification_preferences: NotificationPreferences,
+}
+
+/// Step of the first-run setup wizard, in the order the wizard shows them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStep {
+    NameChosen,
+    ModeChosen,
+    /// The default server was kept or a self-hosted one set up
+    ServerConfigured,
+    /// A test message went out through a notification channel
+    ChannelTested,
+    /// A relationship with a supervisor or supervised device exists
+    SupervisorPaired,
+}
+
+impl OnboardingStep {
+    pub const ALL: [OnboardingStep; 5] = [
+        OnboardingStep::NameChosen,
+        OnboardingStep::ModeChosen,
+        OnboardingStep::ServerConfigured,
+        OnboardingStep::ChannelTested,
+        OnboardingStep::SupervisorPaired,
+    ];
+
+    /// Whether the user may leave the step for later
+    pub fn is_optional(self) -> bool {
+        matches!(self, OnboardingStep::ChannelTested | OnboardingStep::SupervisorPaired)
+    }
+}
+
+/// A finished step of the first-run setup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletedOnboardingStep {
+    pub step: OnboardingStep,
+    pub completed_at: String,
+    /// Whether the user left the step for later instead of doing it
+    #[serde(default)]
+    pub skipped: bool,
+}
+
+/// Progress of the first-run setup, so the wizard resumes where the user left off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnboardingState {
+    /// Finished steps in wizard order
+    pub completed_steps: Vec<CompletedOnboardingStep>,
+    /// Step the wizard shows next, `None` once onboarding is finished
+    pub next_step: Option<OnboardingStep>,
+    /// When the last step was finished
+    pub completed_at: Option<String>,
+}
+
+/// State of the app lock
+#[derive(Debug, Clone, Serialize)]
+pub struct AppLockStatus {
+    /// Whether a PIN is set
+    pub enabled: bool,
+    pub locked: bool,
+    /// Whether the app can be unlocked with biometrics (mobile only)
+    pub biometrics_available: bool,
+}
+
+/// Release channel of app updates
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    /// Pre-releases, tried out before they reach the stable channel
+    Beta,
+}
+
+/// Result of looking for a newer release
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    pub channel: UpdateChannel,
+    pub current_version: String,
+    /// Version of the newer release, `None` when the app is up to date
+    pub version: Option<String>,
+    /// When the newer release was published
+    pub date: Option<String>,
+    /// Changelog of the newer release
+    pub notes: Option<String>,
+}
+
+/// Details of a panic, saved for support with personal data removed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub id: String,
+    pub occurred_at: String,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    /// Name of the thread that panicked
+    pub thread: String,
+    pub message: String,
+    /// Source file and line of the panic
+    pub location: Option<String>,
+    pub backtrace: String,
+    /// Log lines leading up to the panic, oldest first
+    pub log_lines: Vec<String>,
+    /// When the report was sent to the server, `None` while it stays local
+    pub submitted_at: Option<String>,
+}
+
+/// Severity of a log record
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+/// Record read back from the log files
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: LogLevel,
+    /// Module that wrote the record, e.g. "areuok_lib::commands"
+    pub target: String,
+    pub message: String,
+}
+
+/// Language the backend can write its texts in
+#[derive(Debug, Clone, Serialize)]
+pub struct LocaleInfo {
+    /// Locale code, e.g. "zh-CN"
+    pub code: String,
+    /// Name of the language in itself, e.g. "简体中文"
+    pub name: String,
+}
+
+/// Manually configured HTTP proxy
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProxySettings {
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// Where the areuok server is and how connections to it are secured
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerSettings {
+    /// Base URL of a self-hosted server; the default deployment when unset
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Allow a plain http base URL, for development servers on the local network
+    #[serde(default)]
+    pub allow_insecure_http: bool,
+    /// Base64 SHA-256 hashes of the server certificate or its public key (SPKI)
+    ///
+    /// When set, the server must present a certificate matching one of them,
+    /// which is trusted instead of checking it against the system's roots.
+    #[serde(default)]
+    pub pinned_sha256: Vec<String>,
+}
+
+/// How API calls that failed for transient reasons are retried
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiRetryPolicy {
+    /// Attempts per call including the first one; 1 disables retries
+    pub max_attempts: u32,
+    /// Delay before the first retry in milliseconds, doubled for every further one
+    pub base_delay_ms: u64,
+    /// Upper bound for the delay between two attempts in milliseconds
+    pub max_delay_ms: u64,
+}
+
+impl Default for ApiRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 8_000,
+        }
+    }
+}
+
+/// Window during which non-critical notifications are held back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub enabled: bool,
+    /// Local start time (HH:MM)
+    pub start: String,
+    /// Local end time (HH:MM); may be earlier than `start` to span midnight
+    pub end: String,
+    /// Fixed UTC offset in minutes; falls back to the app time zone when unset
+    #[serde(default)]
+    pub timezone_offset_minutes: Option<i32>,
+}
+
+/// Personally meaningful dates used to pick celebratory messages
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileSettings {
+    /// Birthday as MM-DD
+    #[serde(default)]
+    pub birthday: Option<String>,
+    /// Date of the first sign-in (YYYY-MM-DD), filled in automatically
+    #[serde(default)]
+    pub account_created_on: Option<String>,
+    /// Date the current streak started (YYYY-MM-DD), updated on every reset
+    #[serde(default)]
+    pub streak_started_on: Option<String>,
+}
+
+/// Special occasion that swaps the sign-in message for a celebratory one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Milestone {
+    Birthday,
+    StreakAnniversary { years: u32 },
+    AccountAnniversary { years: u32 },
+}
+
+/// Time left before today's check-in window closes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadlineStatus {
+    pub deadline_at: String,
+    pub seconds_remaining: i64,
+    pub is_signed_in_today: bool,
+    /// Streak that will be lost if the deadline passes without a sign-in
+    pub streak_at_risk: i32,
+}
+
+/// Outcome of the automatic sign-in performed at launch, sent to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoSigninEvent {
+    pub success: bool,
+    pub data: Option<SigninData>,
+    pub error: Option<String>,
+}
+
+/// Local supervision state sent to the frontend whenever it changes
+#[derive(Debug, Clone, Serialize)]
+pub struct SupervisionUpdated {
+    pub requests: Vec<SupervisionRequest>,
+    pub relationships: Vec<SupervisionRelationship>,
+}
+
+/// Configuration named by a `config-updated` event
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigKind {
+    Settings,
+    Email,
+    Reminder,
+    Escalation,
+    NotificationChannels,
+    NotificationPreferences,
+    Webhooks,
+}
+
+/// Daily check-in reminder configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReminderConfig {
+    pub enabled: bool,
+    /// Local time of day (HH:MM) after which the reminder fires if not signed in
+    pub reminder_time: String,
+    /// Later reminder times (HH:MM); each one escalates the reminder wording
+    #[serde(default)]
+    pub additional_times: Vec<String>,
+    /// Minutes between repeated reminders until the user signs in (0 disables repeats)
+    pub repeat_interval_minutes: u32,
+    /// Default snooze length in minutes
+    pub snooze_minutes: u32,
+    /// Minutes before the check-in deadline to send a final reminder (0 disables it)
+    #[serde(default = "default_last_chance_minutes")]
+    pub last_chance_minutes: u32,
+}
+
+fn default_last_chance_minutes() -> u32 {
+    30
+}
+
+impl Default for ReminderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            reminder_time: "20:00".to_string(),
+            additional_times: vec![],
+            repeat_interval_minutes: 30,
+            snooze_minutes: 15,
+            last_chance_minutes: default_last_chance_minutes(),
+        }
+    }
+}
+
+/// Channel used by an escalation stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EscalationChannel {
+    /// Local notification on this device
+    Push,
+    /// Email to the configured notification recipient
+    SupervisorEmail,
+    /// Text message to the emergency contact
+    Sms,
+}
+
+/// One step of the missed check-in escalation ladder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscalationStage {
+    /// Minutes past the missed check-in deadline before this stage fires
+    pub after_minutes: u32,
+    pub channel: EscalationChannel,
+}
+
+/// Escalation rules applied when a check-in is missed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscalationPolicy {
+    pub enabled: bool,
+    pub stages: Vec<EscalationStage>,
+    /// Phone number reached by SMS stages
+    #[serde(default)]
+    pub emergency_contact: Option<String>,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            stages: vec![
+                EscalationStage {
+                    after_minutes: 2 * 60,
+                    channel: EscalationChannel::Push,
+                },
+                EscalationStage {
+                    after_minutes: 6 * 60,
+                    channel: EscalationChannel::SupervisorEmail,
+                },
+                EscalationStage {
+                    after_minutes: 24 * 60,
+                    channel: EscalationChannel::Sms,
+                },
+            ],
+            emergency_contact: None,
+        }
+    }
+}
+
+/// Record of an escalation stage that fired
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscalationLogEntry {
+    /// First missed check-in date the escalation belongs to
+    pub missed_date: String,
+    pub stage_index: usize,
+    pub channel: EscalationChannel,
+    pub fired_at: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
 /// Device configuration including device info and supervision data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceConfig {
     pub device: DeviceInfo,
     pub supervision_requests: Vec<SupervisionRequest>,
     pub supervision_relationships: Vec<SupervisionRelationship>,
+    /// Incoming remote requests the user has already been notified about
+    #[serde(default)]
+    pub announced_request_ids: Vec<String>,
+    /// Devices whose supervision requests are rejected without asking
+    #[serde(default)]
+    pub blocked_device_ids: Vec<String>,
+    /// Relationships removed here whose removal hasn't reached the server yet
+    #[serde(default)]
+    pub pending_removals: Vec<SupervisionRelationship>,
+    /// Status updates to supervisors are paused until this time
+    #[serde(default)]
+    pub sharing_paused_until: Option<String>,
 }
 
 impl DeviceConfig {
@@ -133,15 +1682,40 @@ impl DeviceConfig {
         Self {
             device: DeviceInfo {
                 device_id: device_id.clone(),
-                device_name: format!("设备-{}", &device_id[..8]),
+                device_name: t!("device.default_name", id = &device_id[..8]).into_owned(),
                 imei: None,
                 mode: DeviceMode::Signin,
-                created_at: Utc::now().to_rfc3339(),
+                created_at: clock::shared().now().to_rfc3339(),
             },
             supervision_requests: vec![],
             supervision_relationships: vec![],
+            announced_request_ids: vec![],
+            blocked_device_ids: vec![],
+            pending_removals: vec![],
+            sharing_paused_until: None,
         }
     }
+
+    /// Check whether the user has paused status updates to supervisors
+    pub fn sharing_paused(&self, now: DateTime<Utc>) -> bool {
+        is_paused(self.sharing_paused_until.as_deref(), now)
+    }
+}
+
+/// Flow an `areuok://` link asks the app to open
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DeepLink {
+    /// Redeem the invite code of a pairing invitation
+    Pair { code: String },
+    /// Pair with the device whose pairing code this is, as shown in its QR code
+    PairToken {
+        device_id: String,
+        token: String,
+        key_fingerprint: Option<String>,
+    },
+    /// Open the daily check-in
+    Signin,
 }
 
 /// Response from hitokoto.cn API
@@ -0,0 +1,154 @@
+//! One-tap check-in nudges from supervisors.
+//!
+//! A supervisor nudges a supervised device through the server, which pushes a
+//! `nudge` event to it. The supervised device schedules one extra check-in
+//! reminder that names the supervisor, and drops it if today's sign-in is
+//! already done by the time it is due. To keep nudges from turning into
+//! nagging, a supervisor can nudge each device at most a few times a day with
+//! a pause in between, counted from the nudges in the supervision history.
+//!
+//! The supervised device enforces the same limits on its side instead of
+//! trusting the sender: it ignores nudges from devices that don't supervise it
+//! and nudges that come too often, counted from the nudges it received.
+
+use chrono::{DateTime, Duration, Local, Utc};
+use rust_i18n::t;
+use tauri::{AppHandle, Manager};
+
+use crate::api_client::send_nudge_api;
+use crate::clock::{Clock, SharedClock};
+use crate::error::AppError;
+use crate::models::SupervisionHistoryKind;
+use crate::reminders::ReminderState;
+use crate::remote_models::Nudge;
+use crate::state::AppState;
+use crate::storage;
+use crate::supervision::{partner_nickname, record_history};
+
+/// Minimum time between two nudges to the same device
+const NUDGE_INTERVAL_MINUTES: i64 = 60;
+
+/// Most nudges to the same device within a day
+const MAX_NUDGES_PER_DAY: usize = 3;
+
+/// Ask a supervised device to remind its user to check in
+pub async fn nudge_device(
+    state: &AppState,
+    clock: &dyn Clock,
+    device_id: &str,
+) -> Result<Nudge, AppError> {
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let supervisor_id = &config.device.device_id;
+    let relationship = config
+        .supervision_relationships
+        .iter()
+        .find(|r| &r.supervisor_device_id == supervisor_id && r.supervised_device_id == device_id)
+        .ok_or_else(|| {
+            log::warn!("Device {} is not supervised by this device", device_id);
+            t!("error.not_supervised").into_owned()
+        })?;
+    check_rate_limit(clock, SupervisionHistoryKind::NudgeSent, device_id)?;
+
+    let nudge = send_nudge_api(supervisor_id, device_id).await?;
+    record_history(
+        SupervisionHistoryKind::NudgeSent,
+        device_id,
+        Some(relationship.name_of(device_id, supervisor_id).to_string()),
+        Some(nudge.nudge_id.clone()),
+        None,
+    );
+    Ok(nudge)
+}
+
+/// Schedule the reminder of a nudge pushed by the server
+pub fn handle_nudge_event(app: &AppHandle, data: &serde_json::Value) {
+    let nudge: Nudge = match serde_json::from_value(data.clone()) {
+        Ok(nudge) => nudge,
+        Err(e) => {
+            log::warn!("Ignoring malformed nudge event: {}", e);
+            return;
+        }
+    };
+    let state = app.state::<AppState>();
+    let clock = app.state::<SharedClock>();
+    if let Err(e) = check_sender(&state, clock.as_ref(), &nudge.supervisor_id) {
+        log::warn!("Ignoring nudge {} from {}: {}", nudge.nudge_id, nudge.supervisor_id, e);
+        return;
+    }
+    let supervisor = partner_nickname(&state, &nudge.supervisor_id)
+        .or_else(|| nudge.supervisor_name.clone())
+        .unwrap_or_else(|| nudge.supervisor_id.clone());
+    let at = nudge
+        .remind_at
+        .as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Local))
+        .unwrap_or_else(|| clock.now().with_timezone(&Local));
+    log::info!("Nudge {} from {}, reminding at {}", nudge.nudge_id, supervisor, at);
+
+    if let Err(e) = app.state::<ReminderState>().schedule_nudge(at, supervisor) {
+        log::error!("Failed to schedule nudge reminder: {}", e);
+        return;
+    }
+    record_history(
+        SupervisionHistoryKind::NudgeReceived,
+        &nudge.supervisor_id,
+        nudge.supervisor_name,
+        Some(nudge.nudge_id),
+        None,
+    );
+}
+
+/// Refuse a nudge from a device that doesn't supervise this one, or that nudges too often
+fn check_sender(state: &AppState, clock: &dyn Clock, supervisor_id: &str) -> Result<(), AppError> {
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let supervised = config.supervision_relationships.iter().any(|r| {
+        r.supervisor_device_id == supervisor_id && r.supervised_device_id == config.device.device_id
+    });
+    if !supervised {
+        return Err(t!("error.not_supervisor").into());
+    }
+    check_rate_limit(clock, SupervisionHistoryKind::NudgeReceived, supervisor_id)
+}
+
+/// Refuse a nudge that comes too soon after earlier ones between the same devices
+///
+/// `kind` says which side is counting: the supervisor counts the nudges it
+/// sent to `device_id`, the supervised device those it received from it.
+fn check_rate_limit(
+    clock: &dyn Clock,
+    kind: SupervisionHistoryKind,
+    device_id: &str,
+) -> Result<(), AppError> {
+    let history = storage::load_supervision_history().map_err(|e| {
+        log::error!("Failed to load supervision history: {}", e);
+        e.to_string()
+    })?;
+    let now = clock.now();
+    let nudges: Vec<DateTime<Utc>> = history
+        .iter()
+        .filter(|e| e.kind == kind && e.device_id == device_id)
+        .filter_map(|e| DateTime::parse_from_rfc3339(&e.timestamp).ok())
+        .map(|t| t.with_timezone(&Utc))
+        .filter(|t| now - *t < Duration::days(1))
+        .collect();
+
+    if nudges.len() >= MAX_NUDGES_PER_DAY {
+        log::warn!("Nudge limit reached for {}", device_id);
+        return Err(t!("error.nudge_daily_limit", max = MAX_NUDGES_PER_DAY).into());
+    }
+    if let Some(last) = nudges.iter().max() {
+        let next = *last + Duration::minutes(NUDGE_INTERVAL_MINUTES);
+        if next > now {
+            log::warn!("Nudged {} too recently", device_id);
+            return Err(t!("error.nudge_too_soon", minutes = (next - now).num_minutes() + 1).into());
+        }
+    }
+    Ok(())
+}
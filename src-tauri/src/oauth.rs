@@ -0,0 +1,217 @@
+//! OAuth2 authorization for SMTP (XOAUTH2).
+//!
+//! The authorization code flow runs through a one-shot loopback redirect:
+//! the browser is sent to the provider's consent page, the redirect lands on a
+//! temporary local listener, and the code is exchanged (with PKCE) for a
+//! refresh token. The rest of the credentials are saved in the email config,
+//! the refresh token in the [`keychain`](crate::keychain). Access tokens are
+//! minted from the refresh token right before each send.
+
+use std::time::Duration;
+
+use base64::prelude::{Engine, BASE64_URL_SAFE_NO_PAD};
+use rust_i18n::t;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tauri::AppHandle;
+use tauri_plugin_opener::OpenerExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+use crate::api_client::http_client;
+use crate::error::AppError;
+use crate::models::{OAuth2Credentials, OAuth2Provider};
+
+/// How long to wait for the user to finish the consent page
+const AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Token endpoint response
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+}
+
+/// Endpoints and scope for a provider
+struct ProviderEndpoints {
+    authorize_url: &'static str,
+    token_url: &'static str,
+    scope: &'static str,
+}
+
+fn endpoints(provider: OAuth2Provider) -> ProviderEndpoints {
+    match provider {
+        OAuth2Provider::Google => ProviderEndpoints {
+            authorize_url: "https://accounts.google.com/o/oauth2/v2/auth",
+            token_url: "https://oauth2.googleapis.com/token",
+            scope: "https://mail.google.com/",
+        },
+        OAuth2Provider::Microsoft => ProviderEndpoints {
+            authorize_url: "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
+            token_url: "https://login.microsoftonline.com/common/oauth2/v2.0/token",
+            scope: "https://outlook.office.com/SMTP.Send offline_access",
+        },
+    }
+}
+
+/// Run the browser consent flow and return credentials holding a refresh token
+pub async fn authorize(
+    app: &AppHandle,
+    provider: OAuth2Provider,
+    client_id: String,
+    client_secret: Option<String>,
+) -> Result<OAuth2Credentials, AppError> {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .map_err(|e| format!("Failed to start local redirect listener: {}", e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to read listener address: {}", e))?
+        .port();
+    let redirect_uri = format!("http://127.0.0.1:{}", port);
+
+    let endpoints = endpoints(provider);
+    let verifier = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    let challenge = BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+    let state = Uuid::new_v4().simple().to_string();
+
+    let authorize_url = format!(
+        "{}?response_type=code&client_id={}&redirect_uri={}&scope={}&state={}\
+         &code_challenge={}&code_challenge_method=S256&access_type=offline&prompt=consent",
+        endpoints.authorize_url,
+        urlencoding::encode(&client_id),
+        urlencoding::encode(&redirect_uri),
+        urlencoding::encode(endpoints.scope),
+        state,
+        challenge
+    );
+
+    log::info!("Opening {:?} OAuth2 consent page", provider);
+    app.opener()
+        .open_url(&authorize_url, None::<&str>)
+        .map_err(|e| format!("Failed to open browser: {}", e))?;
+
+    let code = tokio::time::timeout(AUTHORIZATION_TIMEOUT, receive_code(&listener, &state))
+        .await
+        .map_err(|_| t!("error.authorization_timeout").into_owned())??;
+
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", redirect_uri.as_str()),
+        ("client_id", client_id.as_str()),
+        ("code_verifier", verifier.as_str()),
+    ];
+    if let Some(secret) = client_secret.as_deref() {
+        form.push(("client_secret", secret));
+    }
+    let token = request_token(endpoints.token_url, &form).await?;
+    let refresh_token = token
+        .refresh_token
+        .ok_or_else(|| t!("error.no_refresh_token").into_owned())?;
+
+    log::info!("OAuth2 authorization with {:?} completed", provider);
+    Ok(OAuth2Credentials {
+        provider,
+        client_id,
+        client_secret,
+        refresh_token,
+    })
+}
+
+/// Exchange the stored refresh token for a fresh access token
+pub async fn access_token(credentials: &OAuth2Credentials) -> Result<String, AppError> {
+    log::debug!("Refreshing {:?} OAuth2 access token", credentials.provider);
+    let mut form = vec![
+        ("grant_type", "refresh_token"),
+        ("refresh_token", credentials.refresh_token.as_str()),
+        ("client_id", credentials.client_id.as_str()),
+    ];
+    if let Some(secret) = credentials.client_secret.as_deref() {
+        form.push(("client_secret", secret));
+    }
+    let token = request_token(endpoints(credentials.provider).token_url, &form).await?;
+    Ok(token.access_token)
+}
+
+/// POST a form to the token endpoint
+async fn request_token(token_url: &str, form: &[(&str, &str)]) -> Result<TokenResponse, AppError> {
+    let response = http_client()?
+        .post(token_url)
+        .form(form)
+        .send()
+        .await
+        .map_err(|e| format!("Token request failed: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        log::error!("OAuth2 token endpoint returned {}: {}", status, error_text);
+        return Err(format!("Token endpoint error {}: {}", status, error_text).into());
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse token response: {}", e).into())
+}
+
+/// Wait for the redirect on the loopback listener and extract the authorization code
+async fn receive_code(listener: &TcpListener, expected_state: &str) -> Result<String, AppError> {
+    loop {
+        let (mut stream, _) = listener
+            .accept()
+            .await
+            .map_err(|e| format!("Failed to accept redirect: {}", e))?;
+
+        let mut buffer = vec![0u8; 8192];
+        let read = stream
+            .read(&mut buffer)
+            .await
+            .map_err(|e| format!("Failed to read redirect: {}", e))?;
+        let request = String::from_utf8_lossy(&buffer[..read]);
+
+        // Browsers may also ask for /favicon.ico; only the redirect carries a query
+        let Some(query) = request
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|target| target.split_once('?'))
+            .map(|(_, query)| query.to_string())
+        else {
+            let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\n\r\n").await;
+            continue;
+        };
+
+        let param = |name: &str| {
+            query.split('&').find_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                (key == name).then(|| urlencoding::decode(value).ok().map(|v| v.into_owned()))?
+            })
+        };
+
+        let result = match (param("code"), param("state"), param("error")) {
+            (_, _, Some(error)) => Err(t!("error.authorization_denied", error = error).into()),
+            (Some(code), Some(state), None) if state == expected_state => Ok(code),
+            _ => Err(t!("error.invalid_redirect").into()),
+        };
+
+        let page = if result.is_ok() {
+            t!("oauth.success")
+        } else {
+            t!("oauth.failure")
+        };
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nConnection: close\r\n\r\n\
+             <html><body><p>{}</p></body></html>",
+            page
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+        return result;
+    }
+}
@@ -0,0 +1,127 @@
+//! Progress of the first-run setup wizard.
+//!
+//! The wizard walks through the [`OnboardingStep`]s in order. Each finished
+//! step is saved right away, so a user who closes the app halfway, which older
+//! users often do when asked for an email password or a pairing code, finds
+//! the wizard on the same step the next time.
+//!
+//! Steps can only be finished in order. The optional ones can be skipped, and
+//! the steps the backend can check, the server settings and the pairing, are
+//! checked before they count as done. Installs that were set up before the
+//! wizard existed count as onboarded.
+
+use rust_i18n::t;
+
+use crate::api_client::validate_server_settings;
+use crate::clock::Clock;
+use crate::error::AppError;
+use crate::models::{CompletedOnboardingStep, OnboardingState, OnboardingStep};
+use crate::state::AppState;
+use crate::storage;
+
+/// Current progress, for the wizard to resume from
+pub fn state(app_state: &AppState, clock: &dyn Clock) -> Result<OnboardingState, AppError> {
+    if let Some(state) = storage::load_onboarding_state().map_err(|e| {
+        log::error!("Failed to load onboarding state: {}", e);
+        e.to_string()
+    })? {
+        return Ok(state);
+    }
+
+    let set_up = app_state
+        .signin_data()
+        .map_err(|e| e.to_string())?
+        .is_some();
+    if set_up {
+        log::info!("Existing install without onboarding state, treating it as onboarded");
+        let now = clock.now().to_rfc3339();
+        return Ok(OnboardingState {
+            completed_steps: OnboardingStep::ALL
+                .iter()
+                .map(|&step| CompletedOnboardingStep {
+                    step,
+                    completed_at: now.clone(),
+                    skipped: false,
+                })
+                .collect(),
+            next_step: None,
+            completed_at: Some(now),
+        });
+    }
+    Ok(OnboardingState {
+        completed_steps: Vec::new(),
+        next_step: OnboardingStep::ALL.first().copied(),
+        completed_at: None,
+    })
+}
+
+/// Mark `step` as done, or as left for later when `skipped`
+///
+/// Finishing a step that is already done updates it, e.g. when the user went
+/// back in the wizard and paired after all.
+pub fn complete_step(
+    app_state: &AppState,
+    clock: &dyn Clock,
+    step: OnboardingStep,
+    skipped: bool,
+) -> Result<OnboardingState, AppError> {
+    let mut state = state(app_state, clock)?;
+    let done = state.completed_steps.iter().any(|c| c.step == step);
+    if let Some(next_step) = state
+        .next_step
+        .filter(|&next_step| !done && next_step != step)
+    {
+        return Err(t!("error.onboarding_step_first", step = next_step : {:?}).into());
+    }
+    if skipped && !step.is_optional() {
+        return Err(t!("error.onboarding_step_required", step = step : {:?}).into());
+    }
+    if !skipped {
+        check(app_state, step)?;
+    }
+
+    let now = clock.now().to_rfc3339();
+    state.completed_steps.retain(|c| c.step != step);
+    state.completed_steps.push(CompletedOnboardingStep {
+        step,
+        completed_at: now.clone(),
+        skipped,
+    });
+    state
+        .completed_steps
+        .sort_by_key(|c| OnboardingStep::ALL.iter().position(|&s| s == c.step));
+    state.next_step = OnboardingStep::ALL
+        .iter()
+        .copied()
+        .find(|&s| !state.completed_steps.iter().any(|c| c.step == s));
+    if state.next_step.is_none() && state.completed_at.is_none() {
+        log::info!("Onboarding finished");
+        state.completed_at = Some(now);
+    }
+
+    storage::save_onboarding_state(&state).map_err(|e| {
+        log::error!("Failed to save onboarding state: {}", e);
+        e.to_string()
+    })?;
+    log::info!("Onboarding step {:?} {}", step, if skipped { "skipped" } else { "completed" });
+    Ok(state)
+}
+
+/// Make sure the parts of a step the backend can see are really done
+fn check(app_state: &AppState, step: OnboardingStep) -> Result<(), AppError> {
+    match step {
+        OnboardingStep::ServerConfigured => {
+            let settings = storage::load_settings().map_err(|e| e.to_string())?;
+            validate_server_settings(&settings.server)?;
+        }
+        OnboardingStep::SupervisorPaired => {
+            let config = app_state.device_config().map_err(|e| e.to_string())?;
+            if config.supervision_relationships.is_empty() {
+                return Err(t!("error.no_relationship_yet").into());
+            }
+        }
+        OnboardingStep::NameChosen | OnboardingStep::ModeChosen | OnboardingStep::ChannelTested => {
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,135 @@
+//! Retry outbox for failed deliveries.
+//!
+//! Deliveries that fail on a channel are parked here instead of being dropped,
+//! and a scheduled job retries them on the same channel with exponential
+//! backoff. Items that still fail after the last attempt are kept, marked as failed and
+//! reported to the frontend until the user flushes or removes them.
+
+use chrono::DateTime;
+use rust_i18n::t;
+use tauri::async_runtime::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use uuid::Uuid;
+
+use crate::channels::registry;
+use crate::clock::{Clock, SharedClock};
+use crate::dispatch;
+use crate::error::AppError;
+use crate::models::{OutboxItem, OutgoingNotification};
+use crate::storage;
+
+/// Delay before the first retry; doubled after every further failure
+const RETRY_BASE_DELAY_MINUTES: i64 = 1;
+
+/// Upper bound for the delay between two retries
+const RETRY_MAX_DELAY_MINUTES: i64 = 6 * 60;
+
+/// Attempts (including the original send) before an item is marked failed
+const MAX_ATTEMPTS: u32 = 8;
+
+/// Serializes retry passes so the worker and `flush_outbox` never send twice
+static RETRY_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Park a failed delivery in the outbox for later retries
+pub fn enqueue(
+    clock: &dyn Clock,
+    channel_id: String,
+    notification: OutgoingNotification,
+    error: &str,
+) {
+    let mut outbox = match storage::load_outbox() {
+        Ok(outbox) => outbox,
+        Err(e) => {
+            log::error!("Failed to load outbox, dropping failed delivery: {}", e);
+            return;
+        }
+    };
+
+    let now = clock.now();
+    outbox.push(OutboxItem {
+        id: Uuid::new_v4().to_string(),
+        channel_id,
+        notification,
+        created_at: now.to_rfc3339(),
+        attempts: 1,
+        next_attempt_at: (now + retry_delay(1)).to_rfc3339(),
+        last_error: error.to_string(),
+        failed: false,
+    });
+    log::info!("Queued failed delivery for retry ({} items in outbox)", outbox.len());
+    if let Err(e) = storage::save_outbox(&outbox) {
+        log::error!("Failed to save outbox: {}", e);
+    }
+}
+
+/// Retry outbox items and return what is left afterwards
+///
+/// With `force` every item is attempted right away, including ones that have
+/// already been marked failed; otherwise only pending items whose backoff has
+/// elapsed are retried.
+pub async fn retry(app: &AppHandle, force: bool) -> Result<Vec<OutboxItem>, AppError> {
+    let _guard = RETRY_LOCK.lock().await;
+    let clock = app.state::<SharedClock>();
+    let now = clock.now();
+
+    let due: Vec<OutboxItem> = storage::load_outbox()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|item| force || (!item.failed && is_due(item, now)))
+        .collect();
+
+    for item in due {
+        log::info!("Retrying outbox item {} (attempt {})", item.id, item.attempts + 1);
+        let result = attempt(app, &item).await;
+
+        // Reload so items enqueued while we were sending are kept
+        let mut outbox = storage::load_outbox().map_err(|e| e.to_string())?;
+        let Some(position) = outbox.iter().position(|i| i.id == item.id) else {
+            continue;
+        };
+        match result {
+            Ok(()) => {
+                log::info!("Outbox item {} delivered", item.id);
+                outbox.remove(position);
+            }
+            Err(e) => {
+                let entry = &mut outbox[position];
+                entry.attempts += 1;
+                entry.last_error = e.to_string();
+                entry.next_attempt_at = (clock.now() + retry_delay(entry.attempts)).to_rfc3339();
+                if entry.attempts >= MAX_ATTEMPTS && !entry.failed {
+                    log::error!("Giving up on outbox item {}: {}", entry.id, entry.last_error);
+                    entry.failed = true;
+                    if let Err(e) = app.emit("outbox-item-failed", entry.clone()) {
+                        log::error!("Failed to emit outbox-item-failed event: {}", e);
+                    }
+                }
+            }
+        }
+        storage::save_outbox(&outbox).map_err(|e| e.to_string())?;
+    }
+
+    storage::load_outbox().map_err(AppError::from)
+}
+
+/// Send an outbox item once on its channel, without parking it again on failure
+async fn attempt(app: &AppHandle, item: &OutboxItem) -> Result<(), AppError> {
+    let channel = registry(app)
+        .into_iter()
+        .find(|c| c.id() == item.channel_id)
+        .ok_or_else(|| t!("error.channel_removed", channel = item.channel_id))?;
+    dispatch::send_on(channel.as_ref(), &item.notification).await
+}
+
+/// Check whether an item's backoff has elapsed
+fn is_due(item: &OutboxItem, now: DateTime<chrono::Utc>) -> bool {
+    DateTime::parse_from_rfc3339(&item.next_attempt_at).map_or(true, |next| next <= now)
+}
+
+/// Backoff before the retry following `attempts` failed attempts
+fn retry_delay(attempts: u32) -> chrono::Duration {
+    let minutes = RETRY_BASE_DELAY_MINUTES
+        .saturating_mul(1i64 << attempts.saturating_sub(1).min(20))
+        .min(RETRY_MAX_DELAY_MINUTES);
+    chrono::Duration::minutes(minutes)
+}
@@ -0,0 +1,117 @@
+//! "Are you OK?" pings from supervisors.
+//!
+//! A supervisor pings a supervised device through the server, which pushes a
+//! `checkin_ping` event to it. The supervised device shows a notification and
+//! the user answers with one tap; the answer reaches the supervisor as a
+//! `checkin_ping_response` event. Both sides record each step in the
+//! supervision history.
+
+use rust_i18n::t;
+use tauri::{AppHandle, Manager};
+
+use crate::api_client::{respond_checkin_ping_api, send_checkin_ping_api};
+use crate::dispatch;
+use crate::error::AppError;
+use crate::models::{NotificationEvent, NotificationMessage, SupervisionHistoryKind};
+use crate::remote_models::CheckinPing;
+use crate::state::AppState;
+use crate::supervision::{partner_nickname, record_history};
+
+/// Ask a supervised device whether they are OK
+pub async fn send_checkin_ping(state: &AppState, device_id: &str) -> Result<CheckinPing, AppError> {
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let ping = send_checkin_ping_api(&config.device.device_id, device_id).await?;
+    record_history(
+        SupervisionHistoryKind::PingSent,
+        device_id,
+        supervised_device_name(state, device_id),
+        Some(ping.ping_id.clone()),
+        None,
+    );
+    Ok(ping)
+}
+
+/// Answer a ping with "I'm OK"
+pub async fn respond_checkin_ping(ping_id: &str) -> Result<CheckinPing, AppError> {
+    let ping = respond_checkin_ping_api(ping_id).await?;
+    record_history(
+        SupervisionHistoryKind::OkResponse,
+        &ping.supervisor_id,
+        ping.supervisor_name.clone(),
+        Some(ping.ping_id.clone()),
+        None,
+    );
+    Ok(ping)
+}
+
+/// Show a ping pushed by the server and remember it
+pub fn handle_ping_event(app: &AppHandle, data: &serde_json::Value) {
+    let Some(ping) = parse_ping(data) else {
+        return;
+    };
+    let supervisor = partner_nickname(&app.state::<AppState>(), &ping.supervisor_id)
+        .or_else(|| ping.supervisor_name.clone())
+        .unwrap_or_else(|| ping.supervisor_id.clone());
+    log::info!("Check-in ping {} from {}", ping.ping_id, supervisor);
+
+    record_history(
+        SupervisionHistoryKind::PingReceived,
+        &ping.supervisor_id,
+        ping.supervisor_name,
+        Some(ping.ping_id),
+        None,
+    );
+    let message = NotificationMessage {
+        event: NotificationEvent::Reminder,
+        title: t!("ping.title").into_owned(),
+        body: t!("ping.body", supervisor = supervisor).into_owned(),
+        quote: None,
+    };
+    dispatch::show_local(app, message, false);
+}
+
+/// Record and show the answer to a ping this supervisor sent
+pub fn handle_ping_response_event(app: &AppHandle, data: &serde_json::Value) {
+    let Some(ping) = parse_ping(data) else {
+        return;
+    };
+    let name = supervised_device_name(&app.state::<AppState>(), &ping.target_id);
+    log::info!("Check-in ping {} answered by {}", ping.ping_id, ping.target_id);
+
+    let body =
+        t!("ping.response_body", name = name.as_deref().unwrap_or(&ping.target_id)).into_owned();
+    record_history(
+        SupervisionHistoryKind::OkResponse,
+        &ping.target_id,
+        name,
+        Some(ping.ping_id),
+        None,
+    );
+    let message = NotificationMessage {
+        event: NotificationEvent::Supervision,
+        title: t!("ping.response_title").into_owned(),
+        body,
+        quote: None,
+    };
+    dispatch::show_local(app, message, false);
+}
+
+/// Parse the ping carried by a server event
+fn parse_ping(data: &serde_json::Value) -> Option<CheckinPing> {
+    serde_json::from_value(data.clone())
+        .map_err(|e| log::warn!("Ignoring malformed check-in ping event: {}", e))
+        .ok()
+}
+
+/// Name of a device this device supervises, if the relationship is known locally
+fn supervised_device_name(state: &AppState, device_id: &str) -> Option<String> {
+    let config = state.device_config().ok()?;
+    config
+        .supervision_relationships
+        .into_iter()
+        .find(|r| r.supervised_device_id == device_id)
+        .map(|r| r.name_of(device_id, &r.supervisor_device_id).to_string())
+}
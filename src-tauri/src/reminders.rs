@@ -0,0 +1,255 @@
+//! Daily check-in reminders.
+//!
+//! This module shows a local notification when a configured reminder time has
+//! passed and today's sign-in hasn't happened yet, repeating at the configured
+//! interval until the user signs in or snoozes. The [`scheduler`](crate::scheduler)
+//! checks for due reminders every minute.
+//! Each later reminder time escalates the wording, and a final "last chance"
+//! notification goes out shortly before the check-in deadline.
+//!
+//! Supervisors can nudge a supervised device, which schedules one extra
+//! reminder here. Nudges fire even when the daily reminders are disabled, but
+//! never once today's sign-in is done.
+
+use std::borrow::Cow;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime};
+use rust_i18n::t;
+use tauri::{AppHandle, Manager};
+
+use crate::clock::{Clock, SharedClock};
+use crate::deadline::{checkin_deadline, configured_offset, signin_day};
+use crate::dispatch;
+use crate::error::AppError;
+use crate::models::{AppSettings, NotificationEvent, NotificationMessage, ReminderConfig};
+use crate::state::AppState;
+use crate::storage;
+
+/// Reminder runtime state shared between the background loop and commands
+#[derive(Default)]
+pub struct ReminderState {
+    inner: Mutex<ReminderRuntime>,
+}
+
+/// Per-day bookkeeping for fired and snoozed reminders
+#[derive(Default)]
+struct ReminderRuntime {
+    day: Option<NaiveDate>,
+    last_fired: Option<DateTime<Local>>,
+    /// Number of reminder times already announced today
+    fired_level: usize,
+    last_chance_sent: bool,
+    snoozed_until: Option<DateTime<Local>>,
+    /// Extra reminders requested by supervisors, with the name of the sender
+    nudges: Vec<(DateTime<Local>, String)>,
+}
+
+impl ReminderState {
+    /// Suppress reminders for the given number of minutes
+    pub fn snooze(&self, clock: &dyn Clock, minutes: u32) -> Result<DateTime<Local>, AppError> {
+        let until =
+            clock.now().with_timezone(&Local) + chrono::Duration::minutes(i64::from(minutes));
+        let mut runtime = self
+            .inner
+            .lock()
+            .map_err(|_| t!("error.reminder_state_unavailable").into_owned())?;
+        runtime.snoozed_until = Some(until);
+        Ok(until)
+    }
+
+    /// Show an extra reminder from `sender` at `at`
+    pub fn schedule_nudge(&self, at: DateTime<Local>, sender: String) -> Result<(), AppError> {
+        let mut runtime = self
+            .inner
+            .lock()
+            .map_err(|_| t!("error.reminder_state_unavailable").into_owned())?;
+        runtime.nudges.push((at, sender));
+        Ok(())
+    }
+}
+
+/// Fire a reminder notification if one is due
+pub fn check_reminder(app: &AppHandle) {
+    fire_due_nudges(app);
+    let config = match storage::load_reminder_config() {
+        Ok(config) if config.enabled => config,
+        Ok(_) => return,
+        Err(e) => {
+            log::warn!("Failed to load reminder config: {}", e);
+            return;
+        }
+    };
+
+    let Some(settings) = load_settings() else {
+        return;
+    };
+    let clock = app.state::<SharedClock>();
+    if is_signed_in_today(app, &settings) {
+        return;
+    }
+    let now = clock.now().with_timezone(&Local);
+    let day = signin_day(clock.as_ref(), &settings);
+    let time_of_day = clock
+        .now()
+        .with_timezone(&configured_offset(&settings, clock.now()))
+        .time();
+
+    let state = app.state::<ReminderState>();
+    let Ok(mut runtime) = state.inner.lock() else {
+        log::warn!("Reminder state lock poisoned, skipping reminder");
+        return;
+    };
+
+    if runtime.day != Some(day) {
+        *runtime = ReminderRuntime {
+            day: Some(day),
+            nudges: std::mem::take(&mut runtime.nudges),
+            ..Default::default()
+        };
+    }
+
+    if !runtime.last_chance_sent && is_last_chance(clock.as_ref(), &config, &settings) {
+        runtime.last_chance_sent = true;
+        drop(runtime);
+        log::info!("Firing last-chance check-in reminder");
+        // The last chance comes right before supervisors are alerted, so it skips quiet hours
+        show_reminder(
+            app,
+            &t!("reminder.last_chance_title"),
+            &t!("reminder.last_chance_body"),
+            true,
+        );
+        return;
+    }
+
+    let level = reminder_times(&config)
+        .iter()
+        .filter(|time| time_of_day >= **time)
+        .count();
+    if level == 0 {
+        return;
+    }
+
+    if runtime.snoozed_until.is_some_and(|until| now < until) {
+        return;
+    }
+
+    if let (Some(last), true) = (runtime.last_fired, level == runtime.fired_level) {
+        let interval = chrono::Duration::minutes(i64::from(config.repeat_interval_minutes));
+        if config.repeat_interval_minutes == 0 || now - last < interval {
+            return;
+        }
+    }
+
+    runtime.last_fired = Some(now);
+    runtime.fired_level = level;
+    runtime.snoozed_until = None;
+    drop(runtime);
+
+    log::info!("Firing daily check-in reminder (level {})", level);
+    let (title, body) = reminder_message(level);
+    show_reminder(app, &title, &body, false);
+}
+
+/// Show the nudges whose time has come, unless today's sign-in is done
+fn fire_due_nudges(app: &AppHandle) {
+    let clock = app.state::<SharedClock>();
+    let now = clock.now().with_timezone(&Local);
+    let state = app.state::<ReminderState>();
+    let due: Vec<String> = {
+        let Ok(mut runtime) = state.inner.lock() else {
+            log::warn!("Reminder state lock poisoned, skipping nudges");
+            return;
+        };
+        let (due, later) = runtime.nudges.drain(..).partition(|(at, _)| *at <= now);
+        runtime.nudges = later;
+        due.into_iter()
+            .map(|(_, sender)| sender)
+            .collect::<Vec<_>>()
+    };
+    if due.is_empty() || load_settings().is_some_and(|s| is_signed_in_today(app, &s)) {
+        return;
+    }
+
+    let mut senders = due;
+    senders.dedup();
+    log::info!("Firing check-in reminder nudged by {}", senders.join(", "));
+    show_reminder(
+        app,
+        &t!("reminder.nudged_title"),
+        &t!("reminder.nudged_body", senders = senders.join(&t!("reminder.sender_separator"))),
+        false,
+    );
+}
+
+/// Parse and sort all configured reminder times, skipping invalid entries
+fn reminder_times(config: &ReminderConfig) -> Vec<NaiveTime> {
+    let mut times: Vec<NaiveTime> = std::iter::once(&config.reminder_time)
+        .chain(&config.additional_times)
+        .filter_map(|time| match NaiveTime::parse_from_str(time, "%H:%M") {
+            Ok(parsed) => Some(parsed),
+            Err(_) => {
+                log::warn!("Invalid reminder time '{}'", time);
+                None
+            }
+        })
+        .collect();
+    times.sort();
+    times.dedup();
+    times
+}
+
+/// Check whether the final reminder window before the deadline has opened
+fn is_last_chance(clock: &dyn Clock, config: &ReminderConfig, settings: &AppSettings) -> bool {
+    if config.last_chance_minutes == 0 {
+        return false;
+    }
+    let deadline = checkin_deadline(clock, settings);
+    let window = chrono::Duration::minutes(i64::from(config.last_chance_minutes));
+    let now = clock.now();
+    now < deadline && deadline - now <= window
+}
+
+/// Reminder wording, growing more urgent with each passed reminder time
+fn reminder_message(level: usize) -> (Cow<'static, str>, Cow<'static, str>) {
+    match level {
+        0 | 1 => (t!("reminder.gentle_title"), t!("reminder.gentle_body")),
+        2 => (t!("reminder.firm_title"), t!("reminder.firm_body")),
+        _ => (t!("reminder.urgent_title"), t!("reminder.urgent_body")),
+    }
+}
+
+/// Show a local reminder notification, held back during quiet hours unless critical
+fn show_reminder(app: &AppHandle, title: &str, body: &str, critical: bool) {
+    let message = NotificationMessage {
+        event: NotificationEvent::Reminder,
+        title: title.to_string(),
+        body: body.to_string(),
+        quote: None,
+    };
+    dispatch::show_local(app, message, critical);
+}
+
+/// Load the settings that decide the sign-in day and deadline
+fn load_settings() -> Option<AppSettings> {
+    storage::load_settings()
+        .map_err(|e| log::warn!("Failed to load app settings for reminder check: {}", e))
+        .ok()
+}
+
+/// Check whether the current sign-in day has already been recorded
+fn is_signed_in_today(app: &AppHandle, settings: &AppSettings) -> bool {
+    let clock = app.state::<SharedClock>();
+    let today = signin_day(clock.as_ref(), settings)
+        .format("%Y-%m-%d")
+        .to_string();
+    match app.state::<AppState>().signin_data() {
+        Ok(Some(data)) => data.last_signin_date == today,
+        Ok(None) => false,
+        Err(e) => {
+            log::warn!("Failed to load sign-in data for reminder check: {}", e);
+            false
+        }
+    }
+}
@@ -11,6 +11,7 @@ use serde::{Deserialize, Serialize};
 pub enum DeviceMode {
     Signin,
     Supervisor,
+    Both,
 }
 
 /// Device information from server
@@ -33,6 +34,26 @@ pub struct SigninRecord {
     pub device_id: String,
     pub date: String,
     pub streak: i32,
+    /// Exact time of the sign-in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signed_at: Option<String>,
+}
+
+/// Sign-in records a device shares with one supervisor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedHistory {
+    /// What the device currently shares with the supervisor asking
+    pub policy: SharingPolicy,
+    /// Recent sign-ins, empty unless the policy includes the history
+    #[serde(default)]
+    pub records: Vec<SigninRecord>,
+}
+
+impl SharedHistory {
+    /// Check whether the device shares its history at all
+    pub fn is_shared(&self) -> bool {
+        self.policy >= SharingPolicy::History
+    }
 }
 
 /// Device status for supervisors
@@ -44,6 +65,86 @@ pub struct DeviceStatus {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_signin: Option<String>,
     pub streak: i32,
+    /// Answers to the wellness questions given at the last check-in
+    #[serde(default)]
+    pub wellness_answers: Vec<WellnessAnswer>,
+    /// Mood of the last check-in, when the device shares it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mood: Option<u8>,
+    /// Recent sign-in dates, when the device shares its history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recent_signins: Option<Vec<String>>,
+    /// Time of the device's last heartbeat
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen_at: Option<String>,
+    /// The device paused its status updates until this time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paused_until: Option<String>,
+    /// [`PrivateStatus`] sealed for the supervisor asking
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sealed: Option<String>,
+}
+
+/// How much a supervised device shares with one supervisor
+///
+/// Each level includes everything shared by the levels before it.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SharingPolicy {
+    /// Current streak only
+    StreakOnly,
+    /// Streak and time of the last sign-in
+    #[default]
+    LastSignin,
+    /// Also the mood given at check-in
+    Mood,
+    /// Also the dates of recent sign-ins
+    History,
+}
+
+/// Status a supervised device publishes for one supervisor, shaped by its sharing policy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedStatus {
+    pub supervisor_id: String,
+    pub policy: SharingPolicy,
+    pub streak: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_signin: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mood: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recent_signins: Option<Vec<String>>,
+    /// [`PrivateStatus`] sealed for the supervisor, replacing the plaintext mood
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sealed: Option<String>,
+}
+
+/// Part of a shared status only the supervisor can read
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrivateStatus {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mood: Option<u8>,
+    /// Answers to this supervisor's wellness questions at the last check-in
+    #[serde(default)]
+    pub wellness_answers: Vec<WellnessAnswer>,
+}
+
+/// Question a supervisor asks the supervised device at every check-in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WellnessQuestion {
+    pub question_id: String,
+    pub supervisor_id: String,
+    pub text: String,
+}
+
+/// Answer to a wellness question
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WellnessAnswer {
+    pub question_id: String,
+    /// Question text at the time of answering
+    #[serde(default)]
+    pub question: String,
+    pub answer: String,
 }
 
 /// Supervision request status from server
@@ -53,6 +154,20 @@ pub enum SupervisionStatus {
     Pending,
     Accepted,
     Rejected,
+    Expired,
+}
+
+/// How a supervisor found the device a supervision request targets
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestIdentifier {
+    /// The device ID, e.g. picked from search results
+    #[default]
+    DeviceId,
+    /// The device's IMEI, which can be guessed or read over someone's shoulder
+    Imei,
+    /// An invitation code the target device issued
+    InviteCode,
 }
 
 /// Supervision request from server
@@ -67,6 +182,15 @@ pub struct SupervisionRequest {
     pub target_name: Option<String>,
     pub status: SupervisionStatus,
     pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Encryption key of the supervisor, sent along with the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supervisor_public_key: Option<String>,
+    #[serde(default)]
+    pub identified_by: RequestIdentifier,
 }
 
 /// Supervision relationship from server
@@ -80,6 +204,156 @@ pub struct SupervisionRelation {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub target_name: Option<String>,
     pub created_at: String,
+    /// End of a temporary supervision
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supervisor_public_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_public_key: Option<String>,
+}
+
+/// Kind of event in a relationship's timeline on the server
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SupervisionEventKind {
+    Requested,
+    Accepted,
+    Rejected,
+    Ping,
+    PingResponse,
+    Sos,
+    Encouragement,
+    MissedCheckin,
+    Removed,
+}
+
+/// One event in a supervision relationship's timeline from server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupervisionEvent {
+    pub event_id: String,
+    pub kind: SupervisionEventKind,
+    /// Device that caused the event
+    pub actor_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor_name: Option<String>,
+    /// ID of the request, ping or message the event belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub created_at: String,
+}
+
+/// Short-lived token that lets another device pair with this one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairingToken {
+    pub token: String,
+    pub expires_at: String,
+}
+
+/// Invitation code a supervisor can redeem to request supervision
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InviteCode {
+    pub code: String,
+    pub expires_at: String,
+}
+
+/// Reminder a supervisor asked to show on a supervised device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Nudge {
+    pub nudge_id: String,
+    pub supervisor_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supervisor_name: Option<String>,
+    pub target_id: String,
+    pub created_at: String,
+    /// When to show the reminder; right away when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remind_at: Option<String>,
+}
+
+/// Code a supervisor issues to hand a relationship over to another device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferCode {
+    pub code: String,
+    pub relation_id: String,
+    pub expires_at: String,
+}
+
+/// "Are you OK?" prompt sent by a supervisor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckinPing {
+    pub ping_id: String,
+    pub supervisor_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supervisor_name: Option<String>,
+    pub target_id: String,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub responded_at: Option<String>,
+}
+
+/// SOS raised by a supervised device, as pushed to its supervisors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SosAlert {
+    pub device_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub sent_at: String,
+}
+
+/// Short message from a supervisor, shown to the supervisee after their next check-in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Encouragement {
+    pub message_id: String,
+    pub sender_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender_name: Option<String>,
+    pub target_id: String,
+    /// Message text; empty while it is still sealed
+    #[serde(default)]
+    pub text: String,
+    /// Message text sealed for the target device
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sealed_text: Option<String>,
+    pub created_at: String,
+}
+
+/// API versions and capabilities reported by the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerVersion {
+    /// Newest API version the server speaks
+    pub api_version: u32,
+    /// Oldest API version the server still accepts
+    pub min_api_version: u32,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+/// Changes to a list since the cursor the client sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListDelta<T> {
+    /// Items added or changed since the cursor
+    pub items: Vec<T>,
+    /// IDs of the items removed since the cursor
+    #[serde(default)]
+    pub removed: Vec<String>,
+    /// Server timestamp to send as `since` on the next fetch
+    pub cursor: String,
+    /// The cursor was too old, so `items` is the whole list
+    #[serde(default)]
+    pub full: bool,
+}
+
+/// List as answered by servers with and without delta support
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ListResponse<T> {
+    Delta(ListDelta<T>),
+    Full(Vec<T>),
 }
 
 /// Sign-in response
@@ -96,3 +370,15 @@ pub type SupervisionListResponse = Vec<SupervisionRelation>;
 
 /// Pending requests response
 pub type PendingRequestsResponse = Vec<SupervisionRequest>;
+
+/// Event pushed by the server over the device event stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerEvent {
+    /// ID to resume the stream after this event from, if the server set one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Event type, e.g. `signin` or `supervision_request`
+    pub event: String,
+    /// Event payload; plain text when the server didn't send JSON
+    pub data: serde_json::Value,
+}
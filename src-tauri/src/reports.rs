@@ -0,0 +1,340 @@
+//! Weekly and monthly sign-in reports.
+//!
+//! This module builds structured summaries from the stored sign-in history and
+//! emails them from a scheduled job when a report schedule is configured.
+//! It also composes the weekly digest that replaces per-sign-in emails in
+//! digest mode.
+
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Datelike, Local, Months, NaiveDate, Timelike};
+use tauri::{AppHandle, Manager};
+
+use crate::clock::SharedClock;
+use crate::deadline::signin_day;
+use crate::dispatch;
+use crate::error::AppError;
+use crate::models::{
+    ChannelSelection, DigestAlert, DigestQuote, DigestState, MoodTrend, OutgoingNotification,
+    PeriodReport, Quote, ReportPeriod, SigninData, WeeklyDigest,
+};
+use crate::services::{digest_email, report_email};
+use crate::state::AppState;
+use crate::storage;
+
+/// Minimum change in average mood between halves of a period to count as a trend
+const MOOD_TREND_THRESHOLD: f32 = 0.5;
+
+/// Get the first and last day of the period containing `date`
+pub fn period_bounds(period: ReportPeriod, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    match period {
+        ReportPeriod::Week => {
+            let offset = i64::from(date.weekday().num_days_from_monday());
+            let start = date - chrono::Duration::days(offset);
+            (start, start + chrono::Duration::days(6))
+        }
+        ReportPeriod::Month => {
+            let start = date.with_day(1).unwrap_or(date);
+            let end = start
+                .checked_add_months(Months::new(1))
+                .and_then(|next| next.pred_opt())
+                .unwrap_or(date);
+            (start, end)
+        }
+    }
+}
+
+/// Build a report for the period containing `reference`, counting days up to `today`
+pub fn build_period_report(
+    data: &Option<SigninData>,
+    period: ReportPeriod,
+    reference: NaiveDate,
+    today: NaiveDate,
+) -> PeriodReport {
+    let (start, end) = period_bounds(period, reference);
+    let last_counted = end.min(today);
+    let elapsed_days = if last_counted < start {
+        0
+    } else {
+        (last_counted - start).num_days() as u32 + 1
+    };
+
+    let in_period = |date: &NaiveDate| *date >= start && *date <= last_counted;
+
+    let signed_dates: BTreeSet<NaiveDate> = data
+        .iter()
+        .flat_map(|d| {
+            d.signin_history
+                .iter()
+                .map(String::as_str)
+                .chain(d.signin_entries.iter().map(|e| e.date.as_str()))
+        })
+        .filter_map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .filter(in_period)
+        .collect();
+
+    let entries: Vec<_> = data
+        .iter()
+        .flat_map(|d| d.signin_entries.iter())
+        .filter(|e| {
+            NaiveDate::parse_from_str(&e.date, "%Y-%m-%d")
+                .map(|date| in_period(&date))
+                .unwrap_or(false)
+        })
+        .collect();
+
+    let signin_seconds: Vec<u32> = entries
+        .iter()
+        .filter_map(|e| DateTime::parse_from_rfc3339(&e.signed_at).ok())
+        .map(|t| t.with_timezone(&Local).num_seconds_from_midnight())
+        .collect();
+
+    let moods: Vec<f32> = entries
+        .iter()
+        .filter_map(|e| e.mood)
+        .map(f32::from)
+        .collect();
+
+    let days_signed_in = signed_dates.len() as u32;
+
+    PeriodReport {
+        name: data.as_ref().map(|d| d.name.clone()).unwrap_or_default(),
+        period,
+        start_date: start.format("%Y-%m-%d").to_string(),
+        end_date: end.format("%Y-%m-%d").to_string(),
+        elapsed_days,
+        days_signed_in,
+        days_missed: elapsed_days.saturating_sub(days_signed_in),
+        average_signin_time: average_time_of_day(&signin_seconds),
+        average_mood: average(&moods),
+        mood_trend: mood_trend(&moods),
+        current_streak: data.as_ref().map(|d| d.streak).unwrap_or(0),
+    }
+}
+
+/// Format the mean of seconds-since-midnight values as HH:MM
+pub fn average_time_of_day(seconds: &[u32]) -> Option<String> {
+    if seconds.is_empty() {
+        return None;
+    }
+    let total: u64 = seconds.iter().map(|&s| u64::from(s)).sum();
+    let mean = total / seconds.len() as u64;
+    Some(format!("{:02}:{:02}", mean / 3600, (mean % 3600) / 60))
+}
+
+/// Arithmetic mean of a list of values
+fn average(values: &[f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().sum::<f32>() / values.len() as f32)
+}
+
+/// Compare the average mood of the first and second half of the period
+fn mood_trend(moods: &[f32]) -> MoodTrend {
+    if moods.len() < 2 {
+        return MoodTrend::Unknown;
+    }
+    let (first, second) = moods.split_at(moods.len() / 2);
+    let (Some(before), Some(after)) = (average(first), average(second)) else {
+        return MoodTrend::Unknown;
+    };
+
+    let delta = after - before;
+    if delta > MOOD_TREND_THRESHOLD {
+        MoodTrend::Improving
+    } else if delta < -MOOD_TREND_THRESHOLD {
+        MoodTrend::Declining
+    } else {
+        MoodTrend::Stable
+    }
+}
+
+// =============================================================================
+// Scheduled Report Emails
+// =============================================================================
+
+/// Email the scheduled report and the weekly digest if they are due
+pub async fn send_due(app: &AppHandle) -> Result<(), AppError> {
+    let report = send_due_report(app).await;
+    let digest = send_due_digest(app).await;
+    report.map_err(|e| format!("Scheduled report failed: {}", e))?;
+    digest.map_err(|e| format!("Weekly digest failed: {}", e).into())
+}
+
+/// Email the report for the previous period if it hasn't been sent yet
+async fn send_due_report(app: &AppHandle) -> Result<(), AppError> {
+    let app_state = app.state::<AppState>();
+    let config = app_state.email_config().map_err(|e| e.to_string())?;
+    let Some(period) = config.report_schedule else {
+        return Ok(());
+    };
+    if !config.enabled {
+        return Ok(());
+    }
+
+    let settings = storage::load_settings().map_err(|e| e.to_string())?;
+    let today = signin_day(app.state::<SharedClock>().as_ref(), &settings);
+    let (current_start, _) = period_bounds(period, today);
+    let Some(previous_day) = current_start.pred_opt() else {
+        return Ok(());
+    };
+    let (previous_start, _) = period_bounds(period, previous_day);
+    let previous_key = previous_start.format("%Y-%m-%d").to_string();
+
+    let mut state = storage::load_report_state().map_err(|e| e.to_string())?;
+    let last_sent = match period {
+        ReportPeriod::Week => &mut state.last_weekly_report,
+        ReportPeriod::Month => &mut state.last_monthly_report,
+    };
+    if last_sent.as_deref() == Some(previous_key.as_str()) {
+        return Ok(());
+    }
+
+    let Some(data) = app_state.signin_data().map_err(|e| e.to_string())? else {
+        log::debug!("No sign-in data yet, skipping scheduled report");
+        return Ok(());
+    };
+
+    log::info!("Sending scheduled {:?} report for period starting {}", period, previous_key);
+    let report = build_period_report(&Some(data), period, previous_day, today);
+    let notification = OutgoingNotification {
+        email: Some(report_email(&report)),
+        ..Default::default()
+    };
+    dispatch::deliver(app, notification, ChannelSelection::email_only(), false).await;
+
+    *last_sent = Some(previous_key);
+    storage::save_report_state(&state).map_err(AppError::from)
+}
+
+// =============================================================================
+// Weekly Digest
+// =============================================================================
+
+/// Compose the weekly digest for the week containing `reference`
+pub fn build_weekly_digest(
+    data: &Option<SigninData>,
+    state: &DigestState,
+    reference: NaiveDate,
+    today: NaiveDate,
+) -> WeeklyDigest {
+    let report = build_period_report(data, ReportPeriod::Week, reference, today);
+    let in_week =
+        |date: &str| date >= report.start_date.as_str() && date <= report.end_date.as_str();
+
+    let mood_on = |date: &str| {
+        data.iter()
+            .flat_map(|d| d.signin_entries.iter())
+            .find(|e| e.date == date)
+            .and_then(|e| e.mood)
+            .unwrap_or(0)
+    };
+    let best_quote = state
+        .quotes
+        .iter()
+        .filter(|q| in_week(&q.date))
+        .max_by(|a, b| {
+            mood_on(&a.date)
+                .cmp(&mood_on(&b.date))
+                .then_with(|| a.date.cmp(&b.date))
+        })
+        .map(|q| q.quote.clone());
+
+    let alerts = state
+        .alerts
+        .iter()
+        .filter(|a| in_week(&a.date))
+        .cloned()
+        .collect();
+
+    WeeklyDigest {
+        report,
+        best_quote,
+        alerts,
+    }
+}
+
+/// Remember the quote received with a sign-in when digest mode is on
+pub fn record_digest_quote(app_state: &AppState, date: &str, quote: &Quote) {
+    update_digest_state(app_state, |state| {
+        state.quotes.retain(|q| q.date != date);
+        state.quotes.push(DigestQuote {
+            date: date.to_string(),
+            quote: quote.clone(),
+        });
+    });
+}
+
+/// Remember a supervision or escalation alert when digest mode is on
+pub fn record_digest_alert(app_state: &AppState, date: &str, message: String) {
+    update_digest_state(app_state, |state| {
+        state.alerts.push(DigestAlert {
+            date: date.to_string(),
+            message,
+        });
+    });
+}
+
+/// Apply a change to the digest state if digest mode is enabled
+fn update_digest_state(app_state: &AppState, change: impl FnOnce(&mut DigestState)) {
+    match app_state.email_config() {
+        Ok(config) if config.enabled && config.weekly_digest => {}
+        Ok(_) => return,
+        Err(e) => {
+            log::warn!("Failed to load email config for digest: {}", e);
+            return;
+        }
+    }
+
+    let result = storage::load_digest_state().and_then(|mut state| {
+        change(&mut state);
+        storage::save_digest_state(&state)
+    });
+    if let Err(e) = result {
+        log::warn!("Failed to update digest state: {}", e);
+    }
+}
+
+/// Email the digest for the previous week if it hasn't been sent yet
+async fn send_due_digest(app: &AppHandle) -> Result<(), AppError> {
+    let app_state = app.state::<AppState>();
+    let config = app_state.email_config().map_err(|e| e.to_string())?;
+    if !config.enabled || !config.weekly_digest {
+        return Ok(());
+    }
+
+    let settings = storage::load_settings().map_err(|e| e.to_string())?;
+    let today = signin_day(app.state::<SharedClock>().as_ref(), &settings);
+    let (current_start, _) = period_bounds(ReportPeriod::Week, today);
+    let Some(previous_day) = current_start.pred_opt() else {
+        return Ok(());
+    };
+    let (previous_start, previous_end) = period_bounds(ReportPeriod::Week, previous_day);
+    let previous_key = previous_start.format("%Y-%m-%d").to_string();
+
+    let mut state = storage::load_digest_state().map_err(|e| e.to_string())?;
+    if state.last_digest_week.as_deref() == Some(previous_key.as_str()) {
+        return Ok(());
+    }
+
+    let data = app_state.signin_data().map_err(|e| e.to_string())?;
+    if data.is_none() {
+        log::debug!("No sign-in data yet, skipping weekly digest");
+        return Ok(());
+    }
+
+    log::info!("Sending weekly digest for week starting {}", previous_key);
+    let digest = build_weekly_digest(&data, &state, previous_day, today);
+    let notification = OutgoingNotification {
+        email: Some(digest_email(&digest)),
+        ..Default::default()
+    };
+    dispatch::deliver(app, notification, ChannelSelection::email_only(), false).await;
+
+    let end_key = previous_end.format("%Y-%m-%d").to_string();
+    state.quotes.retain(|q| q.date > end_key);
+    state.alerts.retain(|a| a.date > end_key);
+    state.last_digest_week = Some(previous_key);
+    storage::save_digest_state(&state).map_err(AppError::from)
+}
@@ -0,0 +1,195 @@
+//! Throttling of outgoing supervision requests.
+//!
+//! Supervision requests end up as notifications on somebody else's phone, so
+//! this device holds back before the network is even involved: a target gets
+//! at most a few requests a day, never a second one while the first is still
+//! awaiting an answer, and after repeated rejections it is left alone for a
+//! while. When the server itself answers 429, no requests are sent until the
+//! wait it asked for is over.
+
+use chrono::{DateTime, Duration, Utc};
+use rust_i18n::t;
+use tauri::{AppHandle, Manager};
+
+use crate::clock::{Clock, SharedClock};
+use crate::error::AppError;
+use crate::models::{RequestLimitState, SupervisionHistoryKind, SUPERVISION_REQUEST_TTL_DAYS};
+use crate::remote_models::{SupervisionRequest, SupervisionStatus};
+use crate::storage;
+use crate::supervision::record_history;
+
+/// Most requests to the same target within a day
+const MAX_REQUESTS_PER_TARGET_PER_DAY: usize = 3;
+
+/// Rejections in a row after which a target is put on cooldown
+const REJECTIONS_BEFORE_COOLDOWN: u32 = 3;
+
+/// How long a target that kept rejecting is left alone, in days
+const COOLDOWN_DAYS: i64 = 7;
+
+/// Longest wait accepted from a 429 response, in seconds
+const MAX_SERVER_WAIT_SECS: u64 = 24 * 60 * 60;
+
+/// Refuse a request to `target_id` that would break one of the limits
+pub fn check_request(clock: &dyn Clock, target_id: &str) -> Result<(), AppError> {
+    let state = load_state()?;
+    let now = clock.now();
+
+    if let Some(retry_at) = state.server_retry_at.as_deref().and_then(parse_time) {
+        if retry_at > now {
+            log::warn!("Server asked to hold requests until {}", retry_at);
+            return Err(t!("error.server_busy", minutes = minutes_until(now, retry_at)).into());
+        }
+    }
+    if let Some(until) = state
+        .cooldown_until
+        .get(target_id)
+        .and_then(|t| parse_time(t))
+    {
+        if until > now {
+            log::warn!("Requests to {} are on cooldown until {}", target_id, until);
+            return Err(t!("error.request_cooldown").into());
+        }
+    }
+    if state
+        .pending
+        .get(target_id)
+        .and_then(|t| parse_time(t))
+        .is_some_and(|expires_at| expires_at > now)
+    {
+        log::warn!("A request to {} is still pending", target_id);
+        return Err(t!("error.request_pending").into());
+    }
+    let sent_today = state
+        .sent
+        .get(target_id)
+        .map(|times| sent_since(times, now - Duration::days(1)))
+        .unwrap_or_default();
+    if sent_today >= MAX_REQUESTS_PER_TARGET_PER_DAY {
+        log::warn!("Request limit reached for {}", target_id);
+        return Err(t!("error.request_daily_limit", max = MAX_REQUESTS_PER_TARGET_PER_DAY).into());
+    }
+    Ok(())
+}
+
+/// Remember a request that was sent to `target_id`
+pub fn record_request(clock: &dyn Clock, target_id: &str, expires_at: Option<&str>) {
+    let now = clock.now();
+    update_state(|state| {
+        let sent = state.sent.entry(target_id.to_string()).or_default();
+        sent.retain(|t| parse_time(t).is_some_and(|t| now - t < Duration::days(1)));
+        sent.push(now.to_rfc3339());
+        let expires_at = expires_at
+            .map(str::to_string)
+            .unwrap_or_else(|| (now + Duration::days(SUPERVISION_REQUEST_TTL_DAYS)).to_rfc3339());
+        state.pending.insert(target_id.to_string(), expires_at);
+    });
+}
+
+/// Forget the pending request to `target_id` once it was cancelled or answered
+pub fn clear_pending(target_id: &str) {
+    update_state(|state| {
+        state.pending.remove(target_id);
+    });
+}
+
+/// Hold back requests when the server reported it is rate limiting this device
+pub fn record_failure(clock: &dyn Clock, error: &AppError) {
+    let AppError::RateLimited {
+        retry_after_secs: secs,
+    } = *error
+    else {
+        return;
+    };
+    let retry_at = clock.now() + Duration::seconds(secs.min(MAX_SERVER_WAIT_SECS) as i64);
+    update_state(|state| state.server_retry_at = Some(retry_at.to_rfc3339()));
+}
+
+/// Track the answer to a request this device sent, pushed by the server
+pub fn handle_request_answered_event(app: &AppHandle, data: &serde_json::Value) {
+    let request: SupervisionRequest = match serde_json::from_value(data.clone()) {
+        Ok(request) => request,
+        Err(e) => {
+            log::warn!("Ignoring malformed supervision answer event: {}", e);
+            return;
+        }
+    };
+    let clock = app.state::<SharedClock>();
+    let now = clock.now();
+    let target_id = request.target_id.clone();
+
+    update_state(|state| {
+        state.pending.remove(&target_id);
+        match request.status {
+            SupervisionStatus::Rejected => {
+                let rejections = state.rejections.entry(target_id.clone()).or_default();
+                *rejections += 1;
+                if *rejections >= REJECTIONS_BEFORE_COOLDOWN {
+                    log::info!("{} rejected {} requests, starting cooldown", target_id, rejections);
+                    state.rejections.remove(&target_id);
+                    state.cooldown_until.insert(
+                        target_id.clone(),
+                        (now + Duration::days(COOLDOWN_DAYS)).to_rfc3339(),
+                    );
+                }
+            }
+            SupervisionStatus::Accepted => {
+                state.rejections.remove(&target_id);
+                state.cooldown_until.remove(&target_id);
+            }
+            SupervisionStatus::Pending | SupervisionStatus::Expired => {}
+        }
+    });
+
+    if request.status == SupervisionStatus::Rejected {
+        record_history(
+            SupervisionHistoryKind::RequestRejected,
+            &request.target_id,
+            request.target_name,
+            Some(request.request_id),
+            None,
+        );
+    }
+}
+
+/// Load the bookkeeping, surfacing storage errors
+fn load_state() -> Result<RequestLimitState, AppError> {
+    storage::load_request_limits().map_err(|e| {
+        log::error!("Failed to load request limits: {}", e);
+        e.to_string().into()
+    })
+}
+
+/// Apply `change` to the stored bookkeeping, logging failures
+fn update_state(change: impl FnOnce(&mut RequestLimitState)) {
+    let mut state = match storage::load_request_limits() {
+        Ok(state) => state,
+        Err(e) => {
+            log::warn!("Failed to load request limits: {}", e);
+            return;
+        }
+    };
+    change(&mut state);
+    if let Err(e) = storage::save_request_limits(&state) {
+        log::warn!("Failed to save request limits: {}", e);
+    }
+}
+
+/// Number of the stored send times at or after `since`
+fn sent_since(times: &[String], since: DateTime<Utc>) -> usize {
+    times
+        .iter()
+        .filter_map(|t| parse_time(t))
+        .filter(|t| *t >= since)
+        .count()
+}
+
+/// Whole minutes until `until`, rounded up
+fn minutes_until(now: DateTime<Utc>, until: DateTime<Utc>) -> i64 {
+    ((until - now).num_seconds() + 59) / 60
+}
+
+/// Parse a stored RFC 3339 time
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time).ok().map(|t| t.to_utc())
+}
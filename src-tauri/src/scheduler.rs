@@ -0,0 +1,262 @@
+//! Periodic background work.
+//!
+//! Every job that runs on a timer, such as reminders, the supervision sync,
+//! report and digest emails or outbox retries, is declared in [`jobs`] with its
+//! interval and started from here. Each job runs on its own task, so a slow
+//! one never holds up the others, and jobs that call the server first wait
+//! until a rate limit the server imposed has passed.
+//!
+//! Jobs can be switched off by name in
+//! [`AppSettings::scheduled_jobs`](crate::models::AppSettings::scheduled_jobs).
+//! The setting is re-read before every run, so changes apply without a
+//! restart. The outcome of each job's latest run is kept for debugging.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use futures::future::{BoxFuture, FutureExt};
+use tauri::{AppHandle, Manager};
+
+use crate::api_client;
+use crate::clock::SharedClock;
+use crate::error::AppError;
+use crate::models::ScheduledJobStatus;
+use crate::{
+    alerts, dispatch, escalation, heartbeat, outbox, reminders, reports, storage, supervision,
+    sync_queue,
+};
+
+/// Status of every started job by name
+static STATUS: Mutex<BTreeMap<&'static str, ScheduledJobStatus>> = Mutex::new(BTreeMap::new());
+
+/// How often a job runs
+enum Interval {
+    /// Fixed time between runs
+    Every(Duration),
+    /// Time between runs taken from the settings, looked up after every run
+    Configured(fn() -> Duration),
+}
+
+/// A periodic background job
+struct Job {
+    /// Name shown in the status and used to switch the job off in the settings
+    name: &'static str,
+    interval: Interval,
+    /// Whether the first run waits one interval instead of starting right away
+    delay_first: bool,
+    /// Whether the job calls the server and must respect its rate limit
+    calls_server: bool,
+    run: fn(AppHandle) -> BoxFuture<'static, Result<(), AppError>>,
+}
+
+impl Job {
+    fn interval(&self) -> Duration {
+        match self.interval {
+            Interval::Every(interval) => interval,
+            Interval::Configured(interval) => interval(),
+        }
+    }
+}
+
+/// Every periodic job of the app
+fn jobs() -> Vec<Job> {
+    #[allow(unused_mut)]
+    let mut jobs = vec![
+        Job {
+            name: "reminders",
+            interval: Interval::Every(Duration::from_secs(60)),
+            delay_first: false,
+            calls_server: false,
+            run: |app| {
+                async move {
+                    reminders::check_reminder(&app);
+                    Ok(())
+                }
+                .boxed()
+            },
+        },
+        Job {
+            name: "reports",
+            interval: Interval::Every(Duration::from_secs(60 * 60)),
+            delay_first: false,
+            calls_server: false,
+            run: |app| async move { reports::send_due(&app).await }.boxed(),
+        },
+        Job {
+            name: "escalation",
+            interval: Interval::Every(Duration::from_secs(60)),
+            delay_first: false,
+            calls_server: false,
+            run: |app| async move { escalation::run_escalation(&app).await }.boxed(),
+        },
+        Job {
+            name: "missed_checkin_alerts",
+            interval: Interval::Every(Duration::from_secs(15 * 60)),
+            delay_first: false,
+            calls_server: true,
+            run: |app| async move { alerts::run_alert_check(&app).await }.boxed(),
+        },
+        Job {
+            name: "outbox",
+            interval: Interval::Every(Duration::from_secs(60)),
+            delay_first: false,
+            calls_server: false,
+            run: |app| async move { outbox::retry(&app, false).await.map(|_| ()) }.boxed(),
+        },
+        Job {
+            name: "quiet_hours_queue",
+            interval: Interval::Every(Duration::from_secs(60)),
+            delay_first: false,
+            calls_server: false,
+            run: |app| async move { dispatch::flush_quiet_queue(&app).await }.boxed(),
+        },
+        Job {
+            name: "supervision_sync",
+            interval: Interval::Configured(supervision::sync_interval),
+            delay_first: false,
+            calls_server: true,
+            run: |app| async move { supervision::sync(&app).await.map(|_| ()) }.boxed(),
+        },
+        Job {
+            name: "heartbeat",
+            interval: Interval::Every(Duration::from_secs(10 * 60)),
+            delay_first: false,
+            calls_server: true,
+            run: |app| async move { heartbeat::beat(&app).await }.boxed(),
+        },
+        Job {
+            name: "sync_queue",
+            interval: Interval::Every(Duration::from_secs(30)),
+            delay_first: true,
+            calls_server: true,
+            run: |app| async move { sync_queue::replay(&app).await }.boxed(),
+        },
+    ];
+    #[cfg(desktop)]
+    jobs.push(Job {
+        name: "tray",
+        interval: Interval::Every(Duration::from_secs(60)),
+        delay_first: true,
+        calls_server: false,
+        run: |app| {
+            async move {
+                crate::tray::refresh(&app);
+                Ok(())
+            }
+            .boxed()
+        },
+    });
+    #[cfg(desktop)]
+    jobs.push(Job {
+        name: "updates",
+        interval: Interval::Every(Duration::from_secs(6 * 60 * 60)),
+        delay_first: false,
+        calls_server: false,
+        run: |app| async move { crate::updater::check_in_background(&app).await }.boxed(),
+    });
+    jobs
+}
+
+/// Names of all jobs, e.g. to check the settings against
+pub fn job_names() -> Vec<&'static str> {
+    jobs().iter().map(|job| job.name).collect()
+}
+
+/// Start every job on its own task
+pub fn start(app: &AppHandle) {
+    let jobs = jobs();
+    log::info!("Starting scheduler with {} jobs", jobs.len());
+    for job in jobs {
+        update_status(job.name, |status| {
+            status.interval_secs = job.interval().as_secs();
+        });
+        let app = app.clone();
+        tauri::async_runtime::spawn(run_job(app, job));
+    }
+}
+
+/// Status of every job, in name order
+pub fn status() -> Vec<ScheduledJobStatus> {
+    STATUS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .values()
+        .map(|status| ScheduledJobStatus {
+            enabled: is_enabled(&status.name),
+            ..status.clone()
+        })
+        .collect()
+}
+
+/// Run a job every interval for as long as the app runs
+async fn run_job(app: AppHandle, job: Job) {
+    if job.delay_first {
+        wait(&app, &job).await;
+    }
+    loop {
+        if is_enabled(job.name) {
+            if job.calls_server {
+                api_client::wait_for_rate_limit().await;
+            }
+            run_once(&app, &job).await;
+        } else {
+            log::debug!("Scheduled job {} is switched off", job.name);
+        }
+        wait(&app, &job).await;
+    }
+}
+
+/// Run a job and record how it went
+async fn run_once(app: &AppHandle, job: &Job) {
+    log::debug!("Running scheduled job {}", job.name);
+    let now = app.state::<SharedClock>().now();
+    update_status(job.name, |status| {
+        status.running = true;
+        status.last_run_at = Some(now.to_rfc3339());
+    });
+    let started = Instant::now();
+    let result = (job.run)(app.clone()).await;
+    if let Err(e) = &result {
+        log::warn!("Scheduled job {} failed: {}", job.name, e);
+    }
+    update_status(job.name, |status| {
+        status.running = false;
+        status.runs += 1;
+        status.last_duration_ms =
+            Some(started.elapsed().as_millis().try_into().unwrap_or(u64::MAX));
+        status.last_error = result.err().map(|e| e.to_string());
+    });
+}
+
+/// Sleep until the next run of a job
+async fn wait(app: &AppHandle, job: &Job) {
+    let interval = job.interval();
+    let now = app.state::<SharedClock>().now();
+    update_status(job.name, |status| {
+        status.interval_secs = interval.as_secs();
+        status.next_run_at = chrono::Duration::from_std(interval)
+            .ok()
+            .map(|interval| (now + interval).to_rfc3339());
+    });
+    tokio::time::sleep(interval).await;
+}
+
+/// Check whether a job is switched on; jobs not named in the settings are on
+fn is_enabled(name: &str) -> bool {
+    storage::load_settings()
+        .ok()
+        .and_then(|settings| settings.scheduled_jobs.get(name).copied())
+        .unwrap_or(true)
+}
+
+/// Change the status of a job, creating it on first use
+fn update_status(name: &'static str, update: impl FnOnce(&mut ScheduledJobStatus)) {
+    let mut jobs = STATUS.lock().unwrap_or_else(|e| e.into_inner());
+    let status = jobs.entry(name).or_insert_with(|| ScheduledJobStatus {
+        name: name.to_string(),
+        enabled: true,
+        ..Default::default()
+    });
+    update(status);
+}
@@ -3,16 +3,43 @@
 //! This module contains integrations with external APIs and services,
 //! including email notifications and daily quote fetching.
 
-use lettre::message::{header::ContentType, Mailbox};
-use lettre::transport::smtp::authentication::Credentials;
-use lettre::{Message, SmtpTransport, Transport};
+use async_trait::async_trait;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use chrono::{Datelike, NaiveDate};
+use hmac::{Hmac, Mac};
+use lettre::message::{
+    header::ContentType, Attachment, Mailbox, MessageBuilder, MultiPart, SinglePart,
+};
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use rust_i18n::t;
+use sha2::Sha256;
 
-use crate::models::{EmailConfig, HitokotoResponse, Quote};
+use crate::api_client::http_client;
+use crate::channels::{message_of, NotificationChannel};
+use crate::clock;
+use crate::error::AppError;
+use crate::i18n;
+use crate::models::{
+    DeliveryChannel, DingTalkConfig, EmailAuthMethod, EmailConfig, EmailTestResult, EmailTestStep,
+    GotifyConfig, HitokotoResponse, Milestone, MoodTrend, NotificationEvent, NotificationMessage,
+    NtfyConfig, OutgoingEmail, OutgoingNotification, PeriodReport, ProfileSettings, Quote,
+    RecipientKind, ReportPeriod, SlackConfig, SmtpSecurity, TelegramConfig, TwilioConfig,
+    WeComConfig, WeeklyDigest,
+};
+use crate::oauth;
+use crate::streak_image::render_streak_png;
+
+/// HTML template for the sign-in confirmation email
+const SIGNIN_EMAIL_TEMPLATE: &str = include_str!("../templates/signin_email.html");
+
+/// Days shown in the HTML calendar row of the sign-in email
+const HTML_CALENDAR_DAYS: usize = 14;
 
 /// Fetch a daily inspirational quote from hitokoto.cn API
-pub async fn fetch_hitokoto() -> Result<Quote, String> {
+pub async fn fetch_hitokoto() -> Result<Quote, AppError> {
     log::info!("Fetching daily quote from hitokoto.cn API");
-    let client = reqwest::Client::new();
+    let client = http_client()?;
     let url = "https://v1.hitokoto.cn/";
 
     let response = client.get(url).send().await.map_err(|e| {
@@ -41,93 +68,1117 @@ pub async fn fetch_hitokoto() -> Result<Quote, String> {
     })
 }
 
-/// Send a sign-in notification email
-pub fn send_signin_email(
+/// Compose the sign-in confirmation email
+pub fn signin_email(
     name: &str,
     streak: i32,
     quote: &Quote,
-    config: &EmailConfig,
-) -> Result<(), String> {
-    if !config.enabled || config.to_email.is_empty() {
+    milestone: Option<Milestone>,
+    recent_days: &[(NaiveDate, bool)],
+) -> OutgoingEmail {
+    log::info!("Preparing sign-in email notification for {} (streak: {} days)", name, streak);
+
+    let (subject, body) = match milestone {
+        Some(milestone) => (
+            t!("signin.milestone_title", name = name, milestone = milestone_title(milestone))
+                .into_owned(),
+            build_milestone_email_body(name, streak, quote, milestone),
+        ),
+        None => (
+            t!("email.signin_subject", name = name, streak = streak).into_owned(),
+            build_email_body(name, streak, quote),
+        ),
+    };
+
+    let inline_png = render_streak_png(streak, recent_days)
+        .inspect_err(|e| log::warn!("Failed to render streak image: {}", e))
+        .ok();
+    let html = build_signin_email_html(
+        name,
+        streak,
+        quote,
+        milestone,
+        recent_days,
+        &subject,
+        inline_png.is_some(),
+    );
+    OutgoingEmail {
+        event: NotificationEvent::Signin,
+        subject,
+        body,
+        html: Some(html),
+        inline_png,
+    }
+}
+
+/// Compose a weekly or monthly report email
+pub fn report_email(report: &PeriodReport) -> OutgoingEmail {
+    log::info!(
+        "Preparing {:?} report email for {} ({} - {})",
+        report.period,
+        report.name,
+        report.start_date,
+        report.end_date
+    );
+
+    let period_label = match report.period {
+        ReportPeriod::Week => t!("email.weekly"),
+        ReportPeriod::Month => t!("email.monthly"),
+    };
+    OutgoingEmail {
+        event: NotificationEvent::Report,
+        subject: t!(
+            "email.report_subject",
+            name = report.name,
+            period = period_label,
+            start = report.start_date,
+            end = report.end_date
+        )
+        .into_owned(),
+        body: build_report_body(report),
+        html: None,
+        inline_png: None,
+    }
+}
+
+/// Compose the weekly digest email
+pub fn digest_email(digest: &WeeklyDigest) -> OutgoingEmail {
+    let report = &digest.report;
+    log::info!(
+        "Preparing weekly digest for {} ({} - {})",
+        report.name,
+        report.start_date,
+        report.end_date
+    );
+
+    OutgoingEmail {
+        event: NotificationEvent::Digest,
+        subject: t!(
+            "email.digest_subject",
+            name = report.name,
+            start = report.start_date,
+            end = report.end_date
+        )
+        .into_owned(),
+        body: build_digest_body(digest),
+        html: None,
+        inline_png: None,
+    }
+}
+
+/// Compose the alert telling the supervisor that a device missed its check-in
+pub fn missed_signin_email(
+    device_name: &str,
+    date: &str,
+    last_signin: Option<&str>,
+) -> OutgoingEmail {
+    log::info!("Preparing missed check-in alert for {} ({})", device_name, date);
+
+    OutgoingEmail {
+        event: NotificationEvent::MissedSignin,
+        subject: t!("email.missed_subject", name = device_name, date = date).into_owned(),
+        body: build_missed_signin_body(device_name, date, last_signin),
+        html: None,
+        inline_png: None,
+    }
+}
+
+/// Compose the alert email for a supervised device that missed several days in a row
+pub fn missed_days_email(device_name: &str, days: i64, last_signin: &str) -> OutgoingEmail {
+    log::info!("Preparing missed-days alert for {} ({} days)", device_name, days);
+
+    OutgoingEmail {
+        event: NotificationEvent::MissedSignin,
+        subject: t!("email.missed_days_subject", name = device_name, days = days).into_owned(),
+        body: build_missed_days_body(device_name, days, last_signin),
+        html: None,
+        inline_png: None,
+    }
+}
+
+/// Compose the SOS email sent to the supervisors' addresses
+pub fn sos_email(name: &str, sent_at: &str, note: Option<&str>) -> OutgoingEmail {
+    log::info!("Preparing SOS email for {}", name);
+
+    OutgoingEmail {
+        event: NotificationEvent::Sos,
+        subject: t!("email.sos_subject", name = name).into_owned(),
+        body: build_sos_body(name, sent_at, note),
+        html: None,
+        inline_png: None,
+    }
+}
+
+/// Address and send a composed email to the configured recipients
+pub async fn send_email(email: &OutgoingEmail, config: &EmailConfig) -> Result<(), AppError> {
+    if !config.enabled || !config.has_recipients() {
         log::debug!("Email notification disabled or recipient email not configured");
         return Ok(());
     }
 
-    log::info!("Preparing sign-in email notification for {} (streak: {} days)", name, streak);
+    let message = match &email.html {
+        Some(html) => build_html_email_message(
+            config,
+            &email.subject,
+            email.body.clone(),
+            html.clone(),
+            email.inline_png.as_deref(),
+        )?,
+        None => build_email_message(config, &email.subject, email.body.clone())?,
+    };
+    send_via_smtp(message, config).await
+}
 
-    let from = parse_email_address(&config.from_email, "from")?;
-    let to = parse_email_address(&config.to_email, "to")?;
+#[async_trait]
+impl NotificationChannel for EmailConfig {
+    fn kind(&self) -> DeliveryChannel {
+        DeliveryChannel::Email
+    }
 
-    let subject = format!("🔥 {} 签到成功！连续签到 {} 天", name, streak);
-    let body = build_email_body(name, streak, quote);
+    fn accepts(&self, notification: &OutgoingNotification) -> bool {
+        notification.email.is_some()
+    }
 
-    let email = build_email_message(from, to, &subject, body)?;
-    send_via_smtp(email, config)
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), AppError> {
+        let email = notification
+            .email
+            .as_ref()
+            .ok_or_else(|| "Notification has no email".to_string())?;
+        send_email(email, self).await
+    }
 }
 
 /// Parse and validate an email address
-fn parse_email_address(email: &str, field_name: &str) -> Result<Mailbox, String> {
+fn parse_email_address(email: &str, field_name: &str) -> Result<Mailbox, AppError> {
     email.parse::<Mailbox>().map_err(|e| {
         log::error!("Invalid {} email address '{}': {}", field_name, email, e);
-        format!("Invalid {} email: {}", field_name, e)
+        format!("Invalid {} email: {}", field_name, e).into()
     })
 }
 
 /// Build the email body content
 fn build_email_body(name: &str, streak: i32, quote: &Quote) -> String {
-    format!(
-        "Hi {},\n\n\
-        恭喜你今天成功签到！🎉\n\n\
-        当前连续签到天数：{} 天 🔥\n\n\
-        每日一言：\n\
-        \"{}\"\n\
-        - {}\n\n\
-        继续保持，加油！💪\n\n\
-        --\n\
-        Are You OK?",
-        name, streak, quote.text, quote.author
+    t!(
+        "email.signin_body",
+        name = name,
+        streak = streak,
+        quote = quote.text,
+        author = quote.author
+    )
+    .into_owned()
+}
+
+/// Render the HTML sign-in email with the streak counter and recent-days calendar
+fn build_signin_email_html(
+    name: &str,
+    streak: i32,
+    quote: &Quote,
+    milestone: Option<Milestone>,
+    recent_days: &[(NaiveDate, bool)],
+    subject: &str,
+    with_image: bool,
+) -> String {
+    let (headline, closing) = match milestone {
+        Some(milestone) => (milestone_title(milestone), t!("email.milestone_closing")),
+        None => (t!("email.signin_headline").into_owned(), t!("email.signin_closing")),
+    };
+
+    let streak_image = if with_image {
+        format!(
+            "<img src=\"cid:streak-image\" alt=\"{}\" width=\"228\" \
+             style=\"display:block;margin:0 auto 24px;border-radius:12px;\">",
+            escape_html(&t!("email.streak_image_alt"))
+        )
+    } else {
+        String::new()
+    };
+
+    let calendar: String = recent_days[recent_days.len().saturating_sub(HTML_CALENDAR_DAYS)..]
+        .iter()
+        .map(|(date, signed)| {
+            let (background, color) = if *signed {
+                ("#34c759", "#ffffff")
+            } else {
+                ("#e5e5ea", "#86868b")
+            };
+            format!(
+                "<td title=\"{}\" style=\"width:24px;height:24px;border-radius:6px;\
+                 background:{};color:{};font-size:11px;text-align:center;\">{}</td>",
+                date.format("%Y-%m-%d"),
+                background,
+                color,
+                date.day()
+            )
+        })
+        .collect();
+
+    render_html_template(
+        SIGNIN_EMAIL_TEMPLATE,
+        &[
+            ("lang", &i18n::current_locale()),
+            ("subject", subject),
+            ("name", name),
+            ("headline", &headline),
+            ("streak", &streak.to_string()),
+            ("streak_label", &t!("email.streak_label")),
+            ("recent_days", &t!("email.recent_days")),
+            ("quote_text", &quote.text),
+            ("quote_author", &quote.author),
+            ("closing", &closing),
+        ],
+        &[("calendar", &calendar), ("streak_image", &streak_image)],
+    )
+}
+
+/// Fill `{{key}}` placeholders with HTML-escaped values and `{{{key}}}` with raw markup
+fn render_html_template(template: &str, text: &[(&str, &str)], raw: &[(&str, &str)]) -> String {
+    let rendered = raw.iter().fold(template.to_string(), |html, (key, value)| {
+        html.replace(&format!("{{{{{{{}}}}}}}", key), value)
+    });
+    text.iter().fold(rendered, |html, (key, value)| {
+        html.replace(&format!("{{{{{}}}}}", key), &escape_html(value))
+    })
+}
+
+/// Escape text for safe inclusion in HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Build the celebratory email body used on milestone days
+fn build_milestone_email_body(
+    name: &str,
+    streak: i32,
+    quote: &Quote,
+    milestone: Milestone,
+) -> String {
+    t!(
+        "email.milestone_body",
+        name = name,
+        milestone = milestone_title(milestone),
+        streak = streak,
+        quote = quote.text,
+        author = quote.author
     )
+    .into_owned()
+}
+
+/// Short celebratory headline for a milestone
+pub fn milestone_title(milestone: Milestone) -> String {
+    match milestone {
+        Milestone::Birthday => t!("milestone.birthday").into_owned(),
+        Milestone::StreakAnniversary { years } => {
+            t!("milestone.streak_anniversary", years = years).into_owned()
+        }
+        Milestone::AccountAnniversary { years } => {
+            t!("milestone.account_anniversary", years = years).into_owned()
+        }
+    }
+}
+
+/// Detect whether `today` is a meaningful date in the user's profile
+///
+/// Birthdays take precedence over streak anniversaries, which take precedence
+/// over account anniversaries. Feb 29 dates are celebrated on Feb 28 in
+/// non-leap years.
+pub fn detect_milestone(profile: &ProfileSettings, today: NaiveDate) -> Option<Milestone> {
+    let birthday = profile.birthday.as_deref().and_then(|b| {
+        let (month, day) = b.split_once('-')?;
+        Some((month.parse::<u32>().ok()?, day.parse::<u32>().ok()?))
+    });
+    if birthday.is_some_and(|(month, day)| falls_on(month, day, today)) {
+        return Some(Milestone::Birthday);
+    }
+
+    let years_since = |date: &Option<String>| {
+        let start = NaiveDate::parse_from_str(date.as_deref()?, "%Y-%m-%d").ok()?;
+        let years = u32::try_from(today.year() - start.year()).ok()?;
+        (years > 0 && falls_on(start.month(), start.day(), today)).then_some(years)
+    };
+
+    if let Some(years) = years_since(&profile.streak_started_on) {
+        return Some(Milestone::StreakAnniversary { years });
+    }
+    years_since(&profile.account_created_on).map(|years| Milestone::AccountAnniversary { years })
+}
+
+/// Check whether the month/day recurs on `today`
+fn falls_on(month: u32, day: u32, today: NaiveDate) -> bool {
+    if month == 2 && day == 29 && NaiveDate::from_ymd_opt(today.year(), 2, 29).is_none() {
+        return today.month() == 2 && today.day() == 28;
+    }
+    today.month() == month && today.day() == day
+}
+
+/// Build the missed check-in alert body content
+fn build_missed_signin_body(device_name: &str, date: &str, last_signin: Option<&str>) -> String {
+    let never = t!("common.never_signed_in");
+    t!(
+        "email.missed_body",
+        name = device_name,
+        date = date,
+        last = last_signin.unwrap_or(&never)
+    )
+    .into_owned()
+}
+
+/// Build the missed-days alert email body content
+fn build_missed_days_body(device_name: &str, days: i64, last_signin: &str) -> String {
+    t!("email.missed_days_body", name = device_name, days = days, last = last_signin).into_owned()
+}
+
+/// Build the SOS email body content
+fn build_sos_body(name: &str, sent_at: &str, note: Option<&str>) -> String {
+    let none = t!("common.none");
+    t!("email.sos_body", name = name, time = sent_at, note = note.unwrap_or(&none)).into_owned()
+}
+
+/// Build the report email body content
+fn build_report_body(report: &PeriodReport) -> String {
+    let mood_trend = match report.mood_trend {
+        MoodTrend::Improving => t!("email.mood_improving"),
+        MoodTrend::Stable => t!("email.mood_stable"),
+        MoodTrend::Declining => t!("email.mood_declining"),
+        MoodTrend::Unknown => t!("email.mood_unknown"),
+    };
+    let average_mood = report
+        .average_mood
+        .map(|m| format!("{:.1} / 5", m))
+        .unwrap_or_else(|| "-".to_string());
+
+    t!(
+        "email.report_body",
+        name = report.name,
+        start = report.start_date,
+        end = report.end_date,
+        signed = report.days_signed_in,
+        missed = report.days_missed,
+        time = report.average_signin_time.as_deref().unwrap_or("-"),
+        mood = average_mood,
+        trend = mood_trend,
+        streak = report.current_streak
+    )
+    .into_owned()
+}
+
+/// Build the weekly digest email body content
+fn build_digest_body(digest: &WeeklyDigest) -> String {
+    let report = &digest.report;
+    let quote = digest
+        .best_quote
+        .as_ref()
+        .map(|q| t!("email.digest_best_quote", quote = q.text, author = q.author).into_owned())
+        .unwrap_or_default();
+    let alerts = if digest.alerts.is_empty() {
+        t!("email.digest_no_alerts").into_owned()
+    } else {
+        let lines: Vec<String> = digest
+            .alerts
+            .iter()
+            .map(|a| format!("- {} {}", a.date, a.message))
+            .collect();
+        t!("email.digest_alerts", alerts = lines.join("\n")).into_owned()
+    };
+
+    t!(
+        "email.digest_body",
+        name = report.name,
+        start = report.start_date,
+        end = report.end_date,
+        signed = report.days_signed_in,
+        missed = report.days_missed,
+        streak = report.current_streak,
+        quote = quote,
+        alerts = alerts
+    )
+    .into_owned()
+}
+
+/// Start a message addressed from the configured sender to every active recipient
+fn address_message(config: &EmailConfig) -> Result<MessageBuilder, AppError> {
+    let mut builder = Message::builder().from(parse_email_address(&config.from_email, "from")?);
+    for recipient in config.active_recipients() {
+        let mailbox = parse_email_address(&recipient.address, "recipient")?;
+        builder = match recipient.kind {
+            RecipientKind::To => builder.to(mailbox),
+            RecipientKind::Cc => builder.cc(mailbox),
+            RecipientKind::Bcc => builder.bcc(mailbox),
+        };
+    }
+    Ok(builder)
 }
 
 /// Build the email message
 fn build_email_message(
-    from: Mailbox,
-    to: Mailbox,
+    config: &EmailConfig,
     subject: &str,
     body: String,
-) -> Result<Message, String> {
-    Message::builder()
-        .from(from)
-        .to(to)
+) -> Result<Message, AppError> {
+    address_message(config)?
         .subject(subject)
         .header(ContentType::TEXT_PLAIN)
         .body(body)
         .map_err(|e| {
             log::error!("Failed to build email message: {}", e);
-            format!("Failed to build email: {}", e)
+            format!("Failed to build email: {}", e).into()
+        })
+}
+
+/// Build a multipart email with an HTML body and a plain-text fallback
+fn build_html_email_message(
+    config: &EmailConfig,
+    subject: &str,
+    plain: String,
+    html: String,
+    inline_png: Option<&[u8]>,
+) -> Result<Message, AppError> {
+    let body = match inline_png {
+        Some(png) => {
+            let png_type = ContentType::parse("image/png")
+                .map_err(|e| format!("Invalid image content type: {}", e))?;
+            MultiPart::alternative()
+                .singlepart(SinglePart::plain(plain))
+                .multipart(
+                    MultiPart::related()
+                        .singlepart(SinglePart::html(html))
+                        .singlepart(
+                            Attachment::new_inline("streak-image".to_string())
+                                .body(png.to_vec(), png_type),
+                        ),
+                )
+        }
+        None => MultiPart::alternative_plain_html(plain, html),
+    };
+    address_message(config)?
+        .subject(subject)
+        .multipart(body)
+        .map_err(|e| {
+            log::error!("Failed to build email message: {}", e);
+            format!("Failed to build email: {}", e).into()
         })
 }
 
 /// Send email via SMTP
-fn send_via_smtp(email: Message, config: &EmailConfig) -> Result<(), String> {
+async fn send_via_smtp(email: Message, config: &EmailConfig) -> Result<(), AppError> {
     log::debug!("Connecting to SMTP server: {}:{}", config.smtp_server, config.smtp_port);
 
-    let credentials = Credentials::new(config.smtp_username.clone(), config.smtp_password.clone());
-
-    let mailer = SmtpTransport::starttls_relay(&config.smtp_server)
-        .map_err(|e| {
-            log::error!("Failed to create SMTP relay for {}: {}", config.smtp_server, e);
-            format!("Failed to create SMTP relay: {}", e)
-        })?
-        .port(config.smtp_port)
-        .credentials(credentials)
-        .build();
-
-    mailer.send(&email).map_err(|e| {
+    let mailer = build_mailer(config, true).await?;
+    mailer.send(email).await.map_err(|e| {
         log::error!("Failed to send email via SMTP: {}", e);
         format!("Failed to send email: {}", e)
     })?;
 
-    log::info!("Successfully sent sign-in email notification to {}", config.to_email);
+    log::info!(
+        "Successfully sent email notification to {} recipients",
+        config.active_recipients().count()
+    );
     Ok(())
 }
+
+/// Build the SMTP transport, optionally with the configured credentials
+async fn build_mailer(
+    config: &EmailConfig,
+    authenticate: bool,
+) -> Result<AsyncSmtpTransport<Tokio1Executor>, AppError> {
+    let builder = match config.security_mode {
+        SmtpSecurity::Starttls => {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_server)
+        }
+        SmtpSecurity::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_server),
+        SmtpSecurity::None => {
+            Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_server))
+        }
+    }
+    .map_err(|e| {
+        log::error!("Failed to create SMTP relay for {}: {}", config.smtp_server, e);
+        format!("Failed to create SMTP relay: {}", e)
+    })?
+    .port(config.smtp_port);
+
+    if !authenticate {
+        return Ok(builder.build());
+    }
+
+    let (credentials, mechanisms) = match config.auth_method {
+        EmailAuthMethod::None => return Ok(builder.build()),
+        EmailAuthMethod::Password => (
+            Credentials::new(config.smtp_username.clone(), config.smtp_password.clone()),
+            vec![Mechanism::Plain, Mechanism::Login],
+        ),
+        EmailAuthMethod::Oauth2 => {
+            let oauth2 = config
+                .oauth2
+                .as_ref()
+                .ok_or_else(|| t!("error.oauth_not_authorized").into_owned())?;
+            let token = oauth::access_token(oauth2).await?;
+            (Credentials::new(config.smtp_username.clone(), token), vec![Mechanism::Xoauth2])
+        }
+    };
+
+    Ok(builder
+        .credentials(credentials)
+        .authentication(mechanisms)
+        .build())
+}
+
+/// Exercise the SMTP path step by step with a test message
+///
+/// The connection is tested first without credentials, then with them, so a
+/// failure can be attributed to the network/TLS setup or to authentication.
+pub async fn run_email_test(config: &EmailConfig) -> EmailTestResult {
+    log::info!("Sending test email via {}:{}", config.smtp_server, config.smtp_port);
+    let mut result = EmailTestResult::default();
+
+    let connection = match build_mailer(config, false).await {
+        Ok(mailer) => mailer.test_connection().await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    match connection {
+        Ok(true) => result.connection_ok = true,
+        Ok(false) => return result.fail(EmailTestStep::Connection, "Server did not respond"),
+        Err(e) => return result.fail(EmailTestStep::Connection, &e),
+    }
+
+    let mailer = match build_mailer(config, true).await {
+        Ok(mailer) => mailer,
+        Err(e) => return result.fail(EmailTestStep::Authentication, &e.to_string()),
+    };
+    match mailer.test_connection().await {
+        Ok(true) => result.auth_ok = true,
+        Ok(false) => return result.fail(EmailTestStep::Authentication, "Server did not respond"),
+        Err(e) => return result.fail(EmailTestStep::Authentication, &e.to_string()),
+    }
+
+    let email =
+        build_email_message(config, &t!("email.test_subject"), t!("email.test_body").into_owned());
+    let sent = match email {
+        Ok(email) => mailer.send(email).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    match sent {
+        Ok(_) => result.send_ok = true,
+        Err(e) => return result.fail(EmailTestStep::Send, &e),
+    }
+
+    log::info!("Test email sent successfully");
+    result
+}
+
+// =============================================================================
+// Chat and Push Channels
+// =============================================================================
+
+/// Compose the sign-in confirmation sent to chat and push channels
+pub fn signin_message(
+    name: &str,
+    streak: i32,
+    milestone: Option<Milestone>,
+    quote: &Quote,
+) -> NotificationMessage {
+    let title = match milestone {
+        Some(milestone) => {
+            t!("signin.milestone_title", name = name, milestone = milestone_title(milestone))
+        }
+        None => t!("signin.message_title", name = name),
+    };
+    NotificationMessage {
+        event: NotificationEvent::Signin,
+        title: title.into_owned(),
+        body: t!("signin.message_body", streak = streak).into_owned(),
+        quote: Some(quote.clone()),
+    }
+}
+
+/// Compose the missed check-in alert sent to chat and push channels
+pub fn missed_signin_message(
+    name: &str,
+    date: &str,
+    last_signin: Option<&str>,
+) -> NotificationMessage {
+    NotificationMessage {
+        event: NotificationEvent::MissedSignin,
+        title: t!("alert.missed_title", name = name, date = date).into_owned(),
+        body: t!("alert.missed_body", last = last_signin.unwrap_or(&t!("common.never_signed_in")))
+            .into_owned(),
+        quote: None,
+    }
+}
+
+/// Build the alert for a supervised device that missed several days in a row
+pub fn missed_days_message(name: &str, days: i64, last_signin: &str) -> NotificationMessage {
+    NotificationMessage {
+        event: NotificationEvent::MissedSignin,
+        title: t!("alert.missed_days_title", name = name, days = days).into_owned(),
+        body: t!("alert.missed_days_body", last = last_signin).into_owned(),
+        quote: None,
+    }
+}
+
+/// Build the notification for a temporary supervision that reached its end
+pub fn supervision_lapsed_message(supervisor: &str, supervised: &str) -> NotificationMessage {
+    NotificationMessage {
+        event: NotificationEvent::Supervision,
+        title: t!("supervision.lapsed_title").into_owned(),
+        body: t!("supervision.lapsed_body", supervisor = supervisor, supervised = supervised)
+            .into_owned(),
+        quote: None,
+    }
+}
+
+/// Build the SOS notification shown on chat channels and devices
+pub fn sos_message(name: &str, sent_at: &str, note: Option<&str>) -> NotificationMessage {
+    let mut body = t!("sos.body", name = name, time = sent_at).into_owned();
+    if let Some(note) = note {
+        body.push('\n');
+        body.push_str(&t!("sos.note", note = note));
+    }
+    NotificationMessage {
+        event: NotificationEvent::Sos,
+        title: t!("sos.title", name = name).into_owned(),
+        body,
+        quote: None,
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for SlackConfig {
+    fn kind(&self) -> DeliveryChannel {
+        DeliveryChannel::Slack
+    }
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), AppError> {
+        send_slack_message(self, message_of(notification)?).await
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for WeComConfig {
+    fn kind(&self) -> DeliveryChannel {
+        DeliveryChannel::Wecom
+    }
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), AppError> {
+        send_wecom_message(self, message_of(notification)?).await
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for DingTalkConfig {
+    fn kind(&self) -> DeliveryChannel {
+        DeliveryChannel::Dingtalk
+    }
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), AppError> {
+        send_dingtalk_message(self, message_of(notification)?).await
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for NtfyConfig {
+    fn kind(&self) -> DeliveryChannel {
+        DeliveryChannel::Ntfy
+    }
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), AppError> {
+        send_ntfy_message(self, message_of(notification)?).await
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for GotifyConfig {
+    fn kind(&self) -> DeliveryChannel {
+        DeliveryChannel::Gotify
+    }
+
+    fn accepts(&self, notification: &OutgoingNotification) -> bool {
+        notification
+            .message
+            .as_ref()
+            .is_some_and(|m| self.events.allows(m.event))
+    }
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), AppError> {
+        send_gotify_message(self, message_of(notification)?).await
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for TelegramConfig {
+    fn kind(&self) -> DeliveryChannel {
+        DeliveryChannel::Telegram
+    }
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), AppError> {
+        send_telegram_message(self, message_of(notification)?).await
+    }
+}
+
+/// Post a message to a Slack incoming webhook
+async fn send_slack_message(
+    config: &SlackConfig,
+    message: &NotificationMessage,
+) -> Result<(), AppError> {
+    log::info!("Posting notification to Slack webhook");
+    let mut text = format!("*{}*\n{}", message.title, message.body);
+    if let Some(quote) = &message.quote {
+        text.push_str(&format!("\n> {}\n> — {}", quote.text, quote.author));
+    }
+
+    post_webhook_json(&config.webhook_url, &serde_json::json!({ "text": text }), "Slack")
+        .await
+        .map(|_| ())
+}
+
+/// Post a markdown card to a WeCom group robot
+async fn send_wecom_message(
+    config: &WeComConfig,
+    message: &NotificationMessage,
+) -> Result<(), AppError> {
+    log::info!("Posting notification to WeCom robot");
+    let mut content = format!("### {}\n{}", message.title, message.body);
+    if let Some(quote) = &message.quote {
+        content.push_str(&format!(
+            "\n> {}{}\n> <font color=\"comment\">—— {}</font>",
+            t!("common.daily_quote"),
+            quote.text,
+            quote.author
+        ));
+    }
+
+    let payload = serde_json::json!({
+        "msgtype": "markdown",
+        "markdown": { "content": content },
+    });
+    let response: serde_json::Value = post_webhook_json(&config.webhook_url, &payload, "WeCom")
+        .await
+        .and_then(|body| serde_json::from_str(&body).map_err(|e| e.to_string()))?;
+    check_robot_errcode(&response, "WeCom")
+}
+
+/// Post a markdown message to a DingTalk group robot, signing the request when a secret is set
+async fn send_dingtalk_message(
+    config: &DingTalkConfig,
+    message: &NotificationMessage,
+) -> Result<(), AppError> {
+    log::info!("Posting notification to DingTalk robot");
+    let mut text = format!("### {}\n\n{}", message.title, message.body);
+    if let Some(quote) = &message.quote {
+        text.push_str(&format!(
+            "\n\n> {}{}\n>\n> —— {}",
+            t!("common.daily_quote"),
+            quote.text,
+            quote.author
+        ));
+    }
+
+    let url = match config.secret.as_deref().filter(|s| !s.is_empty()) {
+        Some(secret) => {
+            let timestamp = clock::shared().now().timestamp_millis();
+            let separator = if config.webhook_url.contains('?') {
+                '&'
+            } else {
+                '?'
+            };
+            format!(
+                "{}{}timestamp={}&sign={}",
+                config.webhook_url,
+                separator,
+                timestamp,
+                dingtalk_signature(timestamp, secret)?
+            )
+        }
+        None => config.webhook_url.clone(),
+    };
+
+    let payload = serde_json::json!({
+        "msgtype": "markdown",
+        "markdown": { "title": message.title, "text": text },
+    });
+    let response: serde_json::Value = post_webhook_json(&url, &payload, "DingTalk")
+        .await
+        .and_then(|body| serde_json::from_str(&body).map_err(|e| e.to_string()))?;
+    check_robot_errcode(&response, "DingTalk")
+}
+
+/// Compute the URL-encoded HMAC-SHA256 signature DingTalk expects for a timestamp
+fn dingtalk_signature(timestamp: i64, secret: &str) -> Result<String, AppError> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|e| format!("Invalid DingTalk secret: {}", e))?;
+    mac.update(format!("{}\n{}", timestamp, secret).as_bytes());
+    let signature = BASE64_STANDARD.encode(mac.finalize().into_bytes());
+    Ok(urlencoding::encode(&signature).into_owned())
+}
+
+/// Publish a message to an ntfy topic
+async fn send_ntfy_message(
+    config: &NtfyConfig,
+    message: &NotificationMessage,
+) -> Result<(), AppError> {
+    if config.topic_url.is_empty() {
+        return Err(t!("error.ntfy_not_configured").into());
+    }
+    log::info!("Publishing notification to ntfy topic");
+
+    let mut body = message.body.clone();
+    if let Some(quote) = &message.quote {
+        body.push_str(&format!("\n\n「{}」—— {}", quote.text, quote.author));
+    }
+
+    // Header values must be ASCII, so the title is sent RFC 2047 encoded
+    let title = format!("=?UTF-8?B?{}?=", BASE64_STANDARD.encode(message.title.as_bytes()));
+    let mut request = http_client()?
+        .post(&config.topic_url)
+        .header("Title", title)
+        .body(body);
+
+    if let Some(token) = config.access_token.as_deref().filter(|t| !t.is_empty()) {
+        request = request.bearer_auth(token);
+    } else if let Some(username) = config.username.as_deref().filter(|u| !u.is_empty()) {
+        request = request.basic_auth(username, config.password.as_deref());
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send ntfy request: {}", e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(format!("ntfy returned {}: {}", status, error_text).into());
+    }
+    Ok(())
+}
+
+/// Push a message to a Gotify server
+async fn send_gotify_message(
+    config: &GotifyConfig,
+    message: &NotificationMessage,
+) -> Result<(), AppError> {
+    if config.server_url.is_empty() || config.app_token.is_empty() {
+        return Err(t!("error.gotify_not_configured").into());
+    }
+    log::info!("Pushing notification to Gotify server");
+
+    let mut body = message.body.clone();
+    if let Some(quote) = &message.quote {
+        body.push_str(&format!("\n\n「{}」—— {}", quote.text, quote.author));
+    }
+    let priority = match message.event {
+        NotificationEvent::Signin => 4,
+        NotificationEvent::MissedSignin => 8,
+        NotificationEvent::Reminder => 5,
+        NotificationEvent::Report | NotificationEvent::Digest => 4,
+        NotificationEvent::Supervision => 5,
+        NotificationEvent::Sos => 10,
+    };
+
+    let url = format!("{}/message", config.server_url.trim_end_matches('/'));
+    let response = http_client()?
+        .post(&url)
+        .header("X-Gotify-Key", &config.app_token)
+        .json(&serde_json::json!({
+            "title": message.title,
+            "message": body,
+            "priority": priority,
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send Gotify request: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(format!("Gotify returned {}: {}", status, error_text).into());
+    }
+    Ok(())
+}
+
+/// Post a message through a Telegram bot
+async fn send_telegram_message(
+    config: &TelegramConfig,
+    message: &NotificationMessage,
+) -> Result<(), AppError> {
+    if config.bot_token.is_empty() || config.chat_id.is_empty() {
+        return Err(t!("error.telegram_not_configured").into());
+    }
+    log::info!("Posting notification to Telegram chat {}", config.chat_id);
+
+    let mut text = format!("{}\n{}", message.title, message.body);
+    if let Some(quote) = &message.quote {
+        text.push_str(&format!("\n\n「{}」—— {}", quote.text, quote.author));
+    }
+    let url = format!("https://api.telegram.org/bot{}/sendMessage", config.bot_token);
+    let response = post_webhook_json(
+        &url,
+        &serde_json::json!({ "chat_id": config.chat_id, "text": text }),
+        "Telegram",
+    )
+    .await?;
+
+    let parsed: serde_json::Value = serde_json::from_str(&response)
+        .map_err(|e| format!("Failed to parse Telegram response: {}", e))?;
+    if parsed.get("ok").and_then(|ok| ok.as_bool()) != Some(true) {
+        return Err(format!("Telegram rejected the message: {}", response).into());
+    }
+    Ok(())
+}
+
+/// Send a text message through Twilio
+pub async fn send_twilio_sms(config: &TwilioConfig, to: &str, body: &str) -> Result<(), AppError> {
+    if config.account_sid.is_empty() || config.auth_token.is_empty() {
+        return Err(t!("error.twilio_not_configured").into());
+    }
+    log::info!("Sending SMS via Twilio to {}", to);
+
+    let url = format!(
+        "https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json",
+        config.account_sid
+    );
+    let response = http_client()?
+        .post(&url)
+        .basic_auth(&config.account_sid, Some(&config.auth_token))
+        .form(&[
+            ("To", to),
+            ("From", config.from_number.as_str()),
+            ("Body", body),
+        ])
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send Twilio request: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(format!("Twilio returned {}: {}", status, error_text).into());
+    }
+    Ok(())
+}
+
+/// Check the `errcode` field returned by chat robot webhooks
+fn check_robot_errcode(response: &serde_json::Value, channel: &str) -> Result<(), AppError> {
+    match response.get("errcode").and_then(|c| c.as_i64()) {
+        Some(0) | None => Ok(()),
+        Some(code) => Err(format!(
+            "{} rejected the message ({}): {}",
+            channel,
+            code,
+            response
+                .get("errmsg")
+                .and_then(|m| m.as_str())
+                .unwrap_or("unknown error")
+        )
+        .into()),
+    }
+}
+
+/// POST a JSON payload to a webhook URL, check the status and return the response body
+async fn post_webhook_json(
+    url: &str,
+    payload: &serde_json::Value,
+    channel: &str,
+) -> Result<String, AppError> {
+    if url.is_empty() {
+        return Err(format!("{} webhook URL is not configured", channel).into());
+    }
+
+    let response = http_client()?
+        .post(url)
+        .json(payload)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send {} request: {}", channel, e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(format!("{} returned {}: {}", channel, status, error_text).into());
+    }
+
+    log::debug!("{} webhook accepted the message", channel);
+    response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read {} response: {}", channel, e).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, FixedClock};
+
+    fn profile(birthday: Option<&str>, streak_started: Option<&str>) -> ProfileSettings {
+        ProfileSettings {
+            birthday: birthday.map(str::to_string),
+            account_created_on: None,
+            streak_started_on: streak_started.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn birthday_starts_at_midnight() {
+        let profile = profile(Some("03-10"), None);
+        let clock = FixedClock::at("2024-03-09T23:59:59Z");
+        assert_eq!(detect_milestone(&profile, clock.today()), None);
+        clock.set("2024-03-10T00:00:00Z");
+        assert_eq!(detect_milestone(&profile, clock.today()), Some(Milestone::Birthday));
+    }
+
+    #[test]
+    fn leap_day_birthday_falls_on_february_28_in_common_years() {
+        let profile = profile(Some("02-29"), None);
+        let clock = FixedClock::at("2023-02-28T12:00:00Z");
+        assert_eq!(detect_milestone(&profile, clock.today()), Some(Milestone::Birthday));
+        clock.set("2024-02-28T12:00:00Z");
+        assert_eq!(detect_milestone(&profile, clock.today()), None);
+        clock.set("2024-02-29T12:00:00Z");
+        assert_eq!(detect_milestone(&profile, clock.today()), Some(Milestone::Birthday));
+    }
+
+    #[test]
+    fn streak_anniversary_counts_whole_years() {
+        let profile = profile(None, Some("2022-03-10"));
+        let clock = FixedClock::at("2024-03-10T08:00:00Z");
+        assert_eq!(
+            detect_milestone(&profile, clock.today()),
+            Some(Milestone::StreakAnniversary { years: 2 })
+        );
+    }
+
+    #[test]
+    fn no_anniversary_on_the_start_date_after_a_backwards_jump() {
+        let profile = profile(None, Some("2024-03-10"));
+        let clock = FixedClock::at("2024-03-10T08:00:00Z");
+        assert_eq!(detect_milestone(&profile, clock.today()), None);
+        clock.set("2023-03-10T08:00:00Z");
+        assert_eq!(detect_milestone(&profile, clock.today()), None);
+    }
+
+    #[test]
+    fn account_anniversary_of_a_leap_day() {
+        let profile = ProfileSettings {
+            account_created_on: Some("2020-02-29".to_string()),
+            ..ProfileSettings::default()
+        };
+        let clock = FixedClock::at("2023-02-28T12:00:00Z");
+        assert_eq!(
+            detect_milestone(&profile, clock.today()),
+            Some(Milestone::AccountAnniversary { years: 3 })
+        );
+    }
+}
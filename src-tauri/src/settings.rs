@@ -0,0 +1,385 @@
+//! All user settings behind one API.
+//!
+//! Settings live in several files, each with its own pair of commands. The
+//! [`Settings`] aggregate collects them so the frontend can read everything at
+//! once and change any part with [`update`], which takes a JSON merge patch
+//! (RFC 7386): only the fields present in the patch change, and `null` clears
+//! an optional field.
+//!
+//! Every section is validated before anything is saved, so an invalid patch
+//! changes nothing. Sections that changed are saved one after the other and
+//! announced with `config-updated` like their own commands do; those commands
+//! now go through the `save_*` functions here as well. When saving or applying
+//! a section fails, it and the sections saved before it are restored, so a
+//! patch is applied either completely or not at all.
+
+use chrono::NaiveTime;
+use rust_i18n::t;
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::api_client::{validate_proxy_settings, validate_server_settings};
+use crate::changes;
+use crate::error::AppError;
+use crate::models::{
+    AppSettings, ConfigKind, DeviceSettings, EmailConfig, EscalationPolicy, NotificationChannels,
+    NotificationPreferences, ReminderConfig, Settings,
+};
+use crate::state::AppState;
+use crate::{i18n, logs, scheduler, storage};
+
+/// Most attempts per API call the retry policy may ask for
+const MAX_API_ATTEMPTS: u32 = 10;
+
+/// Every setting, for the settings screen
+pub fn load(state: &AppState) -> Result<Settings, AppError> {
+    let device = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    Ok(Settings {
+        app: storage::load_settings().map_err(|e| {
+            log::error!("Failed to load app settings: {}", e);
+            e.to_string()
+        })?,
+        device: DeviceSettings {
+            device_name: device.device.device_name,
+            mode: device.device.mode,
+        },
+        email: state.email_config().map_err(|e| {
+            log::error!("Failed to load email config: {}", e);
+            e.to_string()
+        })?,
+        reminder: storage::load_reminder_config().map_err(|e| {
+            log::error!("Failed to load reminder config: {}", e);
+            e.to_string()
+        })?,
+        escalation: storage::load_escalation_policy().map_err(|e| {
+            log::error!("Failed to load escalation policy: {}", e);
+            e.to_string()
+        })?,
+        notification_channels: storage::load_notification_channels().map_err(|e| {
+            log::error!("Failed to load notification channels: {}", e);
+            e.to_string()
+        })?,
+        notification_preferences: storage::load_notification_preferences().map_err(|e| {
+            log::error!("Failed to load notification preferences: {}", e);
+            e.to_string()
+        })?,
+    })
+}
+
+/// Sections of [`Settings`], in the order [`update`] saves them
+const SECTIONS: [&str; 7] = [
+    "app",
+    "device",
+    "email",
+    "reminder",
+    "escalation",
+    "notification_channels",
+    "notification_preferences",
+];
+
+/// Apply a JSON merge patch to the settings and save the sections it changed
+pub fn update(app: &AppHandle, state: &AppState, patch: &Value) -> Result<Settings, AppError> {
+    if !patch.is_object() {
+        return Err(t!("error.settings_not_object").into());
+    }
+    let previous = load(state)?;
+    let current = serde_json::to_value(&previous).map_err(|e| e.to_string())?;
+    let mut patched = current.clone();
+    merge_patch(&mut patched, patch);
+    let settings: Settings = serde_json::from_value(patched.clone()).map_err(|e| {
+        log::warn!("Invalid settings patch: {}", e);
+        t!("error.invalid_settings", error = e)
+    })?;
+    let changed = |section: &str| current.get(section) != patched.get(section);
+
+    if changed("app") {
+        validate_app(&settings.app)?;
+    }
+    if changed("reminder") {
+        validate_reminder(&settings.reminder)?;
+    }
+    if changed("device") {
+        validate_device(&settings.device)?;
+    }
+
+    let mut saved = Vec::new();
+    for section in SECTIONS.into_iter().filter(|section| changed(section)) {
+        // A section may already be written when applying it fails, so it is restored as well
+        saved.push(section);
+        if let Err(e) = save_section(app, state, &settings, section) {
+            log::error!("Failed to save {} settings, restoring the previous ones", section);
+            for section in saved.into_iter().rev() {
+                if let Err(e) = save_section(app, state, &previous, section) {
+                    log::error!("Failed to restore {} settings: {}", section, e);
+                }
+            }
+            return Err(e);
+        }
+    }
+    log::info!("Settings updated");
+    load(state)
+}
+
+/// Save one section of `settings`
+fn save_section(
+    app: &AppHandle,
+    state: &AppState,
+    settings: &Settings,
+    section: &str,
+) -> Result<(), AppError> {
+    match section {
+        "app" => save_app(app, &settings.app),
+        "device" => save_device(state, &settings.device),
+        "email" => save_email(state, settings.email.clone()),
+        "reminder" => save_reminder(app, &settings.reminder),
+        "escalation" => save_escalation(app, &settings.escalation),
+        "notification_channels" => save_notification_channels(app, &settings.notification_channels),
+        "notification_preferences" => {
+            save_notification_preferences(app, &settings.notification_preferences)
+        }
+        _ => unreachable!("unknown settings section {}", section),
+    }
+}
+
+/// Validate and save the app settings, then apply them
+pub fn save_app(app: &AppHandle, settings: &AppSettings) -> Result<(), AppError> {
+    validate_app(settings)?;
+    storage::save_settings(settings).map_err(|e| {
+        log::error!("Failed to save app settings: {}", e);
+        e.to_string()
+    })?;
+    changes::config_updated(app, ConfigKind::Settings);
+    logs::apply_level(settings.log_level);
+    i18n::apply(settings.language.as_deref());
+
+    #[cfg(desktop)]
+    {
+        crate::tray::relabel(app);
+        crate::shortcuts::apply_signin_shortcut(app, settings.signin_shortcut.as_deref())?;
+        crate::autostart::apply_autostart(app, settings.enable_autostart)?;
+    }
+
+    Ok(())
+}
+
+/// Rename the device or change its mode
+pub fn save_device(state: &AppState, settings: &DeviceSettings) -> Result<(), AppError> {
+    validate_device(settings)?;
+    state.update_device_config(|config| {
+        config.device.device_name = settings.device_name.clone();
+        config.device.mode = settings.mode.clone();
+        Ok(())
+    })
+}
+
+/// Save the email configuration, keeping the stored OAuth2 credentials
+/// unless new ones are given
+pub fn save_email(state: &AppState, mut config: EmailConfig) -> Result<(), AppError> {
+    config.migrate_legacy_recipient();
+    if config.oauth2.is_none() {
+        // Keep the stored refresh token; the frontend never round-trips it
+        config.oauth2 = state
+            .email_config()
+            .map_err(|e| {
+                log::error!("Failed to load email config: {}", e);
+                e.to_string()
+            })?
+            .oauth2;
+    }
+    state.save_email_config(&config).map_err(|e| {
+        log::error!("Failed to save email config: {}", e);
+        e.to_string().into()
+    })
+}
+
+pub fn save_reminder(app: &AppHandle, config: &ReminderConfig) -> Result<(), AppError> {
+    validate_reminder(config)?;
+    storage::save_reminder_config(config).map_err(|e| {
+        log::error!("Failed to save reminder config: {}", e);
+        e.to_string()
+    })?;
+    changes::config_updated(app, ConfigKind::Reminder);
+    Ok(())
+}
+
+pub fn save_escalation(app: &AppHandle, policy: &EscalationPolicy) -> Result<(), AppError> {
+    storage::save_escalation_policy(policy).map_err(|e| {
+        log::error!("Failed to save escalation policy: {}", e);
+        e.to_string()
+    })?;
+    changes::config_updated(app, ConfigKind::Escalation);
+    Ok(())
+}
+
+pub fn save_notification_channels(
+    app: &AppHandle,
+    channels: &NotificationChannels,
+) -> Result<(), AppError> {
+    storage::save_notification_channels(channels).map_err(|e| {
+        log::error!("Failed to save notification channels: {}", e);
+        e.to_string()
+    })?;
+    changes::config_updated(app, ConfigKind::NotificationChannels);
+    Ok(())
+}
+
+pub fn save_notification_preferences(
+    app: &AppHandle,
+    preferences: &NotificationPreferences,
+) -> Result<(), AppError> {
+    storage::save_notification_preferences(preferences).map_err(|e| {
+        log::error!("Failed to save notification preferences: {}", e);
+        e.to_string()
+    })?;
+    changes::config_updated(app, ConfigKind::NotificationPreferences);
+    Ok(())
+}
+
+fn validate_app(settings: &AppSettings) -> Result<(), AppError> {
+    if let Some(cutoff) = &settings.checkin_cutoff {
+        NaiveTime::parse_from_str(cutoff, "%H:%M").map_err(|e| {
+            log::warn!("Invalid check-in cutoff '{}': {}", cutoff, e);
+            t!("error.invalid_cutoff", error = e)
+        })?;
+    }
+    if let Some(quiet) = &settings.quiet_hours {
+        for time in [&quiet.start, &quiet.end] {
+            NaiveTime::parse_from_str(time, "%H:%M").map_err(|e| {
+                log::warn!("Invalid quiet hours time '{}': {}", time, e);
+                t!("error.invalid_quiet_hours", error = e)
+            })?;
+        }
+    }
+    if let Some(offset) = settings.timezone_offset_minutes {
+        if !(-14 * 60..=14 * 60).contains(&offset) {
+            return Err(t!("error.invalid_timezone_offset").into());
+        }
+    }
+    if settings.sync_interval_minutes == Some(0) {
+        return Err(t!("error.sync_interval_too_short").into());
+    }
+    let retry = &settings.api_retry;
+    if !(1..=MAX_API_ATTEMPTS).contains(&retry.max_attempts) {
+        return Err(t!("error.api_attempts_range", max = MAX_API_ATTEMPTS).into());
+    }
+    if retry.base_delay_ms == 0 || retry.max_delay_ms < retry.base_delay_ms {
+        return Err(t!("error.invalid_retry_delays").into());
+    }
+    validate_server_settings(&settings.server)?;
+    validate_proxy_settings(settings.proxy.as_ref())?;
+    let jobs = scheduler::job_names();
+    if let Some(name) = settings
+        .scheduled_jobs
+        .keys()
+        .find(|name| !jobs.contains(&name.as_str()))
+    {
+        return Err(t!("error.unknown_job", name = name).into());
+    }
+    if let Some(language) = &settings.language {
+        if i18n::resolve(language) != Some(language.as_str()) {
+            return Err(t!("error.unsupported_language", language = language).into());
+        }
+    }
+    Ok(())
+}
+
+fn validate_device(settings: &DeviceSettings) -> Result<(), AppError> {
+    if settings.device_name.trim().is_empty() {
+        return Err(t!("error.device_name_empty").into());
+    }
+    Ok(())
+}
+
+fn validate_reminder(config: &ReminderConfig) -> Result<(), AppError> {
+    for time in std::iter::once(&config.reminder_time).chain(&config.additional_times) {
+        NaiveTime::parse_from_str(time, "%H:%M").map_err(|e| {
+            log::warn!("Invalid reminder time '{}': {}", time, e);
+            t!("error.invalid_reminder_time", error = e)
+        })?;
+    }
+    Ok(())
+}
+
+/// Apply `patch` to `target` as described in RFC 7386
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let Value::Object(target) = target else {
+        return;
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn merged(mut target: Value, patch: Value) -> Value {
+        merge_patch(&mut target, &patch);
+        target
+    }
+
+    #[test]
+    fn patch_changes_only_the_given_fields() {
+        let target =
+            json!({"app": {"language": "en", "log_level": "info"}, "reminder": {"enabled": true}});
+        let patch = json!({"app": {"language": "zh-CN"}});
+        assert_eq!(
+            merged(target, patch),
+            json!({"app": {"language": "zh-CN", "log_level": "info"}, "reminder": {"enabled": true}})
+        );
+    }
+
+    #[test]
+    fn null_removes_a_field() {
+        let target = json!({"app": {"checkin_cutoff": "21:00", "language": "en"}});
+        let patch = json!({"app": {"checkin_cutoff": null}});
+        assert_eq!(merged(target, patch), json!({"app": {"language": "en"}}));
+    }
+
+    #[test]
+    fn null_for_a_missing_field_changes_nothing() {
+        let target = json!({"app": {"language": "en"}});
+        assert_eq!(merged(target.clone(), json!({"app": {"proxy": null}})), target);
+    }
+
+    #[test]
+    fn arrays_and_scalars_are_replaced_whole() {
+        let target = json!({"reminder": {"additional_times": ["09:00", "12:00"], "enabled": true}});
+        let patch = json!({"reminder": {"additional_times": ["18:00"], "enabled": false}});
+        assert_eq!(
+            merged(target, patch),
+            json!({"reminder": {"additional_times": ["18:00"], "enabled": false}})
+        );
+    }
+
+    #[test]
+    fn object_replaces_a_scalar() {
+        let target = json!({"app": {"proxy": "none"}});
+        let patch = json!({"app": {"proxy": {"host": "proxy.local", "port": 8080}}});
+        assert_eq!(
+            merged(target, patch),
+            json!({"app": {"proxy": {"host": "proxy.local", "port": 8080}}})
+        );
+    }
+
+    #[test]
+    fn non_object_patch_replaces_the_target() {
+        assert_eq!(merged(json!({"a": 1}), json!(["b"])), json!(["b"]));
+    }
+}
@@ -0,0 +1,187 @@
+//! What supervised devices share with their supervisors.
+//!
+//! Each relationship on the supervised side carries a [`SharingPolicy`]. After
+//! every sign-in, and whenever a policy changes, the device publishes one
+//! status per supervisor containing only what that policy allows; the server
+//! serves it as the device status that supervisor sees. Once a supervisor's
+//! encryption key is known, the mood and wellness answers are sealed for them.
+//!
+//! The user can also pause sharing for a bounded time. No statuses are
+//! published meanwhile, and the server shows supervisors an explicit paused
+//! state instead of silence, so the pause doesn't raise missed check-in alerts.
+
+use chrono::{DateTime, Duration};
+use rust_i18n::t;
+
+use crate::api_client::{publish_shared_status, set_sharing_pause_api};
+use crate::clock::Clock;
+use crate::crypto;
+use crate::error::AppError;
+use crate::models::{SigninData, SupervisionRelationship};
+use crate::remote_models::{PrivateStatus, SharedStatus, SharingPolicy, WellnessQuestion};
+use crate::state::AppState;
+use crate::storage;
+use crate::wellness::answers_for;
+
+/// Number of recent sign-in dates shared under [`SharingPolicy::History`]
+const SHARED_HISTORY_DAYS: usize = 30;
+
+/// Longest pause of status updates, in days
+const MAX_PAUSE_DAYS: i64 = 30;
+
+/// Change what this device shares with the supervisor of a relationship
+///
+/// While sharing is paused only the policy is saved; it is published with the
+/// next status after the pause.
+pub async fn set_sharing_policy(
+    state: &AppState,
+    clock: &dyn Clock,
+    relationship_id: &str,
+    policy: SharingPolicy,
+) -> Result<(), AppError> {
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let device_id = config.device.device_id;
+    let mut relationship = config
+        .supervision_relationships
+        .into_iter()
+        .find(|r| r.relationship_id == relationship_id && r.supervised_device_id == device_id)
+        .ok_or_else(|| {
+            log::warn!("Supervised relationship {} not found", relationship_id);
+            t!("error.relationship_not_found").into_owned()
+        })?;
+    relationship.sharing_policy = policy;
+
+    let data = state.signin_data().map_err(|e| e.to_string())?;
+    let questions = storage::load_wellness_questions().unwrap_or_default();
+    if config.sharing_paused(clock.now()) {
+        log::info!("Sharing is paused, saving the policy without publishing");
+    } else if let Some(data) = data {
+        let status = shared_status(&relationship, &questions, &data);
+        publish_shared_status(&device_id, &status).await?;
+    }
+
+    state.update_device_config(|config| {
+        let relationship = config
+            .supervision_relationships
+            .iter_mut()
+            .find(|r| r.relationship_id == relationship_id)
+            .ok_or_else(|| t!("error.relationship_not_found").into_owned())?;
+        relationship.sharing_policy = policy;
+        Ok(())
+    })
+}
+
+/// Pause status updates to supervisors until `until`, or resume them with `None`
+pub async fn pause_sharing(
+    state: &AppState,
+    clock: &dyn Clock,
+    until: Option<String>,
+) -> Result<(), AppError> {
+    if let Some(until) = &until {
+        let end = DateTime::parse_from_rfc3339(until)
+            .map_err(|e| t!("error.invalid_pause_end", error = e))?
+            .to_utc();
+        let now = clock.now();
+        if end <= now {
+            return Err(t!("error.pause_end_in_past").into());
+        }
+        if end > now + Duration::days(MAX_PAUSE_DAYS) {
+            return Err(t!("error.pause_too_long", days = MAX_PAUSE_DAYS).into());
+        }
+    }
+
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    set_sharing_pause_api(&config.device.device_id, until.as_deref()).await?;
+
+    state.update_device_config(|config| {
+        config.sharing_paused_until = until;
+        Ok(())
+    })
+}
+
+/// Publish this device's status to every supervisor in the background
+pub fn share_status(state: &AppState, clock: &dyn Clock, data: &SigninData) {
+    let config = match state.device_config() {
+        Ok(config) => config,
+        Err(e) => {
+            log::warn!("Failed to load device config: {}", e);
+            return;
+        }
+    };
+    if config.sharing_paused(clock.now()) {
+        log::info!("Sharing is paused, not publishing status");
+        return;
+    }
+    let device_id = config.device.device_id.clone();
+    let questions = storage::load_wellness_questions().unwrap_or_default();
+    let statuses: Vec<SharedStatus> = config
+        .supervision_relationships
+        .iter()
+        .filter(|r| r.supervised_device_id == device_id)
+        .map(|r| shared_status(r, &questions, data))
+        .collect();
+    if statuses.is_empty() {
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        for status in statuses {
+            if let Err(e) = publish_shared_status(&device_id, &status).await {
+                log::warn!("Failed to share status with {}: {}", status.supervisor_id, e);
+            }
+        }
+    });
+}
+
+/// Build the status the supervisor of a relationship may see under its policy
+fn shared_status(
+    relationship: &SupervisionRelationship,
+    questions: &[WellnessQuestion],
+    data: &SigninData,
+) -> SharedStatus {
+    let supervisor_id = &relationship.supervisor_device_id;
+    let policy = relationship.sharing_policy;
+    let last_entry = data.signin_entries.last();
+    let last_signin = last_entry
+        .map(|entry| entry.signed_at.clone())
+        .unwrap_or_else(|| data.last_signin_date.clone());
+
+    let mut status = SharedStatus {
+        supervisor_id: supervisor_id.to_string(),
+        policy,
+        streak: data.streak,
+        last_signin: (policy >= SharingPolicy::LastSignin).then_some(last_signin),
+        mood: last_entry
+            .and_then(|entry| entry.mood)
+            .filter(|_| policy >= SharingPolicy::Mood),
+        recent_signins: (policy >= SharingPolicy::History).then(|| {
+            let skip = data
+                .signin_history
+                .len()
+                .saturating_sub(SHARED_HISTORY_DAYS);
+            data.signin_history[skip..].to_vec()
+        }),
+        sealed: None,
+    };
+
+    if let Some(key) = &relationship.partner_public_key {
+        // The plaintext mood is dropped even if sealing fails, so it never leaks
+        let private = PrivateStatus {
+            mood: status.mood.take(),
+            wellness_answers: last_entry
+                .map(|entry| answers_for(supervisor_id, &entry.answers, questions))
+                .unwrap_or_default(),
+        };
+        match crypto::seal(key, &private) {
+            Ok(sealed) => status.sealed = Some(sealed),
+            Err(e) => log::warn!("Failed to seal status for {}: {}", supervisor_id, e),
+        }
+    }
+    status
+}
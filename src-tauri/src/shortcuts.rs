@@ -0,0 +1,91 @@
+//! Global keyboard shortcut for instant sign-in (desktop only).
+//!
+//! Pressing the configured shortcut signs in without opening the window and
+//! shows a system notification with the new streak.
+
+use rust_i18n::t;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::clock::SharedClock;
+use crate::commands::perform_signin;
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// Build the global shortcut plugin with the sign-in handler installed
+pub fn plugin() -> tauri::plugin::TauriPlugin<tauri::Wry> {
+    tauri_plugin_global_shortcut::Builder::new()
+        .with_handler(|app, shortcut, event| {
+            if event.state() == ShortcutState::Pressed {
+                log::info!("Sign-in shortcut {} pressed", shortcut);
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move { signin_in_background(&app).await });
+            }
+        })
+        .build()
+}
+
+/// Parse a shortcut string such as "CommandOrControl+Shift+K"
+pub fn parse_shortcut(shortcut: &str) -> Result<Shortcut, AppError> {
+    shortcut.parse::<Shortcut>().map_err(|e| {
+        log::warn!("Invalid shortcut '{}': {}", shortcut, e);
+        format!("Invalid shortcut: {}", e).into()
+    })
+}
+
+/// Register the configured sign-in shortcut, replacing any previous one
+pub fn apply_signin_shortcut(app: &AppHandle, shortcut: Option<&str>) -> Result<(), AppError> {
+    let manager = app.global_shortcut();
+    manager.unregister_all().map_err(|e| {
+        log::error!("Failed to unregister global shortcuts: {}", e);
+        e.to_string()
+    })?;
+
+    let Some(shortcut) = shortcut.filter(|s| !s.trim().is_empty()) else {
+        log::debug!("No sign-in shortcut configured");
+        return Ok(());
+    };
+
+    manager.register(parse_shortcut(shortcut)?).map_err(|e| {
+        log::error!("Failed to register sign-in shortcut {}: {}", shortcut, e);
+        e.to_string()
+    })?;
+    log::info!("Registered sign-in shortcut {}", shortcut);
+    Ok(())
+}
+
+/// Sign in the stored user and report the outcome as a system notification
+pub(crate) async fn signin_in_background(app: &AppHandle) {
+    let name = match app.state::<AppState>().signin_data() {
+        Ok(Some(data)) => data.name,
+        Ok(None) => {
+            show_notification(app, "Are You OK?", &t!("shortcut.first_signin_needed"));
+            return;
+        }
+        Err(e) => {
+            log::error!("Failed to load sign-in data for shortcut: {}", e);
+            return;
+        }
+    };
+
+    let clock = app.state::<SharedClock>().inner().clone();
+    match perform_signin(app, clock.as_ref(), name, None, None).await {
+        Ok(data) => show_notification(
+            app,
+            &t!("shortcut.signed_in_title"),
+            &t!("shortcut.signed_in_body", streak = data.streak),
+        ),
+        Err(e) => {
+            log::error!("Shortcut sign-in failed: {}", e);
+            show_notification(app, &t!("shortcut.failed_title"), &e.to_string());
+        }
+    }
+}
+
+/// Show a system notification, logging failures
+fn show_notification(app: &AppHandle, title: &str, body: &str) {
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        log::error!("Failed to show notification: {}", e);
+    }
+}
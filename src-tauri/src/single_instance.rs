@@ -0,0 +1,27 @@
+//! Single running instance (desktop only).
+//!
+//! Two processes would race on the same JSON files, so starting the app again
+//! doesn't start a second one: the running instance brings its window to the
+//! front instead and opens any `areuok://` link the new launch was given.
+
+use tauri::AppHandle;
+
+use crate::deep_link::{self, LINK_SCHEME};
+use crate::tray::show_window;
+
+/// Build the single-instance plugin; it must be the first plugin registered
+pub fn plugin() -> tauri::plugin::TauriPlugin<tauri::Wry> {
+    tauri_plugin_single_instance::init(|app, args, _cwd| {
+        log::info!("App launched again, focusing the running instance");
+        show_window(app);
+        open_links(app, &args);
+    })
+}
+
+/// Open the links among the arguments of a launch
+fn open_links(app: &AppHandle, args: &[String]) {
+    let prefix = format!("{}://", LINK_SCHEME);
+    for link in args.iter().filter(|arg| arg.starts_with(&prefix)) {
+        deep_link::open(app, link);
+    }
+}
@@ -0,0 +1,178 @@
+//! "I'm not OK" SOS.
+//!
+//! The SOS is the inverse of a check-in: the user asks for help right away.
+//! It is raised on every route at once — the server, which pushes an `sos`
+//! event to all supervisors, every configured notification channel and the
+//! SMS emergency contact — and skips quiet hours. The routes are tried side by
+//! side, so a slow mail server never holds up the SMS, and a failing route
+//! never stops the others. An SOS the server couldn't be reached for is kept
+//! in the [`sync_queue`](crate::sync_queue) and sent once it is back.
+
+use chrono::{DateTime, Utc};
+use rust_i18n::t;
+use tauri::{AppHandle, Manager};
+
+use crate::api_client::send_sos_api;
+use crate::clock::SharedClock;
+use crate::deadline::configured_offset;
+use crate::dispatch;
+use crate::error::AppError;
+use crate::escalation::send_emergency_sms;
+use crate::models::{
+    AppSettings, ChannelSelection, OutgoingNotification, PendingOperation, SupervisionHistoryKind,
+    WebhookEvent, WebhookPayload,
+};
+use crate::remote_models::SosAlert;
+use crate::services::{sos_email, sos_message};
+use crate::state::AppState;
+use crate::storage;
+use crate::supervision::{self, record_history};
+use crate::sync_queue;
+use crate::webhooks;
+
+/// Ask every supervisor for help on every available route
+///
+/// Fails only when no route delivered the SOS.
+pub async fn send_sos(app: &AppHandle, message: Option<String>) -> Result<(), AppError> {
+    let state = app.state::<AppState>();
+    let clock = app.state::<SharedClock>();
+    let now = clock.now();
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let settings = storage::load_settings().unwrap_or_else(|e| {
+        log::warn!("Failed to load app settings: {}", e);
+        AppSettings::default()
+    });
+    let name = state
+        .signin_data()
+        .ok()
+        .flatten()
+        .map(|data| data.name)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| config.device.device_name.clone());
+    let note = message
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty());
+    let sent_at = local_time(&settings, now);
+
+    let server = async {
+        let sent_at = now.to_rfc3339();
+        match send_sos_api(&config.device.device_id, note.as_deref(), &sent_at).await {
+            Err(e) if e.is_unreachable() => {
+                let operation = PendingOperation::Sos {
+                    device_id: config.device.device_id.clone(),
+                    message: note.clone(),
+                    sent_at,
+                };
+                sync_queue::enqueue(clock.as_ref(), operation, &e)?;
+                Err(t!("error.sos_queued", error = e).into())
+            }
+            result => result,
+        }
+    };
+
+    let vars = vec![
+        ("name", name.clone()),
+        ("device_id", config.device.device_id.clone()),
+        ("message", note.clone().unwrap_or_default()),
+        ("sent_at", sent_at.clone()),
+    ];
+    let notification = OutgoingNotification {
+        message: Some(sos_message(&name, &sent_at, note.as_deref())),
+        email: Some(sos_email(&name, &sent_at, note.as_deref())),
+        webhook: Some(WebhookPayload {
+            event: WebhookEvent::Sos,
+            vars: webhooks::template_vars(clock.as_ref(), WebhookEvent::Sos, vars),
+        }),
+    };
+    let channels = async {
+        match dispatch::send_now(app, &notification, ChannelSelection::remote()).await? {
+            0 => Err(AppError::from(t!("error.no_channel_configured"))),
+            _ => Ok(()),
+        }
+    };
+
+    let body = match &note {
+        Some(note) => t!("sos.sms_with_note", name = name, time = sent_at, note = note),
+        None => t!("sos.sms", name = name, time = sent_at),
+    };
+    let sms = async {
+        match storage::load_escalation_policy() {
+            Ok(policy) => send_emergency_sms(&policy, &body).await,
+            Err(e) => Err(e.into()),
+        }
+    };
+
+    let (server, channels, sms) = futures::join!(server, channels, sms);
+    let results = [("server", server), ("channels", channels), ("sms", sms)];
+    let delivered = results.iter().any(|(_, result)| result.is_ok());
+    let errors: Vec<String> = results
+        .into_iter()
+        .filter_map(|(route, result)| {
+            let e = result.err()?;
+            log::error!("Failed to send SOS via {}: {}", route, e);
+            Some(format!("{}: {}", route, e))
+        })
+        .collect();
+
+    record_history(
+        SupervisionHistoryKind::SosSent,
+        &config.device.device_id,
+        Some(name),
+        None,
+        note,
+    );
+
+    if delivered {
+        Ok(())
+    } else {
+        Err(errors.join("; ").into())
+    }
+}
+
+/// Alert this supervisor about an SOS pushed by the server
+pub async fn handle_sos_event(app: &AppHandle, data: &serde_json::Value) {
+    let alert: SosAlert = match serde_json::from_value(data.clone()) {
+        Ok(alert) => alert,
+        Err(e) => {
+            log::warn!("Ignoring malformed SOS event: {}", e);
+            return;
+        }
+    };
+    let name = supervision::partner_nickname(&app.state::<AppState>(), &alert.device_id)
+        .or_else(|| alert.device_name.clone())
+        .unwrap_or_else(|| alert.device_id.clone());
+    log::warn!("SOS received from {}", name);
+
+    let settings = storage::load_settings().unwrap_or_default();
+    let sent_at = DateTime::parse_from_rfc3339(&alert.sent_at)
+        .map(|t| local_time(&settings, t.to_utc()))
+        .unwrap_or_else(|_| alert.sent_at.clone());
+
+    record_history(
+        SupervisionHistoryKind::SosReceived,
+        &alert.device_id,
+        alert.device_name.clone(),
+        None,
+        alert.message.clone(),
+    );
+
+    let notification = OutgoingNotification {
+        message: Some(sos_message(&name, &sent_at, alert.message.as_deref())),
+        ..Default::default()
+    };
+    let channels = ChannelSelection {
+        system_notification: true,
+        ..ChannelSelection::remote()
+    };
+    dispatch::deliver(app, notification, channels, true).await;
+}
+
+/// Format an instant in the app time zone for messages
+fn local_time(settings: &AppSettings, time: DateTime<Utc>) -> String {
+    time.with_timezone(&configured_offset(settings, time))
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
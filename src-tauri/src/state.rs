@@ -0,0 +1,242 @@
+//! Configuration kept in memory between commands.
+//!
+//! Nearly every command needs the device configuration, and many the email
+//! configuration or the sign-in data. Instead of reading and parsing their
+//! files each time, [`AppState`] keeps them in Tauri managed state, loaded on
+//! first use. Changes are written through: the file is saved first and the
+//! copy in memory only replaced once that succeeded, so the two never differ.
+//!
+//! All writes must go through [`AppState`]; reading the files directly stays
+//! correct for code without access to the managed state. The device
+//! configuration is only changed in place with
+//! [`update_device_config`](AppState::update_device_config), so a command
+//! that waited on the network can't save a copy older than another's change. Every save is also
+//! announced to the frontend through [`changes`]. Files changed by another
+//! process are picked up with the `reload_*` methods, which the file watcher
+//! calls on desktop.
+
+use std::io;
+use std::sync::{OnceLock, RwLock, TryLockError};
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::changes;
+use crate::error::AppError;
+use crate::models::{ConfigKind, DeviceConfig, EmailConfig, SigninData};
+use crate::storage;
+
+/// Cached configuration files, registered as Tauri managed state
+#[derive(Default)]
+pub struct AppState {
+    device_config: Cached<DeviceConfig>,
+    email_config: Cached<EmailConfig>,
+    signin_data: Cached<Option<SigninData>>,
+    /// Handle to announce changes through, set once the app is running
+    app: OnceLock<AppHandle>,
+}
+
+impl AppState {
+    /// Announce saved changes through `app` from now on
+    pub fn attach(&self, app: AppHandle) {
+        let _ = self.app.set(app);
+    }
+
+    /// Device configuration, created on first use
+    pub fn device_config(&self) -> io::Result<DeviceConfig> {
+        self.device_config
+            .get(storage::load_or_create_device_config)
+    }
+
+    /// Change the device configuration in place and save it
+    ///
+    /// Other writers wait until the change is saved, so none of them can
+    /// overwrite it with a copy read before it was made.
+    pub fn update_device_config<R>(
+        &self,
+        update: impl FnOnce(&mut DeviceConfig) -> Result<R, AppError>,
+    ) -> Result<R, AppError> {
+        let result = self.device_config.update(
+            storage::load_or_create_device_config,
+            storage::save_device_config,
+            update,
+        )?;
+        if let (Some(app), Ok(config)) = (self.app.get(), self.device_config()) {
+            changes::supervision_updated(app, &config);
+        }
+        Ok(result)
+    }
+
+    /// Email configuration, the default if none was saved
+    pub fn email_config(&self) -> io::Result<EmailConfig> {
+        self.email_config.get(storage::load_email_config)
+    }
+
+    /// Save the email configuration
+    pub fn save_email_config(&self, config: &EmailConfig) -> io::Result<()> {
+        self.email_config
+            .set(config.clone(), storage::save_email_config)?;
+        if let Some(app) = self.app.get() {
+            changes::config_updated(app, ConfigKind::Email);
+        }
+        Ok(())
+    }
+
+    /// Sign-in data, `None` before the first sign-in
+    pub fn signin_data(&self) -> io::Result<Option<SigninData>> {
+        self.signin_data.get(storage::load_data)
+    }
+
+    /// Save the sign-in data
+    pub fn save_signin_data(&self, data: &SigninData) -> io::Result<()> {
+        self.signin_data
+            .set(Some(data.clone()), |data| match data {
+                Some(data) => storage::save_data(data),
+                None => Ok(()),
+            })?;
+        if let Some(app) = self.app.get() {
+            changes::signin_updated(app, Some(data));
+        }
+        Ok(())
+    }
+
+    /// Delete the sign-in data
+    pub fn delete_signin_data(&self) -> io::Result<()> {
+        self.signin_data.set(None, |_| storage::delete_data())?;
+        if let Some(app) = self.app.get() {
+            changes::signin_updated(app, None);
+        }
+        Ok(())
+    }
+
+    /// Cached copies loaded so far, without reading files or waiting on a writer
+    ///
+    /// For the panic hook, which may run while its thread holds one of the locks.
+    pub fn peek(&self) -> (Option<DeviceConfig>, Option<EmailConfig>, Option<Option<SigninData>>) {
+        (self.device_config.peek(), self.email_config.peek(), self.signin_data.peek())
+    }
+
+    /// Re-read the device configuration after its file changed on disk
+    ///
+    /// A deleted file is ignored rather than replaced with a new device.
+    pub fn reload_device_config(&self) -> io::Result<()> {
+        if let Some(config) = self.device_config.reload(storage::load_device_config)? {
+            log::info!("Device configuration changed on disk");
+            if let Some(app) = self.app.get() {
+                changes::supervision_updated(app, &config);
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-read the email configuration after its file changed on disk
+    pub fn reload_email_config(&self) -> io::Result<()> {
+        if self
+            .email_config
+            .reload(|| storage::load_email_config().map(Some))?
+            .is_some()
+        {
+            log::info!("Email configuration changed on disk");
+            if let Some(app) = self.app.get() {
+                changes::config_updated(app, ConfigKind::Email);
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-read the sign-in data after its file changed on disk
+    pub fn reload_signin_data(&self) -> io::Result<()> {
+        if let Some(data) = self.signin_data.reload(|| storage::load_data().map(Some))? {
+            log::info!("Sign-in data changed on disk");
+            if let Some(app) = self.app.get() {
+                changes::signin_updated(app, data.as_ref());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Copy of a file's contents, `None` until first loaded
+struct Cached<T>(RwLock<Option<T>>);
+
+impl<T> Default for Cached<T> {
+    fn default() -> Self {
+        Self(RwLock::new(None))
+    }
+}
+
+impl<T: Clone> Cached<T> {
+    /// The cached value, loading it on first use
+    fn get(&self, load: fn() -> io::Result<T>) -> io::Result<T> {
+        if let Some(value) = self.0.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            return Ok(value.clone());
+        }
+        let mut cached = self.0.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(value) = cached.as_ref() {
+            return Ok(value.clone());
+        }
+        let value = load()?;
+        *cached = Some(value.clone());
+        Ok(value)
+    }
+
+    /// The cached value if loaded, unless a writer holds the lock
+    fn peek(&self) -> Option<T> {
+        match self.0.try_read() {
+            Ok(cached) => cached.clone(),
+            Err(TryLockError::Poisoned(e)) => e.into_inner().clone(),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
+    /// Save `value` and cache it once saved
+    fn set(&self, value: T, save: impl FnOnce(&T) -> io::Result<()>) -> io::Result<()> {
+        let mut cached = self.0.write().unwrap_or_else(|e| e.into_inner());
+        save(&value)?;
+        *cached = Some(value);
+        Ok(())
+    }
+
+    /// Apply `update` to the current value and save it, holding off other writers
+    fn update<R>(
+        &self,
+        load: fn() -> io::Result<T>,
+        save: fn(&T) -> io::Result<()>,
+        update: impl FnOnce(&mut T) -> Result<R, AppError>,
+    ) -> Result<R, AppError> {
+        let mut cached = self.0.write().unwrap_or_else(|e| e.into_inner());
+        let mut value = match cached.as_ref() {
+            Some(value) => value.clone(),
+            None => load().map_err(|e| e.to_string())?,
+        };
+        let result = update(&mut value)?;
+        save(&value).map_err(|e| {
+            log::error!("Failed to save configuration: {}", e);
+            e.to_string()
+        })?;
+        *cached = Some(value);
+        Ok(result)
+    }
+}
+
+impl<T: Clone + Serialize> Cached<T> {
+    /// Replace the cached value with the file's contents if they differ
+    ///
+    /// Returns the new value when it changed. Nothing happens before the first
+    /// load, which reads the file anyway, or when `load` finds no file.
+    fn reload(&self, load: impl FnOnce() -> io::Result<Option<T>>) -> io::Result<Option<T>> {
+        let mut cached = self.0.write().unwrap_or_else(|e| e.into_inner());
+        let Some(current) = cached.as_ref() else {
+            return Ok(None);
+        };
+        let Some(value) = load()? else {
+            return Ok(None);
+        };
+        // Our own saves trigger a reload too; they leave the contents unchanged
+        if serde_json::to_value(current).ok() == serde_json::to_value(&value).ok() {
+            return Ok(None);
+        }
+        *cached = Some(value.clone());
+        Ok(Some(value))
+    }
+}
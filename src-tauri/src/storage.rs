@@ -3,16 +3,26 @@
 //! This module handles all file I/O operations for storing and loading
 //! application data, configurations, and device settings.
 
+use std::collections::BTreeMap;
 use std::fs;
 use std::io;
 use std::path::PathBuf;
 
 use uuid::Uuid;
 
-use crate::models::{DeviceConfig, EmailConfig, SigninData};
+use crate::keychain::{self, Secret};
+use crate::remote_models::{Encouragement, SharedHistory, WellnessQuestion};
+
+use crate::models::{
+    AppSettings, CachedResponse, CrashReport, DeviceConfig, DeviceStatus, DigestState, EmailConfig,
+    EscalationLogEntry, EscalationPolicy, MissedAlertState, NotificationChannels,
+    NotificationHistoryEntry, NotificationPreferences, OnboardingState, OutboxItem,
+    PendingSyncItem, QueuedNotification, ReminderConfig, ReportState, RequestLimitState,
+    SigninData, SigninUndo, SupervisionHistoryEntry, SupervisionSnapshot, SyncedList, Webhook,
+};
 
 /// Get the application data directory path
-fn get_app_dir() -> io::Result<PathBuf> {
+pub fn get_app_dir() -> io::Result<PathBuf> {
     let config_dir = dirs::config_dir()
         .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Config directory not found"))?;
     let app_dir = config_dir.join("areuok");
@@ -20,11 +30,21 @@ fn get_app_dir() -> io::Result<PathBuf> {
     Ok(app_dir)
 }
 
+/// Directory of the log files
+pub fn log_dir() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("logs"))
+}
+
 /// Get the path to the sign-in data file
 pub fn get_data_file_path() -> io::Result<PathBuf> {
     Ok(get_app_dir()?.join("data.json"))
 }
 
+/// Get the path to the sign-in undo snapshot file
+pub fn get_signin_undo_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("signin_undo.json"))
+}
+
 /// Get the path to the email config file
 pub fn get_email_config_path() -> io::Result<PathBuf> {
     Ok(get_app_dir()?.join("email_config.json"))
@@ -35,6 +55,137 @@ pub fn get_device_config_path() -> io::Result<PathBuf> {
     Ok(get_app_dir()?.join("device_config.json"))
 }
 
+/// Get the path to the app settings file
+pub fn get_settings_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("settings.json"))
+}
+
+/// Get the path to the reminder config file
+pub fn get_reminder_config_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("reminder_config.json"))
+}
+
+/// Get the path to the report state file
+pub fn get_report_state_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("report_state.json"))
+}
+
+/// Get the path to the missed check-in alert state file
+pub fn get_missed_alert_state_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("missed_alert_state.json"))
+}
+
+/// Get the path to the supervision request limits file
+pub fn get_request_limits_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("request_limits.json"))
+}
+
+/// Get the path to the queue of server calls made while offline
+pub fn get_sync_queue_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("sync_queue.json"))
+}
+
+/// Get the path to the cache of GET responses keyed by endpoint
+pub fn get_http_cache_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("http_cache.json"))
+}
+
+/// Get the path to the server lists kept current with delta fetches
+pub fn get_synced_lists_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("synced_lists.json"))
+}
+
+/// Get the path to the escalation policy file
+pub fn get_escalation_policy_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("escalation_policy.json"))
+}
+
+/// Get the path to the escalation log file
+pub fn get_escalation_log_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("escalation_log.json"))
+}
+
+/// Get the path to the notification channels config file
+pub fn get_notification_channels_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("notification_channels.json"))
+}
+
+/// Get the path to the webhooks file
+pub fn get_webhooks_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("webhooks.json"))
+}
+
+/// Get the path to the weekly digest state file
+pub fn get_digest_state_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("digest_state.json"))
+}
+
+/// Get the path to the notification history file
+pub fn get_notification_history_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("notification_history.json"))
+}
+
+/// Get the path to the delivery retry outbox file
+pub fn get_outbox_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("outbox.json"))
+}
+
+/// Get the path to the notification preferences file
+pub fn get_notification_preferences_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("notification_preferences.json"))
+}
+
+/// Get the path to the quiet-hours queue file
+pub fn get_quiet_queue_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("quiet_queue.json"))
+}
+
+/// Get the path to the cached statuses of supervised devices
+pub fn get_device_status_cache_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("device_status_cache.json"))
+}
+
+/// Get the path to the last supervision sync snapshot
+pub fn get_supervision_snapshot_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("supervision_snapshot.json"))
+}
+
+/// Get the path to the report of the last crash
+pub fn get_crash_report_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("crash_report.json"))
+}
+
+/// Get the path to the first-run setup progress file
+pub fn get_onboarding_state_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("onboarding.json"))
+}
+
+/// Get the path to the supervision interaction history file
+pub fn get_supervision_history_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("supervision_history.json"))
+}
+
+/// Get the path to the inbox of encouragements awaiting the next sign-in
+pub fn get_encouragement_inbox_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("encouragement_inbox.json"))
+}
+
+/// Get the path to the cached wellness questions asked at check-in
+pub fn get_wellness_questions_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("wellness_questions.json"))
+}
+
+/// Get the path to the synced sign-in records of supervised devices
+pub fn get_supervised_signins_path() -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join("supervised_signins.json"))
+}
+
+/// Get the path to a secret on platforms without a keychain
+#[cfg(mobile)]
+pub fn get_secret_path(name: &str) -> io::Result<PathBuf> {
+    Ok(get_app_dir()?.join(name))
+}
+
 /// Load sign-in data from storage
 pub fn load_data() -> io::Result<Option<SigninData>> {
     log::debug!("Attempting to load sign-in data");
@@ -74,6 +225,36 @@ pub fn delete_data() -> io::Result<()> {
     Ok(())
 }
 
+/// Load the pre-sign-in undo snapshot, if any
+pub fn load_signin_undo() -> io::Result<Option<SigninUndo>> {
+    let path = get_signin_undo_path()?;
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        Ok(Some(serde_json::from_str(&contents)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Save the pre-sign-in undo snapshot
+pub fn save_signin_undo(undo: &SigninUndo) -> io::Result<()> {
+    log::debug!("Saving sign-in undo snapshot");
+    let path = get_signin_undo_path()?;
+    let json = serde_json::to_string_pretty(undo)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// Delete the pre-sign-in undo snapshot
+pub fn delete_signin_undo() -> io::Result<()> {
+    let path = get_signin_undo_path()?;
+    if path.exists() {
+        fs::remove_file(&path)?;
+        log::debug!("Deleted sign-in undo snapshot");
+    }
+    Ok(())
+}
+
 /// Load email configuration from storage
 pub fn load_email_config() -> io::Result<EmailConfig> {
     log::debug!("Attempting to load email configuration");
@@ -81,7 +262,21 @@ pub fn load_email_config() -> io::Result<EmailConfig> {
 
     if path.exists() {
         let contents = fs::read_to_string(&path)?;
-        let config: EmailConfig = serde_json::from_str(&contents)?;
+        let mut config: EmailConfig = serde_json::from_str(&contents)?;
+        if config.migrate_legacy_recipient() {
+            log::info!("Migrating single-recipient email configuration");
+            save_email_config(&config)?;
+        }
+        if let Some(oauth2) = &mut config.oauth2 {
+            if oauth2.refresh_token.is_empty() {
+                oauth2.refresh_token = keychain::load(Secret::OAuthRefreshToken)
+                    .map_err(io::Error::other)?
+                    .unwrap_or_default();
+            } else {
+                log::info!("Moving the OAuth2 refresh token to the keychain");
+                save_email_config(&config)?;
+            }
+        }
         log::info!("Successfully loaded email configuration: enabled={}", config.enabled);
         Ok(config)
     } else {
@@ -90,18 +285,93 @@ pub fn load_email_config() -> io::Result<EmailConfig> {
     }
 }
 
-/// Save email configuration to storage
+/// Save email configuration to storage, with the OAuth2 refresh token in the keychain
 pub fn save_email_config(config: &EmailConfig) -> io::Result<()> {
     log::debug!("Saving email configuration: enabled={}", config.enabled);
+    let mut config = config.clone();
+    match &mut config.oauth2 {
+        Some(oauth2) if !oauth2.refresh_token.is_empty() => {
+            let refresh_token = std::mem::take(&mut oauth2.refresh_token);
+            keychain::store(Secret::OAuthRefreshToken, &refresh_token).map_err(io::Error::other)?;
+        }
+        Some(_) => {}
+        None => keychain::delete(Secret::OAuthRefreshToken).map_err(io::Error::other)?,
+    }
     let path = get_email_config_path()?;
-    let json = serde_json::to_string_pretty(config)?;
+    let json = serde_json::to_string_pretty(&config)?;
     fs::write(&path, json)?;
     log::info!("Successfully saved email configuration to {:?}", path);
     Ok(())
 }
 
+/// Load application settings from storage
+pub fn load_settings() -> io::Result<AppSettings> {
+    log::debug!("Attempting to load app settings");
+    let path = get_settings_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let settings: AppSettings = serde_json::from_str(&contents)?;
+        log::info!("Successfully loaded app settings");
+        Ok(settings)
+    } else {
+        log::info!("No existing app settings found, returning default");
+        Ok(AppSettings::default())
+    }
+}
+
+/// Save application settings to storage
+pub fn save_settings(settings: &AppSettings) -> io::Result<()> {
+    log::debug!("Saving app settings");
+    let path = get_settings_path()?;
+    let json = serde_json::to_string_pretty(settings)?;
+    fs::write(&path, json)?;
+    log::info!("Successfully saved app settings to {:?}", path);
+    Ok(())
+}
+
+/// Load reminder configuration from storage
+pub fn load_reminder_config() -> io::Result<ReminderConfig> {
+    log::debug!("Attempting to load reminder configuration");
+    let path = get_reminder_config_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let config: ReminderConfig = serde_json::from_str(&contents)?;
+        log::debug!("Successfully loaded reminder configuration: enabled={}", config.enabled);
+        Ok(config)
+    } else {
+        log::debug!("No existing reminder configuration found, returning default");
+        Ok(ReminderConfig::default())
+    }
+}
+
+/// Save reminder configuration to storage
+pub fn save_reminder_config(config: &ReminderConfig) -> io::Result<()> {
+    log::debug!("Saving reminder configuration: enabled={}", config.enabled);
+    let path = get_reminder_config_path()?;
+    let json = serde_json::to_string_pretty(config)?;
+    fs::write(&path, json)?;
+    log::info!("Successfully saved reminder configuration to {:?}", path);
+    Ok(())
+}
+
 /// Load or create device configuration
 pub fn load_or_create_device_config() -> io::Result<DeviceConfig> {
+    if let Some(config) = load_device_config()? {
+        Ok(config)
+    } else {
+        log::info!("No existing device configuration found, creating new one");
+        let device_id = Uuid::new_v4().to_string();
+        let config = DeviceConfig::new(device_id.clone());
+        save_device_config(&config)?;
+        log::info!("Created new device with device_id={}", device_id);
+        Ok(config)
+    }
+}
+
+/// Load device configuration from storage, `None` if none was created yet
+pub fn load_device_config() -> io::Result<Option<DeviceConfig>> {
     log::debug!("Attempting to load device configuration");
     let path = get_device_config_path()?;
 
@@ -114,14 +384,9 @@ pub fn load_or_create_device_config() -> io::Result<DeviceConfig> {
             config.device.device_name,
             config.device.mode
         );
-        Ok(config)
+        Ok(Some(config))
     } else {
-        log::info!("No existing device configuration found, creating new one");
-        let device_id = Uuid::new_v4().to_string();
-        let config = DeviceConfig::new(device_id.clone());
-        save_device_config(&config)?;
-        log::info!("Created new device with device_id={}", device_id);
-        Ok(config)
+        Ok(None)
     }
 }
 
@@ -138,3 +403,581 @@ pub fn save_device_config(config: &DeviceConfig) -> io::Result<()> {
     log::info!("Successfully saved device configuration to {:?}", path);
     Ok(())
 }
+
+/// Load scheduled report bookkeeping from storage
+pub fn load_report_state() -> io::Result<ReportState> {
+    log::debug!("Attempting to load report state");
+    let path = get_report_state_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let state: ReportState = serde_json::from_str(&contents)?;
+        Ok(state)
+    } else {
+        log::info!("No existing report state found, returning default");
+        Ok(ReportState::default())
+    }
+}
+
+/// Save scheduled report bookkeeping to storage
+pub fn save_report_state(state: &ReportState) -> io::Result<()> {
+    log::debug!("Saving report state");
+    let path = get_report_state_path()?;
+    let json = serde_json::to_string_pretty(state)?;
+    fs::write(&path, json)?;
+    log::info!("Successfully saved report state to {:?}", path);
+    Ok(())
+}
+
+/// Load missed check-in alert bookkeeping from storage
+pub fn load_missed_alert_state() -> io::Result<MissedAlertState> {
+    log::debug!("Attempting to load missed alert state");
+    let path = get_missed_alert_state_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let state: MissedAlertState = serde_json::from_str(&contents)?;
+        Ok(state)
+    } else {
+        log::info!("No existing missed alert state found, returning default");
+        Ok(MissedAlertState::default())
+    }
+}
+
+/// Save missed check-in alert bookkeeping to storage
+pub fn save_missed_alert_state(state: &MissedAlertState) -> io::Result<()> {
+    log::debug!("Saving missed alert state");
+    let path = get_missed_alert_state_path()?;
+    let json = serde_json::to_string_pretty(state)?;
+    fs::write(&path, json)?;
+    log::info!("Successfully saved missed alert state to {:?}", path);
+    Ok(())
+}
+
+/// Load the supervision request limits bookkeeping from storage
+pub fn load_request_limits() -> io::Result<RequestLimitState> {
+    log::debug!("Attempting to load request limits");
+    let path = get_request_limits_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let state: RequestLimitState = serde_json::from_str(&contents)?;
+        Ok(state)
+    } else {
+        Ok(RequestLimitState::default())
+    }
+}
+
+/// Save the supervision request limits bookkeeping to storage
+pub fn save_request_limits(state: &RequestLimitState) -> io::Result<()> {
+    log::debug!("Saving request limits");
+    let path = get_request_limits_path()?;
+    let json = serde_json::to_string_pretty(state)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// Load the queue of server calls made while offline
+pub fn load_sync_queue() -> io::Result<Vec<PendingSyncItem>> {
+    log::debug!("Attempting to load sync queue");
+    let path = get_sync_queue_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let queue: Vec<PendingSyncItem> = serde_json::from_str(&contents)?;
+        Ok(queue)
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// Save the queue of server calls made while offline
+pub fn save_sync_queue(queue: &[PendingSyncItem]) -> io::Result<()> {
+    log::debug!("Saving sync queue ({} items)", queue.len());
+    let path = get_sync_queue_path()?;
+    let json = serde_json::to_string_pretty(queue)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// Load the cached GET responses, keyed by endpoint
+pub fn load_http_cache() -> io::Result<BTreeMap<String, CachedResponse>> {
+    log::debug!("Attempting to load HTTP cache");
+    let path = get_http_cache_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let cache: BTreeMap<String, CachedResponse> = serde_json::from_str(&contents)?;
+        Ok(cache)
+    } else {
+        Ok(BTreeMap::new())
+    }
+}
+
+/// Save the cached GET responses
+pub fn save_http_cache(cache: &BTreeMap<String, CachedResponse>) -> io::Result<()> {
+    log::debug!("Saving HTTP cache ({} entries)", cache.len());
+    let path = get_http_cache_path()?;
+    let json = serde_json::to_string_pretty(cache)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// Load the server lists kept current with delta fetches, keyed by endpoint
+pub fn load_synced_lists() -> io::Result<BTreeMap<String, SyncedList>> {
+    log::debug!("Attempting to load synced lists");
+    let path = get_synced_lists_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let lists: BTreeMap<String, SyncedList> = serde_json::from_str(&contents)?;
+        Ok(lists)
+    } else {
+        Ok(BTreeMap::new())
+    }
+}
+
+/// Save the server lists kept current with delta fetches
+pub fn save_synced_lists(lists: &BTreeMap<String, SyncedList>) -> io::Result<()> {
+    log::debug!("Saving synced lists ({} lists)", lists.len());
+    let path = get_synced_lists_path()?;
+    let json = serde_json::to_string_pretty(lists)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// Load the missed check-in escalation policy from storage
+pub fn load_escalation_policy() -> io::Result<EscalationPolicy> {
+    log::debug!("Attempting to load escalation policy");
+    let path = get_escalation_policy_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let policy: EscalationPolicy = serde_json::from_str(&contents)?;
+        log::debug!("Successfully loaded escalation policy: enabled={}", policy.enabled);
+        Ok(policy)
+    } else {
+        log::debug!("No existing escalation policy found, returning default");
+        Ok(EscalationPolicy::default())
+    }
+}
+
+/// Save the missed check-in escalation policy to storage
+pub fn save_escalation_policy(policy: &EscalationPolicy) -> io::Result<()> {
+    log::debug!("Saving escalation policy: enabled={}", policy.enabled);
+    let path = get_escalation_policy_path()?;
+    let json = serde_json::to_string_pretty(policy)?;
+    fs::write(&path, json)?;
+    log::info!("Successfully saved escalation policy to {:?}", path);
+    Ok(())
+}
+
+/// Load the log of fired escalation stages from storage
+pub fn load_escalation_log() -> io::Result<Vec<EscalationLogEntry>> {
+    log::debug!("Attempting to load escalation log");
+    let path = get_escalation_log_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let log: Vec<EscalationLogEntry> = serde_json::from_str(&contents)?;
+        Ok(log)
+    } else {
+        log::debug!("No existing escalation log found, returning empty list");
+        Ok(vec![])
+    }
+}
+
+/// Save the log of fired escalation stages to storage
+pub fn save_escalation_log(log: &[EscalationLogEntry]) -> io::Result<()> {
+    log::debug!("Saving escalation log ({} entries)", log.len());
+    let path = get_escalation_log_path()?;
+    let json = serde_json::to_string_pretty(log)?;
+    fs::write(&path, json)?;
+    log::info!("Successfully saved escalation log to {:?}", path);
+    Ok(())
+}
+
+/// Load notification channel configuration from storage
+pub fn load_notification_channels() -> io::Result<NotificationChannels> {
+    log::debug!("Attempting to load notification channels");
+    let path = get_notification_channels_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let channels: NotificationChannels = serde_json::from_str(&contents)?;
+        Ok(channels)
+    } else {
+        log::debug!("No existing notification channels found, returning default");
+        Ok(NotificationChannels::default())
+    }
+}
+
+/// Save notification channel configuration to storage
+pub fn save_notification_channels(channels: &NotificationChannels) -> io::Result<()> {
+    log::debug!("Saving notification channels");
+    let path = get_notification_channels_path()?;
+    let json = serde_json::to_string_pretty(channels)?;
+    fs::write(&path, json)?;
+    log::info!("Successfully saved notification channels to {:?}", path);
+    Ok(())
+}
+
+/// Load outgoing webhooks from storage
+pub fn load_webhooks() -> io::Result<Vec<Webhook>> {
+    log::debug!("Attempting to load webhooks");
+    let path = get_webhooks_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let webhooks: Vec<Webhook> = serde_json::from_str(&contents)?;
+        Ok(webhooks)
+    } else {
+        log::debug!("No existing webhooks found, returning empty list");
+        Ok(vec![])
+    }
+}
+
+/// Save outgoing webhooks to storage
+pub fn save_webhooks(webhooks: &[Webhook]) -> io::Result<()> {
+    log::debug!("Saving {} webhooks", webhooks.len());
+    let path = get_webhooks_path()?;
+    let json = serde_json::to_string_pretty(webhooks)?;
+    fs::write(&path, json)?;
+    log::info!("Successfully saved webhooks to {:?}", path);
+    Ok(())
+}
+
+/// Load accumulated weekly digest events from storage
+pub fn load_digest_state() -> io::Result<DigestState> {
+    log::debug!("Attempting to load digest state");
+    let path = get_digest_state_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let state: DigestState = serde_json::from_str(&contents)?;
+        Ok(state)
+    } else {
+        log::debug!("No existing digest state found, returning default");
+        Ok(DigestState::default())
+    }
+}
+
+/// Save accumulated weekly digest events to storage
+pub fn save_digest_state(state: &DigestState) -> io::Result<()> {
+    log::debug!("Saving digest state");
+    let path = get_digest_state_path()?;
+    let json = serde_json::to_string_pretty(state)?;
+    fs::write(&path, json)?;
+    log::info!("Successfully saved digest state to {:?}", path);
+    Ok(())
+}
+
+/// Load notifications held back during quiet hours
+pub fn load_quiet_queue() -> io::Result<Vec<QueuedNotification>> {
+    log::debug!("Attempting to load quiet-hours queue");
+    let path = get_quiet_queue_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let queue: Vec<QueuedNotification> = serde_json::from_str(&contents)?;
+        Ok(queue)
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// Save notifications held back during quiet hours
+pub fn save_quiet_queue(queue: &[QueuedNotification]) -> io::Result<()> {
+    log::debug!("Saving quiet-hours queue ({} items)", queue.len());
+    let path = get_quiet_queue_path()?;
+    let json = serde_json::to_string_pretty(queue)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// Load the last fetched statuses of supervised devices
+pub fn load_device_status_cache() -> io::Result<Vec<DeviceStatus>> {
+    log::debug!("Attempting to load device status cache");
+    let path = get_device_status_cache_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let statuses: Vec<DeviceStatus> = serde_json::from_str(&contents)?;
+        Ok(statuses)
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// Save the last fetched statuses of supervised devices
+pub fn save_device_status_cache(statuses: &[DeviceStatus]) -> io::Result<()> {
+    log::debug!("Saving device status cache ({} devices)", statuses.len());
+    let path = get_device_status_cache_path()?;
+    let json = serde_json::to_string_pretty(statuses)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// Load the supervision state from the last background sync
+pub fn load_supervision_snapshot() -> io::Result<SupervisionSnapshot> {
+    log::debug!("Attempting to load supervision snapshot");
+    let path = get_supervision_snapshot_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let snapshot: SupervisionSnapshot = serde_json::from_str(&contents)?;
+        Ok(snapshot)
+    } else {
+        Ok(SupervisionSnapshot::default())
+    }
+}
+
+/// Save the supervision state of the latest background sync
+pub fn save_supervision_snapshot(snapshot: &SupervisionSnapshot) -> io::Result<()> {
+    log::debug!("Saving supervision snapshot");
+    let path = get_supervision_snapshot_path()?;
+    let json = serde_json::to_string_pretty(snapshot)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// Load the report of the last crash, `None` if the app never crashed
+pub fn load_crash_report() -> io::Result<Option<CrashReport>> {
+    let path = get_crash_report_path()?;
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        Ok(Some(serde_json::from_str(&contents)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Save a crash report, replacing the previous one
+///
+/// Called from the panic hook, so unlike the other writers this doesn't log.
+pub fn save_crash_report(report: &CrashReport) -> io::Result<()> {
+    let path = get_crash_report_path()?;
+    let json = serde_json::to_string_pretty(report)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// Load the first-run setup progress, `None` if onboarding never started
+pub fn load_onboarding_state() -> io::Result<Option<OnboardingState>> {
+    log::debug!("Attempting to load onboarding state");
+    let path = get_onboarding_state_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        Ok(Some(serde_json::from_str(&contents)?))
+    } else {
+        log::info!("No existing onboarding state found");
+        Ok(None)
+    }
+}
+
+/// Save the first-run setup progress
+pub fn save_onboarding_state(state: &OnboardingState) -> io::Result<()> {
+    log::debug!("Saving onboarding state");
+    let path = get_onboarding_state_path()?;
+    let json = serde_json::to_string_pretty(state)?;
+    fs::write(&path, json)?;
+    log::info!("Successfully saved onboarding state to {:?}", path);
+    Ok(())
+}
+
+/// Load the supervision interaction history from storage
+pub fn load_supervision_history() -> io::Result<Vec<SupervisionHistoryEntry>> {
+    log::debug!("Attempting to load supervision history");
+    let path = get_supervision_history_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let history: Vec<SupervisionHistoryEntry> = serde_json::from_str(&contents)?;
+        Ok(history)
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// Save the supervision interaction history to storage
+pub fn save_supervision_history(history: &[SupervisionHistoryEntry]) -> io::Result<()> {
+    log::debug!("Saving supervision history ({} entries)", history.len());
+    let path = get_supervision_history_path()?;
+    let json = serde_json::to_string_pretty(history)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// Load encouragements waiting to be shown after the next sign-in
+pub fn load_encouragement_inbox() -> io::Result<Vec<Encouragement>> {
+    log::debug!("Attempting to load encouragement inbox");
+    let path = get_encouragement_inbox_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let inbox: Vec<Encouragement> = serde_json::from_str(&contents)?;
+        Ok(inbox)
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// Save encouragements waiting to be shown after the next sign-in
+pub fn save_encouragement_inbox(inbox: &[Encouragement]) -> io::Result<()> {
+    log::debug!("Saving encouragement inbox ({} messages)", inbox.len());
+    let path = get_encouragement_inbox_path()?;
+    let json = serde_json::to_string_pretty(inbox)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// Load the wellness questions last fetched from the server
+pub fn load_wellness_questions() -> io::Result<Vec<WellnessQuestion>> {
+    log::debug!("Attempting to load wellness questions");
+    let path = get_wellness_questions_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let questions: Vec<WellnessQuestion> = serde_json::from_str(&contents)?;
+        Ok(questions)
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// Save the wellness questions fetched from the server
+pub fn save_wellness_questions(questions: &[WellnessQuestion]) -> io::Result<()> {
+    log::debug!("Saving wellness questions ({} questions)", questions.len());
+    let path = get_wellness_questions_path()?;
+    let json = serde_json::to_string_pretty(questions)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// Load the synced sign-in records of supervised devices, keyed by device ID
+pub fn load_supervised_signins() -> io::Result<BTreeMap<String, SharedHistory>> {
+    log::debug!("Attempting to load supervised sign-in records");
+    let path = get_supervised_signins_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let records: BTreeMap<String, SharedHistory> = serde_json::from_str(&contents)?;
+        Ok(records)
+    } else {
+        Ok(BTreeMap::new())
+    }
+}
+
+/// Save the synced sign-in records of supervised devices
+pub fn save_supervised_signins(records: &BTreeMap<String, SharedHistory>) -> io::Result<()> {
+    log::debug!("Saving supervised sign-in records ({} devices)", records.len());
+    let path = get_supervised_signins_path()?;
+    let json = serde_json::to_string_pretty(records)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// Load a secret stored under `name`
+#[cfg(mobile)]
+pub fn load_secret(name: &str) -> io::Result<Option<String>> {
+    log::debug!("Attempting to load secret {}", name);
+    let path = get_secret_path(name)?;
+
+    if path.exists() {
+        Ok(Some(fs::read_to_string(&path)?.trim().to_string()))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Save a secret under `name`
+#[cfg(mobile)]
+pub fn save_secret(name: &str, value: &str) -> io::Result<()> {
+    log::debug!("Saving secret {}", name);
+    let path = get_secret_path(name)?;
+    fs::write(&path, value)?;
+    Ok(())
+}
+
+/// Delete the secret stored under `name`
+#[cfg(mobile)]
+pub fn delete_secret(name: &str) -> io::Result<()> {
+    log::debug!("Deleting secret {}", name);
+    match fs::remove_file(get_secret_path(name)?) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Load the notification delivery history from storage
+pub fn load_notification_history() -> io::Result<Vec<NotificationHistoryEntry>> {
+    log::debug!("Attempting to load notification history");
+    let path = get_notification_history_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let history: Vec<NotificationHistoryEntry> = serde_json::from_str(&contents)?;
+        Ok(history)
+    } else {
+        log::debug!("No existing notification history found, returning empty list");
+        Ok(vec![])
+    }
+}
+
+/// Save the notification delivery history to storage
+pub fn save_notification_history(history: &[NotificationHistoryEntry]) -> io::Result<()> {
+    log::debug!("Saving notification history ({} entries)", history.len());
+    let path = get_notification_history_path()?;
+    let json = serde_json::to_string_pretty(history)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// Load failed deliveries waiting to be retried
+pub fn load_outbox() -> io::Result<Vec<OutboxItem>> {
+    log::debug!("Attempting to load outbox");
+    let path = get_outbox_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let outbox: Vec<OutboxItem> = serde_json::from_str(&contents)?;
+        Ok(outbox)
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// Save failed deliveries waiting to be retried
+pub fn save_outbox(outbox: &[OutboxItem]) -> io::Result<()> {
+    log::debug!("Saving outbox ({} items)", outbox.len());
+    let path = get_outbox_path()?;
+    let json = serde_json::to_string_pretty(outbox)?;
+    fs::write(&path, json)?;
+    Ok(())
+}
+
+/// Load the event-to-channel notification preferences from storage
+pub fn load_notification_preferences() -> io::Result<NotificationPreferences> {
+    log::debug!("Attempting to load notification preferences");
+    let path = get_notification_preferences_path()?;
+
+    if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let preferences: NotificationPreferences = serde_json::from_str(&contents)?;
+        Ok(preferences)
+    } else {
+        log::debug!("No existing notification preferences found, returning default");
+        Ok(NotificationPreferences::default())
+    }
+}
+
+/// Save the event-to-channel notification preferences to storage
+pub fn save_notification_preferences(preferences: &NotificationPreferences) -> io::Result<()> {
+    log::debug!("Saving notification preferences");
+    let path = get_notification_preferences_path()?;
+    let json = serde_json::to_string_pretty(preferences)?;
+    fs::write(&path, json)?;
+    log::info!("Successfully saved notification preferences to {:?}", path);
+    Ok(())
+}
@@ -0,0 +1,212 @@
+//! Streak calculation.
+//!
+//! The current streak is always derived from the complete dated sign-in history,
+//! so the history never has to be reset when a streak breaks.
+
+use std::collections::BTreeSet;
+
+use chrono::{Datelike, NaiveDate, Weekday};
+
+use crate::models::{GoalProgress, StreakPolicy};
+
+/// Days that may be missed in a trailing week under the five-of-seven policy
+const FIVE_OF_SEVEN_ALLOWED_MISSES: usize = 2;
+
+/// Parse a list of YYYY-MM-DD strings, skipping malformed entries
+pub fn parse_dates(dates: &[String]) -> BTreeSet<NaiveDate> {
+    dates
+        .iter()
+        .filter_map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .collect()
+}
+
+/// Derive the streak ending at the most recent sign-in
+///
+/// `bridges` are missed dates covered by a streak restore: they keep the chain
+/// unbroken but don't add to the count.
+pub fn calculate_streak(history: &[String], bridges: &[String], policy: StreakPolicy) -> i32 {
+    let signed = parse_dates(history);
+    let covered: BTreeSet<NaiveDate> = signed.union(&parse_dates(bridges)).copied().collect();
+
+    let mut streak = 0;
+    let mut chain_start: Option<NaiveDate> = None;
+    let mut previous: Option<NaiveDate> = None;
+
+    for &date in &covered {
+        let counts = i32::from(signed.contains(&date));
+        match (previous, chain_start) {
+            (Some(last), Some(start)) if continues_streak(last, date, start, &covered, policy) => {
+                streak += counts;
+            }
+            _ => {
+                chain_start = Some(date);
+                streak = counts;
+            }
+        }
+        previous = Some(date);
+    }
+
+    streak
+}
+
+/// Check whether signing in on `next` continues a chain whose latest sign-in was `last`
+pub fn continues_streak(
+    last: NaiveDate,
+    next: NaiveDate,
+    chain_start: NaiveDate,
+    covered: &BTreeSet<NaiveDate>,
+    policy: StreakPolicy,
+) -> bool {
+    if next <= last {
+        return false;
+    }
+
+    match policy {
+        StreakPolicy::StrictDaily => (next - last).num_days() == 1,
+        StreakPolicy::WeekdaysOnly => last
+            .iter_days()
+            .skip(1)
+            .take_while(|d| *d < next)
+            .all(|d| matches!(d.weekday(), Weekday::Sat | Weekday::Sun)),
+        StreakPolicy::FiveOfSeven => {
+            let missed = (1..=7)
+                .map(|n| next - chrono::Duration::days(n))
+                .filter(|d| *d >= chain_start && !covered.contains(d))
+                .count();
+            missed <= FIVE_OF_SEVEN_ALLOWED_MISSES
+        }
+    }
+}
+
+/// Compute progress towards a streak goal as of `today`
+pub fn goal_progress(streak: i32, target: u32, today: NaiveDate) -> GoalProgress {
+    let current = streak.max(0) as u32;
+    let achieved = current >= target;
+    let progress_percent = if target == 0 {
+        100.0
+    } else {
+        (current as f32 / target as f32 * 100.0).min(100.0)
+    };
+    let projected_completion_date = (!achieved).then(|| {
+        let remaining = i64::from(target - current);
+        (today + chrono::Duration::days(remaining))
+            .format("%Y-%m-%d")
+            .to_string()
+    });
+
+    GoalProgress {
+        target,
+        progress_percent,
+        achieved,
+        projected_completion_date,
+    }
+}
+
+/// List the `days` calendar days ending at `today`, flagging the ones with a sign-in
+pub fn recent_days(history: &[String], today: NaiveDate, days: u32) -> Vec<(NaiveDate, bool)> {
+    let signed = parse_dates(history);
+    (0..i64::from(days))
+        .rev()
+        .map(|offset| today - chrono::Duration::days(offset))
+        .map(|date| (date, signed.contains(&date)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, FixedClock};
+
+    fn dates(dates: &[&str]) -> Vec<String> {
+        dates.iter().map(|d| d.to_string()).collect()
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn streak_continues_across_midnight() {
+        let history = dates(&["2024-03-09", "2024-03-10", "2024-03-11"]);
+        assert_eq!(calculate_streak(&history, &[], StreakPolicy::StrictDaily), 3);
+    }
+
+    #[test]
+    fn streak_breaks_on_a_missed_day() {
+        let history = dates(&["2024-03-07", "2024-03-08", "2024-03-10"]);
+        assert_eq!(calculate_streak(&history, &[], StreakPolicy::StrictDaily), 1);
+    }
+
+    #[test]
+    fn streak_counts_leap_day() {
+        let history = dates(&["2024-02-28", "2024-02-29", "2024-03-01"]);
+        assert_eq!(calculate_streak(&history, &[], StreakPolicy::StrictDaily), 3);
+    }
+
+    #[test]
+    fn streak_skips_missing_leap_day_in_common_years() {
+        let history = dates(&["2023-02-28", "2023-03-01"]);
+        assert_eq!(calculate_streak(&history, &[], StreakPolicy::StrictDaily), 2);
+    }
+
+    #[test]
+    fn streak_ignores_order_and_duplicates_after_backwards_jump() {
+        let history = dates(&["2024-03-10", "2024-03-11", "2024-03-10", "2024-03-09"]);
+        assert_eq!(calculate_streak(&history, &[], StreakPolicy::StrictDaily), 3);
+    }
+
+    #[test]
+    fn bridged_day_keeps_streak_without_counting() {
+        let history = dates(&["2024-03-08", "2024-03-10"]);
+        let bridges = dates(&["2024-03-09"]);
+        assert_eq!(calculate_streak(&history, &bridges, StreakPolicy::StrictDaily), 2);
+    }
+
+    #[test]
+    fn weekdays_only_skips_weekend() {
+        // Friday and the following Monday
+        let history = dates(&["2024-03-08", "2024-03-11"]);
+        assert_eq!(calculate_streak(&history, &[], StreakPolicy::WeekdaysOnly), 2);
+        assert_eq!(calculate_streak(&history, &[], StreakPolicy::StrictDaily), 1);
+    }
+
+    #[test]
+    fn five_of_seven_allows_two_misses() {
+        let history = dates(&["2024-03-01", "2024-03-02", "2024-03-05"]);
+        assert_eq!(calculate_streak(&history, &[], StreakPolicy::FiveOfSeven), 3);
+    }
+
+    #[test]
+    fn recent_days_end_at_today_across_leap_day() {
+        let clock = FixedClock::at("2024-03-01T00:00:00Z");
+        let history = dates(&["2024-02-29"]);
+        assert_eq!(
+            recent_days(&history, clock.today(), 3),
+            vec![
+                (date("2024-02-28"), false),
+                (date("2024-02-29"), true),
+                (date("2024-03-01"), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn recent_days_roll_over_at_midnight() {
+        let clock = FixedClock::at("2024-03-10T23:59:59Z");
+        let history = dates(&["2024-03-10"]);
+        assert_eq!(recent_days(&history, clock.today(), 1), vec![(date("2024-03-10"), true)]);
+        clock.set("2024-03-11T00:00:00Z");
+        assert_eq!(recent_days(&history, clock.today(), 1), vec![(date("2024-03-11"), false)]);
+    }
+
+    #[test]
+    fn recent_days_leave_out_sign_ins_after_a_backwards_jump() {
+        let clock = FixedClock::at("2024-03-11T08:00:00Z");
+        let history = dates(&["2024-03-10", "2024-03-11"]);
+        clock.set("2024-03-10T08:00:00Z");
+        assert_eq!(
+            recent_days(&history, clock.today(), 2),
+            vec![(date("2024-03-09"), false), (date("2024-03-10"), true)]
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Streak image for sign-in emails.
+//!
+//! Renders a small PNG with the current streak number above a grid of the last
+//! 30 days, drawn pixel by pixel so no font or graphics stack is needed. The
+//! colors match the HTML email template.
+
+use chrono::NaiveDate;
+
+use crate::error::AppError;
+
+/// Number of days shown in the grid
+pub const STREAK_IMAGE_DAYS: usize = 30;
+
+const GRID_COLUMNS: usize = 10;
+const CELL: usize = 16;
+const GAP: usize = 4;
+const PADDING: usize = 16;
+/// Each glyph pixel is drawn as a SCALE x SCALE block
+const SCALE: usize = 4;
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+
+const BACKGROUND: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+const STREAK_COLOR: [u8; 4] = [0xff, 0x95, 0x00, 0xff];
+const SIGNED_COLOR: [u8; 4] = [0x34, 0xc7, 0x59, 0xff];
+const MISSED_COLOR: [u8; 4] = [0xe5, 0xe5, 0xea, 0xff];
+
+/// 5x7 bitmaps for the digits 0-9, one byte per row with the low five bits used
+const DIGITS: [[u8; GLYPH_HEIGHT]; 10] = [
+    [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+    [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+    [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+    [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+    [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+    [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+    [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+    [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+];
+
+/// RGBA pixel buffer
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: BACKGROUND.repeat(width * height),
+        }
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: [u8; 4]) {
+        for row in y..(y + height).min(self.height) {
+            for column in x..(x + width).min(self.width) {
+                let offset = (row * self.width + column) * 4;
+                self.pixels[offset..offset + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    fn draw_digit(&mut self, digit: usize, x: usize, y: usize, color: [u8; 4]) {
+        for (row, bits) in DIGITS[digit].iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                    self.fill_rect(x + column * SCALE, y + row * SCALE, SCALE, SCALE, color);
+                }
+            }
+        }
+    }
+}
+
+/// Render the streak number and the last 30 days as a PNG
+///
+/// `recent_days` is ordered oldest first; only the trailing 30 entries are drawn.
+pub fn render_streak_png(
+    streak: i32,
+    recent_days: &[(NaiveDate, bool)],
+) -> Result<Vec<u8>, AppError> {
+    let days = &recent_days[recent_days.len().saturating_sub(STREAK_IMAGE_DAYS)..];
+    let rows = STREAK_IMAGE_DAYS.div_ceil(GRID_COLUMNS);
+    let grid_width = GRID_COLUMNS * CELL + (GRID_COLUMNS - 1) * GAP;
+    let grid_height = rows * CELL + (rows - 1) * GAP;
+    let digits_height = GLYPH_HEIGHT * SCALE;
+
+    let width = grid_width + 2 * PADDING;
+    let height = PADDING + digits_height + PADDING + grid_height + PADDING;
+    let mut canvas = Canvas::new(width, height);
+
+    let number = streak.max(0).to_string();
+    for (index, digit) in number.bytes().map(|b| usize::from(b - b'0')).enumerate() {
+        let x = PADDING + index * (GLYPH_WIDTH + 1) * SCALE;
+        canvas.draw_digit(digit, x, PADDING, STREAK_COLOR);
+    }
+
+    let grid_top = PADDING + digits_height + PADDING;
+    for (index, (_, signed)) in days.iter().enumerate() {
+        let x = PADDING + (index % GRID_COLUMNS) * (CELL + GAP);
+        let y = grid_top + (index / GRID_COLUMNS) * (CELL + GAP);
+        let color = if *signed { SIGNED_COLOR } else { MISSED_COLOR };
+        canvas.fill_rect(x, y, CELL, CELL, color);
+    }
+
+    encode_png(&canvas)
+}
+
+/// Encode an RGBA canvas as PNG
+fn encode_png(canvas: &Canvas) -> Result<Vec<u8>, AppError> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, canvas.width as u32, canvas.height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("Failed to write PNG header: {}", e))?;
+    writer
+        .write_image_data(&canvas.pixels)
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    writer
+        .finish()
+        .map_err(|e| format!("Failed to finish PNG: {}", e))?;
+    Ok(png)
+}
@@ -0,0 +1,1282 @@
+//! Supervision state synced with the remote server.
+//!
+//! A supervisor's view of each supervised device comes from the server's
+//! device status endpoint. Fetched statuses are cached locally: a recent entry
+//! is served without another request, and the last known one is shown when
+//! the server can't be reached.
+//!
+//! A scheduled job also refreshes the supervised devices and the incoming
+//! requests every few minutes, saves them as a snapshot for offline viewing
+//! and emits `supervision-synced` so the frontend updates on its own. The same
+//! pass marks supervision requests that were left pending too long as expired,
+//! locally and on the server, rejects requests from blocked devices, and ends
+//! temporary relationships whose `valid_until` has passed, notifying the user
+//! of each. Removals that couldn't reach the server are retried, and
+//! relationships the other side removed are dropped, so both devices converge.
+//!
+//! Devices in the same room can pair by QR code instead of typing ids: the
+//! supervised device shows its id and a short-lived server-issued token, and
+//! the supervisor scans it to establish the relationship directly. Over the
+//! phone, the supervised device can instead share a six-character invitation
+//! code that the supervisor types in to send a request.
+//!
+//! A supervisor replacing their phone can hand each relationship over with a
+//! transfer code instead: the new device redeems it and takes the old one's
+//! place, and the supervised device picks up the new supervisor on its next
+//! sync without accepting a fresh request.
+//!
+//! An IMEI can be guessed or read over someone's shoulder, so it only ever
+//! leads to an ordinary pending request. The request remembers how the target
+//! was found, the target is told, and accepting an IMEI request requires the
+//! user to confirm having seen that.
+
+use std::time::Duration;
+
+use chrono::DateTime;
+use rust_i18n::t;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::alerts;
+use crate::api_client::{
+    accept_supervision_request_api, accept_transfer_api, create_invite_code_api,
+    create_pairing_token, expire_supervision_request_api, get_device_statuses,
+    get_pending_requests, get_supervision_list, initiate_transfer_api, pair_with_token,
+    redeem_invite_code_api, reject_supervision_request_api, remove_supervision_relationship_api,
+    search_devices,
+};
+use crate::changes;
+use crate::clock::{self, Clock, SharedClock};
+use crate::crypto;
+use crate::dashboard;
+use crate::deadline::signin_day;
+use crate::delta_sync;
+use crate::dispatch::{self, EventNotification};
+use crate::encouragements;
+use crate::error::AppError;
+use crate::models::{
+    AppSettings, DeviceConfig, DeviceStatus, NotificationEvent, NotificationMessage, PairingQr,
+    PartnerKeyStatus, PreferenceEvent, StreakPolicy, SupervisionHistoryEntry,
+    SupervisionHistoryKind, SupervisionRelationship, SupervisionRequest, SupervisionRequestStatus,
+    SupervisionSnapshot, WebhookEvent, WebhookPayload,
+};
+use crate::remote_models::{
+    self, InviteCode, PrivateStatus, RequestIdentifier, SharingPolicy, SupervisionRelation,
+    SupervisionRequest as RemoteSupervisionRequest, SupervisionStatus, TransferCode,
+};
+use crate::services::supervision_lapsed_message;
+use crate::state::AppState;
+use crate::storage;
+use crate::webhooks;
+use crate::wellness;
+
+/// How long a fetched status is served from the cache before asking the server again
+const STATUS_CACHE_TTL_SECS: i64 = 60;
+
+/// Scheme and path of the text encoded in pairing QR codes
+const PAIRING_URI: &str = "areuok://pair";
+
+/// Length of an invitation code
+const INVITE_CODE_LENGTH: usize = 6;
+
+/// Minutes an invitation code stays valid
+const INVITE_CODE_VALID_MINUTES: u32 = 15;
+
+/// Minutes a transfer code stays valid
+const TRANSFER_CODE_VALID_MINUTES: u32 = 30;
+
+/// Number of digits in an IMEI
+const IMEI_LENGTH: usize = 15;
+
+/// Maximum number of entries kept in the supervision history
+pub const SUPERVISION_HISTORY_LIMIT: usize = 500;
+
+/// Minutes between background syncs when the settings don't say otherwise
+const DEFAULT_SYNC_INTERVAL_MINUTES: u32 = 5;
+
+/// Delay until the next background sync, re-read every round so changes apply without a restart
+pub fn sync_interval() -> Duration {
+    let minutes = storage::load_settings()
+        .ok()
+        .and_then(|settings| settings.sync_interval_minutes)
+        .unwrap_or(DEFAULT_SYNC_INTERVAL_MINUTES)
+        .max(1);
+    Duration::from_secs(u64::from(minutes) * 60)
+}
+
+/// Refresh supervision state, save the snapshot and tell the frontend
+///
+/// Incoming requests from the previous snapshot are kept when the server can't be reached.
+pub async fn sync(app: &AppHandle) -> Result<SupervisionSnapshot, AppError> {
+    let clock = app.state::<SharedClock>();
+    let state = app.state::<AppState>();
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let previous = storage::load_supervision_snapshot().unwrap_or_else(|e| {
+        log::warn!("Failed to load supervision snapshot: {}", e);
+        SupervisionSnapshot::default()
+    });
+
+    expire_local_requests(&state, clock.as_ref())?;
+    expire_relationships(app).await?;
+    if let Err(e) = reconcile_relationships(app).await {
+        log::warn!("Failed to reconcile supervision relationships: {}", e);
+    }
+    let supervised_devices = supervised_devices(&state, clock.as_ref()).await?;
+    let pending_requests = match delta_sync::pending_requests(&config.device.device_id).await {
+        Ok(requests) => {
+            let requests = reject_blocked_requests(&config, requests).await;
+            let requests = expire_remote_requests(clock.as_ref(), requests).await;
+            announce_new_requests(app, &requests).await;
+            requests
+        }
+        Err(e) => {
+            log::warn!("Failed to sync pending supervision requests: {}", e);
+            previous.pending_requests
+        }
+    };
+
+    alerts::check_missed_day_thresholds(app, &supervised_devices).await;
+    if let Err(e) =
+        dashboard::refresh_signin_records(&state, clock.as_ref(), &supervised_devices).await
+    {
+        log::warn!("Failed to sync supervised sign-in records: {}", e);
+    }
+    if let Err(e) = encouragements::refresh_inbox(&state, &config.device.device_id).await {
+        log::warn!("Failed to sync encouragements: {}", e);
+    }
+    if let Err(e) = wellness::refresh_questions(&config.device.device_id).await {
+        log::warn!("Failed to sync wellness questions: {}", e);
+    }
+
+    let snapshot = SupervisionSnapshot {
+        synced_at: clock.now().to_rfc3339(),
+        supervised_devices,
+        pending_requests,
+    };
+    storage::save_supervision_snapshot(&snapshot).map_err(|e| {
+        log::error!("Failed to save supervision snapshot: {}", e);
+        e.to_string()
+    })?;
+    if let Err(e) = app.emit("supervision-synced", snapshot.clone()) {
+        log::warn!("Failed to emit supervision-synced event: {}", e);
+    }
+    if let Ok(config) = state.device_config() {
+        changes::supervision_updated(app, &config);
+    }
+    Ok(snapshot)
+}
+
+/// Mark pending local requests past their expiry as expired
+fn expire_local_requests(state: &AppState, clock: &dyn Clock) -> Result<(), AppError> {
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let now = clock.now();
+    let is_stale =
+        |r: &SupervisionRequest| r.status == SupervisionRequestStatus::Pending && r.is_expired(now);
+    if !config.supervision_requests.iter().any(is_stale) {
+        return Ok(());
+    }
+
+    let expired = state.update_device_config(|config| {
+        let mut expired = 0;
+        for request in config
+            .supervision_requests
+            .iter_mut()
+            .filter(|r| is_stale(r))
+        {
+            request.status = SupervisionRequestStatus::Expired;
+            expired += 1;
+        }
+        Ok(expired)
+    })?;
+    log::info!("Expired {} stale supervision requests", expired);
+    Ok(())
+}
+
+/// Remove temporary relationships that reached their end and tell the user
+async fn expire_relationships(app: &AppHandle) -> Result<(), AppError> {
+    let clock = app.state::<SharedClock>();
+    let state = app.state::<AppState>();
+    let now = clock.now();
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    if !config
+        .supervision_relationships
+        .iter()
+        .any(|r| r.has_lapsed(now))
+    {
+        return Ok(());
+    }
+
+    let (config, lapsed) = state.update_device_config(|config| {
+        let (lapsed, active): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut config.supervision_relationships)
+                .into_iter()
+                .partition(|r| r.has_lapsed(now));
+        config.supervision_relationships = active;
+        Ok((config.clone(), lapsed))
+    })?;
+    let my_device_id = config.device.device_id.clone();
+    for relationship in lapsed {
+        log::info!("Temporary supervision {} has ended", relationship.relationship_id);
+        record_removal(&config, &relationship, relationship.valid_until.clone());
+        let vars = vec![
+            ("name", relationship.supervised_device_name.clone()),
+            ("supervisor_name", relationship.supervisor_device_name.clone()),
+            ("supervisor_device_id", relationship.supervisor_device_id.clone()),
+            ("supervised_device_id", relationship.supervised_device_id.clone()),
+        ];
+        dispatch::notify(
+            app,
+            EventNotification {
+                event: PreferenceEvent::RelationshipRemoved,
+                message: supervision_lapsed_message(
+                    relationship.name_of(&relationship.supervisor_device_id, &my_device_id),
+                    relationship.name_of(&relationship.supervised_device_id, &my_device_id),
+                ),
+                email: None,
+                webhook: Some(WebhookPayload {
+                    event: WebhookEvent::SupervisionRemoved,
+                    vars: webhooks::template_vars(
+                        clock.as_ref(),
+                        WebhookEvent::SupervisionRemoved,
+                        vars,
+                    ),
+                }),
+                critical: false,
+            },
+        )
+        .await;
+    }
+    Ok(())
+}
+
+/// Reject incoming server requests from blocked devices and keep the rest
+async fn reject_blocked_requests(
+    config: &DeviceConfig,
+    requests: Vec<RemoteSupervisionRequest>,
+) -> Vec<RemoteSupervisionRequest> {
+    let mut pending = Vec::with_capacity(requests.len());
+    for request in requests {
+        if !config.blocked_device_ids.contains(&request.supervisor_id) {
+            pending.push(request);
+            continue;
+        }
+        log::info!("Rejecting supervision request from blocked device {}", request.supervisor_id);
+        if let Err(e) =
+            reject_supervision_request_api(&request.supervisor_id, &request.target_id).await
+        {
+            log::warn!("Failed to reject blocked supervision request: {}", e);
+        }
+    }
+    pending
+}
+
+/// Expire incoming server requests past their expiry and keep the rest
+async fn expire_remote_requests(
+    clock: &dyn Clock,
+    requests: Vec<RemoteSupervisionRequest>,
+) -> Vec<RemoteSupervisionRequest> {
+    let now = clock.now();
+    let mut pending = Vec::with_capacity(requests.len());
+    for request in requests {
+        let expired = request
+            .expires_at
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .is_some_and(|t| now >= t.to_utc());
+        if !expired {
+            pending.push(request);
+            continue;
+        }
+        log::info!("Supervision request {} has expired", request.request_id);
+        if let Err(e) = expire_supervision_request_api(&request.request_id).await {
+            log::warn!("Failed to expire supervision request on the server: {}", e);
+        }
+    }
+    pending
+}
+
+/// Status of every device supervised by this device
+pub async fn supervised_devices(
+    state: &AppState,
+    clock: &dyn Clock,
+) -> Result<Vec<DeviceStatus>, AppError> {
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let mut cache = storage::load_device_status_cache().unwrap_or_else(|e| {
+        log::warn!("Failed to load device status cache: {}", e);
+        vec![]
+    });
+
+    let relationships: Vec<&SupervisionRelationship> = config
+        .supervision_relationships
+        .iter()
+        .filter(|r| r.supervisor_device_id == config.device.device_id)
+        .collect();
+    let settings = storage::load_settings().unwrap_or_else(|e| {
+        log::warn!("Failed to load app settings: {}", e);
+        AppSettings::default()
+    });
+    let today = signin_day(clock, &settings).format("%Y-%m-%d").to_string();
+    let mut statuses = Vec::with_capacity(relationships.len());
+    let mut synced = vec![];
+
+    let stale: Vec<String> = relationships
+        .iter()
+        .map(|r| &r.supervised_device_id)
+        .filter(|id| {
+            !cache
+                .iter()
+                .any(|s| &&s.device_id == id && is_fresh(s, clock))
+        })
+        .cloned()
+        .collect();
+    let mut fetched = get_device_statuses(&stale).await.unwrap_or_else(|e| {
+        log::warn!("Failed to sync status of supervised devices: {}", e);
+        vec![]
+    });
+
+    for relationship in &relationships {
+        let device_id = &relationship.supervised_device_id;
+        let cached = cache.iter().find(|s| &s.device_id == device_id).cloned();
+        if !stale.contains(device_id) {
+            statuses.extend(cached);
+            continue;
+        }
+
+        match fetched.iter().position(|s| &s.device_id == device_id) {
+            Some(index) => {
+                let status = device_status(
+                    fetched.swap_remove(index),
+                    relationship.partner_public_key.as_deref(),
+                    &today,
+                    clock.now().to_rfc3339(),
+                );
+                synced.push((device_id.clone(), status.last_sync_at.clone()));
+                cache.retain(|s| &s.device_id != device_id);
+                cache.push(status.clone());
+                statuses.push(status);
+            }
+            None => {
+                log::warn!("No fresh status for device {}", device_id);
+                statuses.push(cached.unwrap_or_else(|| unsynced_status(relationship)));
+            }
+        }
+    }
+
+    if !synced.is_empty() {
+        cache.retain(|s| {
+            relationships
+                .iter()
+                .any(|r| r.supervised_device_id == s.device_id)
+        });
+        if let Err(e) = storage::save_device_status_cache(&cache) {
+            log::warn!("Failed to save device status cache: {}", e);
+        }
+        record_sync_times(state, &synced)?;
+    }
+
+    for status in &mut statuses {
+        if let Some(relationship) = relationships
+            .iter()
+            .find(|r| r.supervised_device_id == status.device_id)
+        {
+            status.device_name = relationship
+                .name_of(&status.device_id, &config.device.device_id)
+                .to_string();
+        }
+    }
+
+    log::info!(
+        "Found {} supervised devices ({} synced from server)",
+        statuses.len(),
+        synced.len()
+    );
+    Ok(statuses)
+}
+
+/// Notify the user once about each incoming request they haven't seen yet
+pub async fn announce_new_requests(app: &AppHandle, requests: &[RemoteSupervisionRequest]) {
+    let state = app.state::<AppState>();
+    let announced = match state.device_config() {
+        Ok(config) => config.announced_request_ids,
+        Err(e) => {
+            log::warn!("Failed to load device config: {}", e);
+            return;
+        }
+    };
+    if requests.iter().all(|r| announced.contains(&r.request_id)) {
+        return;
+    }
+
+    // Only still-pending ids are kept, so the list never grows unbounded
+    let pending_ids = requests.iter().map(|r| r.request_id.clone()).collect();
+    let announced = state
+        .update_device_config(|config| {
+            Ok(std::mem::replace(&mut config.announced_request_ids, pending_ids))
+        })
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to save announced supervision requests: {}", e);
+            announced
+        });
+
+    let new = requests
+        .iter()
+        .filter(|r| !announced.contains(&r.request_id));
+    for request in new {
+        let supervisor = request
+            .supervisor_name
+            .clone()
+            .unwrap_or_else(|| request.supervisor_id.clone());
+        log::info!("New supervision request from {}", supervisor);
+        record_history(
+            SupervisionHistoryKind::RequestReceived,
+            &request.supervisor_id,
+            request.supervisor_name.clone(),
+            Some(request.request_id.clone()),
+            request.message.clone(),
+        );
+        dispatch::notify(
+            app,
+            EventNotification {
+                event: PreferenceEvent::SupervisionRequestReceived,
+                message: NotificationMessage {
+                    event: NotificationEvent::Supervision,
+                    title: t!("supervision.request_title").into_owned(),
+                    body: request_body(&supervisor, request),
+                    quote: None,
+                },
+                email: None,
+                webhook: None,
+                critical: false,
+            },
+        )
+        .await;
+    }
+}
+
+/// Notification text for an incoming request, naming how the device was found
+fn request_body(supervisor: &str, request: &RemoteSupervisionRequest) -> String {
+    let body = match &request.message {
+        Some(message) => t!(
+            "supervision.request_body_with_message",
+            supervisor = supervisor,
+            message = message
+        ),
+        None => t!("supervision.request_body", supervisor = supervisor),
+    };
+    if request.identified_by == RequestIdentifier::Imei {
+        t!("supervision.found_by_imei", body = body).into_owned()
+    } else {
+        body.into_owned()
+    }
+}
+
+/// Check whether a cached status is recent enough to skip the server
+fn is_fresh(status: &DeviceStatus, clock: &dyn Clock) -> bool {
+    DateTime::parse_from_rfc3339(&status.last_sync_at)
+        .map(|synced_at| (clock.now() - synced_at.to_utc()).num_seconds() < STATUS_CACHE_TTL_SECS)
+        .unwrap_or(false)
+}
+
+/// Convert the server's view of a device into the status shown to supervisors
+///
+/// The server reports neither the device's streak policy nor its goal, so the
+/// default policy is assumed and no goal progress is shown. A sealed private
+/// part is opened with the device's key and takes the place of the plaintext.
+fn device_status(
+    mut remote: remote_models::DeviceStatus,
+    partner_key: Option<&str>,
+    today: &str,
+    synced_at: String,
+) -> DeviceStatus {
+    if let (Some(sealed), Some(key)) = (&remote.sealed, partner_key) {
+        match crypto::open::<PrivateStatus>(key, sealed) {
+            Ok(private) => {
+                remote.mood = private.mood;
+                remote.wellness_answers = private.wellness_answers;
+            }
+            Err(e) => log::warn!("Failed to open status of {}: {}", remote.device_id, e),
+        }
+    }
+
+    let last_signin_date = remote
+        .last_signin
+        .as_deref()
+        .map(|date| date.get(..10).unwrap_or(date).to_string())
+        .unwrap_or_default();
+
+    DeviceStatus {
+        device_id: remote.device_id,
+        device_name: remote.device_name,
+        is_signed_in_today: last_signin_date == today,
+        last_signin_date,
+        streak: remote.streak,
+        last_sync_at: synced_at,
+        streak_policy: StreakPolicy::default(),
+        goal_progress: None,
+        wellness_answers: remote.wellness_answers,
+        mood: remote.mood,
+        recent_signins: remote.recent_signins,
+        last_seen_at: remote.last_seen_at,
+        paused_until: remote.paused_until,
+    }
+}
+
+/// Placeholder status for a device that has never been synced
+fn unsynced_status(relationship: &SupervisionRelationship) -> DeviceStatus {
+    DeviceStatus {
+        device_id: relationship.supervised_device_id.clone(),
+        device_name: relationship
+            .name_of(&relationship.supervised_device_id, &relationship.supervisor_device_id)
+            .to_string(),
+        last_signin_date: String::new(),
+        streak: 0,
+        is_signed_in_today: false,
+        last_sync_at: relationship.last_sync_at.clone(),
+        streak_policy: StreakPolicy::default(),
+        goal_progress: None,
+        wellness_answers: vec![],
+        mood: None,
+        recent_signins: None,
+        last_seen_at: None,
+        paused_until: None,
+    }
+}
+
+/// Store the latest successful sync time on each relationship
+///
+/// The change is applied to the current config, as requests may have been
+/// accepted or removed while the statuses were being fetched.
+fn record_sync_times(state: &AppState, synced: &[(String, String)]) -> Result<(), AppError> {
+    state.update_device_config(|config| {
+        let device_id = config.device.device_id.clone();
+        for relationship in config
+            .supervision_relationships
+            .iter_mut()
+            .filter(|r| r.supervisor_device_id == device_id)
+        {
+            if let Some((_, synced_at)) = synced
+                .iter()
+                .find(|(id, _)| *id == relationship.supervised_device_id)
+            {
+                relationship.last_sync_at = synced_at.clone();
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Create a pairing code for this device
+pub async fn generate_pairing_qr(state: &AppState) -> Result<PairingQr, AppError> {
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let device_id = &config.device.device_id;
+    let public_key = crypto::shareable_public_key();
+    let pairing = create_pairing_token(device_id, public_key.as_deref()).await?;
+
+    let mut payload = format!(
+        "{}?device_id={}&token={}",
+        PAIRING_URI,
+        urlencoding::encode(device_id),
+        urlencoding::encode(&pairing.token)
+    );
+    if let Some(key) = &public_key {
+        payload.push_str("&key=");
+        payload.push_str(&urlencoding::encode(&crypto::key_fingerprint(key)));
+    }
+    Ok(PairingQr {
+        payload,
+        expires_at: pairing.expires_at,
+    })
+}
+
+/// Start supervising the device whose pairing code was scanned
+pub async fn pair_from_qr(app: &AppHandle, payload: &str) -> Result<SupervisionRelation, AppError> {
+    let pairing = parse_pairing_payload(payload)?;
+    let (target_id, token) = (&pairing.device_id, &pairing.token);
+    let state = app.state::<AppState>();
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    if *target_id == config.device.device_id {
+        return Err(t!("error.pair_with_self").into());
+    }
+
+    let public_key = crypto::shareable_public_key();
+    let relation =
+        pair_with_token(&config.device.device_id, target_id, token, public_key.as_deref()).await?;
+    if let Some(fingerprint) = &pairing.key_fingerprint {
+        let reported = relation
+            .target_public_key
+            .as_deref()
+            .map(crypto::key_fingerprint);
+        if reported.as_ref() != Some(fingerprint) {
+            log::error!("Key reported for {} does not match the pairing code", target_id);
+            if let Err(e) = remove_supervision_relationship_api(&relation.relation_id).await {
+                log::warn!("Failed to undo pairing with mismatched key: {}", e);
+            }
+            return Err(t!("error.pairing_key_mismatch").into());
+        }
+    }
+    if let Err(e) = sync(app).await {
+        log::warn!("Failed to sync supervision state after pairing: {}", e);
+    }
+    Ok(relation)
+}
+
+/// Contents of a pairing code
+pub struct PairingPayload {
+    pub device_id: String,
+    pub token: String,
+    /// Fingerprint of the showing device's encryption key, if it has one
+    pub key_fingerprint: Option<String>,
+}
+
+/// Extract the device id, token and key fingerprint from a scanned pairing code
+pub fn parse_pairing_payload(payload: &str) -> Result<PairingPayload, AppError> {
+    let query = payload
+        .trim()
+        .strip_prefix(PAIRING_URI)
+        .and_then(|rest| rest.strip_prefix('?'))
+        .ok_or_else(|| t!("error.not_pairing_code").into_owned())?;
+
+    let mut device_id = None;
+    let mut token = None;
+    let mut key_fingerprint = None;
+    for pair in query.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = urlencoding::decode(value)
+            .map_err(|e| t!("error.invalid_pairing_code", error = e))?
+            .into_owned();
+        match key {
+            "device_id" => device_id = Some(value),
+            "token" => token = Some(value),
+            "key" => key_fingerprint = Some(value).filter(|v| !v.is_empty()),
+            _ => {}
+        }
+    }
+
+    match (device_id, token) {
+        (Some(device_id), Some(token)) if !device_id.is_empty() && !token.is_empty() => {
+            Ok(PairingPayload {
+                device_id,
+                token,
+                key_fingerprint,
+            })
+        }
+        _ => Err(t!("error.pairing_code_incomplete").into()),
+    }
+}
+
+/// Create an invitation code for this device
+pub async fn create_invite_code(state: &AppState) -> Result<InviteCode, AppError> {
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    create_invite_code_api(&config.device.device_id, INVITE_CODE_VALID_MINUTES).await
+}
+
+/// Request supervision of the device that issued `code`
+///
+/// Codes are read out over the phone, so case, spaces and dashes are ignored.
+pub async fn redeem_invite_code(
+    state: &AppState,
+    code: &str,
+) -> Result<RemoteSupervisionRequest, AppError> {
+    let code = normalize_code(code);
+    if code.len() != INVITE_CODE_LENGTH || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(t!("error.invite_code_format", length = INVITE_CODE_LENGTH).into());
+    }
+
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let public_key = crypto::shareable_public_key();
+    redeem_invite_code_api(&config.device.device_id, &code, public_key.as_deref()).await
+}
+
+/// Find the device a supervision request targets, and how it was found
+///
+/// A target that is a well-formed IMEI is looked up on the server; anything
+/// else is taken as a device ID. The identifier comes from this lookup, never
+/// from the caller, so a request can't hide that it found the device by IMEI.
+pub async fn resolve_target(target: &str) -> Result<(String, RequestIdentifier), AppError> {
+    if is_valid_imei(&normalize_code(target)) {
+        Ok((resolve_imei(target).await?, RequestIdentifier::Imei))
+    } else {
+        Ok((target.trim().to_string(), RequestIdentifier::DeviceId))
+    }
+}
+
+/// Find the registered device an IMEI belongs to
+pub async fn resolve_imei(imei: &str) -> Result<String, AppError> {
+    let imei = normalize_code(imei);
+    if !is_valid_imei(&imei) {
+        return Err(t!("error.imei_format", length = IMEI_LENGTH).into());
+    }
+    let mut matches = search_devices(&imei)
+        .await?
+        .into_iter()
+        .filter(|d| d.imei.as_deref() == Some(imei.as_str()));
+    match (matches.next(), matches.next()) {
+        (Some(device), None) => Ok(device.device_id),
+        (Some(_), Some(_)) => {
+            log::warn!("IMEI matches several devices");
+            Err(t!("error.imei_ambiguous").into())
+        }
+        (None, _) => Err(t!("error.imei_not_found").into()),
+    }
+}
+
+/// Check the IMEI format and its Luhn check digit
+fn is_valid_imei(imei: &str) -> bool {
+    if imei.len() != IMEI_LENGTH || !imei.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+    let sum: u32 = imei
+        .bytes()
+        .rev()
+        .map(|b| u32::from(b - b'0'))
+        .enumerate()
+        .map(|(i, d)| match (i % 2, d * 2) {
+            (1, doubled) if doubled > 9 => doubled - 9,
+            (1, doubled) => doubled,
+            _ => d,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Make sure the user confirmed how the supervisor found this device
+///
+/// Requests sent by IMEI can only be accepted after the user saw that the
+/// supervisor used it, which the frontend confirms by echoing the identifier.
+pub fn confirm_identifier(
+    identified_by: RequestIdentifier,
+    confirmed: Option<RequestIdentifier>,
+) -> Result<(), AppError> {
+    if identified_by == RequestIdentifier::Imei && confirmed != Some(RequestIdentifier::Imei) {
+        log::warn!("IMEI supervision request accepted without confirmation");
+        return Err(t!("error.imei_unconfirmed").into());
+    }
+    Ok(())
+}
+
+/// Accept a pending request the server holds for this device
+pub async fn accept_remote_request(
+    state: &AppState,
+    supervisor_id: &str,
+    target_id: &str,
+    confirmed: Option<RequestIdentifier>,
+) -> Result<(), AppError> {
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    if target_id != config.device.device_id {
+        log::warn!("Refusing to accept a request for another device ({})", target_id);
+        return Err(t!("error.request_not_for_device").into());
+    }
+    let request = get_pending_requests(target_id)
+        .await?
+        .into_iter()
+        .find(|r| r.supervisor_id == supervisor_id && r.status == SupervisionStatus::Pending)
+        .ok_or_else(|| {
+            log::warn!("No pending request from {}", supervisor_id);
+            t!("error.no_pending_request").into_owned()
+        })?;
+    confirm_identifier(request.identified_by, confirmed)?;
+
+    let public_key = crypto::shareable_public_key();
+    accept_supervision_request_api(
+        supervisor_id,
+        target_id,
+        &request.request_id,
+        request.identified_by,
+        public_key.as_deref(),
+    )
+    .await
+}
+
+/// Strip the spaces and dashes people add when reading a code out and uppercase it
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Issue a code that hands a relationship this device supervises to another device
+pub async fn initiate_transfer(
+    state: &AppState,
+    relationship_id: &str,
+) -> Result<TransferCode, AppError> {
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let relationship = config
+        .supervision_relationships
+        .iter()
+        .find(|r| {
+            r.relationship_id == relationship_id
+                && r.supervisor_device_id == config.device.device_id
+        })
+        .ok_or_else(|| {
+            log::warn!("Supervising relationship {} not found", relationship_id);
+            t!("error.relationship_not_found").into_owned()
+        })?;
+    let relation_id = remote_relation_id(&config, relationship)
+        .await?
+        .ok_or_else(|| t!("error.relationship_not_on_server").into_owned())?;
+
+    initiate_transfer_api(&relation_id, &config.device.device_id, TRANSFER_CODE_VALID_MINUTES).await
+}
+
+/// Take over a relationship from another supervisor device with its transfer code
+pub async fn accept_transfer(
+    app: &AppHandle,
+    code: &str,
+) -> Result<SupervisionRelationship, AppError> {
+    let code = normalize_code(code);
+    if code.is_empty() || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(t!("error.transfer_code_format").into());
+    }
+
+    let state = app.state::<AppState>();
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    if !config.device.mode.can_supervise() {
+        return Err(t!("error.supervisor_only_transfer").into());
+    }
+    let public_key = crypto::shareable_public_key();
+    let relation =
+        accept_transfer_api(&config.device.device_id, &code, public_key.as_deref()).await?;
+    log::info!("Took over supervision of {} via transfer", relation.target_id);
+
+    let now = app.state::<SharedClock>().now().to_rfc3339();
+    let relationship = SupervisionRelationship {
+        relationship_id: relation.relation_id.clone(),
+        supervisor_device_id: config.device.device_id.clone(),
+        supervisor_device_name: config.device.device_name.clone(),
+        supervised_device_id: relation.target_id.clone(),
+        supervised_device_name: relation
+            .target_name
+            .clone()
+            .unwrap_or_else(|| relation.target_id.clone()),
+        established_at: relation.created_at.clone(),
+        last_sync_at: now,
+        muted: false,
+        wellness_questions: vec![],
+        sharing_policy: SharingPolicy::default(),
+        missed_days_threshold: None,
+        valid_until: relation.valid_until.clone(),
+        remote_relation_id: Some(relation.relation_id.clone()),
+        nickname: None,
+        partner_public_key: relation.target_public_key.clone(),
+        offered_partner_key: None,
+        partner_key_verified: false,
+    };
+    state.update_device_config(|config| {
+        config
+            .supervision_relationships
+            .retain(|r| !is_same_relationship(r, &relation));
+        config.supervision_relationships.push(relationship.clone());
+        Ok(())
+    })?;
+
+    if let Err(e) = sync(app).await {
+        log::warn!("Failed to sync supervision state after transfer: {}", e);
+    }
+    Ok(relationship)
+}
+
+/// Server ID of a relationship, looked up by its two devices when not yet known
+async fn remote_relation_id(
+    config: &DeviceConfig,
+    relationship: &SupervisionRelationship,
+) -> Result<Option<String>, AppError> {
+    if let Some(relation_id) = &relationship.remote_relation_id {
+        return Ok(Some(relation_id.clone()));
+    }
+    Ok(get_supervision_list(&config.device.device_id)
+        .await?
+        .into_iter()
+        .find(|r| is_same_relationship(relationship, r))
+        .map(|r| r.relation_id))
+}
+
+/// Delete a relationship on the server, finding it by its two devices
+///
+/// Relationships the server doesn't know about count as removed.
+pub async fn remove_remote_relationship(
+    config: &DeviceConfig,
+    relationship: &SupervisionRelationship,
+) -> Result<(), AppError> {
+    match remote_relation_id(config, relationship).await? {
+        Some(relation_id) => remove_supervision_relationship_api(&relation_id).await,
+        None => Ok(()),
+    }
+}
+
+/// Retry pending removals and drop relationships the other side removed
+///
+/// Only relationships that were seen on the server before are dropped, so
+/// relationships that never reached the server are left alone.
+async fn reconcile_relationships(app: &AppHandle) -> Result<(), AppError> {
+    let state = app.state::<AppState>();
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+
+    let mut done = vec![];
+    for relationship in &config.pending_removals {
+        match remove_remote_relationship(&config, relationship).await {
+            Ok(()) => done.push(relationship.relationship_id.clone()),
+            Err(e) => {
+                log::warn!("Failed to remove relationship {}: {}", relationship.relationship_id, e)
+            }
+        }
+    }
+    if !done.is_empty() {
+        state.update_device_config(|config| {
+            config
+                .pending_removals
+                .retain(|r| !done.contains(&r.relationship_id));
+            Ok(())
+        })?;
+    }
+
+    let relations = delta_sync::supervision_list(&config.device.device_id).await?;
+    let (config, removed, key_changed) = state.update_device_config(|config| {
+        let (removed, key_changed) = apply_relations(config, &relations);
+        Ok((config.clone(), removed, key_changed))
+    })?;
+    for relationship in removed {
+        log::info!("Relationship {} was removed remotely", relationship.relationship_id);
+        record_removal(&config, &relationship, None);
+        notify_relationship_removed(app, &config, relationship);
+    }
+    for relationship in key_changed {
+        announce_key_change(app, &config, &relationship);
+    }
+    Ok(())
+}
+
+/// Bring the relationships in line with the server's relations
+///
+/// Returns the relationships the other side removed and those whose partner
+/// now offers a different key.
+fn apply_relations(
+    config: &mut DeviceConfig,
+    relations: &[SupervisionRelation],
+) -> (Vec<SupervisionRelationship>, Vec<SupervisionRelationship>) {
+    let my_device_id = config.device.device_id.clone();
+    let mut removed = vec![];
+    let mut key_changed = vec![];
+    config.supervision_relationships.retain_mut(|relationship| {
+        let known = relations
+            .iter()
+            .find(|r| relationship.remote_relation_id.as_ref() == Some(&r.relation_id))
+            .or_else(|| {
+                relations
+                    .iter()
+                    .find(|r| is_same_relationship(relationship, r))
+            });
+        match known {
+            Some(relation) => {
+                relationship.remote_relation_id = Some(relation.relation_id.clone());
+                // A transferred relationship keeps its ID but has a new supervisor
+                if relationship.supervised_device_id == my_device_id
+                    && relation.supervisor_id != relationship.supervisor_device_id
+                {
+                    log::info!(
+                        "Relationship {} was transferred to {}",
+                        relationship.relationship_id,
+                        relation.supervisor_id
+                    );
+                    relationship.supervisor_device_id = relation.supervisor_id.clone();
+                    relationship.supervisor_device_name = relation
+                        .supervisor_name
+                        .clone()
+                        .unwrap_or_else(|| relation.supervisor_id.clone());
+                    relationship.nickname = None;
+                }
+                let partner_key = if relationship.supervised_device_id == my_device_id {
+                    &relation.supervisor_public_key
+                } else {
+                    &relation.target_public_key
+                };
+                if let Some(key) = partner_key {
+                    if pin_partner_key(relationship, key) {
+                        key_changed.push(relationship.clone());
+                    }
+                }
+                true
+            }
+            None if relationship.remote_relation_id.is_some() => {
+                removed.push(relationship.clone());
+                false
+            }
+            None => true,
+        }
+    });
+    (removed, key_changed)
+}
+
+/// Pin the first key the server reports for the other device of a relationship
+///
+/// A key differing from the pinned one is only kept as offered, so the server
+/// can't swap in a key of its own. Returns whether that offer is new.
+fn pin_partner_key(relationship: &mut SupervisionRelationship, key: &str) -> bool {
+    match relationship.partner_public_key.as_deref() {
+        None => {
+            relationship.partner_public_key = Some(key.to_string());
+            false
+        }
+        Some(pinned) if pinned == key => {
+            relationship.offered_partner_key = None;
+            false
+        }
+        Some(_) if relationship.offered_partner_key.as_deref() == Some(key) => false,
+        Some(_) => {
+            log::warn!(
+                "Server reported a new encryption key for relationship {}, keeping the pinned one",
+                relationship.relationship_id
+            );
+            relationship.offered_partner_key = Some(key.to_string());
+            true
+        }
+    }
+}
+
+/// Tell the user the partner of a relationship presents a different key
+fn announce_key_change(
+    app: &AppHandle,
+    config: &DeviceConfig,
+    relationship: &SupervisionRelationship,
+) {
+    let my_device_id = &config.device.device_id;
+    let partner_id = if &relationship.supervisor_device_id == my_device_id {
+        &relationship.supervised_device_id
+    } else {
+        &relationship.supervisor_device_id
+    };
+    let name = relationship.name_of(partner_id, my_device_id).to_string();
+    record_history(
+        SupervisionHistoryKind::PartnerKeyChanged,
+        partner_id,
+        Some(name.clone()),
+        Some(relationship.relationship_id.clone()),
+        None,
+    );
+    let message = NotificationMessage {
+        event: NotificationEvent::Supervision,
+        title: t!("supervision.key_changed_title").into_owned(),
+        body: t!("supervision.key_changed_body", name = name).into_owned(),
+        quote: None,
+    };
+    dispatch::show_local(app, message, false);
+}
+
+/// Safety number and key state of a relationship, for comparing with the partner
+pub fn partner_key_status(
+    state: &AppState,
+    relationship_id: &str,
+) -> Result<PartnerKeyStatus, AppError> {
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let relationship = config
+        .supervision_relationships
+        .iter()
+        .find(|r| r.relationship_id == relationship_id)
+        .ok_or_else(|| t!("error.relationship_not_found").into_owned())?;
+    Ok(PartnerKeyStatus {
+        safety_number: relationship
+            .partner_public_key
+            .as_deref()
+            .map(crypto::safety_number)
+            .transpose()?,
+        verified: relationship.partner_key_verified,
+        offered_safety_number: relationship
+            .offered_partner_key
+            .as_deref()
+            .map(crypto::safety_number)
+            .transpose()?,
+    })
+}
+
+/// Mark the partner's key as verified after the users compared safety numbers
+///
+/// `safety_number` is the number the user compared. When it belongs to a key
+/// the server offered in place of the pinned one, that key is pinned instead.
+pub fn confirm_partner_key(
+    state: &AppState,
+    relationship_id: &str,
+    safety_number: &str,
+) -> Result<(), AppError> {
+    let matches = |key: &Option<String>| -> Result<bool, AppError> {
+        match key.as_deref() {
+            Some(key) => Ok(crypto::safety_number(key)? == safety_number.trim()),
+            None => Ok(false),
+        }
+    };
+    state.update_device_config(|config| {
+        let relationship = config
+            .supervision_relationships
+            .iter_mut()
+            .find(|r| r.relationship_id == relationship_id)
+            .ok_or_else(|| t!("error.relationship_not_found").into_owned())?;
+        if matches(&relationship.offered_partner_key)? {
+            log::info!("Accepting new partner key for relationship {}", relationship_id);
+            relationship.partner_public_key = relationship.offered_partner_key.take();
+        } else if !matches(&relationship.partner_public_key)? {
+            log::warn!("Safety number mismatch for relationship {}", relationship_id);
+            return Err(t!("error.safety_number_mismatch").into());
+        }
+        relationship.offered_partner_key = None;
+        relationship.partner_key_verified = true;
+        Ok(())
+    })
+}
+
+/// Check whether a server relation links the same two devices
+fn is_same_relationship(
+    relationship: &SupervisionRelationship,
+    relation: &SupervisionRelation,
+) -> bool {
+    relation.supervisor_id == relationship.supervisor_device_id
+        && relation.target_id == relationship.supervised_device_id
+}
+
+/// Record the end of a relationship in the history of the partner device
+pub fn record_removal(
+    config: &DeviceConfig,
+    relationship: &SupervisionRelationship,
+    note: Option<String>,
+) {
+    let my_device_id = &config.device.device_id;
+    let partner_id = if &relationship.supervisor_device_id == my_device_id {
+        &relationship.supervised_device_id
+    } else {
+        &relationship.supervisor_device_id
+    };
+    record_history(
+        SupervisionHistoryKind::RelationshipRemoved,
+        partner_id,
+        Some(relationship.name_of(partner_id, my_device_id).to_string()),
+        Some(relationship.relationship_id.clone()),
+        note,
+    );
+}
+
+/// Announce a removed relationship in the background
+pub fn notify_relationship_removed(
+    app: &AppHandle,
+    config: &DeviceConfig,
+    removed: SupervisionRelationship,
+) {
+    let my_device_id = &config.device.device_id;
+    let message = NotificationMessage {
+        event: NotificationEvent::Supervision,
+        title: t!("supervision.removed_title").into_owned(),
+        body: t!(
+            "supervision.removed_body",
+            supervisor = removed.name_of(&removed.supervisor_device_id, my_device_id),
+            supervised = removed.name_of(&removed.supervised_device_id, my_device_id)
+        )
+        .into_owned(),
+        quote: None,
+    };
+    let clock = app.state::<SharedClock>();
+    let vars = webhooks::template_vars(
+        clock.as_ref(),
+        WebhookEvent::SupervisionRemoved,
+        vec![
+            ("name", removed.supervised_device_name),
+            ("supervisor_name", removed.supervisor_device_name),
+            ("supervisor_device_id", removed.supervisor_device_id),
+            ("supervised_device_id", removed.supervised_device_id),
+        ],
+    );
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        dispatch::notify(
+            &app,
+            EventNotification {
+                event: PreferenceEvent::RelationshipRemoved,
+                message,
+                email: None,
+                webhook: Some(WebhookPayload {
+                    event: WebhookEvent::SupervisionRemoved,
+                    vars,
+                }),
+                critical: false,
+            },
+        )
+        .await;
+    });
+}
+
+/// Nickname this device's user gave a supervision partner, if any
+pub fn partner_nickname(state: &AppState, device_id: &str) -> Option<String> {
+    let config = state.device_config().ok()?;
+    config
+        .supervision_relationships
+        .into_iter()
+        .find(|r| r.supervisor_device_id == device_id || r.supervised_device_id == device_id)
+        .and_then(|r| r.nickname)
+}
+
+/// Append an interaction with a supervision partner to the history
+pub fn record_history(
+    kind: SupervisionHistoryKind,
+    device_id: &str,
+    device_name: Option<String>,
+    reference_id: Option<String>,
+    note: Option<String>,
+) {
+    let mut history = match storage::load_supervision_history() {
+        Ok(history) => history,
+        Err(e) => {
+            log::warn!("Failed to load supervision history: {}", e);
+            return;
+        }
+    };
+
+    history.push(SupervisionHistoryEntry {
+        timestamp: clock::shared().now().to_rfc3339(),
+        kind,
+        device_id: device_id.to_string(),
+        device_name,
+        reference_id,
+        note,
+    });
+    let excess = history.len().saturating_sub(SUPERVISION_HISTORY_LIMIT);
+    history.drain(..excess);
+
+    if let Err(e) = storage::save_supervision_history(&history) {
+        log::warn!("Failed to save supervision history: {}", e);
+    }
+}
@@ -0,0 +1,190 @@
+//! Queue of server calls made while offline.
+//!
+//! Sign-ins and answers to supervision requests shouldn't fail just because
+//! the phone has no connection. When the server can't be reached, the call is
+//! stored here instead and replayed in order once connectivity returns: when
+//! the server event stream reconnects, and from a scheduled job that retries
+//! every little while as long as the queue isn't empty.
+//!
+//! Each replay first checks that the call still applies. A sign-in the server
+//! already has for that day, or an answer to a request that is no longer
+//! pending, is dropped and reported as a `sync-conflict` event instead.
+
+use chrono::{DateTime, NaiveDate};
+use rust_i18n::t;
+use tauri::async_runtime::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use uuid::Uuid;
+
+use crate::api_client::{
+    device_signin, get_pending_requests, get_signin_records_api, reject_supervision_request_api,
+    send_sos_api,
+};
+use crate::clock::{Clock, SharedClock};
+use crate::deadline::{configured_offset, signin_day};
+use crate::error::AppError;
+use crate::models::{PendingOperation, PendingSyncItem, SyncConflict};
+use crate::remote_models::SupervisionStatus;
+use crate::state::AppState;
+use crate::storage;
+use crate::supervision;
+
+/// Serializes replays so the watcher and a reconnect never send an item twice
+static REPLAY_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Queue a call that couldn't reach the server
+///
+/// A call identical to one already queued is not queued again.
+pub fn enqueue(
+    clock: &dyn Clock,
+    operation: PendingOperation,
+    error: &AppError,
+) -> Result<(), AppError> {
+    let mut queue = storage::load_sync_queue().map_err(|e| {
+        log::error!("Failed to load sync queue: {}", e);
+        e.to_string()
+    })?;
+    if queue.iter().any(|item| item.operation == operation) {
+        log::info!("Identical call already queued, not queuing again");
+        return Ok(());
+    }
+
+    queue.push(PendingSyncItem {
+        id: Uuid::new_v4().to_string(),
+        operation,
+        queued_at: clock.now().to_rfc3339(),
+        attempts: 0,
+        last_error: Some(error.to_string()),
+    });
+    log::info!("Queued call for replay ({} items in sync queue)", queue.len());
+    storage::save_sync_queue(&queue).map_err(|e| {
+        log::error!("Failed to save sync queue: {}", e);
+        e.to_string().into()
+    })
+}
+
+/// Replay queued calls in order, stopping at the first one that can't get through
+pub async fn replay(app: &AppHandle) -> Result<(), AppError> {
+    let _guard = REPLAY_LOCK.lock().await;
+    let clock = app.state::<SharedClock>();
+
+    loop {
+        let Some(item) = storage::load_sync_queue()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .next()
+        else {
+            return Ok(());
+        };
+        let result = execute(&app.state::<AppState>(), clock.as_ref(), &item.operation).await;
+
+        // Reload so calls queued while we were sending are kept
+        let mut queue = storage::load_sync_queue().map_err(|e| e.to_string())?;
+        let Some(position) = queue.iter().position(|i| i.id == item.id) else {
+            continue;
+        };
+        match result {
+            Ok(()) => {
+                log::info!("Replayed queued call {}", item.id);
+                queue.remove(position);
+            }
+            Err(e) if e.is_unreachable() => {
+                let entry = &mut queue[position];
+                entry.attempts += 1;
+                entry.last_error = Some(e.to_string());
+                storage::save_sync_queue(&queue).map_err(|e| e.to_string())?;
+                log::debug!("Server still unreachable, keeping {} queued calls", queue.len());
+                return Ok(());
+            }
+            Err(AppError::RateLimited { retry_after_secs }) => {
+                log::debug!(
+                    "Server is rate limiting, keeping {} queued calls for {}s",
+                    queue.len(),
+                    retry_after_secs
+                );
+                return Ok(());
+            }
+            Err(reason) => {
+                log::warn!("Dropping queued call {}: {}", item.id, reason);
+                let item = queue.remove(position);
+                if let Err(e) = app.emit("sync-conflict", SyncConflict { item, reason }) {
+                    log::warn!("Failed to emit sync conflict: {}", e);
+                }
+            }
+        }
+        storage::save_sync_queue(&queue).map_err(|e| e.to_string())?;
+    }
+}
+
+/// Calls still waiting to reach the server, oldest first
+pub fn pending_items() -> Result<Vec<PendingSyncItem>, AppError> {
+    storage::load_sync_queue().map_err(|e| {
+        log::error!("Failed to load sync queue: {}", e);
+        e.to_string().into()
+    })
+}
+
+/// Check that a queued call still applies and send it
+async fn execute(
+    state: &AppState,
+    clock: &dyn Clock,
+    operation: &PendingOperation,
+) -> Result<(), AppError> {
+    match operation {
+        PendingOperation::Signin {
+            device_id,
+            signed_at,
+            day,
+        } => {
+            let settings = storage::load_settings().map_err(|e| e.to_string())?;
+            let date = match day {
+                Some(day) => NaiveDate::parse_from_str(day, "%Y-%m-%d")
+                    .map_err(|e| AppError::from(t!("error.invalid_date", error = e)))?,
+                None => {
+                    let at = DateTime::parse_from_rfc3339(signed_at)
+                        .map_err(|e| AppError::from(t!("error.invalid_signin_time", error = e)))?
+                        .to_utc();
+                    at.with_timezone(&configured_offset(&settings, at))
+                        .date_naive()
+                }
+            };
+            let days = (signin_day(clock, &settings) - date).num_days().max(0) + 1;
+            let recorded = get_signin_records_api(device_id, days as u32)
+                .await?
+                .iter()
+                .any(|r| r.date == date.format("%Y-%m-%d").to_string());
+            if recorded {
+                return Err(AppError::Conflict {
+                    message: t!("error.signin_exists", date = date).into_owned(),
+                });
+            }
+            device_signin(device_id, Some(signed_at), date)
+                .await
+                .map(|_| ())
+        }
+        PendingOperation::AcceptRequest {
+            supervisor_id,
+            target_id,
+            confirmed,
+        } => supervision::accept_remote_request(state, supervisor_id, target_id, *confirmed).await,
+        PendingOperation::RejectRequest {
+            supervisor_id,
+            target_id,
+        } => {
+            let pending = get_pending_requests(target_id).await?.iter().any(|r| {
+                &r.supervisor_id == supervisor_id && r.status == SupervisionStatus::Pending
+            });
+            if !pending {
+                return Err(AppError::Conflict {
+                    message: t!("error.request_not_pending").into_owned(),
+                });
+            }
+            reject_supervision_request_api(supervisor_id, target_id).await
+        }
+        PendingOperation::Sos {
+            device_id,
+            message,
+            sent_at,
+        } => send_sos_api(device_id, message.as_deref(), sent_at).await,
+    }
+}
@@ -0,0 +1,153 @@
+//! Per-relationship supervision timeline.
+//!
+//! Every interaction with a supervision partner lands in the supervision
+//! history as it happens on this device. The server keeps its own timeline
+//! per relationship, which also holds what happened on the other device or
+//! while this one was offline. Viewing a relationship's timeline merges the
+//! server's events into the local history first, so both parties see the same
+//! account of what happened and when.
+
+use chrono::DateTime;
+use rust_i18n::t;
+
+use crate::api_client::{get_supervision_events_api, get_supervision_list};
+use crate::error::AppError;
+use crate::models::{SupervisionHistoryEntry, SupervisionHistoryKind};
+use crate::remote_models::{SupervisionEvent, SupervisionEventKind};
+use crate::state::AppState;
+use crate::storage;
+use crate::supervision::SUPERVISION_HISTORY_LIMIT;
+
+/// Timeline of one supervision relationship, newest first
+pub async fn relationship_history(
+    state: &AppState,
+    relationship_id: &str,
+) -> Result<Vec<SupervisionHistoryEntry>, AppError> {
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let my_device_id = config.device.device_id.clone();
+    let partner = match config
+        .supervision_relationships
+        .iter()
+        .find(|r| r.relationship_id == relationship_id)
+    {
+        Some(r) => partner_of(&my_device_id, &r.supervisor_device_id, &r.supervised_device_id),
+        None => get_supervision_list(&my_device_id)
+            .await?
+            .into_iter()
+            .find(|r| r.relation_id == relationship_id)
+            .map(|r| partner_of(&my_device_id, &r.supervisor_id, &r.target_id))
+            .ok_or_else(|| {
+                log::warn!("Supervision relationship {} not found", relationship_id);
+                t!("error.relationship_not_found").into_owned()
+            })?,
+    };
+
+    match get_supervision_events_api(relationship_id).await {
+        Ok(events) => merge_remote_events(&my_device_id, &partner, events)?,
+        Err(e) => log::warn!("Failed to fetch supervision events, showing local history: {}", e),
+    }
+
+    let mut history: Vec<SupervisionHistoryEntry> = storage::load_supervision_history()
+        .map_err(|e| {
+            log::error!("Failed to load supervision history: {}", e);
+            e.to_string()
+        })?
+        .into_iter()
+        .filter(|entry| {
+            // SOS broadcasts are recorded against this device, not a partner
+            entry.device_id == partner
+                || (entry.kind == SupervisionHistoryKind::SosSent
+                    && entry.device_id == my_device_id)
+        })
+        .collect();
+    history.reverse();
+    Ok(history)
+}
+
+/// The device on the other side of a relationship
+fn partner_of(my_device_id: &str, supervisor_id: &str, supervised_id: &str) -> String {
+    if supervisor_id == my_device_id {
+        supervised_id.to_string()
+    } else {
+        supervisor_id.to_string()
+    }
+}
+
+/// Add server events that are not in the local history yet
+fn merge_remote_events(
+    my_device_id: &str,
+    partner: &str,
+    events: Vec<SupervisionEvent>,
+) -> Result<(), AppError> {
+    let mut history = storage::load_supervision_history().map_err(|e| {
+        log::error!("Failed to load supervision history: {}", e);
+        e.to_string()
+    })?;
+
+    let mut added = 0;
+    for event in events {
+        let entry = history_entry(my_device_id, partner, event);
+        let known = history
+            .iter()
+            .any(|e| e.kind == entry.kind && e.reference_id == entry.reference_id);
+        if !known {
+            history.push(entry);
+            added += 1;
+        }
+    }
+    if added == 0 {
+        return Ok(());
+    }
+
+    log::info!("Merged {} supervision events from the server", added);
+    history.sort_by_key(|entry| DateTime::parse_from_rfc3339(&entry.timestamp).ok());
+    let excess = history.len().saturating_sub(SUPERVISION_HISTORY_LIMIT);
+    history.drain(..excess);
+    storage::save_supervision_history(&history).map_err(|e| {
+        log::error!("Failed to save supervision history: {}", e);
+        e.to_string().into()
+    })
+}
+
+/// Translate a server event into a history entry from this device's view
+fn history_entry(
+    my_device_id: &str,
+    partner: &str,
+    event: SupervisionEvent,
+) -> SupervisionHistoryEntry {
+    let mine = event.actor_id == my_device_id;
+    let kind = match (event.kind, mine) {
+        (SupervisionEventKind::Requested, true) => SupervisionHistoryKind::RequestSent,
+        (SupervisionEventKind::Requested, false) => SupervisionHistoryKind::RequestReceived,
+        (SupervisionEventKind::Accepted, _) => SupervisionHistoryKind::RequestAccepted,
+        (SupervisionEventKind::Rejected, _) => SupervisionHistoryKind::RequestRejected,
+        (SupervisionEventKind::Ping, true) => SupervisionHistoryKind::PingSent,
+        (SupervisionEventKind::Ping, false) => SupervisionHistoryKind::PingReceived,
+        (SupervisionEventKind::PingResponse, _) => SupervisionHistoryKind::OkResponse,
+        (SupervisionEventKind::Sos, true) => SupervisionHistoryKind::SosSent,
+        (SupervisionEventKind::Sos, false) => SupervisionHistoryKind::SosReceived,
+        (SupervisionEventKind::Encouragement, true) => SupervisionHistoryKind::EncouragementSent,
+        (SupervisionEventKind::Encouragement, false) => {
+            SupervisionHistoryKind::EncouragementReceived
+        }
+        (SupervisionEventKind::MissedCheckin, _) => SupervisionHistoryKind::MissedCheckin,
+        (SupervisionEventKind::Removed, _) => SupervisionHistoryKind::RelationshipRemoved,
+    };
+    let device_id = if kind == SupervisionHistoryKind::SosSent {
+        my_device_id.to_string()
+    } else {
+        partner.to_string()
+    };
+
+    SupervisionHistoryEntry {
+        timestamp: event.created_at,
+        kind,
+        device_id,
+        device_name: if mine { None } else { event.actor_name },
+        reference_id: Some(event.reference_id.unwrap_or(event.event_id)),
+        note: event.note,
+    }
+}
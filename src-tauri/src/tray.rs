@@ -0,0 +1,120 @@
+//! System tray icon with one-click check-in (desktop only).
+//!
+//! The tooltip shows whether the user signed in today and the current streak,
+//! and the menu signs in, opens the window or snoozes the reminder without
+//! going through the window first.
+
+use rust_i18n::t;
+use tauri::menu::{Menu, MenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Manager, Wry};
+
+use crate::clock::SharedClock;
+use crate::commands::snooze_reminder;
+use crate::deadline::signin_day;
+use crate::shortcuts::signin_in_background;
+use crate::state::AppState;
+use crate::storage;
+
+/// ID the tray icon is registered under
+const TRAY_ID: &str = "main";
+
+/// Create the tray icon
+pub fn create(app: &AppHandle) -> tauri::Result<()> {
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip(tooltip(app))
+        .menu(&menu(app)?)
+        .on_menu_event(|app, event| handle_menu_event(app, event.id.as_ref()));
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    Ok(())
+}
+
+/// Menu with sign-in, open and snooze items in the current language
+fn menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let signin = MenuItem::with_id(app, "signin", t!("tray.signin"), true, None::<&str>)?;
+    let open = MenuItem::with_id(app, "open", t!("tray.open"), true, None::<&str>)?;
+    let snooze = MenuItem::with_id(app, "snooze", t!("tray.snooze"), true, None::<&str>)?;
+    Menu::with_items(app, &[&signin, &open, &snooze])
+}
+
+/// Rebuild the menu and tooltip after the language changed
+pub fn relabel(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    if let Err(e) = menu(app).and_then(|menu| tray.set_menu(Some(menu))) {
+        log::warn!("Failed to update tray menu: {}", e);
+    }
+    refresh(app);
+}
+
+/// Update the tooltip to today's status
+///
+/// Besides whenever the sign-in data changes, this runs as a scheduled job so
+/// the tooltip follows the day change.
+pub fn refresh(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    if let Err(e) = tray.set_tooltip(Some(tooltip(app))) {
+        log::warn!("Failed to update tray tooltip: {}", e);
+    }
+}
+
+/// Today's sign-in status and streak, e.g. "✔ 3-day streak"
+fn tooltip(app: &AppHandle) -> String {
+    let clock = app.state::<SharedClock>();
+    let settings = storage::load_settings().unwrap_or_default();
+    let today = signin_day(clock.as_ref(), &settings)
+        .format("%Y-%m-%d")
+        .to_string();
+    match app.state::<AppState>().signin_data() {
+        Ok(Some(data)) if data.last_signin_date == today => {
+            t!("tray.signed_in", streak = data.streak).into_owned()
+        }
+        Ok(Some(data)) => t!("tray.not_signed_in", streak = data.streak).into_owned(),
+        Ok(None) => t!("tray.never_signed_in").into_owned(),
+        Err(e) => {
+            log::warn!("Failed to load sign-in data for tray: {}", e);
+            "Are You OK?".to_string()
+        }
+    }
+}
+
+/// Run the action of a tray menu item
+fn handle_menu_event(app: &AppHandle, id: &str) {
+    log::info!("Tray menu item {} selected", id);
+    match id {
+        "signin" => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                signin_in_background(&app).await;
+            });
+        }
+        "open" => show_window(app),
+        "snooze" => {
+            if let Err(e) = snooze_reminder(app.state(), app.state(), None) {
+                log::warn!("Failed to snooze reminder from tray: {}", e);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Bring the main window to the front
+pub fn show_window(app: &AppHandle) {
+    let Some(window) = app.get_webview_window("main") else {
+        log::warn!("Main window not found");
+        return;
+    };
+    let shown = window
+        .show()
+        .and_then(|_| window.unminimize())
+        .and_then(|_| window.set_focus());
+    if let Err(e) = shown {
+        log::warn!("Failed to show main window: {}", e);
+    }
+}
@@ -0,0 +1,176 @@
+//! Updates of the app itself (desktop only).
+//!
+//! Devices of elderly users are rarely updated by hand, and an old build keeps
+//! talking to the server with an API client it may no longer understand. The
+//! updater plugin therefore looks for new releases in the background, see the
+//! `updates` job in [`scheduler`](crate::scheduler), and a notification points
+//! the user to an update once one is found.
+//!
+//! Releases are published on GitHub in one of two channels, picked with
+//! [`AppSettings::update_channel`](crate::models::AppSettings::update_channel):
+//! stable releases, or beta releases under the rolling `beta` tag. Updates are
+//! signed, and the public key they are checked against comes from the
+//! `AREUOK_UPDATER_PUBKEY` environment variable at build time. Builds made
+//! without it, such as local development builds, have the updater switched
+//! off: the plugin isn't registered, the background check does nothing and
+//! the update commands fail, rather than trusting whatever key the config
+//! holds.
+//!
+//! Signed update bundles are only made for releases, by building with
+//! `--config src-tauri/tauri.release.conf.json` and the matching private key
+//! in `TAURI_SIGNING_PRIVATE_KEY`; a plain `tauri build` needs no key.
+
+use std::sync::Mutex;
+
+use chrono::DateTime;
+use rust_i18n::t;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::error::AppError;
+use crate::models::{ProxySettings, UpdateChannel, UpdateInfo};
+use crate::storage;
+
+/// Release manifest of the stable channel
+const STABLE_ENDPOINT: &str =
+    "https://github.com/areuok-service/areuok-app/releases/latest/download/latest.json";
+
+/// Release manifest of the beta channel
+const BETA_ENDPOINT: &str =
+    "https://github.com/areuok-service/areuok-app/releases/download/beta/latest.json";
+
+/// Public key release signatures are checked against, set at build time
+const PUBKEY: Option<&str> = option_env!("AREUOK_UPDATER_PUBKEY");
+
+/// Latest version the user was notified about in this run
+static NOTIFIED_VERSION: Mutex<Option<String>> = Mutex::new(None);
+
+/// Build the updater plugin, `None` when this build has no key to check releases with
+pub fn plugin() -> Option<tauri::plugin::TauriPlugin<tauri::Wry, tauri_plugin_updater::Config>> {
+    let Some(pubkey) = pubkey() else {
+        log::warn!("No updater public key was set at build time, updates are disabled");
+        return None;
+    };
+    Some(tauri_plugin_updater::Builder::new().pubkey(pubkey).build())
+}
+
+/// Key to check release signatures with, if one was set at build time
+fn pubkey() -> Option<&'static str> {
+    PUBKEY.map(str::trim).filter(|pubkey| !pubkey.is_empty())
+}
+
+/// Look for a newer release in the configured channel
+pub async fn check(app: &AppHandle) -> Result<UpdateInfo, AppError> {
+    let channel = storage::load_settings().unwrap_or_default().update_channel;
+    let update = find_update(app, channel).await?;
+    let info = update_info(app, channel, update.as_ref());
+    match &info.version {
+        Some(version) => log::info!("Update to {} available ({:?} channel)", version, channel),
+        None => log::info!("No update available ({:?} channel)", channel),
+    }
+    Ok(info)
+}
+
+/// Background check: announce a new release once per version
+pub async fn check_in_background(app: &AppHandle) -> Result<(), AppError> {
+    if pubkey().is_none() {
+        log::debug!("Updates are disabled in this build");
+        return Ok(());
+    }
+    let info = check(app).await?;
+    let Some(version) = info.version.clone() else {
+        return Ok(());
+    };
+    {
+        let mut notified = NOTIFIED_VERSION.lock().unwrap_or_else(|e| e.into_inner());
+        if notified.as_deref() == Some(version.as_str()) {
+            return Ok(());
+        }
+        *notified = Some(version.clone());
+    }
+
+    if let Err(e) = app.emit("update-available", &info) {
+        log::warn!("Failed to emit update-available event: {}", e);
+    }
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title(t!("update.title"))
+        .body(t!("update.body", version = version))
+        .show()
+    {
+        log::error!("Failed to show update notification: {}", e);
+    }
+    Ok(())
+}
+
+/// Download and install the latest release, then restart into it
+pub async fn install(app: &AppHandle) -> Result<(), AppError> {
+    let channel = storage::load_settings().unwrap_or_default().update_channel;
+    let update = find_update(app, channel)
+        .await?
+        .ok_or_else(|| t!("error.no_update").into_owned())?;
+    log::info!("Installing update {}", update.version);
+    update
+        .download_and_install(|_, _| {}, || log::info!("Update downloaded"))
+        .await
+        .map_err(|e| {
+            log::error!("Failed to install update {}: {}", update.version, e);
+            e.to_string()
+        })?;
+    app.restart()
+}
+
+async fn find_update(app: &AppHandle, channel: UpdateChannel) -> Result<Option<Update>, AppError> {
+    let pubkey = pubkey().ok_or_else(|| t!("error.updates_disabled").into_owned())?;
+    let endpoint = match channel {
+        UpdateChannel::Stable => STABLE_ENDPOINT,
+        UpdateChannel::Beta => BETA_ENDPOINT,
+    };
+    let endpoint = endpoint
+        .parse()
+        .map_err(|e| format!("Invalid update URL: {}", e))?;
+    let mut builder = app
+        .updater_builder()
+        .pubkey(pubkey)
+        .endpoints(vec![endpoint])
+        .map_err(|e| e.to_string())?;
+    if let Some(proxy) = storage::load_settings().unwrap_or_default().proxy {
+        builder = builder.proxy(proxy_url(&proxy)?);
+    }
+    let updater = builder.build().map_err(|e| {
+        log::error!("Failed to set up updater: {}", e);
+        e.to_string()
+    })?;
+    updater.check().await.map_err(|e| {
+        log::warn!("Failed to check for updates: {}", e);
+        e.to_string().into()
+    })
+}
+
+/// Proxy URL for the updater's own HTTP client
+fn proxy_url(proxy: &ProxySettings) -> Result<tauri::Url, AppError> {
+    let mut url: tauri::Url = format!("http://{}:{}", proxy.host.trim(), proxy.port)
+        .parse()
+        .map_err(|e| format!("Invalid proxy {}:{}: {}", proxy.host, proxy.port, e))?;
+    if let Some(username) = &proxy.username {
+        url.set_username(username)
+            .and_then(|_| url.set_password(proxy.password.as_deref()))
+            .map_err(|_| format!("Invalid proxy credentials for {}", proxy.host))?;
+    }
+    Ok(url)
+}
+
+fn update_info(app: &AppHandle, channel: UpdateChannel, update: Option<&Update>) -> UpdateInfo {
+    UpdateInfo {
+        channel,
+        current_version: app.package_info().version.to_string(),
+        version: update.map(|update| update.version.clone()),
+        date: update
+            .and_then(|update| update.date)
+            .and_then(|date| DateTime::from_timestamp(date.unix_timestamp(), 0))
+            .map(|date| date.to_rfc3339()),
+        notes: update.and_then(|update| update.body.clone()),
+    }
+}
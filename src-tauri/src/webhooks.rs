@@ -0,0 +1,157 @@
+//! User-defined outgoing webhooks.
+//!
+//! Each webhook renders its JSON body template with the variables of the
+//! triggering event (`{{name}}`, `{{streak}}`, `{{event}}`, ...) and sends it to
+//! the configured URL, so the app can be wired to services we don't integrate
+//! with directly.
+
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use reqwest::Method;
+use rust_i18n::t;
+use tauri::{AppHandle, Manager};
+
+use crate::api_client::http_client;
+use crate::channels::NotificationChannel;
+use crate::clock::{Clock, SharedClock};
+use crate::dispatch;
+use crate::error::AppError;
+use crate::history::event_name;
+use crate::models::{
+    ChannelSelection, DeliveryChannel, OutgoingNotification, Webhook, WebhookEvent, WebhookPayload,
+};
+
+/// Send `event` to every subscribed webhook in the background, respecting quiet hours
+///
+/// Missed check-ins are critical and bypass quiet hours.
+pub fn dispatch(app: &AppHandle, event: WebhookEvent, vars: Vec<(&str, String)>) {
+    let clock = app.state::<SharedClock>();
+    let notification = OutgoingNotification {
+        webhook: Some(WebhookPayload {
+            event,
+            vars: template_vars(clock.as_ref(), event, vars),
+        }),
+        ..Default::default()
+    };
+    let critical = event == WebhookEvent::MissedSignin;
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        dispatch::deliver(&app, notification, ChannelSelection::webhook_only(), critical).await;
+    });
+}
+
+/// Own the event variables and add the `{{event}}` and `{{timestamp}}` placeholders
+pub fn template_vars(
+    clock: &dyn Clock,
+    event: WebhookEvent,
+    vars: Vec<(&str, String)>,
+) -> Vec<(String, String)> {
+    let mut vars: Vec<(String, String)> = vars
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+    vars.push(("event".to_string(), event_name(event)));
+    vars.push(("timestamp".to_string(), clock.now().to_rfc3339()));
+    vars
+}
+
+#[async_trait]
+impl NotificationChannel for Webhook {
+    fn kind(&self) -> DeliveryChannel {
+        DeliveryChannel::Webhook
+    }
+
+    fn id(&self) -> String {
+        format!("webhook:{}", self.id)
+    }
+
+    fn accepts(&self, notification: &OutgoingNotification) -> bool {
+        notification
+            .webhook
+            .as_ref()
+            .is_some_and(|p| self.events.is_empty() || self.events.contains(&p.event))
+    }
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), AppError> {
+        let payload = notification
+            .webhook
+            .as_ref()
+            .ok_or_else(|| "Notification has no webhook payload".to_string())?;
+        send_webhook(self, &payload.vars).await
+    }
+}
+
+/// Render and send a single webhook
+async fn send_webhook(webhook: &Webhook, vars: &[(String, String)]) -> Result<(), AppError> {
+    let method = parse_method(&webhook.method)?;
+    let body = render_template(&webhook.body_template, vars);
+    serde_json::from_str::<serde_json::Value>(&body)
+        .map_err(|e| t!("error.webhook_invalid_json", error = e))?;
+
+    log::info!("Sending webhook '{}' to {}", webhook.name, webhook.url);
+    let mut request = http_client()?
+        .request(method, &webhook.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+    for (key, value) in &webhook.headers {
+        request = request.header(key.as_str(), value.as_str());
+    }
+
+    let response = request
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(format!("Webhook returned {}: {}", status, error_text).into());
+    }
+    Ok(())
+}
+
+/// Parse an HTTP method name
+pub fn parse_method(method: &str) -> Result<Method, AppError> {
+    Method::from_str(&method.to_uppercase())
+        .map_err(|e| t!("error.invalid_http_method", error = e).into())
+}
+
+/// Replace `{{key}}` placeholders with JSON-escaped values
+///
+/// Values are escaped as JSON string contents, so placeholders belong inside
+/// quotes for text and may stand alone for numbers like `{{streak}}`.
+/// The template is read once from start to end, so a value that itself
+/// contains `{{key}}` is inserted as is. Unknown placeholders are kept.
+fn render_template(template: &str, vars: &[(String, String)]) -> String {
+    let mut body = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        body.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let Some(end) = placeholder.find("}}") else {
+            rest = placeholder;
+            break;
+        };
+        let key = &placeholder[2..end];
+        match vars.iter().find(|(name, _)| name == key) {
+            Some((_, value)) => {
+                let escaped = serde_json::to_string(value).unwrap_or_default();
+                body.push_str(
+                    escaped
+                        .strip_prefix('"')
+                        .and_then(|v| v.strip_suffix('"'))
+                        .unwrap_or(&escaped),
+                );
+            }
+            None => body.push_str(&placeholder[..end + 2]),
+        }
+        rest = &placeholder[end + 2..];
+    }
+    body.push_str(rest);
+    body
+}
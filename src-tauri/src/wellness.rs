@@ -0,0 +1,151 @@
+//! Supervisor-defined wellness questions.
+//!
+//! A supervisor can attach a few questions to a relationship, such as "did you
+//! take your medication?". The supervised device keeps the questions of all
+//! its supervisors in a local cache refreshed by the supervision sync, asks
+//! them at check-in and sends the answers back to the server, where they show
+//! up in the supervisor's view of the device. Answers for supervisors whose
+//! encryption key is known travel sealed inside the shared status instead.
+
+use rust_i18n::t;
+
+use crate::api_client::{
+    get_wellness_questions_api, set_wellness_questions_api, submit_wellness_answers_api,
+};
+use crate::error::AppError;
+use crate::remote_models::{WellnessAnswer, WellnessQuestion};
+use crate::state::AppState;
+use crate::storage;
+
+/// Maximum number of questions per relationship
+const MAX_QUESTIONS: usize = 5;
+
+/// Maximum length of a question, in characters
+const MAX_QUESTION_CHARS: usize = 100;
+
+/// Replace the questions this supervisor asks a supervised device
+pub async fn set_questions(
+    state: &AppState,
+    device_id: &str,
+    questions: Vec<String>,
+) -> Result<Vec<WellnessQuestion>, AppError> {
+    let questions: Vec<String> = questions
+        .into_iter()
+        .map(|q| q.trim().to_string())
+        .filter(|q| !q.is_empty())
+        .collect();
+    if questions.len() > MAX_QUESTIONS {
+        return Err(t!("error.too_many_questions", max = MAX_QUESTIONS).into());
+    }
+    if questions
+        .iter()
+        .any(|q| q.chars().count() > MAX_QUESTION_CHARS)
+    {
+        return Err(t!("error.question_too_long", max = MAX_QUESTION_CHARS).into());
+    }
+
+    let config = state.device_config().map_err(|e| {
+        log::error!("Failed to load device config: {}", e);
+        e.to_string()
+    })?;
+    let saved = set_wellness_questions_api(&config.device.device_id, device_id, &questions).await?;
+
+    state.update_device_config(|config| {
+        let supervisor_id = config.device.device_id.clone();
+        if let Some(relationship) = config.supervision_relationships.iter_mut().find(|r| {
+            r.supervisor_device_id == supervisor_id && r.supervised_device_id == device_id
+        }) {
+            relationship.wellness_questions = saved.clone();
+        }
+        Ok(())
+    })?;
+    Ok(saved)
+}
+
+/// Fetch the questions this device must answer at check-in into the local cache
+pub async fn refresh_questions(device_id: &str) -> Result<(), AppError> {
+    let questions = get_wellness_questions_api(device_id).await?;
+    storage::save_wellness_questions(&questions).map_err(AppError::from)
+}
+
+/// Check that every cached question has an answer and attach the question texts
+pub fn validate_answers(answers: Vec<WellnessAnswer>) -> Result<Vec<WellnessAnswer>, AppError> {
+    let questions = storage::load_wellness_questions().map_err(|e| {
+        log::error!("Failed to load wellness questions: {}", e);
+        e.to_string()
+    })?;
+
+    questions
+        .into_iter()
+        .map(|question| {
+            let answer = answers
+                .iter()
+                .find(|a| a.question_id == question.question_id)
+                .map(|a| a.answer.trim())
+                .filter(|a| !a.is_empty())
+                .ok_or_else(|| t!("error.answer_required", question = question.text))?;
+            Ok(WellnessAnswer {
+                question_id: question.question_id,
+                question: question.text,
+                answer: answer.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Answers to the questions of one supervisor
+pub fn answers_for(
+    supervisor_id: &str,
+    answers: &[WellnessAnswer],
+    questions: &[WellnessQuestion],
+) -> Vec<WellnessAnswer> {
+    answers
+        .iter()
+        .filter(|answer| {
+            questions
+                .iter()
+                .any(|q| q.question_id == answer.question_id && q.supervisor_id == supervisor_id)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Send the answers given at today's check-in in the background
+///
+/// Answers to supervisors with a known encryption key are left out, as the
+/// shared status carries them sealed.
+pub fn submit_answers(
+    state: &AppState,
+    device_id: String,
+    date: String,
+    answers: Vec<WellnessAnswer>,
+) {
+    let sealed_supervisors: Vec<String> = state
+        .device_config()
+        .map(|config| {
+            config
+                .supervision_relationships
+                .into_iter()
+                .filter(|r| r.supervised_device_id == device_id && r.partner_public_key.is_some())
+                .map(|r| r.supervisor_device_id)
+                .collect()
+        })
+        .unwrap_or_default();
+    let questions = storage::load_wellness_questions().unwrap_or_default();
+    let answers: Vec<WellnessAnswer> = answers
+        .into_iter()
+        .filter(|answer| {
+            !questions.iter().any(|q| {
+                q.question_id == answer.question_id && sealed_supervisors.contains(&q.supervisor_id)
+            })
+        })
+        .collect();
+    if answers.is_empty() {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = submit_wellness_answers_api(&device_id, &date, &answers).await {
+            log::warn!("Failed to submit wellness answers: {}", e);
+        }
+    });
+}
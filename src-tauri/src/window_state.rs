@@ -0,0 +1,37 @@
+//! Window size and position kept across restarts (desktop only).
+//!
+//! Supervisors often keep a small dashboard window in a corner of one of
+//! their monitors. The window state plugin saves its size, position and
+//! maximized state on exit and puts them back on the next start; when that
+//! monitor is no longer connected the window opens on the primary one.
+
+use tauri::{AppHandle, Manager};
+use tauri_plugin_window_state::{StateFlags, WindowExt};
+
+/// Window properties to restore
+///
+/// Visibility is left out: the window stays hidden when the app was started at
+/// login, and restoring it would show the window again.
+fn state_flags() -> StateFlags {
+    StateFlags::all() - StateFlags::VISIBLE
+}
+
+/// Build the window state plugin
+pub fn plugin() -> tauri::plugin::TauriPlugin<tauri::Wry> {
+    tauri_plugin_window_state::Builder::default()
+        .with_state_flags(state_flags())
+        .build()
+}
+
+/// Put the main window back where it was on the last run
+///
+/// The plugin only restores windows created after it was registered, and the
+/// main window already exists during setup.
+pub fn restore(app: &AppHandle) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    if let Err(e) = window.restore_state(state_flags()) {
+        log::warn!("Failed to restore window state: {}", e);
+    }
+}